@@ -0,0 +1,388 @@
+//! Atomic closures of `Conn::atomic`.
+//!
+//! The closure runs in a single txn of one shard: the commands of `Tx` are executed in the txn,
+//! so all their writes are committed together, or none of them when the closure fails. The
+//! closure is run again from the start when the txn conflicts with another one, so it should
+//! not have other side effects.
+
+use bytes::Bytes;
+
+use crate::cmd::{
+    Command, Del, Expire, Get, Hdel, Hget, Hgetall, Hincrby, Hlen, Hset, IncrDecr, Llen, Lrange,
+    Pop, Push, Sadd, Scard, Set, Sismember, Smembers, Srem, Zadd, Zcard, Zincrby, Zrange, Zrem,
+    Zscore,
+};
+use crate::config::txn_retry_count;
+use crate::db::DBInner;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::{
+    RError, ATOMIC_ABORTED_ERR, RAFT_DIRECT_WRITE_ERR, REDIS_CROSSSLOT_ERR, REDIS_READONLY_ERR,
+    TXN_ERROR, UNEXPECTED_REPLY_ERR,
+};
+use crate::rocks::transaction::RocksTransaction;
+use crate::shard::keys_shard;
+use crate::{Frame, Result};
+
+enum Abort {
+    // the txn conflicts with another one
+    Conflict,
+    // the first key is in another shard, run the closure in the txn of that shard
+    Shard(usize),
+}
+
+/// The typed access to the data in the txn of `Conn::atomic`.
+///
+/// Every method executes the command of the same name. A failed command returns `Err`, which
+/// is usually returned by the closure with `?` to discard the writes. On a sharded db all the
+/// keys must be in the shard of the first key.
+pub struct Tx<'a> {
+    inner_db: &'a DBInner,
+    shard: usize,
+    txn: &'a RocksTransaction<'a>,
+    // whether a key is accessed, the shard can't be changed after it
+    bound: bool,
+    abort: Option<Abort>,
+    // the writes recorded in the journal after the txn is committed
+    writes: Vec<Command>,
+}
+
+impl<'a> Tx<'a> {
+    fn new(inner_db: &'a DBInner, shard: usize, txn: &'a RocksTransaction<'a>) -> Self {
+        Self {
+            inner_db,
+            shard,
+            txn,
+            bound: false,
+            abort: None,
+            writes: vec![],
+        }
+    }
+
+    async fn execute(&mut self, cmd: Command) -> Result<Frame> {
+        if self.abort.is_some() {
+            return Err(ATOMIC_ABORTED_ERR.into());
+        }
+        let shards = self.inner_db.shards.len();
+        if shards > 1 {
+            match keys_shard(&cmd.keys(), shards) {
+                None => return Err(REDIS_CROSSSLOT_ERR.into()),
+                Some(shard) if shard != self.shard => {
+                    if self.bound {
+                        return Err(REDIS_CROSSSLOT_ERR.into());
+                    }
+                    self.abort = Some(Abort::Shard(shard));
+                    return Err(ATOMIC_ABORTED_ERR.into());
+                }
+                Some(_) => {}
+            }
+        }
+        self.bound = true;
+
+        let client = &self.inner_db.shards[self.shard];
+        if cmd.is_write() {
+            if client.is_read_only() {
+                return Err(REDIS_READONLY_ERR.into());
+            }
+            if self.inner_db.raft.is_some() {
+                return Err(RAFT_DIRECT_WRITE_ERR.into());
+            }
+        }
+        if let Some(cluster) = &self.inner_db.cluster {
+            if let Some(frame) = cluster.redirect(&cmd, false, self.inner_db).await? {
+                return Err(frame_error(frame));
+            }
+        }
+
+        // the journal sets the same ttls when it's replayed, like `Conn::execute`
+        let cmd = cmd.with_absolute_expire();
+        let journaled = (cmd.is_write() && self.inner_db.journal.is_some()).then(|| cmd.clone());
+        let frame = match cmd.execute_on(ClientRef::with_txn(client, self.txn)).await {
+            Ok(frame) => frame,
+            Err(e) => match e.downcast_ref::<RError>() {
+                Some(RError::Txn(msg)) => Frame::TxnFailed(msg.to_string()),
+                _ => return Err(e),
+            },
+        };
+        match frame {
+            Frame::TxnFailed(_) => {
+                self.abort = Some(Abort::Conflict);
+                Err(TXN_ERROR.into())
+            }
+            Frame::Error(_) => Err(frame_error(frame)),
+            frame => {
+                self.writes.extend(journaled);
+                Ok(frame)
+            }
+        }
+    }
+
+    pub async fn get(&mut self, key: impl ToString) -> Result<Option<Bytes>> {
+        to_bulk(self.execute(Command::Get(Get::new(key))).await?)
+    }
+
+    pub async fn set(&mut self, key: impl ToString, value: impl ToString) -> Result<()> {
+        self.execute(Command::Set(Set::new(key, value, None, None)))
+            .await?;
+        Ok(())
+    }
+
+    /// Delete the keys, return the number of the deleted keys.
+    pub async fn del(&mut self, keys: &[impl ToString]) -> Result<i64> {
+        to_int(self.execute(Command::Del(Del::new(keys))).await?)
+    }
+
+    /// Increment the integer value of the key by `step`, return the new value.
+    pub async fn incrby(&mut self, key: impl ToString, step: i64) -> Result<i64> {
+        to_int(
+            self.execute(Command::Incrby(IncrDecr::new(key, step)))
+                .await?,
+        )
+    }
+
+    /// Set the time to live of the key in seconds, return false if the key doesn't exist.
+    pub async fn expire(&mut self, key: impl ToString, seconds: i64) -> Result<bool> {
+        Ok(to_int(
+            self.execute(Command::Expire(Expire::new(key, seconds)))
+                .await?,
+        )? == 1)
+    }
+
+    pub async fn hget(
+        &mut self,
+        key: impl ToString,
+        field: impl ToString,
+    ) -> Result<Option<Bytes>> {
+        to_bulk(self.execute(Command::Hget(Hget::new(key, field))).await?)
+    }
+
+    /// Set the fields of the hash, return the number of the added fields.
+    pub async fn hset(
+        &mut self,
+        key: impl ToString,
+        field_and_value: &[(impl ToString, impl ToString)],
+    ) -> Result<i64> {
+        to_int(
+            self.execute(Command::Hset(Hset::new(key, field_and_value)))
+                .await?,
+        )
+    }
+
+    /// Delete the fields of the hash, return the number of the deleted fields.
+    pub async fn hdel(&mut self, key: impl ToString, fields: &[impl ToString]) -> Result<i64> {
+        to_int(self.execute(Command::Hdel(Hdel::new(key, fields))).await?)
+    }
+
+    /// Return the fields and the values of the hash.
+    pub async fn hgetall(&mut self, key: impl ToString) -> Result<Vec<(Bytes, Bytes)>> {
+        let values = to_bulks(self.execute(Command::Hgetall(Hgetall::new(key))).await?)?;
+        Ok(values
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect())
+    }
+
+    /// Increment the integer value of the field by `step`, return the new value.
+    pub async fn hincrby(
+        &mut self,
+        key: impl ToString,
+        field: impl ToString,
+        step: i64,
+    ) -> Result<i64> {
+        to_int(
+            self.execute(Command::Hincrby(Hincrby::new(key, field, step)))
+                .await?,
+        )
+    }
+
+    pub async fn hlen(&mut self, key: impl ToString) -> Result<i64> {
+        to_int(self.execute(Command::Hlen(Hlen::new(key))).await?)
+    }
+
+    /// Insert the values at the head of the list, return the length of the list.
+    pub async fn lpush(&mut self, key: impl ToString, values: &[impl ToString]) -> Result<i64> {
+        to_int(self.execute(Command::Lpush(Push::new(key, values))).await?)
+    }
+
+    /// Insert the values at the tail of the list, return the length of the list.
+    pub async fn rpush(&mut self, key: impl ToString, values: &[impl ToString]) -> Result<i64> {
+        to_int(self.execute(Command::Rpush(Push::new(key, values))).await?)
+    }
+
+    pub async fn lpop(&mut self, key: impl ToString) -> Result<Option<Bytes>> {
+        to_bulk(self.execute(Command::Lpop(Pop::new(key, 1))).await?)
+    }
+
+    pub async fn rpop(&mut self, key: impl ToString) -> Result<Option<Bytes>> {
+        to_bulk(self.execute(Command::Rpop(Pop::new(key, 1))).await?)
+    }
+
+    pub async fn lrange(
+        &mut self,
+        key: impl ToString,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<Bytes>> {
+        to_bulks(
+            self.execute(Command::Lrange(Lrange::new(key, start, stop)))
+                .await?,
+        )
+    }
+
+    pub async fn llen(&mut self, key: impl ToString) -> Result<i64> {
+        to_int(self.execute(Command::Llen(Llen::new(key))).await?)
+    }
+
+    /// Add the members to the set, return the number of the added members.
+    pub async fn sadd(&mut self, key: impl ToString, members: &[impl ToString]) -> Result<i64> {
+        to_int(self.execute(Command::Sadd(Sadd::new(key, members))).await?)
+    }
+
+    /// Remove the members from the set, return the number of the removed members.
+    pub async fn srem(&mut self, key: impl ToString, members: &[impl ToString]) -> Result<i64> {
+        to_int(self.execute(Command::Srem(Srem::new(key, members))).await?)
+    }
+
+    pub async fn sismember(&mut self, key: impl ToString, member: impl ToString) -> Result<bool> {
+        Ok(to_int(
+            self.execute(Command::Sismember(Sismember::new(key, member)))
+                .await?,
+        )? == 1)
+    }
+
+    pub async fn smembers(&mut self, key: impl ToString) -> Result<Vec<Bytes>> {
+        to_bulks(self.execute(Command::Smembers(Smembers::new(key))).await?)
+    }
+
+    pub async fn scard(&mut self, key: impl ToString) -> Result<i64> {
+        to_int(self.execute(Command::Scard(Scard::new(key))).await?)
+    }
+
+    /// Add the member with the score to the sorted set or update its score, return 1 if the
+    /// member is added.
+    pub async fn zadd(
+        &mut self,
+        key: impl ToString,
+        score: f64,
+        member: impl ToString,
+    ) -> Result<i64> {
+        let cmd = Zadd::new(key, &[member], &[score], None, false);
+        to_int(self.execute(Command::Zadd(cmd)).await?)
+    }
+
+    /// Remove the members from the sorted set, return the number of the removed members.
+    pub async fn zrem(&mut self, key: impl ToString, members: &[impl ToString]) -> Result<i64> {
+        to_int(self.execute(Command::Zrem(Zrem::new(key, members))).await?)
+    }
+
+    pub async fn zscore(
+        &mut self,
+        key: impl ToString,
+        member: impl ToString,
+    ) -> Result<Option<f64>> {
+        match to_bulk(
+            self.execute(Command::Zscore(Zscore::new(key, member)))
+                .await?,
+        )? {
+            Some(score) => to_float(&score).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Increment the score of the member by `step`, return the new score.
+    pub async fn zincrby(
+        &mut self,
+        key: impl ToString,
+        step: f64,
+        member: impl ToString,
+    ) -> Result<f64> {
+        let frame = self
+            .execute(Command::Zincrby(Zincrby::new(key, step, member)))
+            .await?;
+        to_float(&to_bulk(frame)?.ok_or(UNEXPECTED_REPLY_ERR)?)
+    }
+
+    /// Return the members between the ranks `start` and `stop` ordered by the scores.
+    pub async fn zrange(
+        &mut self,
+        key: impl ToString,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<Bytes>> {
+        let cmd = Zrange::new(key, start, stop, false, false);
+        to_bulks(self.execute(Command::Zrange(cmd)).await?)
+    }
+
+    pub async fn zcard(&mut self, key: impl ToString) -> Result<i64> {
+        to_int(self.execute(Command::Zcard(Zcard::new(key))).await?)
+    }
+}
+
+/// Run the closure in a txn, the closure is run again if the txn conflicts with another one.
+/// Return the value of the closure and the writes to record in the journal.
+pub(crate) async fn run<T, F>(inner_db: &DBInner, mut f: F) -> Result<(T, Vec<Command>)>
+where
+    F: AsyncFnMut(&mut Tx<'_>) -> Result<T>,
+{
+    let mut shard = 0;
+    for _ in 0..txn_retry_count() {
+        let client = &inner_db.shards[shard];
+        let txn = client.begin_txn(true);
+        let mut tx = Tx::new(inner_db, shard, &txn);
+        let res = f(&mut tx).await;
+        let Tx { abort, writes, .. } = tx;
+        // the writes are discarded even if the closure ignores the abort
+        match (res, abort) {
+            (Ok(value), None) => match client.commit(txn) {
+                Ok(()) => return Ok((value, writes)),
+                // the commit conflicts with another txn
+                Err(RError::Txn(_)) => {}
+                Err(e) => return Err(e.into()),
+            },
+            (_, Some(Abort::Conflict)) => {}
+            (_, Some(Abort::Shard(s))) => shard = s,
+            (Err(e), None) => return Err(e),
+        }
+    }
+    Err(TXN_ERROR.into())
+}
+
+fn frame_error(frame: Frame) -> crate::Error {
+    match frame {
+        Frame::Error(e) => e.into(),
+        _ => UNEXPECTED_REPLY_ERR.into(),
+    }
+}
+
+fn to_int(frame: Frame) -> Result<i64> {
+    match frame {
+        Frame::Integer(n) => Ok(n),
+        _ => Err(UNEXPECTED_REPLY_ERR.into()),
+    }
+}
+
+fn to_bulk(frame: Frame) -> Result<Option<Bytes>> {
+    match frame {
+        Frame::Bulk(value) => Ok(Some(value)),
+        Frame::Simple(value) => Ok(Some(value.into())),
+        Frame::Null => Ok(None),
+        _ => Err(UNEXPECTED_REPLY_ERR.into()),
+    }
+}
+
+fn to_bulks(frame: Frame) -> Result<Vec<Bytes>> {
+    match frame {
+        Frame::Array(values) => values
+            .into_iter()
+            .map(|value| to_bulk(value)?.ok_or_else(|| UNEXPECTED_REPLY_ERR.into()))
+            .collect(),
+        Frame::Null => Ok(vec![]),
+        _ => Err(UNEXPECTED_REPLY_ERR.into()),
+    }
+}
+
+fn to_float(value: &[u8]) -> Result<f64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| UNEXPECTED_REPLY_ERR.into())
+}
@@ -0,0 +1,447 @@
+//! Redis Cluster topology of a group of dbs.
+//!
+//! Every db in the cluster is a node owning a set of the hash slots, the ownership is
+//! configured by `OpenOptions::cluster_node` and `OpenOptions::cluster_peer` on all the nodes.
+//! A command whose keys are not in a slot of this node is answered by a MOVED error pointing
+//! to the owner, so a cluster-aware client talking to the RESP front end of the embedding
+//! server can find the right node.
+//!
+//! A slot is moved between nodes by marking it IMPORTING on the target and MIGRATING on the
+//! source, moving its keys with DUMP and RESTORE and assigning it to the target, which is what
+//! `migrate_slot` does. While a slot is migrating, the commands on the keys already moved are
+//! answered by an ASK error, and the target only serves the commands following ASKING.
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, RwLock};
+
+use bytes::Bytes;
+
+use crate::cmd::{Asking, Cluster, ClusterOp, Command, Del, Dump, Exists, Restore, SlotState};
+use crate::db::DBInner;
+use crate::rocks::client::RocksClient;
+use crate::rocks::engine::CF_NAME_DEFAULT;
+use crate::rocks::errors::{
+    RError, REDIS_CLUSTERDOWN_ERR, REDIS_CROSSSLOT_ERR, REDIS_INVALID_SLOT_ERR, REDIS_TRYAGAIN_ERR,
+};
+use crate::rocks::Result as RocksResult;
+use crate::shard::{key_hash_slot, SLOT_COUNT};
+use crate::utils::{resp_array, resp_bulk, resp_err, resp_int};
+use crate::{Conn, Error, Frame, Result};
+
+// key in the default column family saving the slots assigned by CLUSTER SETSLOT NODE
+const CLUSTER_SLOTS_KEY: &[u8] = b"mapuche_cluster_slots";
+// number of the keys moved in a round of `migrate_slot`
+const MIGRATE_BATCH_SIZE: u64 = 100;
+
+/// A node of the cluster and the hash slots it owns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterNode {
+    pub id: String,
+    /// Address in `ip:port` form, it is returned to the clients in the redirections.
+    pub addr: String,
+    pub slots: Vec<RangeInclusive<u16>>,
+}
+
+impl ClusterNode {
+    fn ip_port(&self) -> (&str, i64) {
+        match self.addr.rsplit_once(':') {
+            Some((ip, port)) => (ip, port.parse().unwrap_or_default()),
+            None => (&self.addr, 0),
+        }
+    }
+}
+
+struct Topology {
+    nodes: Vec<ClusterNode>,
+    // index of the owner node of every slot
+    owners: Vec<Option<usize>>,
+    // slots moving to or from the other nodes
+    migrating: BTreeMap<u16, usize>,
+    importing: BTreeMap<u16, usize>,
+}
+
+impl Topology {
+    fn node_index(&self, id: &str) -> RocksResult<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.id == id)
+            .ok_or_else(|| RError::unknown_cluster_node_error(id))
+    }
+
+    /// Return the contiguous slot ranges and their owners.
+    fn ranges(&self) -> Vec<(u16, u16, usize)> {
+        let mut ranges: Vec<(u16, u16, usize)> = vec![];
+        for (slot, owner) in self.owners.iter().enumerate() {
+            let Some(owner) = *owner else {
+                continue;
+            };
+            match ranges.last_mut() {
+                Some((_, end, last)) if *last == owner && *end as usize + 1 == slot => {
+                    *end = slot as u16;
+                }
+                _ => ranges.push((slot as u16, slot as u16, owner)),
+            }
+        }
+        ranges
+    }
+}
+
+enum Redirect {
+    Local,
+    // execute the command if its keys are not moved yet
+    Migrating(u16, String),
+    Moved(u16, String),
+    Down,
+}
+
+pub(crate) struct ClusterState {
+    myself: usize,
+    client: Arc<RocksClient>,
+    topology: RwLock<Topology>,
+}
+
+impl ClusterState {
+    /// Create the topology from the configured nodes, the slots assigned by CLUSTER SETSLOT
+    /// NODE and saved in `client` replace the configured ones.
+    pub(crate) fn open(
+        myself: &ClusterNode,
+        peers: &[ClusterNode],
+        client: Arc<RocksClient>,
+    ) -> RocksResult<Self> {
+        let mut nodes = vec![myself.clone()];
+        nodes.extend(peers.iter().cloned());
+
+        let mut owners = vec![None; SLOT_COUNT as usize];
+        let saved: Option<Vec<(u16, u16, String)>> =
+            match client.engine().get(CF_NAME_DEFAULT, CLUSTER_SLOTS_KEY)? {
+                Some(value) => Some(
+                    serde_json::from_slice(&value)
+                        .map_err(|e| RError::owned_error(e.to_string()))?,
+                ),
+                None => None,
+            };
+        let ranges = match saved {
+            Some(saved) => saved,
+            None => nodes
+                .iter()
+                .flat_map(|node| {
+                    node.slots
+                        .iter()
+                        .map(|range| (*range.start(), *range.end(), node.id.clone()))
+                })
+                .collect(),
+        };
+        for (start, end, id) in ranges {
+            // the saved slots of a node no longer configured are not served
+            let owner = nodes.iter().position(|node| node.id == id);
+            for slot in start..=end.min(SLOT_COUNT - 1) {
+                owners[slot as usize] = owner;
+            }
+        }
+
+        Ok(Self {
+            myself: 0,
+            client,
+            topology: RwLock::new(Topology {
+                nodes,
+                owners,
+                migrating: BTreeMap::new(),
+                importing: BTreeMap::new(),
+            }),
+        })
+    }
+
+    /// Return the id of this node.
+    pub(crate) fn myid(&self) -> String {
+        self.topology.read().unwrap().nodes[self.myself].id.clone()
+    }
+
+    /// Return the MOVED, ASK, CROSSSLOT or CLUSTERDOWN error if the command can't be executed
+    /// on this node. `asking` is whether the command follows ASKING.
+    pub(crate) async fn redirect(
+        &self,
+        cmd: &Command,
+        asking: bool,
+        inner_db: &DBInner,
+    ) -> Result<Option<Frame>> {
+        let keys = cmd.keys();
+        let mut slots = keys.iter().map(|key| key_hash_slot(key.as_bytes()));
+        let Some(slot) = slots.next() else {
+            return Ok(None);
+        };
+        if !slots.all(|other| other == slot) {
+            return Ok(Some(resp_err(REDIS_CROSSSLOT_ERR)));
+        }
+
+        let redirect = {
+            let topology = self.topology.read().unwrap();
+            match topology.owners[slot as usize] {
+                Some(owner) if owner == self.myself => match topology.migrating.get(&slot) {
+                    Some(target) => Redirect::Migrating(slot, topology.nodes[*target].addr.clone()),
+                    None => Redirect::Local,
+                },
+                _ if asking && topology.importing.contains_key(&slot) => Redirect::Local,
+                Some(owner) => Redirect::Moved(slot, topology.nodes[owner].addr.clone()),
+                None => Redirect::Down,
+            }
+        };
+        match redirect {
+            Redirect::Local => Ok(None),
+            Redirect::Moved(slot, addr) => Ok(Some(resp_err(RError::moved_error(slot, &addr)))),
+            Redirect::Down => Ok(Some(resp_err(REDIS_CLUSTERDOWN_ERR))),
+            Redirect::Migrating(slot, addr) => {
+                let mut keys = keys;
+                keys.sort_unstable();
+                keys.dedup();
+                let exists = Command::Exists(Exists::new(&keys))
+                    .execute(inner_db)
+                    .await?;
+                match exists {
+                    Frame::Integer(0) => Ok(Some(resp_err(RError::ask_error(slot, &addr)))),
+                    Frame::Integer(n) if (n as usize) < keys.len() => {
+                        Ok(Some(resp_err(REDIS_TRYAGAIN_ERR)))
+                    }
+                    _ => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Change the state of the slot by CLUSTER SETSLOT.
+    pub(crate) fn set_slot(&self, slot: u16, state: &SlotState) -> RocksResult<()> {
+        if slot >= SLOT_COUNT {
+            return Err(REDIS_INVALID_SLOT_ERR);
+        }
+        let mut topology = self.topology.write().unwrap();
+        let owned = topology.owners[slot as usize] == Some(self.myself);
+        match state {
+            SlotState::Importing(id) => {
+                if owned {
+                    return Err(RError::owned_error(format!(
+                        "ERR I'm already the owner of hash slot {slot}"
+                    )));
+                }
+                let node = topology.node_index(id)?;
+                topology.importing.insert(slot, node);
+            }
+            SlotState::Migrating(id) => {
+                if !owned {
+                    return Err(RError::owned_error(format!(
+                        "ERR I'm not the owner of hash slot {slot}"
+                    )));
+                }
+                let node = topology.node_index(id)?;
+                topology.migrating.insert(slot, node);
+            }
+            SlotState::Stable => {
+                topology.migrating.remove(&slot);
+                topology.importing.remove(&slot);
+            }
+            SlotState::Node(id) => {
+                let node = topology.node_index(id)?;
+                topology.owners[slot as usize] = Some(node);
+                topology.migrating.remove(&slot);
+                topology.importing.remove(&slot);
+
+                let saved: Vec<(u16, u16, &str)> = topology
+                    .ranges()
+                    .into_iter()
+                    .map(|(start, end, owner)| (start, end, topology.nodes[owner].id.as_str()))
+                    .collect();
+                let value =
+                    serde_json::to_vec(&saved).map_err(|e| RError::owned_error(e.to_string()))?;
+                self.client
+                    .engine()
+                    .put(CF_NAME_DEFAULT, CLUSTER_SLOTS_KEY, &value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the reply of CLUSTER SLOTS.
+    pub(crate) fn slots_frame(&self) -> Frame {
+        let topology = self.topology.read().unwrap();
+        let slots = topology
+            .ranges()
+            .into_iter()
+            .map(|(start, end, owner)| {
+                let node = &topology.nodes[owner];
+                let (ip, port) = node.ip_port();
+                resp_array(vec![
+                    resp_int(start as i64),
+                    resp_int(end as i64),
+                    resp_array(vec![
+                        resp_bulk(ip.as_bytes().to_vec()),
+                        resp_int(port),
+                        resp_bulk(node.id.as_bytes().to_vec()),
+                    ]),
+                ])
+            })
+            .collect();
+        resp_array(slots)
+    }
+
+    /// Return the reply of CLUSTER SHARDS, every node is a shard without replicas.
+    pub(crate) fn shards_frame(&self) -> Frame {
+        let topology = self.topology.read().unwrap();
+        let ranges = topology.ranges();
+        let shards = topology
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let slots = ranges
+                    .iter()
+                    .filter(|range| range.2 == i)
+                    .flat_map(|(start, end, _)| [resp_int(*start as i64), resp_int(*end as i64)])
+                    .collect();
+                let (ip, port) = node.ip_port();
+                let info = resp_array(vec![
+                    resp_bulk(b"id".to_vec()),
+                    resp_bulk(node.id.as_bytes().to_vec()),
+                    resp_bulk(b"port".to_vec()),
+                    resp_int(port),
+                    resp_bulk(b"ip".to_vec()),
+                    resp_bulk(ip.as_bytes().to_vec()),
+                    resp_bulk(b"endpoint".to_vec()),
+                    resp_bulk(ip.as_bytes().to_vec()),
+                    resp_bulk(b"role".to_vec()),
+                    resp_bulk(b"master".to_vec()),
+                    resp_bulk(b"replication-offset".to_vec()),
+                    resp_int(0),
+                    resp_bulk(b"health".to_vec()),
+                    resp_bulk(b"online".to_vec()),
+                ]);
+                resp_array(vec![
+                    resp_bulk(b"slots".to_vec()),
+                    resp_array(slots),
+                    resp_bulk(b"nodes".to_vec()),
+                    resp_array(vec![info]),
+                ])
+            })
+            .collect();
+        resp_array(shards)
+    }
+
+    /// Return the reply of CLUSTER NODES.
+    pub(crate) fn nodes_frame(&self) -> Frame {
+        let topology = self.topology.read().unwrap();
+        let ranges = topology.ranges();
+        let mut lines = String::new();
+        for (i, node) in topology.nodes.iter().enumerate() {
+            let (ip, port) = node.ip_port();
+            let flags = if i == self.myself {
+                "myself,master"
+            } else {
+                "master"
+            };
+            lines.push_str(&format!(
+                "{} {ip}:{port}@{} {flags} - 0 0 0 connected",
+                node.id,
+                port + 10000
+            ));
+            for (start, end, _) in ranges.iter().filter(|range| range.2 == i) {
+                if start == end {
+                    lines.push_str(&format!(" {start}"));
+                } else {
+                    lines.push_str(&format!(" {start}-{end}"));
+                }
+            }
+            if i == self.myself {
+                for (slot, target) in &topology.migrating {
+                    lines.push_str(&format!(" [{slot}->-{}]", topology.nodes[*target].id));
+                }
+                for (slot, source) in &topology.importing {
+                    lines.push_str(&format!(" [{slot}-<-{}]", topology.nodes[*source].id));
+                }
+            }
+            lines.push('\n');
+        }
+        resp_bulk(lines.into_bytes())
+    }
+}
+
+/// Move all the keys in `slot` from the node of `source` to the node of `target` and assign
+/// the slot to the target. The keys are moved one by one with DUMP, RESTORE and DEL, so a key
+/// written between its DUMP and DEL loses the write.
+/// Returns the number of the moved keys.
+pub async fn migrate_slot(source: &Conn, target: &Conn, slot: u16) -> Result<usize> {
+    let source_id = expect_bulk(source.execute(cluster_cmd(ClusterOp::Myid)).await?)?;
+    let target_id = expect_bulk(target.execute(cluster_cmd(ClusterOp::Myid)).await?)?;
+    let source_id = String::from_utf8_lossy(&source_id).to_string();
+    let target_id = String::from_utf8_lossy(&target_id).to_string();
+
+    let importing = ClusterOp::Setslot {
+        slot,
+        state: SlotState::Importing(source_id),
+    };
+    expect_ok(target.execute(cluster_cmd(importing)).await?)?;
+    let migrating = ClusterOp::Setslot {
+        slot,
+        state: SlotState::Migrating(target_id.clone()),
+    };
+    expect_ok(source.execute(cluster_cmd(migrating)).await?)?;
+
+    let mut count = 0;
+    loop {
+        let get_keys = ClusterOp::Getkeysinslot {
+            slot,
+            count: MIGRATE_BATCH_SIZE,
+        };
+        let keys = match source.execute(cluster_cmd(get_keys)).await? {
+            Frame::Array(keys) => keys,
+            frame => return Err(frame_error(frame)),
+        };
+        if keys.is_empty() {
+            break;
+        }
+        for key in keys {
+            let key = String::from_utf8_lossy(&expect_bulk(key)?).to_string();
+            let value = match source.execute(Command::Dump(Dump::new(&key))).await? {
+                Frame::Bulk(value) => value,
+                // expired or deleted after listed
+                Frame::Null => continue,
+                frame => return Err(frame_error(frame)),
+            };
+            expect_ok(target.execute(Command::Asking(Asking::new())).await?)?;
+            let restore = Restore::new(&key, 0, value, true);
+            expect_ok(target.execute(Command::Restore(restore)).await?)?;
+            source.execute(Command::Del(Del::new(&[&key]))).await?;
+            count += 1;
+        }
+    }
+
+    let node = ClusterOp::Setslot {
+        slot,
+        state: SlotState::Node(target_id),
+    };
+    expect_ok(target.execute(cluster_cmd(node.clone())).await?)?;
+    expect_ok(source.execute(cluster_cmd(node)).await?)?;
+    Ok(count)
+}
+
+fn cluster_cmd(op: ClusterOp) -> Command {
+    Command::Cluster(Cluster::new(op))
+}
+
+fn expect_ok(frame: Frame) -> Result<()> {
+    match frame {
+        Frame::Simple(_) => Ok(()),
+        frame => Err(frame_error(frame)),
+    }
+}
+
+fn expect_bulk(frame: Frame) -> Result<Bytes> {
+    match frame {
+        Frame::Bulk(value) => Ok(value),
+        frame => Err(frame_error(frame)),
+    }
+}
+
+fn frame_error(frame: Frame) -> Error {
+    match frame {
+        Frame::Error(e) => e.into(),
+        frame => format!("unexpected reply {frame}").into(),
+    }
+}
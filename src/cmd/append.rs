@@ -0,0 +1,61 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+use bytes::Bytes;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Append `value` at the end of the string stored at `key`.
+///
+/// If `key` does not exist it is created and set as an empty string first.
+/// The time to live of an existing key is kept.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Append {
+    key: String,
+    value: Bytes,
+    valid: bool,
+}
+
+impl Append {
+    pub fn new(key: impl ToString, value: impl ToString) -> Append {
+        Append {
+            key: key.to_string(),
+            value: value.to_string().into(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .append(&self.key, &self.value)
+            .await
+    }
+}
+
+impl Invalid for Append {
+    fn new_invalid() -> Append {
+        Append {
+            key: "".to_owned(),
+            value: Bytes::new(),
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::errors::REDIS_CLUSTER_DISABLED_ERR;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Allow the next command of the connection on a slot being imported to this node.
+/// It is handled by the connection of a cluster node, so executing it is always an error.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Asking {
+    valid: bool,
+}
+
+impl Asking {
+    pub fn new() -> Asking {
+        Asking { valid: true }
+    }
+
+    pub fn execute(&self) -> Frame {
+        if !self.valid {
+            return resp_invalid_arguments();
+        }
+        resp_err(REDIS_CLUSTER_DISABLED_ERR)
+    }
+}
+
+impl Default for Asking {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Invalid for Asking {
+    fn new_invalid() -> Self {
+        Asking { valid: false }
+    }
+}
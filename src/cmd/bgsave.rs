@@ -0,0 +1,41 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::backup::BackupCommand;
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Create a backup in the background, the backup directory must be configured.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bgsave {
+    valid: bool,
+}
+
+impl Bgsave {
+    pub fn new() -> Bgsave {
+        Bgsave { valid: true }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        BackupCommand::new(client.client()).bgsave().await
+    }
+}
+
+impl Default for Bgsave {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Invalid for Bgsave {
+    fn new_invalid() -> Self {
+        Bgsave { valid: false }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Count the number of set bits in the string value stored at `key`.
+///
+/// # Options
+///
+/// * `start` `end` -- Only count the bits in the range, negative index is supported.
+/// * BYTE | BIT -- The unit of the range, BYTE by default.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bitcount {
+    key: String,
+    range: Option<(i64, i64)>,
+    unit: BitUnit,
+    valid: bool,
+}
+
+/// Unit of the range in bitmap commands.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitUnit {
+    Byte,
+    Bit,
+}
+
+impl Bitcount {
+    pub fn new(key: impl ToString, range: Option<(i64, i64)>, unit: BitUnit) -> Bitcount {
+        Bitcount {
+            key: key.to_string(),
+            range,
+            unit,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .bitcount(&self.key, self.range, self.unit == BitUnit::Bit)
+            .await
+    }
+}
+
+impl Invalid for Bitcount {
+    fn new_invalid() -> Bitcount {
+        Bitcount {
+            key: "".to_owned(),
+            range: None,
+            unit: BitUnit::Byte,
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::config::config_proto_max_bulk_len;
+use crate::rocks::bitmap;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::{REDIS_BITFIELD_TYPE_ERR, REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Treat the string value stored at `key` as an array of integers with arbitrary bit width.
+///
+/// The operations are executed in order, the `#N` offset form of redis can be expressed as
+/// `N * bits`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bitfield {
+    key: String,
+    ops: Vec<BitfieldOp>,
+    valid: bool,
+}
+
+/// Integer type of the bitfield, `i1` to `i64` and `u1` to `u63` are supported.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitfieldType {
+    pub signed: bool,
+    pub bits: u8,
+}
+
+/// Overflow behavior of SET and INCRBY operations.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitfieldOverflow {
+    /// Wrap around on overflow and underflow
+    Wrap,
+    /// Saturate to the minimum or maximum value
+    Sat,
+    /// Do nothing and return nil
+    Fail,
+}
+
+/// Sub operations of BITFIELD command.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitfieldOp {
+    Get {
+        ty: BitfieldType,
+        offset: u64,
+    },
+    Set {
+        ty: BitfieldType,
+        offset: u64,
+        value: i64,
+    },
+    Incrby {
+        ty: BitfieldType,
+        offset: u64,
+        increment: i64,
+    },
+    /// Change the overflow behavior of the following operations
+    Overflow(BitfieldOverflow),
+}
+
+impl BitfieldType {
+    pub fn signed(bits: u8) -> BitfieldType {
+        BitfieldType { signed: true, bits }
+    }
+
+    pub fn unsigned(bits: u8) -> BitfieldType {
+        BitfieldType {
+            signed: false,
+            bits,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.bits > 0 && (self.bits <= 63 || (self.signed && self.bits == 64))
+    }
+
+    pub(crate) fn get(&self, data: &[u8], offset: u64) -> i64 {
+        if self.signed {
+            bitmap::get_signed(data, offset, self.bits)
+        } else {
+            bitmap::get_unsigned(data, offset, self.bits) as i64
+        }
+    }
+}
+
+impl BitfieldOp {
+    fn type_and_offset(&self) -> Option<(BitfieldType, u64)> {
+        match *self {
+            BitfieldOp::Get { ty, offset }
+            | BitfieldOp::Set { ty, offset, .. }
+            | BitfieldOp::Incrby { ty, offset, .. } => Some((ty, offset)),
+            BitfieldOp::Overflow(_) => None,
+        }
+    }
+}
+
+impl Bitfield {
+    pub fn new(key: impl ToString, ops: Vec<BitfieldOp>) -> Bitfield {
+        Bitfield {
+            key: key.to_string(),
+            ops,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn ops(&self) -> &Vec<BitfieldOp> {
+        &self.ops
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        let max_bits = config_proto_max_bulk_len() as u64 * 8;
+        for (ty, offset) in self.ops.iter().filter_map(BitfieldOp::type_and_offset) {
+            if !ty.is_valid() {
+                return Ok(resp_err(REDIS_BITFIELD_TYPE_ERR));
+            }
+            if offset + ty.bits as u64 > max_bits {
+                return Ok(resp_err(REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR));
+            }
+        }
+        StringCommand::new(client)
+            .bitfield(&self.key, &self.ops)
+            .await
+    }
+}
+
+impl Invalid for Bitfield {
+    fn new_invalid() -> Bitfield {
+        Bitfield {
+            key: "".to_owned(),
+            ops: vec![],
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::REDIS_BITOP_NOT_ERR;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Perform a bitwise operation between multiple keys and store the result in `destkey`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bitop {
+    op: BitOperation,
+    destkey: String,
+    keys: Vec<String>,
+    valid: bool,
+}
+
+/// Bitwise operations of BITOP command.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl Bitop {
+    pub fn new(op: BitOperation, destkey: impl ToString, keys: &[impl ToString]) -> Bitop {
+        if keys.is_empty() {
+            return Bitop::new_invalid();
+        }
+        Bitop {
+            op,
+            destkey: destkey.to_string(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            valid: true,
+        }
+    }
+
+    pub fn destkey(&self) -> &str {
+        &self.destkey
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if self.op == BitOperation::Not && self.keys.len() != 1 {
+            return Ok(resp_err(REDIS_BITOP_NOT_ERR));
+        }
+        StringCommand::new(client)
+            .bitop(self.op, &self.destkey, &self.keys)
+            .await
+    }
+}
+
+impl Invalid for Bitop {
+    fn new_invalid() -> Bitop {
+        Bitop {
+            op: BitOperation::And,
+            destkey: "".to_owned(),
+            keys: vec![],
+            valid: false,
+        }
+    }
+}
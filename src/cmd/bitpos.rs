@@ -0,0 +1,86 @@
+use crate::Frame;
+
+use crate::cmd::{BitUnit, Invalid};
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::REDIS_BITPOS_BIT_ERR;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Return the position of the first bit set to 1 or 0 in the string value stored at `key`.
+///
+/// # Options
+///
+/// * `start` `end` -- Only search in the range, negative index is supported.
+/// * BYTE | BIT -- The unit of the range, BYTE by default.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bitpos {
+    key: String,
+    bit: i64,
+    start: Option<i64>,
+    end: Option<i64>,
+    unit: BitUnit,
+    valid: bool,
+}
+
+impl Bitpos {
+    pub fn new(
+        key: impl ToString,
+        bit: i64,
+        start: Option<i64>,
+        end: Option<i64>,
+        unit: BitUnit,
+    ) -> Bitpos {
+        // end can not be given without start
+        if start.is_none() && end.is_some() {
+            return Bitpos::new_invalid();
+        }
+        Bitpos {
+            key: key.to_string(),
+            bit,
+            start,
+            end,
+            unit,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if self.bit != 0 && self.bit != 1 {
+            return Ok(resp_err(REDIS_BITPOS_BIT_ERR));
+        }
+        StringCommand::new(client)
+            .bitpos(
+                &self.key,
+                self.bit == 1,
+                self.start,
+                self.end,
+                self.unit == BitUnit::Bit,
+            )
+            .await
+    }
+}
+
+impl Invalid for Bitpos {
+    fn new_invalid() -> Bitpos {
+        Bitpos {
+            key: "".to_owned(),
+            bit: 0,
+            start: None,
+            end: None,
+            unit: BitUnit::Byte,
+            valid: false,
+        }
+    }
+}
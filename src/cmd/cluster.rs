@@ -0,0 +1,103 @@
+use crate::cmd::Invalid;
+
+use crate::db::DBInner;
+use crate::rocks::errors::{REDIS_CLUSTER_DISABLED_ERR, REDIS_INVALID_SLOT_ERR};
+use crate::rocks::string::StringCommand;
+use crate::shard::{key_hash_slot, slot_shard, SLOT_COUNT};
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_array, resp_bulk, resp_err, resp_int, resp_invalid_arguments, resp_ok};
+
+/// Inspect or change the cluster topology, the db must be opened as a cluster node.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cluster {
+    op: ClusterOp,
+    valid: bool,
+}
+
+/// Sub commands of CLUSTER command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ClusterOp {
+    Slots,
+    Shards,
+    Nodes,
+    Myid,
+    Keyslot { key: String },
+    Countkeysinslot { slot: u16 },
+    Getkeysinslot { slot: u16, count: u64 },
+    Setslot { slot: u16, state: SlotState },
+}
+
+/// State of a slot set by CLUSTER SETSLOT, the node is given by its id.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SlotState {
+    /// The slot is moving from the node to this node
+    Importing(String),
+    /// The slot is moving from this node to the node
+    Migrating(String),
+    /// The slot is owned by the node
+    Node(String),
+    /// Clear the importing and migrating state
+    Stable,
+}
+
+impl Cluster {
+    pub fn new(op: ClusterOp) -> Cluster {
+        Cluster { op, valid: true }
+    }
+
+    /// Get the sub command
+    pub fn op(&self) -> &ClusterOp {
+        &self.op
+    }
+
+    pub(crate) async fn execute(&self, inner_db: &DBInner) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        let Some(cluster) = &inner_db.cluster else {
+            return Ok(resp_err(REDIS_CLUSTER_DISABLED_ERR));
+        };
+        match &self.op {
+            ClusterOp::Slots => Ok(cluster.slots_frame()),
+            ClusterOp::Shards => Ok(cluster.shards_frame()),
+            ClusterOp::Nodes => Ok(cluster.nodes_frame()),
+            ClusterOp::Myid => Ok(resp_bulk(cluster.myid().into_bytes())),
+            ClusterOp::Keyslot { key } => Ok(resp_int(key_hash_slot(key.as_bytes()) as i64)),
+            ClusterOp::Countkeysinslot { slot } => {
+                let keys = Self::slot_keys(inner_db, *slot, usize::MAX)?;
+                Ok(resp_int(keys.len() as i64))
+            }
+            ClusterOp::Getkeysinslot { slot, count } => {
+                let keys = Self::slot_keys(inner_db, *slot, *count as usize)?;
+                Ok(resp_array(keys.into_iter().map(resp_bulk).collect()))
+            }
+            ClusterOp::Setslot { slot, state } => {
+                if let Err(e) = cluster.set_slot(*slot, state) {
+                    return Ok(resp_err(e));
+                }
+                Ok(resp_ok())
+            }
+        }
+    }
+
+    fn slot_keys(inner_db: &DBInner, slot: u16, count: usize) -> RocksResult<Vec<Vec<u8>>> {
+        if slot >= SLOT_COUNT {
+            return Err(REDIS_INVALID_SLOT_ERR);
+        }
+        let shard = &inner_db.shards[slot_shard(slot, inner_db.shards.len())];
+        StringCommand::new(shard).slot_keys(slot, count)
+    }
+}
+
+impl Invalid for Cluster {
+    fn new_invalid() -> Cluster {
+        Cluster {
+            op: ClusterOp::Myid,
+            valid: false,
+        }
+    }
+}
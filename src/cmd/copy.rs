@@ -0,0 +1,59 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Copy `source` to `destination`, the existing `destination` is only overwritten
+/// with `replace`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Copy {
+    source: String,
+    destination: String,
+    replace: bool,
+    valid: bool,
+}
+
+impl Copy {
+    pub fn new(source: impl ToString, destination: impl ToString, replace: bool) -> Copy {
+        Copy {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            replace,
+            valid: true,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .copy(&self.source, &self.destination, self.replace)
+            .await
+    }
+}
+
+impl Invalid for Copy {
+    fn new_invalid() -> Copy {
+        Copy {
+            source: "".to_owned(),
+            destination: "".to_owned(),
+            replace: false,
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Return the number of keys.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Dbsize {
+    valid: bool,
+}
+
+impl Dbsize {
+    pub fn new() -> Dbsize {
+        Dbsize { valid: true }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client).dbsize().await
+    }
+}
+
+impl Default for Dbsize {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Invalid for Dbsize {
+    fn new_invalid() -> Self {
+        Dbsize { valid: false }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 
 use serde::{Deserialize, Serialize};
 
@@ -28,7 +28,15 @@ impl Del {
         &self.keys
     }
 
-    pub async fn execute(&self, client: &RocksClient) -> RocksResult<Frame> {
+    /// Return the command with the keys at `indices`, it is used to split the command by shard.
+    pub(crate) fn select(&self, indices: &[usize]) -> Del {
+        Del {
+            keys: indices.iter().map(|i| self.keys[*i].clone()).collect(),
+            valid: self.valid,
+        }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::rocks::gc::GcCommand;
 
 use serde::{Deserialize, Serialize};
@@ -19,11 +19,11 @@ impl Gc {
         Gc { valid: true }
     }
 
-    pub async fn execute(&self, client: &RocksClient) -> RocksResult<Frame> {
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
-        GcCommand::new(client).run().await
+        GcCommand::new(client.client()).run().await
     }
 }
 
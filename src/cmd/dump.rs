@@ -0,0 +1,47 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Serialize the value stored at `key` with its ttl, the result can be passed to RESTORE.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Dump {
+    key: String,
+    valid: bool,
+}
+
+impl Dump {
+    pub fn new(key: impl ToString) -> Dump {
+        Dump {
+            key: key.to_string(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client).dump(&self.key).await
+    }
+}
+
+impl Invalid for Dump {
+    fn new_invalid() -> Dump {
+        Dump {
+            key: "".to_owned(),
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::cmd::Invalid;
+
+use crate::db::DBInner;
+use crate::rocks::client::ClientRef;
+use crate::script;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Run a Lua script with the keys and the arguments, the script is given by its code for EVAL
+/// and by the sha of a cached script for EVALSHA.
+///
+/// The script runs in a single txn of the shard of its keys, the keys are available in the
+/// `KEYS` table and the arguments in the `ARGV` table of the script.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Eval {
+    script: String,
+    keys: Vec<String>,
+    args: Vec<String>,
+    valid: bool,
+}
+
+impl Eval {
+    pub fn new(script: impl ToString, keys: &[impl ToString], args: &[impl ToString]) -> Eval {
+        Eval {
+            script: script.to_string(),
+            keys: keys.iter().map(|it| it.to_string()).collect(),
+            args: args.iter().map(|it| it.to_string()).collect(),
+            valid: true,
+        }
+    }
+
+    /// Get the code or the sha of the script
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    pub fn args(&self) -> &Vec<String> {
+        &self.args
+    }
+
+    /// Return the same command with the script replaced by `script`.
+    pub(crate) fn with_script(&self, script: &str) -> Eval {
+        Eval {
+            script: script.to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        inner_db: &DBInner,
+        shards: &[ClientRef<'_>],
+        by_sha: bool,
+        read_only: bool,
+    ) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        match script::eval(inner_db, shards, self, by_sha, read_only).await {
+            Ok(frame) => Ok(frame),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+}
+
+impl Invalid for Eval {
+    fn new_invalid() -> Eval {
+        Eval {
+            script: "".to_owned(),
+            keys: vec![],
+            args: vec![],
+            valid: false,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::cmd::{retry_call, Invalid};
 
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::RError;
 use crate::Frame;
 
 use futures::FutureExt;
@@ -8,20 +9,65 @@ use serde::{Deserialize, Serialize};
 
 use crate::rocks::string::StringCommand;
 use crate::rocks::Result as RocksResult;
-use crate::utils::{resp_invalid_arguments, timestamp_from_ttl};
+use crate::utils::{now_timestamp_in_millis, resp_err, resp_invalid_arguments};
 
+/// Set a timeout on `key`, the key will be deleted if the timeout is in the past.
+///
+/// # Options
+///
+/// * NX -- Set expiry only when the key has no expiry.
+/// * XX -- Set expiry only when the key has an existing expiry.
+/// * GT -- Set expiry only when the new expiry is greater than current one.
+/// * LT -- Set expiry only when the new expiry is less than current one.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Expire {
     key: String,
     seconds: i64,
+    condition: Option<ExpireCondition>,
     valid: bool,
 }
 
+/// Conditions of EXPIRE family commands.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// Set expiry only when the key has no expiry
+    Nx,
+    /// Set expiry only when the key has an existing expiry
+    Xx,
+    /// Set expiry only when the new expiry is greater than current one
+    Gt,
+    /// Set expiry only when the new expiry is less than current one
+    Lt,
+}
+
+impl ExpireCondition {
+    /// Check the condition with the current `ttl` saved with the key and the new
+    /// `timestamp`, a key without ttl is regarded as an infinite ttl.
+    pub(crate) fn check(&self, ttl: i64, timestamp: i64) -> bool {
+        let has_ttl = ttl > 0;
+        match self {
+            ExpireCondition::Nx => !has_ttl,
+            ExpireCondition::Xx => has_ttl,
+            ExpireCondition::Gt => has_ttl && timestamp > ttl,
+            ExpireCondition::Lt => !has_ttl || timestamp < ttl,
+        }
+    }
+}
+
 impl Expire {
     pub fn new(key: impl ToString, seconds: i64) -> Expire {
+        Expire::new_with_condition(key, seconds, None)
+    }
+
+    pub fn new_with_condition(
+        key: impl ToString,
+        seconds: i64,
+        condition: Option<ExpireCondition>,
+    ) -> Expire {
         Expire {
             key: key.to_string(),
             seconds,
+            condition,
             valid: true,
         }
     }
@@ -35,9 +81,30 @@ impl Expire {
         self.seconds
     }
 
+    /// Get the condition
+    pub fn condition(&self) -> Option<ExpireCondition> {
+        self.condition
+    }
+
+    /// Return the PEXPIREAT of the relative expire time, none if the time is invalid.
+    pub(crate) fn absolute(&self, is_millis: bool) -> Option<Expire> {
+        if !self.valid {
+            return None;
+        }
+        let ttl = if is_millis {
+            Some(self.seconds)
+        } else {
+            self.seconds.checked_mul(1000)
+        };
+        Some(Expire {
+            seconds: ttl?.checked_add(now_timestamp_in_millis())?,
+            ..self.clone()
+        })
+    }
+
     pub async fn execute(
         &self,
-        client: &RocksClient,
+        client: ClientRef<'_>,
         is_millis: bool,
         expire_at: bool,
     ) -> RocksResult<Frame> {
@@ -46,17 +113,28 @@ impl Expire {
                 if !self.valid {
                     return Ok(resp_invalid_arguments());
                 }
-                let mut ttl = self.seconds;
+                let mut ttl = Some(self.seconds);
                 if !is_millis {
-                    ttl *= 1000;
+                    ttl = ttl.and_then(|ttl| ttl.checked_mul(1000));
                 }
                 if !expire_at {
-                    ttl = timestamp_from_ttl(ttl);
+                    ttl = ttl.and_then(|ttl| ttl.checked_add(now_timestamp_in_millis()));
                 }
+                let ttl = match ttl {
+                    Some(ttl) => ttl,
+                    None => {
+                        let cmd = match (is_millis, expire_at) {
+                            (false, false) => "expire",
+                            (false, true) => "expireat",
+                            (true, false) => "pexpire",
+                            (true, true) => "pexpireat",
+                        };
+                        return Ok(resp_err(RError::invalid_expire_time_error(cmd)));
+                    }
+                };
                 StringCommand::new(client)
-                    .expire(&self.key, ttl)
+                    .expire(&self.key, ttl, self.condition)
                     .await
-                    .map_err(Into::into)
             }
             .boxed()
         })
@@ -70,6 +148,7 @@ impl Invalid for Expire {
         Expire {
             key: "".to_owned(),
             seconds: 0,
+            condition: None,
             valid: false,
         }
     }
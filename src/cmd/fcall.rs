@@ -0,0 +1,74 @@
+use crate::cmd::Invalid;
+
+use crate::db::DBInner;
+use crate::rocks::client::ClientRef;
+use crate::script;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Call a function of the libraries loaded by FUNCTION LOAD with the keys and the arguments.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Fcall {
+    function: String,
+    keys: Vec<String>,
+    args: Vec<String>,
+    valid: bool,
+}
+
+impl Fcall {
+    pub fn new(function: impl ToString, keys: &[impl ToString], args: &[impl ToString]) -> Fcall {
+        Fcall {
+            function: function.to_string(),
+            keys: keys.iter().map(|it| it.to_string()).collect(),
+            args: args.iter().map(|it| it.to_string()).collect(),
+            valid: true,
+        }
+    }
+
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        inner_db: &DBInner,
+        shards: &[ClientRef<'_>],
+        read_only: bool,
+    ) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        match script::fcall(
+            inner_db,
+            shards,
+            &self.function,
+            &self.keys,
+            &self.args,
+            read_only,
+        )
+        .await
+        {
+            Ok(frame) => Ok(frame),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+}
+
+impl Invalid for Fcall {
+    fn new_invalid() -> Fcall {
+        Fcall {
+            function: "".to_owned(),
+            keys: vec![],
+            args: vec![],
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::cmd::Invalid;
+
+use crate::db::DBInner;
+use crate::script;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Manage the function libraries called by FCALL, the libraries are stored in the db.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Function {
+    op: FunctionOp,
+    valid: bool,
+}
+
+/// Sub commands of FUNCTION command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum FunctionOp {
+    /// Load the library starting with the `#!lua name=<library>` header, an existing library
+    /// of the same name is replaced if `replace` is true
+    Load { code: String, replace: bool },
+    /// Delete the library
+    Delete(String),
+    /// List the libraries matching the glob-style pattern
+    List {
+        pattern: Option<String>,
+        with_code: bool,
+    },
+    /// Delete all the libraries
+    Flush,
+}
+
+impl Function {
+    pub fn new(op: FunctionOp) -> Function {
+        Function { op, valid: true }
+    }
+
+    /// Get the sub command
+    pub fn op(&self) -> &FunctionOp {
+        &self.op
+    }
+
+    pub(crate) async fn execute(&self, inner_db: &DBInner) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        let resp = match &self.op {
+            FunctionOp::Load { code, replace } => script::function_load(inner_db, code, *replace),
+            FunctionOp::Delete(name) => script::function_delete(inner_db, name),
+            FunctionOp::List { pattern, with_code } => {
+                script::function_list(inner_db, pattern.as_deref(), *with_code)
+            }
+            FunctionOp::Flush => script::function_flush(inner_db),
+        };
+        match resp {
+            Ok(frame) => Ok(frame),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+}
+
+impl Invalid for Function {
+    fn new_invalid() -> Function {
+        Function {
+            op: FunctionOp::Flush,
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::errors::RError;
+use crate::rocks::geo::{GeoCommand, GEO_LAT_MAX, GEO_LAT_MIN, GEO_LONG_MAX, GEO_LONG_MIN};
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Add the members with (longitude, latitude) to the geospatial index stored at `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Geoadd {
+    key: String,
+    coords: Vec<(f64, f64)>,
+    members: Vec<String>,
+    exists: Option<bool>,
+    changed_only: bool,
+    valid: bool,
+}
+
+impl Geoadd {
+    pub fn new(
+        key: impl ToString,
+        coords: &[(f64, f64)],
+        members: &[impl ToString],
+        exists: Option<bool>,
+        changed_only: bool,
+    ) -> Geoadd {
+        Geoadd {
+            key: key.to_string(),
+            coords: coords.to_vec(),
+            members: members.iter().map(|it| it.to_string()).collect(),
+            exists,
+            changed_only,
+            valid: coords.len() == members.len() && !members.is_empty(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        for (lon, lat) in &self.coords {
+            if !(GEO_LONG_MIN..=GEO_LONG_MAX).contains(lon)
+                || !(GEO_LAT_MIN..=GEO_LAT_MAX).contains(lat)
+            {
+                return Ok(resp_err(RError::invalid_lonlat_error(*lon, *lat)));
+            }
+        }
+        GeoCommand::new(client)
+            .geoadd(
+                &self.key,
+                &self.members,
+                &self.coords,
+                self.exists,
+                self.changed_only,
+            )
+            .await
+    }
+}
+
+impl Invalid for Geoadd {
+    fn new_invalid() -> Geoadd {
+        Geoadd {
+            key: "".to_string(),
+            coords: vec![],
+            members: vec![],
+            exists: None,
+            changed_only: false,
+            valid: false,
+        }
+    }
+}
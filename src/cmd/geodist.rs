@@ -0,0 +1,73 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::geo::GeoCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Unit of the distances in geo commands.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeoUnit {
+    #[default]
+    M,
+    Km,
+    Ft,
+    Mi,
+}
+
+/// Return the distance between two members in the geospatial index stored at `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Geodist {
+    key: String,
+    member1: String,
+    member2: String,
+    unit: GeoUnit,
+    valid: bool,
+}
+
+impl Geodist {
+    pub fn new(
+        key: impl ToString,
+        member1: impl ToString,
+        member2: impl ToString,
+        unit: GeoUnit,
+    ) -> Geodist {
+        Geodist {
+            key: key.to_string(),
+            member1: member1.to_string(),
+            member2: member2.to_string(),
+            unit,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        GeoCommand::new(client)
+            .geodist(&self.key, &self.member1, &self.member2, self.unit)
+            .await
+    }
+}
+
+impl Invalid for Geodist {
+    fn new_invalid() -> Geodist {
+        Geodist {
+            key: "".to_string(),
+            member1: "".to_string(),
+            member2: "".to_string(),
+            unit: GeoUnit::M,
+            valid: false,
+        }
+    }
+}
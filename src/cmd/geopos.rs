@@ -0,0 +1,52 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::geo::GeoCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Return the (longitude, latitude) of the members in the geospatial index stored at `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Geopos {
+    key: String,
+    members: Vec<String>,
+    valid: bool,
+}
+
+impl Geopos {
+    pub fn new(key: impl ToString, members: &[impl ToString]) -> Geopos {
+        Geopos {
+            key: key.to_string(),
+            members: members.iter().map(|it| it.to_string()).collect(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        GeoCommand::new(client)
+            .geopos(&self.key, &self.members)
+            .await
+    }
+}
+
+impl Invalid for Geopos {
+    fn new_invalid() -> Geopos {
+        Geopos {
+            key: "".to_string(),
+            members: vec![],
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::Frame;
+
+use crate::cmd::{GeoUnit, Invalid};
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::errors::{REDIS_GEO_ANY_WITHOUT_COUNT_ERR, REDIS_GEO_COUNT_ERR};
+use crate::rocks::geo::GeoCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Center of the search area, FROMMEMBER or FROMLONLAT.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GeoFrom {
+    Member(String),
+    LonLat(f64, f64),
+}
+
+/// Shape of the search area, BYRADIUS radius or BYBOX width height.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box(f64, f64),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoSort {
+    Asc,
+    Desc,
+}
+
+/// Optional arguments of GEOSEARCH and GEOSEARCHSTORE.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GeoSearchOptions {
+    pub sort: Option<GeoSort>,
+    pub count: Option<u64>,
+    pub any: bool,
+    pub with_coord: bool,
+    pub with_dist: bool,
+    pub with_hash: bool,
+}
+
+impl GeoSearchOptions {
+    pub(crate) fn check(&self) -> RocksResult<()> {
+        match self.count {
+            Some(0) => Err(REDIS_GEO_COUNT_ERR),
+            None if self.any => Err(REDIS_GEO_ANY_WITHOUT_COUNT_ERR),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Return the members of the geospatial index stored at `key` which are within the
+/// area specified by `from` and `shape`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Geosearch {
+    key: String,
+    from: GeoFrom,
+    shape: GeoShape,
+    unit: GeoUnit,
+    options: GeoSearchOptions,
+    valid: bool,
+}
+
+impl Geosearch {
+    pub fn new(
+        key: impl ToString,
+        from: GeoFrom,
+        shape: GeoShape,
+        unit: GeoUnit,
+        options: GeoSearchOptions,
+    ) -> Geosearch {
+        Geosearch {
+            key: key.to_string(),
+            from,
+            shape,
+            unit,
+            options,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if let Err(e) = self.options.check() {
+            return Ok(resp_err(e));
+        }
+        GeoCommand::new(client)
+            .geosearch(&self.key, &self.from, self.shape, self.unit, &self.options)
+            .await
+    }
+}
+
+impl Invalid for Geosearch {
+    fn new_invalid() -> Geosearch {
+        Geosearch {
+            key: "".to_string(),
+            from: GeoFrom::LonLat(0.0, 0.0),
+            shape: GeoShape::Radius(0.0),
+            unit: GeoUnit::M,
+            options: GeoSearchOptions::default(),
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::Frame;
+
+use crate::cmd::{GeoFrom, GeoSearchOptions, GeoShape, GeoUnit, Invalid};
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::geo::GeoCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Like GEOSEARCH, but store the result in `destination` as a sorted set.
+/// The scores are the distances in `unit` if `store_dist` is set.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Geosearchstore {
+    destination: String,
+    source: String,
+    from: GeoFrom,
+    shape: GeoShape,
+    unit: GeoUnit,
+    options: GeoSearchOptions,
+    store_dist: bool,
+    valid: bool,
+}
+
+impl Geosearchstore {
+    pub fn new(
+        destination: impl ToString,
+        source: impl ToString,
+        from: GeoFrom,
+        shape: GeoShape,
+        unit: GeoUnit,
+        options: GeoSearchOptions,
+        store_dist: bool,
+    ) -> Geosearchstore {
+        // WITHCOORD, WITHDIST and WITHHASH are not allowed when storing
+        let valid = !options.with_coord && !options.with_dist && !options.with_hash;
+        Geosearchstore {
+            destination: destination.to_string(),
+            source: source.to_string(),
+            from,
+            shape,
+            unit,
+            options,
+            store_dist,
+            valid,
+        }
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if let Err(e) = self.options.check() {
+            return Ok(resp_err(e));
+        }
+        GeoCommand::new(client)
+            .geosearchstore(
+                &self.destination,
+                &self.source,
+                &self.from,
+                self.shape,
+                self.unit,
+                &self.options,
+                self.store_dist,
+            )
+            .await
+    }
+}
+
+impl Invalid for Geosearchstore {
+    fn new_invalid() -> Geosearchstore {
+        Geosearchstore {
+            destination: "".to_string(),
+            source: "".to_string(),
+            from: GeoFrom::LonLat(0.0, 0.0),
+            shape: GeoShape::Radius(0.0),
+            unit: GeoUnit::M,
+            options: GeoSearchOptions::default(),
+            store_dist: false,
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Return the bit value at `offset` in the string value stored at `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Getbit {
+    key: String,
+    offset: i64,
+    valid: bool,
+}
+
+impl Getbit {
+    pub fn new(key: impl ToString, offset: i64) -> Getbit {
+        Getbit {
+            key: key.to_string(),
+            offset,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if self.offset < 0 {
+            return Ok(resp_err(REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR));
+        }
+        StringCommand::new(client)
+            .getbit(&self.key, self.offset as u64)
+            .await
+    }
+}
+
+impl Invalid for Getbit {
+    fn new_invalid() -> Getbit {
+        Getbit {
+            key: "".to_owned(),
+            offset: 0,
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Get the value of `key` and delete the key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Getdel {
+    key: String,
+    valid: bool,
+}
+
+impl Getdel {
+    pub fn new(key: impl ToString) -> Getdel {
+        Getdel {
+            key: key.to_string(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client).getdel(&self.key).await
+    }
+}
+
+impl Invalid for Getdel {
+    fn new_invalid() -> Getdel {
+        Getdel {
+            key: "".to_owned(),
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::Frame;
+
+use crate::cmd::{Expiration, Invalid};
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::RError;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Get the value of `key` and optionally set its expiration.
+///
+/// # Options
+///
+/// * EX `seconds` -- Set the specified expire time, in seconds.
+/// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp-seconds` -- Set the specified Unix time at which the key will expire.
+/// * PXAT `timestamp-milliseconds` -- Same as EXAT, in milliseconds.
+/// * PERSIST -- Remove the time to live associated with the key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Getex {
+    key: String,
+    expiration: Option<Expiration>,
+    valid: bool,
+}
+
+impl Getex {
+    pub fn new(key: impl ToString, expiration: Option<Expiration>) -> Getex {
+        Getex {
+            key: key.to_string(),
+            expiration,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the expiration
+    pub fn expiration(&self) -> Option<Expiration> {
+        self.expiration
+    }
+
+    /// Return the command with the relative expire time replaced by the absolute one.
+    pub(crate) fn absolute(self) -> Getex {
+        Getex {
+            expiration: self.expiration.map(Expiration::absolute),
+            ..self
+        }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        let timestamp = match self.expiration {
+            Some(expiration) => match expiration.timestamp() {
+                Some(ts) => Some(ts),
+                None => return Ok(resp_err(RError::invalid_expire_time_error("getex"))),
+            },
+            None => None,
+        };
+        StringCommand::new(client).getex(&self.key, timestamp).await
+    }
+}
+
+impl Invalid for Getex {
+    fn new_invalid() -> Getex {
+        Getex {
+            key: "".to_owned(),
+            expiration: None,
+            valid: false,
+        }
+    }
+}
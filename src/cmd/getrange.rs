@@ -0,0 +1,56 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Get the substring of the string stored at `key`, determined by the offsets
+/// `start` and `end` (both inclusive). Negative offsets count from the end.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Getrange {
+    key: String,
+    start: i64,
+    end: i64,
+    valid: bool,
+}
+
+impl Getrange {
+    pub fn new(key: impl ToString, start: i64, end: i64) -> Getrange {
+        Getrange {
+            key: key.to_string(),
+            start,
+            end,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .getrange(&self.key, self.start, self.end)
+            .await
+    }
+}
+
+impl Invalid for Getrange {
+    fn new_invalid() -> Getrange {
+        Getrange {
+            key: "".to_owned(),
+            start: 0,
+            end: 0,
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+use bytes::Bytes;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Atomically set `key` to `value` and return the old value stored at `key`.
+///
+/// Any previous time to live associated with the key is discarded.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Getset {
+    key: String,
+    value: Bytes,
+    valid: bool,
+}
+
+impl Getset {
+    pub fn new(key: impl ToString, value: impl ToString) -> Getset {
+        Getset {
+            key: key.to_string(),
+            value: value.to_string().into(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .getset(&self.key, &self.value)
+            .await
+    }
+}
+
+impl Invalid for Getset {
+    fn new_invalid() -> Getset {
+        Getset {
+            key: "".to_owned(),
+            value: Bytes::new(),
+            valid: false,
+        }
+    }
+}
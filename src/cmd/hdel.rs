@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::rocks::hash::HashCommand;
 
 use serde::{Deserialize, Serialize};
@@ -29,7 +29,7 @@ impl Hdel {
         &self.key
     }
 
-    pub async fn execute(&self, client: &RocksClient) -> RocksResult<Frame> {
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
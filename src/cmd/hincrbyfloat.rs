@@ -0,0 +1,63 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR;
+use crate::rocks::hash::HashCommand;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Increment the floating point number stored at `field` in the hash `key` by `increment`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hincrbyfloat {
+    key: String,
+    field: String,
+    increment: f64,
+    valid: bool,
+}
+
+impl Hincrbyfloat {
+    pub fn new(key: impl ToString, field: impl ToString, increment: f64) -> Hincrbyfloat {
+        Hincrbyfloat {
+            key: key.to_string(),
+            field: field.to_string(),
+            increment,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if !self.increment.is_finite() {
+            return Ok(resp_err(REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR));
+        }
+        HashCommand::new(client)
+            .hincrbyfloat(&self.key, &self.field, self.increment)
+            .await
+    }
+}
+
+impl Invalid for Hincrbyfloat {
+    fn new_invalid() -> Hincrbyfloat {
+        Hincrbyfloat {
+            key: "".to_string(),
+            field: "".to_string(),
+            increment: 0.0,
+            valid: false,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::rocks::hash::HashCommand;
 use crate::rocks::kv::kvpair::KvPair;
 
@@ -41,7 +41,7 @@ impl Hset {
 
     pub async fn execute(
         &self,
-        client: &RocksClient,
+        client: ClientRef<'_>,
         is_hmset: bool,
         is_nx: bool,
     ) -> RocksResult<Frame> {
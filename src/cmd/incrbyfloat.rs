@@ -0,0 +1,60 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Increment the floating point number stored at `key` by `increment`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Incrbyfloat {
+    key: String,
+    increment: f64,
+    valid: bool,
+}
+
+impl Incrbyfloat {
+    pub fn new(key: impl ToString, increment: f64) -> Incrbyfloat {
+        Incrbyfloat {
+            key: key.to_string(),
+            increment,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if !self.increment.is_finite() {
+            return Ok(resp_err(REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR));
+        }
+        StringCommand::new(client)
+            .incr_by_float(&self.key, self.increment)
+            .await
+    }
+}
+
+impl Invalid for Incrbyfloat {
+    fn new_invalid() -> Incrbyfloat {
+        Incrbyfloat {
+            key: "".to_owned(),
+            increment: 0.0,
+            valid: false,
+        }
+    }
+}
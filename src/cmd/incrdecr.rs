@@ -1,6 +1,6 @@
 use crate::cmd::Invalid;
 
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::rocks::errors::DECREMENT_OVERFLOW;
 use crate::Frame;
 
@@ -30,7 +30,7 @@ impl IncrDecr {
         &self.key
     }
 
-    pub async fn execute(&mut self, client: &RocksClient, inc: bool) -> RocksResult<Frame> {
+    pub async fn execute(&mut self, client: ClientRef<'_>, inc: bool) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
@@ -1,4 +1,4 @@
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::Frame;
 use serde::{Deserialize, Serialize};
 
@@ -26,7 +26,7 @@ impl Keys {
         self.valid
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
@@ -0,0 +1,41 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::backup::BackupCommand;
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Return the unix time in seconds of the last successful backup.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lastsave {
+    valid: bool,
+}
+
+impl Lastsave {
+    pub fn new() -> Lastsave {
+        Lastsave { valid: true }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        BackupCommand::new(client.client()).lastsave().await
+    }
+}
+
+impl Default for Lastsave {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Invalid for Lastsave {
+    fn new_invalid() -> Self {
+        Lastsave { valid: false }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::rocks::list::ListCommand;
 use bytes::Bytes;
 
@@ -35,7 +35,12 @@ impl Linsert {
         }
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::rocks::list::ListCommand;
 
 use serde::{Deserialize, Serialize};
@@ -31,7 +31,7 @@ impl Lrange {
         &self.key
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
@@ -1,4 +1,4 @@
-use crate::{cmd::Invalid, rocks::client::RocksClient};
+use crate::{cmd::Invalid, rocks::client::ClientRef};
 
 use crate::rocks::string::StringCommand;
 use crate::utils::resp_invalid_arguments;
@@ -28,7 +28,15 @@ impl Mget {
         &self.keys
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    /// Return the command with the keys at `indices`, it is used to split the command by shard.
+    pub(crate) fn select(&self, indices: &[usize]) -> Mget {
+        Mget {
+            keys: indices.iter().map(|i| self.keys[*i].clone()).collect(),
+            valid: self.valid,
+        }
+    }
+
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
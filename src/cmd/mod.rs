@@ -5,7 +5,7 @@ pub use get::Get;
 use serde::{Deserialize, Serialize};
 
 mod set;
-pub use set::Set;
+pub use set::{Set, SetCondition};
 
 mod unknown;
 pub use unknown::Unknown;
@@ -29,11 +29,35 @@ mod incrdecr;
 pub use incrdecr::IncrDecr;
 
 mod expire;
-pub use expire::Expire;
+pub use expire::{Expire, ExpireCondition};
 
 mod ttl;
 pub use ttl::TTL;
 
+mod persist;
+pub use persist::Persist;
+
+mod rename;
+pub use rename::Rename;
+
+mod copy;
+pub use copy::Copy;
+
+mod touch;
+pub use touch::Touch;
+
+mod randomkey;
+pub use randomkey::Randomkey;
+
+mod dbsize;
+pub use dbsize::Dbsize;
+
+mod dump;
+pub use dump::Dump;
+
+mod restore;
+pub use restore::Restore;
+
 mod del;
 pub use del::Del;
 
@@ -124,6 +148,9 @@ pub use hdel::Hdel;
 mod hincrby;
 pub use hincrby::Hincrby;
 
+mod hincrbyfloat;
+pub use hincrbyfloat::Hincrbyfloat;
+
 mod zadd;
 pub use zadd::Zadd;
 
@@ -166,14 +193,116 @@ pub use zremrangebyscore::Zremrangebyscore;
 mod keys;
 pub use keys::Keys;
 
+mod append;
+pub use append::Append;
+
+mod getrange;
+pub use getrange::Getrange;
+
+mod setrange;
+pub use setrange::Setrange;
+
+mod getset;
+pub use getset::Getset;
+
+mod getdel;
+pub use getdel::Getdel;
+
+mod getex;
+pub use getex::Getex;
+
+mod setex;
+pub use setex::Setex;
+
+mod incrbyfloat;
+pub use incrbyfloat::Incrbyfloat;
+
+mod setbit;
+pub use setbit::Setbit;
+
+mod getbit;
+pub use getbit::Getbit;
+
+mod bitcount;
+pub use bitcount::{BitUnit, Bitcount};
+
+mod bitpos;
+pub use bitpos::Bitpos;
+
+mod bitop;
+pub use bitop::{BitOperation, Bitop};
+
+mod bitfield;
+pub use bitfield::{Bitfield, BitfieldOp, BitfieldOverflow, BitfieldType};
+
+mod pfadd;
+pub use pfadd::Pfadd;
+
+mod pfcount;
+pub use pfcount::Pfcount;
+
+mod pfmerge;
+pub use pfmerge::Pfmerge;
+
+mod geoadd;
+pub use geoadd::Geoadd;
+
+mod geopos;
+pub use geopos::Geopos;
+
+mod geodist;
+pub use geodist::{GeoUnit, Geodist};
+
+mod geosearch;
+pub use geosearch::{GeoFrom, GeoSearchOptions, GeoShape, GeoSort, Geosearch};
+
+mod geosearchstore;
+pub use geosearchstore::Geosearchstore;
+
+mod bgsave;
+pub use bgsave::Bgsave;
+
+mod lastsave;
+pub use lastsave::Lastsave;
+
 mod dogc;
 pub use dogc::Gc;
 
+mod cluster;
+pub use cluster::{Cluster, ClusterOp, SlotState};
+
+mod asking;
+pub use asking::Asking;
+
+mod publish;
+pub use publish::Publish;
+
+mod pubsub;
+pub use pubsub::{Pubsub, PubsubOp};
+
+mod eval;
+pub use eval::Eval;
+
+mod script;
+pub use script::{Script, ScriptOp};
+
+mod function;
+pub use function::{Function, FunctionOp};
+
+mod fcall;
+pub use fcall::Fcall;
+
+mod parse;
+
 use crate::config::txn_retry_count;
 use crate::db::DBInner;
+use crate::rocks::client::ClientRef;
+use crate::shard;
 use crate::Frame;
 
 use crate::rocks::Result as RocksResult;
+use crate::utils::timestamp_from_ttl;
+use std::iter::once;
 
 /// Enumeration of supported Redis commands.
 ///
@@ -190,14 +319,48 @@ pub enum Command {
     Exists(Exists),
     Incr(IncrDecr),
     Decr(IncrDecr),
+    Incrby(IncrDecr),
+    Decrby(IncrDecr),
+    Incrbyfloat(Incrbyfloat),
     Expire(Expire),
     ExpireAt(Expire),
     Pexpire(Expire),
     PexpireAt(Expire),
     TTL(TTL),
     PTTL(TTL),
+    ExpireTime(TTL),
+    PexpireTime(TTL),
+    Persist(Persist),
+    Rename(Rename),
+    Renamenx(Rename),
+    Copy(Copy),
+    Touch(Touch),
+    Randomkey(Randomkey),
+    Dbsize(Dbsize),
+    Dump(Dump),
+    Restore(Restore),
     Scan(Scan),
     Keys(Keys),
+    Append(Append),
+    Getrange(Getrange),
+    Setrange(Setrange),
+    Getset(Getset),
+    Getdel(Getdel),
+    Getex(Getex),
+    Msetnx(Mset),
+    Setex(Setex),
+    Psetex(Setex),
+    Setbit(Setbit),
+    Getbit(Getbit),
+    Bitcount(Bitcount),
+    Bitpos(Bitpos),
+    Bitop(Bitop),
+    Bitfield(Bitfield),
+
+    // hyperloglog
+    Pfadd(Pfadd),
+    Pfcount(Pfcount),
+    Pfmerge(Pfmerge),
 
     // set
     Sadd(Sadd),
@@ -234,6 +397,7 @@ pub enum Command {
     Hkeys(Hkeys),
     Hvals(Hvals),
     Hincrby(Hincrby),
+    Hincrbyfloat(Hincrbyfloat),
     Hexists(Hexists),
     Hstrlen(Hstrlen),
 
@@ -254,21 +418,84 @@ pub enum Command {
     Zrank(Zrank),
     Zincrby(Zincrby),
 
+    // geo
+    Geoadd(Geoadd),
+    Geopos(Geopos),
+    Geodist(Geodist),
+    Geosearch(Geosearch),
+    Geosearchstore(Geosearchstore),
+
+    // backup
+    Bgsave(Bgsave),
+    Lastsave(Lastsave),
+
     Gc(Gc),
 
+    // cluster
+    Cluster(Cluster),
+    Asking(Asking),
+
+    // pub/sub
+    Publish(Publish),
+    Pubsub(Pubsub),
+
+    // scripting
+    Eval(Eval),
+    EvalRo(Eval),
+    Evalsha(Eval),
+    EvalshaRo(Eval),
+    Script(Script),
+    Function(Function),
+    Fcall(Fcall),
+    FcallRo(Fcall),
+
     Unknown(Unknown),
 }
 
 impl Command {
-    pub(crate) async fn execute(mut self, inner_db: &DBInner) -> crate::Result<Frame> {
-        use Command::*;
+    pub(crate) async fn execute(self, inner_db: &DBInner) -> crate::Result<Frame> {
+        let shards: Vec<ClientRef> = inner_db.shards.iter().map(ClientRef::from).collect();
+        self.execute_in(inner_db, &shards).await
+    }
 
-        let client = &inner_db.client;
+    /// Execute the command on `shards`, which are the shards of `inner_db`, or the shards
+    /// with the txns of a snapshot.
+    pub(crate) async fn execute_in(
+        self,
+        inner_db: &DBInner,
+        shards: &[ClientRef<'_>],
+    ) -> crate::Result<Frame> {
+        // the commands executed with the state of the db instead of a shard
+        match &self {
+            Command::Cluster(cmd) => return Ok(cmd.execute(inner_db).await?),
+            Command::Publish(cmd) => return Ok(cmd.execute(&inner_db.pubsub).await),
+            Command::Pubsub(cmd) => return Ok(cmd.execute(&inner_db.pubsub)),
+            Command::Eval(cmd) => return Ok(cmd.execute(inner_db, shards, false, false).await?),
+            Command::EvalRo(cmd) => return Ok(cmd.execute(inner_db, shards, false, true).await?),
+            Command::Evalsha(cmd) => return Ok(cmd.execute(inner_db, shards, true, false).await?),
+            Command::EvalshaRo(cmd) => {
+                return Ok(cmd.execute(inner_db, shards, true, true).await?)
+            }
+            Command::Script(cmd) => return Ok(cmd.execute(inner_db).await?),
+            Command::Function(cmd) => return Ok(cmd.execute(inner_db).await?),
+            Command::Fcall(cmd) => return Ok(cmd.execute(inner_db, shards, false).await?),
+            Command::FcallRo(cmd) => return Ok(cmd.execute(inner_db, shards, true).await?),
+            _ => {}
+        }
+        if shards.len() > 1 {
+            return shard::execute(self, shards).await;
+        }
+        self.execute_on(shards[0]).await
+    }
+
+    /// Execute the command on the db of a shard.
+    pub(crate) async fn execute_on(mut self, client: ClientRef<'_>) -> crate::Result<Frame> {
+        use Command::*;
 
         let response = match &mut self {
             Get(cmd) => cmd.execute(client).await,
             Mget(cmd) => cmd.execute(client).await,
-            Mset(cmd) => cmd.execute(client).await,
+            Mset(cmd) => cmd.execute(client, false).await,
             Set(cmd) => cmd.execute(client).await,
             Del(cmd) => cmd.execute(client).await,
             Strlen(cmd) => cmd.execute(client).await,
@@ -276,14 +503,46 @@ impl Command {
             Exists(cmd) => cmd.execute(client).await,
             Incr(cmd) => cmd.execute(client, true).await,
             Decr(cmd) => cmd.execute(client, false).await,
+            Incrby(cmd) => cmd.execute(client, true).await,
+            Decrby(cmd) => cmd.execute(client, false).await,
+            Incrbyfloat(cmd) => cmd.execute(client).await,
             Expire(cmd) => cmd.execute(client, false, false).await,
             ExpireAt(cmd) => cmd.execute(client, false, true).await,
             Pexpire(cmd) => cmd.execute(client, true, false).await,
             PexpireAt(cmd) => cmd.execute(client, true, true).await,
-            TTL(cmd) => cmd.execute(client, false).await,
-            PTTL(cmd) => cmd.execute(client, true).await,
+            TTL(cmd) => cmd.execute(client, false, false).await,
+            PTTL(cmd) => cmd.execute(client, true, false).await,
+            ExpireTime(cmd) => cmd.execute(client, false, true).await,
+            PexpireTime(cmd) => cmd.execute(client, true, true).await,
+            Persist(cmd) => cmd.execute(client).await,
+            Rename(cmd) => cmd.execute(client, false).await,
+            Renamenx(cmd) => cmd.execute(client, true).await,
+            Copy(cmd) => cmd.execute(client).await,
+            Touch(cmd) => cmd.execute(client).await,
+            Randomkey(cmd) => cmd.execute(client).await,
+            Dbsize(cmd) => cmd.execute(client).await,
+            Dump(cmd) => cmd.execute(client).await,
+            Restore(cmd) => cmd.execute(client).await,
             Scan(cmd) => cmd.execute(client).await,
             Keys(cmd) => cmd.execute(client).await,
+            Append(cmd) => cmd.execute(client).await,
+            Getrange(cmd) => cmd.execute(client).await,
+            Setrange(cmd) => cmd.execute(client).await,
+            Getset(cmd) => cmd.execute(client).await,
+            Getdel(cmd) => cmd.execute(client).await,
+            Getex(cmd) => cmd.execute(client).await,
+            Msetnx(cmd) => cmd.execute(client, true).await,
+            Setex(cmd) => cmd.execute(client, false).await,
+            Psetex(cmd) => cmd.execute(client, true).await,
+            Setbit(cmd) => cmd.execute(client).await,
+            Getbit(cmd) => cmd.execute(client).await,
+            Bitcount(cmd) => cmd.execute(client).await,
+            Bitpos(cmd) => cmd.execute(client).await,
+            Bitop(cmd) => cmd.execute(client).await,
+            Bitfield(cmd) => cmd.execute(client).await,
+            Pfadd(cmd) => cmd.execute(client).await,
+            Pfcount(cmd) => cmd.execute(client).await,
+            Pfmerge(cmd) => cmd.execute(client).await,
             Sadd(cmd) => cmd.execute(client).await,
             Scard(cmd) => cmd.execute(client).await,
             Sismember(cmd) => cmd.execute(client).await,
@@ -314,6 +573,7 @@ impl Command {
             Hkeys(cmd) => cmd.execute(client).await,
             Hvals(cmd) => cmd.execute(client).await,
             Hincrby(cmd) => cmd.execute(client).await,
+            Hincrbyfloat(cmd) => cmd.execute(client).await,
             Hexists(cmd) => cmd.execute(client).await,
             Hstrlen(cmd) => cmd.execute(client).await,
             Zadd(cmd) => cmd.execute(client).await,
@@ -331,14 +591,231 @@ impl Command {
             Zpopmax(cmd) => cmd.execute(client, false).await,
             Zrank(cmd) => cmd.execute(client).await,
             Zincrby(cmd) => cmd.execute(client).await,
+            Geoadd(cmd) => cmd.execute(client).await,
+            Geopos(cmd) => cmd.execute(client).await,
+            Geodist(cmd) => cmd.execute(client).await,
+            Geosearch(cmd) => cmd.execute(client).await,
+            Geosearchstore(cmd) => cmd.execute(client).await,
+
+            Bgsave(cmd) => cmd.execute(client).await,
+            Lastsave(cmd) => cmd.execute(client).await,
 
             Gc(cmd) => cmd.execute(client).await,
 
+            Asking(cmd) => Ok(cmd.execute()),
+
+            Cluster(_) | Publish(_) | Pubsub(_) | Eval(_) | EvalRo(_) | Evalsha(_)
+            | EvalshaRo(_) | Script(_) | Function(_) | Fcall(_) | FcallRo(_) => {
+                unreachable!("executed with the state of the db by `Command::execute`")
+            }
+
             Unknown(cmd) => cmd.apply().await,
         }?;
 
         Ok(response)
     }
+
+    /// Return the keys of the command, which are used to route the command to its shard.
+    pub fn keys(&self) -> Vec<&str> {
+        use Command::*;
+
+        match self {
+            Mget(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Del(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Exists(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Touch(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Pfcount(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Mset(cmd) | Msetnx(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Bitop(cmd) => once(cmd.destkey())
+                .chain(cmd.keys().iter().map(String::as_str))
+                .collect(),
+            Pfmerge(cmd) => once(cmd.destkey())
+                .chain(cmd.sourcekeys().iter().map(String::as_str))
+                .collect(),
+            Rename(cmd) | Renamenx(cmd) => vec![cmd.key(), cmd.newkey()],
+            Copy(cmd) => vec![cmd.source(), cmd.destination()],
+            Geosearchstore(cmd) => vec![cmd.destination(), cmd.source()],
+            Randomkey(_) | Dbsize(_) | Scan(_) | Keys(_) | Bgsave(_) | Lastsave(_) | Gc(_)
+            | Cluster(_) | Asking(_) | Publish(_) | Pubsub(_) | Script(_) | Function(_)
+            | Unknown(_) => vec![],
+            Eval(cmd) | EvalRo(cmd) | Evalsha(cmd) | EvalshaRo(cmd) => {
+                cmd.keys().iter().map(String::as_str).collect()
+            }
+            Fcall(cmd) | FcallRo(cmd) => cmd.keys().iter().map(String::as_str).collect(),
+            Get(cmd) => vec![cmd.key()],
+            Set(cmd) => vec![cmd.key()],
+            Strlen(cmd) => vec![cmd.key()],
+            Type(cmd) => vec![cmd.key()],
+            Incr(cmd) | Decr(cmd) | Incrby(cmd) | Decrby(cmd) => vec![cmd.key()],
+            Incrbyfloat(cmd) => vec![cmd.key()],
+            Expire(cmd) | ExpireAt(cmd) | Pexpire(cmd) | PexpireAt(cmd) => vec![cmd.key()],
+            TTL(cmd) | PTTL(cmd) | ExpireTime(cmd) | PexpireTime(cmd) => vec![cmd.key()],
+            Persist(cmd) => vec![cmd.key()],
+            Dump(cmd) => vec![cmd.key()],
+            Restore(cmd) => vec![cmd.key()],
+            Append(cmd) => vec![cmd.key()],
+            Getrange(cmd) => vec![cmd.key()],
+            Setrange(cmd) => vec![cmd.key()],
+            Getset(cmd) => vec![cmd.key()],
+            Getdel(cmd) => vec![cmd.key()],
+            Getex(cmd) => vec![cmd.key()],
+            Setex(cmd) | Psetex(cmd) => vec![cmd.key()],
+            Setbit(cmd) => vec![cmd.key()],
+            Getbit(cmd) => vec![cmd.key()],
+            Bitcount(cmd) => vec![cmd.key()],
+            Bitpos(cmd) => vec![cmd.key()],
+            Bitfield(cmd) => vec![cmd.key()],
+            Pfadd(cmd) => vec![cmd.key()],
+            Sadd(cmd) => vec![cmd.key()],
+            Scard(cmd) => vec![cmd.key()],
+            Sismember(cmd) => vec![cmd.key()],
+            Smismember(cmd) => vec![cmd.key()],
+            Smembers(cmd) => vec![cmd.key()],
+            Srandmember(cmd) => vec![cmd.key()],
+            Spop(cmd) => vec![cmd.key()],
+            Srem(cmd) => vec![cmd.key()],
+            Lpush(cmd) | Rpush(cmd) => vec![cmd.key()],
+            Lpop(cmd) | Rpop(cmd) => vec![cmd.key()],
+            Lrange(cmd) => vec![cmd.key()],
+            Ltrim(cmd) => vec![cmd.key()],
+            Llen(cmd) => vec![cmd.key()],
+            Lindex(cmd) => vec![cmd.key()],
+            Lset(cmd) => vec![cmd.key()],
+            Lrem(cmd) => vec![cmd.key()],
+            Linsert(cmd) => vec![cmd.key()],
+            Hset(cmd) | Hmset(cmd) | Hsetnx(cmd) => vec![cmd.key()],
+            Hget(cmd) => vec![cmd.key()],
+            Hmget(cmd) => vec![cmd.key()],
+            Hlen(cmd) => vec![cmd.key()],
+            Hgetall(cmd) => vec![cmd.key()],
+            Hdel(cmd) => vec![cmd.key()],
+            Hkeys(cmd) => vec![cmd.key()],
+            Hvals(cmd) => vec![cmd.key()],
+            Hincrby(cmd) => vec![cmd.key()],
+            Hincrbyfloat(cmd) => vec![cmd.key()],
+            Hexists(cmd) => vec![cmd.key()],
+            Hstrlen(cmd) => vec![cmd.key()],
+            Zadd(cmd) => vec![cmd.key()],
+            Zcard(cmd) => vec![cmd.key()],
+            Zscore(cmd) => vec![cmd.key()],
+            Zrem(cmd) => vec![cmd.key()],
+            Zremrangebyscore(cmd) => vec![cmd.key()],
+            Zremrangebyrank(cmd) => vec![cmd.key()],
+            Zrange(cmd) => vec![cmd.key()],
+            Zrevrange(cmd) => vec![cmd.key()],
+            Zrangebyscore(cmd) | Zrevrangebyscore(cmd) => vec![cmd.key()],
+            Zcount(cmd) => vec![cmd.key()],
+            Zpopmin(cmd) | Zpopmax(cmd) => vec![cmd.key()],
+            Zrank(cmd) => vec![cmd.key()],
+            Zincrby(cmd) => vec![cmd.key()],
+            Geoadd(cmd) => vec![cmd.key()],
+            Geopos(cmd) => vec![cmd.key()],
+            Geodist(cmd) => vec![cmd.key()],
+            Geosearch(cmd) => vec![cmd.key()],
+        }
+    }
+
+    /// Return the command with the relative expire times replaced by the absolute ones, so
+    /// it sets the same ttl when it is executed later, like the entries of the raft log.
+    pub(crate) fn with_absolute_expire(self) -> Command {
+        use Command::*;
+        match self {
+            Expire(cmd) => cmd.absolute(false).map_or(Expire(cmd), PexpireAt),
+            Pexpire(cmd) => cmd.absolute(true).map_or(Pexpire(cmd), PexpireAt),
+            Set(cmd) => Set(cmd.absolute()),
+            Getex(cmd) => Getex(cmd.absolute()),
+            Setex(cmd) => cmd.absolute(false).map_or(Setex(cmd), Set),
+            Psetex(cmd) => cmd.absolute(true).map_or(Psetex(cmd), Set),
+            cmd => cmd,
+        }
+    }
+
+    /// Return false if the result of the command depends on the random numbers or the time it
+    /// runs at, the scripts applied by the raft members can't call these commands.
+    pub(crate) fn is_deterministic(&self) -> bool {
+        use Command::*;
+
+        let relative = |expiration: Option<Expiration>| {
+            matches!(expiration, Some(Expiration::Ex(_) | Expiration::Px(_)))
+        };
+        match self {
+            Randomkey(_) | Srandmember(_) | Spop(_) | TTL(_) | PTTL(_) => false,
+            Expire(_) | Pexpire(_) | Setex(_) | Psetex(_) => false,
+            Set(cmd) => !relative(cmd.expiration()),
+            Getex(cmd) => !relative(cmd.expiration()),
+            _ => true,
+        }
+    }
+
+    /// Return true if the command may modify the data, these commands are recorded in
+    /// the journal, except the gc, and proposed to the raft log.
+    pub fn is_write(&self) -> bool {
+        use Command::*;
+
+        matches!(
+            self,
+            Mset(_)
+                | Set(_)
+                | Del(_)
+                | Incr(_)
+                | Decr(_)
+                | Incrby(_)
+                | Decrby(_)
+                | Incrbyfloat(_)
+                | Expire(_)
+                | ExpireAt(_)
+                | Pexpire(_)
+                | PexpireAt(_)
+                | Persist(_)
+                | Rename(_)
+                | Renamenx(_)
+                | Copy(_)
+                | Restore(_)
+                | Append(_)
+                | Setrange(_)
+                | Getset(_)
+                | Getdel(_)
+                | Getex(_)
+                | Msetnx(_)
+                | Setex(_)
+                | Psetex(_)
+                | Setbit(_)
+                | Bitop(_)
+                | Bitfield(_)
+                | Pfadd(_)
+                | Pfmerge(_)
+                | Sadd(_)
+                | Spop(_)
+                | Srem(_)
+                | Lpush(_)
+                | Rpush(_)
+                | Lpop(_)
+                | Rpop(_)
+                | Ltrim(_)
+                | Lset(_)
+                | Lrem(_)
+                | Linsert(_)
+                | Hset(_)
+                | Hmset(_)
+                | Hsetnx(_)
+                | Hdel(_)
+                | Hincrby(_)
+                | Hincrbyfloat(_)
+                | Zadd(_)
+                | Zrem(_)
+                | Zremrangebyscore(_)
+                | Zremrangebyrank(_)
+                | Zpopmin(_)
+                | Zpopmax(_)
+                | Zincrby(_)
+                | Geoadd(_)
+                | Geosearchstore(_)
+                | Gc(_)
+                | Eval(_)
+                | Evalsha(_)
+                | Fcall(_)
+        ) || matches!(self, Function(cmd) if !matches!(cmd.op(), FunctionOp::List { .. }))
+    }
 }
 
 impl From<&str> for Command {
@@ -353,6 +830,50 @@ impl From<&Command> for String {
     }
 }
 
+/// Expiration options of the commands which may update the time to live of a key.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    /// Expire after the given seconds
+    Ex(i64),
+    /// Expire after the given milliseconds
+    Px(i64),
+    /// Expire at the given unix time in seconds
+    ExAt(i64),
+    /// Expire at the given unix time in milliseconds
+    PxAt(i64),
+    /// Remove the time to live
+    Persist,
+}
+
+impl Expiration {
+    /// Convert to the expire timestamp in milliseconds saved with the key,
+    /// `0` means no ttl. `None` is returned if the expire time is invalid.
+    pub(crate) fn timestamp(&self) -> Option<i64> {
+        match *self {
+            Expiration::Ex(secs) if secs > 0 => secs
+                .checked_mul(1000)
+                .and_then(|ms| ms.checked_add(timestamp_from_ttl(0))),
+            Expiration::Px(ms) if ms > 0 => ms.checked_add(timestamp_from_ttl(0)),
+            Expiration::ExAt(secs) if secs > 0 => secs.checked_mul(1000),
+            Expiration::PxAt(ms) if ms > 0 => Some(ms),
+            Expiration::Persist => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Replace a relative expire time with the absolute one, an invalid expire time is kept
+    /// for the error of the command.
+    pub(crate) fn absolute(self) -> Expiration {
+        match self {
+            Expiration::Ex(_) | Expiration::Px(_) => match self.timestamp() {
+                Some(ts) => Expiration::PxAt(ts),
+                None => self,
+            },
+            _ => self,
+        }
+    }
+}
+
 /// All commands should be implement new_invalid() for invalid check
 pub trait Invalid {
     fn new_invalid() -> Self;
@@ -360,7 +881,7 @@ pub trait Invalid {
 
 async fn retry_call<'a, F>(mut f: F) -> RocksResult<Frame>
 where
-    F: FnMut() -> BoxFuture<'a, RocksResult<Frame>> + Copy,
+    F: FnMut() -> BoxFuture<'a, RocksResult<Frame>> + std::marker::Copy,
 {
     let mut retry = txn_retry_count();
     let mut res = Frame::Null;
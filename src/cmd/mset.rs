@@ -1,4 +1,4 @@
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::{cmd::Invalid, rocks::encoding::KeyEncoder};
 
 use crate::rocks::kv::kvpair::KvPair;
@@ -32,14 +32,28 @@ impl Mset {
         &self.keys
     }
 
+    /// Return the command with the keys at `indices`, it is used to split the command by shard.
+    pub(crate) fn select(&self, indices: &[usize]) -> Mset {
+        Mset {
+            keys: indices.iter().map(|i| self.keys[*i].clone()).collect(),
+            vals: indices.iter().map(|i| self.vals[*i].clone()).collect(),
+            valid: self.valid,
+        }
+    }
+
     pub fn vals(&self) -> &Vec<Bytes> {
         &self.vals
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    pub async fn execute(&mut self, client: ClientRef<'_>, nx: bool) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
+        if nx {
+            return StringCommand::new(client)
+                .msetnx(&self.keys, &self.vals)
+                .await;
+        }
         let mut kvs = Vec::new();
         for (idx, key) in self.keys.iter().enumerate() {
             let val = &self.vals[idx];
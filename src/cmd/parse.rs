@@ -0,0 +1,791 @@
+use bytes::Bytes;
+
+use crate::cmd::*;
+use crate::rocks::errors::{
+    RError, REDIS_SYNTAX_ERR, REDIS_VALUE_IS_NOT_INTEGER_ERR, REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR,
+};
+
+/// Cursor over the arguments of a command in the Redis syntax.
+struct Args<'a> {
+    name: &'a str,
+    args: &'a [Vec<u8>],
+    pos: usize,
+}
+
+impl<'a> Args<'a> {
+    fn arity_error(&self) -> RError {
+        RError::wrong_arguments_error(self.name)
+    }
+
+    fn remaining(&self) -> usize {
+        self.args.len() - self.pos
+    }
+
+    fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    fn next_bytes(&mut self) -> Result<&'a [u8], RError> {
+        let arg = self.args.get(self.pos).ok_or_else(|| self.arity_error())?;
+        self.pos += 1;
+        Ok(arg)
+    }
+
+    fn next_str(&mut self) -> Result<String, RError> {
+        Ok(String::from_utf8_lossy(self.next_bytes()?).into_owned())
+    }
+
+    fn next_upper(&mut self) -> Result<String, RError> {
+        Ok(self.next_str()?.to_ascii_uppercase())
+    }
+
+    fn next_i64(&mut self) -> Result<i64, RError> {
+        std::str::from_utf8(self.next_bytes()?)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(REDIS_VALUE_IS_NOT_INTEGER_ERR)
+    }
+
+    fn next_f64(&mut self) -> Result<f64, RError> {
+        std::str::from_utf8(self.next_bytes()?)
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|f| !f.is_nan())
+            .ok_or(REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR)
+    }
+
+    /// Parse a score bound of the sorted set commands like `(1.5` or `-inf`.
+    fn next_score_bound(&mut self) -> Result<(f64, bool), RError> {
+        let arg = self.next_bytes()?;
+        let (arg, inclusive) = match arg.strip_prefix(b"(") {
+            Some(rest) => (rest, false),
+            None => (arg, true),
+        };
+        let score = std::str::from_utf8(arg)
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|f| !f.is_nan())
+            .ok_or(RError::String("ERR min or max is not a float"))?;
+        Ok((score, inclusive))
+    }
+
+    fn rest_strs(&mut self) -> Vec<String> {
+        let rest = self.args[self.pos..]
+            .iter()
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+        self.pos = self.args.len();
+        rest
+    }
+
+    fn rest_strs_non_empty(&mut self) -> Result<Vec<String>, RError> {
+        if self.is_empty() {
+            return Err(self.arity_error());
+        }
+        Ok(self.rest_strs())
+    }
+
+    fn done(&self) -> Result<(), RError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(REDIS_SYNTAX_ERR)
+        }
+    }
+}
+
+fn geo_unit(unit: &str) -> Result<GeoUnit, RError> {
+    match unit {
+        "M" => Ok(GeoUnit::M),
+        "KM" => Ok(GeoUnit::Km),
+        "FT" => Ok(GeoUnit::Ft),
+        "MI" => Ok(GeoUnit::Mi),
+        _ => Err(RError::String(
+            "ERR unsupported unit provided. please use M, KM, FT, MI",
+        )),
+    }
+}
+
+fn bitfield_type(ty: &str) -> Result<BitfieldType, RError> {
+    let (signed, bits) = match ty.as_bytes().first() {
+        Some(b'I') | Some(b'i') => (true, &ty[1..]),
+        Some(b'U') | Some(b'u') => (false, &ty[1..]),
+        _ => return Err(crate::rocks::errors::REDIS_BITFIELD_TYPE_ERR),
+    };
+    let bits: u8 = bits
+        .parse()
+        .map_err(|_| crate::rocks::errors::REDIS_BITFIELD_TYPE_ERR)?;
+    Ok(if signed {
+        BitfieldType::signed(bits)
+    } else {
+        BitfieldType::unsigned(bits)
+    })
+}
+
+// parse the offset of BITFIELD, `#n` is multiplied by the width of the type
+fn bitfield_offset(offset: &str, ty: &BitfieldType) -> Result<u64, RError> {
+    let res = match offset.strip_prefix('#') {
+        Some(n) => n
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(ty.bits as u64)),
+        None => offset.parse().ok(),
+    };
+    res.ok_or(crate::rocks::errors::REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR)
+}
+
+/// Convert the glob-style pattern of SCAN to the regex of `Scan`.
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+fn expiration(option: &str, args: &mut Args) -> Result<Option<Expiration>, RError> {
+    let expiration = match option {
+        "EX" => Expiration::Ex(args.next_i64()?),
+        "PX" => Expiration::Px(args.next_i64()?),
+        "EXAT" => Expiration::ExAt(args.next_i64()?),
+        "PXAT" => Expiration::PxAt(args.next_i64()?),
+        _ => return Ok(None),
+    };
+    Ok(Some(expiration))
+}
+
+fn parse_set(key: String, value: String, args: &mut Args) -> Result<Set, RError> {
+    let mut exp = None;
+    let mut condition = None;
+    let mut keep_ttl = false;
+    let mut get = false;
+    while !args.is_empty() {
+        let option = args.next_upper()?;
+        match option.as_str() {
+            "NX" if condition.is_none() => condition = Some(SetCondition::Nx),
+            "XX" if condition.is_none() => condition = Some(SetCondition::Xx),
+            "GET" => get = true,
+            "KEEPTTL" if exp.is_none() => keep_ttl = true,
+            _ if exp.is_none() && !keep_ttl => match expiration(&option, args)? {
+                Some(e) => exp = Some(e),
+                None => return Err(REDIS_SYNTAX_ERR),
+            },
+            _ => return Err(REDIS_SYNTAX_ERR),
+        }
+    }
+    Ok(Set::new_with_options(
+        key, value, exp, condition, keep_ttl, get,
+    ))
+}
+
+fn parse_geo_search(
+    args: &mut Args,
+) -> Result<(GeoFrom, GeoShape, GeoUnit, GeoSearchOptions, bool), RError> {
+    let mut from = None;
+    let mut shape = None;
+    let mut unit = GeoUnit::M;
+    let mut options = GeoSearchOptions::default();
+    let mut store_dist = false;
+    while !args.is_empty() {
+        match args.next_upper()?.as_str() {
+            "FROMMEMBER" => from = Some(GeoFrom::Member(args.next_str()?)),
+            "FROMLONLAT" => from = Some(GeoFrom::LonLat(args.next_f64()?, args.next_f64()?)),
+            "BYRADIUS" => {
+                shape = Some(GeoShape::Radius(args.next_f64()?));
+                unit = geo_unit(&args.next_upper()?)?;
+            }
+            "BYBOX" => {
+                shape = Some(GeoShape::Box(args.next_f64()?, args.next_f64()?));
+                unit = geo_unit(&args.next_upper()?)?;
+            }
+            "ASC" => options.sort = Some(GeoSort::Asc),
+            "DESC" => options.sort = Some(GeoSort::Desc),
+            "COUNT" => {
+                let count = args.next_i64()?;
+                if count <= 0 {
+                    return Err(crate::rocks::errors::REDIS_GEO_COUNT_ERR);
+                }
+                options.count = Some(count as u64);
+            }
+            "ANY" => options.any = true,
+            "WITHCOORD" => options.with_coord = true,
+            "WITHDIST" => options.with_dist = true,
+            "WITHHASH" => options.with_hash = true,
+            "STOREDIST" => store_dist = true,
+            _ => return Err(REDIS_SYNTAX_ERR),
+        }
+    }
+    match (from, shape) {
+        (Some(from), Some(shape)) => Ok((from, shape, unit, options, store_dist)),
+        _ => Err(REDIS_SYNTAX_ERR),
+    }
+}
+
+impl Command {
+    /// Parse a command from its arguments in the Redis syntax, the first argument is the name
+    /// of the command. The commands which are not supported are parsed as `Unknown`.
+    pub(crate) fn from_args(args: &[Vec<u8>]) -> Result<Command, RError> {
+        let Some(name) = args.first() else {
+            return Err(RError::String(
+                "ERR Please specify at least one argument for this call",
+            ));
+        };
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        let mut args = Args {
+            name: &name,
+            args,
+            pos: 1,
+        };
+        let cmd = parse_command(&name, &mut args)?;
+        args.done()?;
+        Ok(cmd)
+    }
+}
+
+fn parse_command(name: &str, args: &mut Args) -> Result<Command, RError> {
+    let cmd = match name {
+        // string
+        "get" => Command::Get(Get::new(args.next_str()?)),
+        "set" => {
+            let key = args.next_str()?;
+            let value = args.next_str()?;
+            Command::Set(parse_set(key, value, args)?)
+        }
+        "mget" => Command::Mget(Mget::new(&args.rest_strs_non_empty()?)),
+        "mset" | "msetnx" => {
+            let pairs = args.rest_strs_non_empty()?;
+            if pairs.len() % 2 != 0 {
+                return Err(args.arity_error());
+            }
+            let keys: Vec<_> = pairs.iter().step_by(2).collect();
+            let vals: Vec<_> = pairs.iter().skip(1).step_by(2).collect();
+            if name == "mset" {
+                Command::Mset(Mset::new(&keys, &vals))
+            } else {
+                Command::Msetnx(Mset::new(&keys, &vals))
+            }
+        }
+        "strlen" => Command::Strlen(Strlen::new(args.next_str()?)),
+        "incr" => Command::Incr(IncrDecr::new(args.next_str()?, 1)),
+        "decr" => Command::Decr(IncrDecr::new(args.next_str()?, 1)),
+        "incrby" => Command::Incrby(IncrDecr::new(args.next_str()?, args.next_i64()?)),
+        "decrby" => Command::Decrby(IncrDecr::new(args.next_str()?, args.next_i64()?)),
+        "incrbyfloat" => Command::Incrbyfloat(Incrbyfloat::new(args.next_str()?, args.next_f64()?)),
+        "append" => Command::Append(Append::new(args.next_str()?, args.next_str()?)),
+        "getrange" => Command::Getrange(Getrange::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_i64()?,
+        )),
+        "setrange" => Command::Setrange(Setrange::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_str()?,
+        )),
+        "getset" => Command::Getset(Getset::new(args.next_str()?, args.next_str()?)),
+        "getdel" => Command::Getdel(Getdel::new(args.next_str()?)),
+        "getex" => {
+            let key = args.next_str()?;
+            let exp = match args.is_empty() {
+                true => None,
+                false => match args.next_upper()?.as_str() {
+                    "PERSIST" => Some(Expiration::Persist),
+                    option => Some(expiration(option, args)?.ok_or(REDIS_SYNTAX_ERR)?),
+                },
+            };
+            Command::Getex(Getex::new(key, exp))
+        }
+        "setex" | "psetex" => {
+            let cmd = Setex::new(args.next_str()?, args.next_i64()?, args.next_str()?);
+            if name == "setex" {
+                Command::Setex(cmd)
+            } else {
+                Command::Psetex(cmd)
+            }
+        }
+        "setbit" => Command::Setbit(Setbit::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_i64()?,
+        )),
+        "getbit" => Command::Getbit(Getbit::new(args.next_str()?, args.next_i64()?)),
+        "bitcount" => {
+            let key = args.next_str()?;
+            let mut range = None;
+            let mut unit = BitUnit::Byte;
+            if !args.is_empty() {
+                range = Some((args.next_i64()?, args.next_i64()?));
+                if !args.is_empty() {
+                    unit = match args.next_upper()?.as_str() {
+                        "BYTE" => BitUnit::Byte,
+                        "BIT" => BitUnit::Bit,
+                        _ => return Err(REDIS_SYNTAX_ERR),
+                    };
+                }
+            }
+            Command::Bitcount(Bitcount::new(key, range, unit))
+        }
+        "bitpos" => {
+            let key = args.next_str()?;
+            let bit = args.next_i64()?;
+            let start = (!args.is_empty()).then(|| args.next_i64()).transpose()?;
+            let end = (!args.is_empty()).then(|| args.next_i64()).transpose()?;
+            let unit = match args.is_empty() {
+                true => BitUnit::Byte,
+                false => match args.next_upper()?.as_str() {
+                    "BYTE" => BitUnit::Byte,
+                    "BIT" => BitUnit::Bit,
+                    _ => return Err(REDIS_SYNTAX_ERR),
+                },
+            };
+            Command::Bitpos(Bitpos::new(key, bit, start, end, unit))
+        }
+        "bitop" => {
+            let op = match args.next_upper()?.as_str() {
+                "AND" => BitOperation::And,
+                "OR" => BitOperation::Or,
+                "XOR" => BitOperation::Xor,
+                "NOT" => BitOperation::Not,
+                _ => return Err(REDIS_SYNTAX_ERR),
+            };
+            let dest = args.next_str()?;
+            Command::Bitop(Bitop::new(op, dest, &args.rest_strs_non_empty()?))
+        }
+        "bitfield" => {
+            let key = args.next_str()?;
+            let mut ops = vec![];
+            while !args.is_empty() {
+                let op = match args.next_upper()?.as_str() {
+                    "GET" => {
+                        let ty = bitfield_type(&args.next_str()?)?;
+                        let offset = bitfield_offset(&args.next_str()?, &ty)?;
+                        BitfieldOp::Get { ty, offset }
+                    }
+                    "SET" => {
+                        let ty = bitfield_type(&args.next_str()?)?;
+                        let offset = bitfield_offset(&args.next_str()?, &ty)?;
+                        let value = args.next_i64()?;
+                        BitfieldOp::Set { ty, offset, value }
+                    }
+                    "INCRBY" => {
+                        let ty = bitfield_type(&args.next_str()?)?;
+                        let offset = bitfield_offset(&args.next_str()?, &ty)?;
+                        let increment = args.next_i64()?;
+                        BitfieldOp::Incrby {
+                            ty,
+                            offset,
+                            increment,
+                        }
+                    }
+                    "OVERFLOW" => BitfieldOp::Overflow(match args.next_upper()?.as_str() {
+                        "WRAP" => BitfieldOverflow::Wrap,
+                        "SAT" => BitfieldOverflow::Sat,
+                        "FAIL" => BitfieldOverflow::Fail,
+                        _ => return Err(RError::String("ERR Invalid OVERFLOW type specified")),
+                    }),
+                    _ => return Err(REDIS_SYNTAX_ERR),
+                };
+                ops.push(op);
+            }
+            Command::Bitfield(Bitfield::new(key, ops))
+        }
+
+        // keys
+        "del" | "unlink" => Command::Del(Del::new(&args.rest_strs_non_empty()?)),
+        "exists" => Command::Exists(Exists::new(&args.rest_strs_non_empty()?)),
+        "touch" => Command::Touch(Touch::new(&args.rest_strs_non_empty()?)),
+        "type" => Command::Type(Type::new(args.next_str()?)),
+        "expire" | "expireat" | "pexpire" | "pexpireat" => {
+            let key = args.next_str()?;
+            let time = args.next_i64()?;
+            let condition = match args.is_empty() {
+                true => None,
+                false => Some(match args.next_upper()?.as_str() {
+                    "NX" => ExpireCondition::Nx,
+                    "XX" => ExpireCondition::Xx,
+                    "GT" => ExpireCondition::Gt,
+                    "LT" => ExpireCondition::Lt,
+                    _ => return Err(REDIS_SYNTAX_ERR),
+                }),
+            };
+            let cmd = Expire::new_with_condition(key, time, condition);
+            match name {
+                "expire" => Command::Expire(cmd),
+                "expireat" => Command::ExpireAt(cmd),
+                "pexpire" => Command::Pexpire(cmd),
+                _ => Command::PexpireAt(cmd),
+            }
+        }
+        "ttl" => Command::TTL(TTL::new(args.next_str()?)),
+        "pttl" => Command::PTTL(TTL::new(args.next_str()?)),
+        "expiretime" => Command::ExpireTime(TTL::new(args.next_str()?)),
+        "pexpiretime" => Command::PexpireTime(TTL::new(args.next_str()?)),
+        "persist" => Command::Persist(Persist::new(args.next_str()?)),
+        "rename" => Command::Rename(Rename::new(args.next_str()?, args.next_str()?)),
+        "renamenx" => Command::Renamenx(Rename::new(args.next_str()?, args.next_str()?)),
+        "copy" => {
+            let source = args.next_str()?;
+            let destination = args.next_str()?;
+            let replace = match args.is_empty() {
+                true => false,
+                false if args.next_upper()? == "REPLACE" => true,
+                false => return Err(REDIS_SYNTAX_ERR),
+            };
+            Command::Copy(Copy::new(source, destination, replace))
+        }
+        "randomkey" => Command::Randomkey(Randomkey::new()),
+        "dbsize" => Command::Dbsize(Dbsize::new()),
+        "dump" => Command::Dump(Dump::new(args.next_str()?)),
+        "restore" => {
+            let key = args.next_str()?;
+            let ttl = args.next_i64()?;
+            let value = Bytes::copy_from_slice(args.next_bytes()?);
+            let replace = match args.is_empty() {
+                true => false,
+                false if args.next_upper()? == "REPLACE" => true,
+                false => return Err(REDIS_SYNTAX_ERR),
+            };
+            Command::Restore(Restore::new(key, ttl, value, replace))
+        }
+        "keys" => {
+            let pattern = args.next_str()?;
+            if glob::Pattern::new(&pattern).is_err() {
+                return Err(RError::String("ERR invalid pattern"));
+            }
+            Command::Keys(Keys::new(pattern))
+        }
+        "scan" => {
+            let cursor = args.next_str()?;
+            let mut count = 10;
+            let mut regex = String::from(".*");
+            while !args.is_empty() {
+                match args.next_upper()?.as_str() {
+                    "MATCH" => regex = glob_to_regex(&args.next_str()?),
+                    "COUNT" => count = args.next_i64()?.max(1),
+                    _ => return Err(REDIS_SYNTAX_ERR),
+                }
+            }
+            // the cursor is the key to start from, "0" starts from the first key
+            let start = if cursor == "0" { String::new() } else { cursor };
+            Command::Scan(Scan::new(start, count, regex))
+        }
+
+        // hyperloglog
+        "pfadd" => {
+            let key = args.next_str()?;
+            Command::Pfadd(Pfadd::new(key, &args.rest_strs()))
+        }
+        "pfcount" => Command::Pfcount(Pfcount::new(&args.rest_strs_non_empty()?)),
+        "pfmerge" => {
+            let dest = args.next_str()?;
+            Command::Pfmerge(Pfmerge::new(dest, &args.rest_strs()))
+        }
+
+        // set
+        "sadd" => {
+            let key = args.next_str()?;
+            Command::Sadd(Sadd::new(key, &args.rest_strs_non_empty()?))
+        }
+        "srem" => {
+            let key = args.next_str()?;
+            Command::Srem(Srem::new(key, &args.rest_strs_non_empty()?))
+        }
+        "smismember" => {
+            let key = args.next_str()?;
+            Command::Smismember(Smismember::new(key, &args.rest_strs_non_empty()?))
+        }
+        "scard" => Command::Scard(Scard::new(args.next_str()?)),
+        "sismember" => Command::Sismember(Sismember::new(args.next_str()?, args.next_str()?)),
+        "smembers" => Command::Smembers(Smembers::new(args.next_str()?)),
+        "srandmember" => {
+            let key = args.next_str()?;
+            let count = (!args.is_empty()).then(|| args.next_i64()).transpose()?;
+            Command::Srandmember(Srandmember::new(key, count))
+        }
+        "spop" => {
+            let key = args.next_str()?;
+            let count = (!args.is_empty()).then(|| args.next_i64()).transpose()?;
+            Command::Spop(Spop::new(key, count.unwrap_or(1)))
+        }
+
+        // list
+        "lpush" | "rpush" => {
+            let key = args.next_str()?;
+            let cmd = Push::new(key, &args.rest_strs_non_empty()?);
+            if name == "lpush" {
+                Command::Lpush(cmd)
+            } else {
+                Command::Rpush(cmd)
+            }
+        }
+        "lpop" | "rpop" => {
+            let key = args.next_str()?;
+            let count = (!args.is_empty()).then(|| args.next_i64()).transpose()?;
+            let cmd = Pop::new(key, count.unwrap_or(1));
+            if name == "lpop" {
+                Command::Lpop(cmd)
+            } else {
+                Command::Rpop(cmd)
+            }
+        }
+        "lrange" => Command::Lrange(Lrange::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_i64()?,
+        )),
+        "ltrim" => Command::Ltrim(Ltrim::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_i64()?,
+        )),
+        "llen" => Command::Llen(Llen::new(args.next_str()?)),
+        "lindex" => Command::Lindex(Lindex::new(args.next_str()?, args.next_i64()?)),
+        "lset" => Command::Lset(Lset::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_str()?,
+        )),
+        "lrem" => Command::Lrem(Lrem::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_str()?,
+        )),
+        "linsert" => {
+            let key = args.next_str()?;
+            let before = match args.next_upper()?.as_str() {
+                "BEFORE" => true,
+                "AFTER" => false,
+                _ => return Err(REDIS_SYNTAX_ERR),
+            };
+            Command::Linsert(Linsert::new(
+                key,
+                before,
+                args.next_str()?,
+                args.next_str()?,
+            ))
+        }
+
+        // hash
+        "hset" | "hmset" | "hsetnx" => {
+            let key = args.next_str()?;
+            let rest = args.rest_strs_non_empty()?;
+            if rest.len() % 2 != 0 || (name == "hsetnx" && rest.len() != 2) {
+                return Err(args.arity_error());
+            }
+            let pairs: Vec<_> = rest
+                .chunks(2)
+                .map(|pair| (pair[0].clone(), pair[1].clone()))
+                .collect();
+            let cmd = Hset::new(key, &pairs);
+            match name {
+                "hset" => Command::Hset(cmd),
+                "hmset" => Command::Hmset(cmd),
+                _ => Command::Hsetnx(cmd),
+            }
+        }
+        "hget" => Command::Hget(Hget::new(args.next_str()?, args.next_str()?)),
+        "hmget" => {
+            let key = args.next_str()?;
+            Command::Hmget(Hmget::new(key, &args.rest_strs_non_empty()?))
+        }
+        "hdel" => {
+            let key = args.next_str()?;
+            Command::Hdel(Hdel::new(key, &args.rest_strs_non_empty()?))
+        }
+        "hlen" => Command::Hlen(Hlen::new(args.next_str()?)),
+        "hgetall" => Command::Hgetall(Hgetall::new(args.next_str()?)),
+        "hkeys" => Command::Hkeys(Hkeys::new(args.next_str()?)),
+        "hvals" => Command::Hvals(Hvals::new(args.next_str()?)),
+        "hincrby" => Command::Hincrby(Hincrby::new(
+            args.next_str()?,
+            args.next_str()?,
+            args.next_i64()?,
+        )),
+        "hincrbyfloat" => Command::Hincrbyfloat(Hincrbyfloat::new(
+            args.next_str()?,
+            args.next_str()?,
+            args.next_f64()?,
+        )),
+        "hexists" => Command::Hexists(Hexists::new(args.next_str()?, args.next_str()?)),
+        "hstrlen" => Command::Hstrlen(Hstrlen::new(args.next_str()?, args.next_str()?)),
+
+        // sorted set
+        "zadd" => {
+            let key = args.next_str()?;
+            let mut exists = None;
+            let mut changed_only = false;
+            let mut scores = vec![];
+            let mut members = vec![];
+            while !args.is_empty() {
+                let arg = args.next_str()?;
+                match arg.to_ascii_uppercase().as_str() {
+                    "NX" if scores.is_empty() => exists = Some(false),
+                    "XX" if scores.is_empty() => exists = Some(true),
+                    "CH" if scores.is_empty() => changed_only = true,
+                    _ => {
+                        let score = arg
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|f| !f.is_nan())
+                            .ok_or(REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR)?;
+                        scores.push(score);
+                        members.push(args.next_str()?);
+                    }
+                }
+            }
+            if scores.is_empty() {
+                return Err(args.arity_error());
+            }
+            Command::Zadd(Zadd::new(key, &members, &scores, exists, changed_only))
+        }
+        "zcard" => Command::Zcard(Zcard::new(args.next_str()?)),
+        "zscore" => Command::Zscore(Zscore::new(args.next_str()?, args.next_str()?)),
+        "zrem" => {
+            let key = args.next_str()?;
+            Command::Zrem(Zrem::new(key, &args.rest_strs_non_empty()?))
+        }
+        "zremrangebyscore" => Command::Zremrangebyscore(Zremrangebyscore::new(
+            args.next_str()?,
+            args.next_f64()?,
+            args.next_f64()?,
+        )),
+        "zremrangebyrank" => Command::Zremrangebyrank(Zremrangebyrank::new(
+            args.next_str()?,
+            args.next_i64()?,
+            args.next_i64()?,
+        )),
+        "zrange" | "zrevrange" => {
+            let key = args.next_str()?;
+            let start = args.next_i64()?;
+            let stop = args.next_i64()?;
+            let withscores = match args.is_empty() {
+                true => false,
+                false if args.next_upper()? == "WITHSCORES" => true,
+                false => return Err(REDIS_SYNTAX_ERR),
+            };
+            if name == "zrange" {
+                Command::Zrange(Zrange::new(key, start, stop, withscores, false))
+            } else {
+                Command::Zrevrange(Zrevrange::new(key, start, stop, withscores))
+            }
+        }
+        "zrangebyscore" | "zrevrangebyscore" => {
+            let key = args.next_str()?;
+            let mut first = args.next_score_bound()?;
+            let mut second = args.next_score_bound()?;
+            let withscores = match args.is_empty() {
+                true => false,
+                false if args.next_upper()? == "WITHSCORES" => true,
+                false => return Err(REDIS_SYNTAX_ERR),
+            };
+            // ZREVRANGEBYSCORE takes the max before the min
+            if name == "zrevrangebyscore" {
+                std::mem::swap(&mut first, &mut second);
+            }
+            let cmd = Zrangebyscore::new(key, first.0, first.1, second.0, second.1, withscores);
+            if name == "zrangebyscore" {
+                Command::Zrangebyscore(cmd)
+            } else {
+                Command::Zrevrangebyscore(cmd)
+            }
+        }
+        "zcount" => {
+            let key = args.next_str()?;
+            let min = args.next_score_bound()?;
+            let max = args.next_score_bound()?;
+            Command::Zcount(Zcount::new(key, min.0, min.1, max.0, max.1))
+        }
+        "zpopmin" | "zpopmax" => {
+            let key = args.next_str()?;
+            let count = (!args.is_empty()).then(|| args.next_i64()).transpose()?;
+            let cmd = Zpop::new(key, count.unwrap_or(1));
+            if name == "zpopmin" {
+                Command::Zpopmin(cmd)
+            } else {
+                Command::Zpopmax(cmd)
+            }
+        }
+        "zrank" => Command::Zrank(Zrank::new(args.next_str()?, args.next_str()?)),
+        "zincrby" => Command::Zincrby(Zincrby::new(
+            args.next_str()?,
+            args.next_f64()?,
+            args.next_str()?,
+        )),
+
+        // geo
+        "geoadd" => {
+            let key = args.next_str()?;
+            let mut exists = None;
+            let mut changed_only = false;
+            let mut coords = vec![];
+            let mut members = vec![];
+            while !args.is_empty() {
+                let arg = args.next_str()?;
+                match arg.to_ascii_uppercase().as_str() {
+                    "NX" if coords.is_empty() => exists = Some(false),
+                    "XX" if coords.is_empty() => exists = Some(true),
+                    "CH" if coords.is_empty() => changed_only = true,
+                    _ => {
+                        let lon = arg
+                            .parse::<f64>()
+                            .map_err(|_| REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR)?;
+                        coords.push((lon, args.next_f64()?));
+                        members.push(args.next_str()?);
+                    }
+                }
+            }
+            if coords.is_empty() {
+                return Err(args.arity_error());
+            }
+            Command::Geoadd(Geoadd::new(key, &coords, &members, exists, changed_only))
+        }
+        "geopos" => {
+            let key = args.next_str()?;
+            Command::Geopos(Geopos::new(key, &args.rest_strs()))
+        }
+        "geodist" => {
+            let key = args.next_str()?;
+            let member1 = args.next_str()?;
+            let member2 = args.next_str()?;
+            let unit = match args.is_empty() {
+                true => GeoUnit::M,
+                false => geo_unit(&args.next_upper()?)?,
+            };
+            Command::Geodist(Geodist::new(key, member1, member2, unit))
+        }
+        "geosearch" => {
+            let key = args.next_str()?;
+            let (from, shape, unit, options, store_dist) = parse_geo_search(args)?;
+            if store_dist {
+                return Err(REDIS_SYNTAX_ERR);
+            }
+            Command::Geosearch(Geosearch::new(key, from, shape, unit, options))
+        }
+        "geosearchstore" => {
+            let destination = args.next_str()?;
+            let source = args.next_str()?;
+            let (from, shape, unit, options, store_dist) = parse_geo_search(args)?;
+            Command::Geosearchstore(Geosearchstore::new(
+                destination,
+                source,
+                from,
+                shape,
+                unit,
+                options,
+                store_dist,
+            ))
+        }
+
+        _ => {
+            // skip the arguments of the unknown command
+            args.rest_strs();
+            Command::Unknown(Unknown::new(name))
+        }
+    };
+    Ok(cmd)
+}
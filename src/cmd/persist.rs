@@ -0,0 +1,47 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Remove the existing timeout on `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Persist {
+    key: String,
+    valid: bool,
+}
+
+impl Persist {
+    pub fn new(key: impl ToString) -> Persist {
+        Persist {
+            key: key.to_string(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client).persist(&self.key).await
+    }
+}
+
+impl Invalid for Persist {
+    fn new_invalid() -> Persist {
+        Persist {
+            key: "".to_owned(),
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Add the elements to the HyperLogLog stored at `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pfadd {
+    key: String,
+    elements: Vec<Bytes>,
+    valid: bool,
+}
+
+impl Pfadd {
+    pub fn new(key: impl ToString, elements: &[impl ToString]) -> Pfadd {
+        Pfadd {
+            key: key.to_string(),
+            elements: elements.iter().map(|e| e.to_string().into()).collect(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn elements(&self) -> &Vec<Bytes> {
+        &self.elements
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .pfadd(&self.key, &self.elements)
+            .await
+    }
+}
+
+impl Invalid for Pfadd {
+    fn new_invalid() -> Pfadd {
+        Pfadd {
+            key: "".to_owned(),
+            elements: vec![],
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Return the approximated cardinality of the union of the HyperLogLogs stored at `keys`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pfcount {
+    keys: Vec<String>,
+    valid: bool,
+}
+
+impl Pfcount {
+    pub fn new(keys: &[impl ToString]) -> Pfcount {
+        if keys.is_empty() {
+            return Pfcount::new_invalid();
+        }
+        Pfcount {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            valid: true,
+        }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client).pfcount(&self.keys).await
+    }
+}
+
+impl Invalid for Pfcount {
+    fn new_invalid() -> Pfcount {
+        Pfcount {
+            keys: vec![],
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Merge the HyperLogLogs stored at `sourcekeys` into `destkey`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pfmerge {
+    destkey: String,
+    sourcekeys: Vec<String>,
+    valid: bool,
+}
+
+impl Pfmerge {
+    pub fn new(destkey: impl ToString, sourcekeys: &[impl ToString]) -> Pfmerge {
+        Pfmerge {
+            destkey: destkey.to_string(),
+            sourcekeys: sourcekeys.iter().map(|key| key.to_string()).collect(),
+            valid: true,
+        }
+    }
+
+    pub fn destkey(&self) -> &str {
+        &self.destkey
+    }
+
+    pub fn sourcekeys(&self) -> &Vec<String> {
+        &self.sourcekeys
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .pfmerge(&self.destkey, &self.sourcekeys)
+            .await
+    }
+}
+
+impl Invalid for Pfmerge {
+    fn new_invalid() -> Pfmerge {
+        Pfmerge {
+            destkey: "".to_owned(),
+            sourcekeys: vec![],
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::cmd::Invalid;
+
+use crate::pubsub::PubSub;
+use crate::Frame;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{resp_int, resp_invalid_arguments};
+
+/// Post `message` to `channel`, returns the number of the subscribers received it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Publish {
+    channel: String,
+    message: Bytes,
+    valid: bool,
+}
+
+impl Publish {
+    pub fn new(channel: impl ToString, message: Bytes) -> Publish {
+        Publish {
+            channel: channel.to_string(),
+            message,
+            valid: true,
+        }
+    }
+
+    /// Get the channel
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    pub(crate) async fn execute(&self, hub: &PubSub) -> Frame {
+        if !self.valid {
+            return resp_invalid_arguments();
+        }
+        resp_int(hub.publish(&self.channel, self.message.clone()).await)
+    }
+}
+
+impl Invalid for Publish {
+    fn new_invalid() -> Publish {
+        Publish {
+            channel: "".to_owned(),
+            message: Bytes::new(),
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::cmd::Invalid;
+
+use crate::pubsub::PubSub;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{resp_array, resp_bulk, resp_int, resp_invalid_arguments};
+
+/// Inspect the state of the pub/sub.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pubsub {
+    op: PubsubOp,
+    valid: bool,
+}
+
+/// Sub commands of PUBSUB command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum PubsubOp {
+    /// The channels with subscribers, optionally matching the glob-style pattern
+    Channels { pattern: Option<String> },
+    /// The number of the subscribers of every channel, the pattern subscribers are excluded
+    Numsub { channels: Vec<String> },
+    /// The number of the subscribed patterns
+    Numpat,
+}
+
+impl Pubsub {
+    pub fn new(op: PubsubOp) -> Pubsub {
+        Pubsub { op, valid: true }
+    }
+
+    /// Get the sub command
+    pub fn op(&self) -> &PubsubOp {
+        &self.op
+    }
+
+    pub(crate) fn execute(&self, hub: &PubSub) -> Frame {
+        if !self.valid {
+            return resp_invalid_arguments();
+        }
+        match &self.op {
+            PubsubOp::Channels { pattern } => resp_array(
+                hub.channels(pattern.as_deref())
+                    .into_iter()
+                    .map(|channel| resp_bulk(channel.into_bytes()))
+                    .collect(),
+            ),
+            PubsubOp::Numsub { channels } => resp_array(
+                channels
+                    .iter()
+                    .flat_map(|channel| {
+                        [
+                            resp_bulk(channel.as_bytes().to_vec()),
+                            resp_int(hub.numsub(channel) as i64),
+                        ]
+                    })
+                    .collect(),
+            ),
+            PubsubOp::Numpat => resp_int(hub.numpat() as i64),
+        }
+    }
+}
+
+impl Invalid for Pubsub {
+    fn new_invalid() -> Pubsub {
+        Pubsub {
+            op: PubsubOp::Numpat,
+            valid: false,
+        }
+    }
+}
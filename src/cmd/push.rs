@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::rocks::list::ListCommand;
 use bytes::Bytes;
 
@@ -34,7 +34,7 @@ impl Push {
         &self.items
     }
 
-    pub async fn execute(&mut self, client: &RocksClient, op_left: bool) -> RocksResult<Frame> {
+    pub async fn execute(&mut self, client: ClientRef<'_>, op_left: bool) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
@@ -0,0 +1,41 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Return a random key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Randomkey {
+    valid: bool,
+}
+
+impl Randomkey {
+    pub fn new() -> Randomkey {
+        Randomkey { valid: true }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client).randomkey().await
+    }
+}
+
+impl Default for Randomkey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Invalid for Randomkey {
+    fn new_invalid() -> Self {
+        Randomkey { valid: false }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Rename `key` to `newkey`, used by both RENAME and RENAMENX.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rename {
+    key: String,
+    newkey: String,
+    valid: bool,
+}
+
+impl Rename {
+    pub fn new(key: impl ToString, newkey: impl ToString) -> Rename {
+        Rename {
+            key: key.to_string(),
+            newkey: newkey.to_string(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn newkey(&self) -> &str {
+        &self.newkey
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>, nx: bool) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .rename(&self.key, &self.newkey, nx)
+            .await
+    }
+}
+
+impl Invalid for Rename {
+    fn new_invalid() -> Rename {
+        Rename {
+            key: "".to_owned(),
+            newkey: "".to_owned(),
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Create `key` from the `value` produced by DUMP.
+/// `ttl` is in milliseconds, 0 means the ttl saved in `value` is used.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Restore {
+    key: String,
+    ttl: i64,
+    value: Bytes,
+    replace: bool,
+    valid: bool,
+}
+
+impl Restore {
+    pub fn new(key: impl ToString, ttl: i64, value: Bytes, replace: bool) -> Restore {
+        Restore {
+            key: key.to_string(),
+            ttl,
+            value,
+            replace,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .restore(&self.key, self.ttl, &self.value, self.replace)
+            .await
+    }
+}
+
+impl Invalid for Restore {
+    fn new_invalid() -> Restore {
+        Restore {
+            key: "".to_owned(),
+            ttl: 0,
+            value: Bytes::new(),
+            replace: false,
+            valid: false,
+        }
+    }
+}
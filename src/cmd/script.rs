@@ -0,0 +1,69 @@
+use crate::cmd::Invalid;
+
+use crate::db::DBInner;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_array, resp_bulk, resp_err, resp_int, resp_invalid_arguments, resp_ok};
+
+/// Manage the cache of the scripts run by EVALSHA.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Script {
+    op: ScriptOp,
+    valid: bool,
+}
+
+/// Sub commands of SCRIPT command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ScriptOp {
+    /// Cache the script and return its sha
+    Load(String),
+    /// Return 1 for every sha of a cached script, 0 otherwise
+    Exists(Vec<String>),
+    /// Remove all the cached scripts
+    Flush,
+}
+
+impl Script {
+    pub fn new(op: ScriptOp) -> Script {
+        Script { op, valid: true }
+    }
+
+    /// Get the sub command
+    pub fn op(&self) -> &ScriptOp {
+        &self.op
+    }
+
+    pub(crate) async fn execute(&self, inner_db: &DBInner) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        let scripts = &inner_db.scripts;
+        match &self.op {
+            ScriptOp::Load(code) => match scripts.load(code) {
+                Ok(sha) => Ok(resp_bulk(sha.into_bytes())),
+                Err(e) => Ok(resp_err(e)),
+            },
+            ScriptOp::Exists(shas) => Ok(resp_array(
+                shas.iter()
+                    .map(|sha| resp_int(scripts.exists(sha) as i64))
+                    .collect(),
+            )),
+            ScriptOp::Flush => {
+                scripts.flush();
+                Ok(resp_ok())
+            }
+        }
+    }
+}
+
+impl Invalid for Script {
+    fn new_invalid() -> Script {
+        Script {
+            op: ScriptOp::Flush,
+            valid: false,
+        }
+    }
+}
@@ -1,13 +1,14 @@
-use crate::cmd::Invalid;
+use crate::cmd::{Expiration, Invalid};
 
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::RError;
 use crate::Frame;
 
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
 use crate::rocks::string::StringCommand;
-use crate::utils::{resp_invalid_arguments, timestamp_from_ttl};
+use crate::utils::{resp_err, resp_invalid_arguments};
 
 use crate::rocks::Result as RocksResult;
 
@@ -23,6 +24,12 @@ use crate::rocks::Result as RocksResult;
 ///
 /// * EX `seconds` -- Set the specified expire time, in seconds.
 /// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp-seconds` -- Set the specified Unix time at which the key will expire.
+/// * PXAT `timestamp-milliseconds` -- Same as EXAT, in milliseconds.
+/// * NX -- Only set the key if it does not already exist.
+/// * XX -- Only set the key if it already exists.
+/// * KEEPTTL -- Retain the time to live associated with the key.
+/// * GET -- Return the old string stored at key, or nil if key did not exist.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Set {
     /// the lookup key
@@ -32,30 +39,84 @@ pub struct Set {
     value: Bytes,
 
     /// When to expire the key
-    expire: Option<i64>,
+    expiration: Option<Expiration>,
 
-    /// Set if key is not present
-    nx: Option<bool>,
+    /// Set only if the key is not present or already present
+    condition: Option<SetCondition>,
+
+    /// Retain the time to live of the key
+    keep_ttl: bool,
+
+    /// Return the old value of the key
+    get: bool,
 
     valid: bool,
 }
 
+/// Conditions of SET command.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    /// Only set the key if it does not already exist
+    Nx,
+    /// Only set the key if it already exists
+    Xx,
+}
+
 impl Set {
     /// Create a new `Set` command which sets `key` to `value`.
     ///
     /// If `expire` is `Some`, the value should expire after the specified
-    /// duration.
+    /// duration in milliseconds.
     pub fn new(
         key: impl ToString,
         value: impl ToString,
         expire: Option<i64>,
         nx: Option<bool>,
     ) -> Set {
+        Set::new_with_options(
+            key,
+            value,
+            expire.map(Expiration::Px),
+            nx.map(|_| SetCondition::Nx),
+            false,
+            false,
+        )
+    }
+
+    /// Create a new `Set` command with the full options of SET.
+    ///
+    /// `expiration` and `keep_ttl` can not be specified at the same time.
+    pub fn new_with_options(
+        key: impl ToString,
+        value: impl ToString,
+        expiration: Option<Expiration>,
+        condition: Option<SetCondition>,
+        keep_ttl: bool,
+        get: bool,
+    ) -> Set {
+        if keep_ttl && expiration.is_some() {
+            return Set::new_invalid();
+        }
         Set {
             key: key.to_string(),
             value: value.to_string().into(),
-            expire,
-            nx,
+            expiration,
+            condition,
+            keep_ttl,
+            get,
+            valid: true,
+        }
+    }
+
+    /// Create the SET of `value` with `expiration`, which replaces SETEX in the raft log.
+    pub(crate) fn with_expiration(key: &str, value: Bytes, expiration: Expiration) -> Set {
+        Set {
+            key: key.to_string(),
+            value,
+            expiration: Some(expiration),
+            condition: None,
+            keep_ttl: false,
+            get: false,
             valid: true,
         }
     }
@@ -70,31 +131,38 @@ impl Set {
         &self.value
     }
 
-    /// Get the expire
-    pub fn expire(&self) -> Option<i64> {
-        self.expire
+    /// Get the expiration
+    pub fn expiration(&self) -> Option<Expiration> {
+        self.expiration
+    }
+
+    /// Return the command with the relative expire time replaced by the absolute one.
+    pub(crate) fn absolute(self) -> Set {
+        Set {
+            expiration: self.expiration.map(Expiration::absolute),
+            ..self
+        }
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
-        match self.nx {
-            Some(_) => self.put_not_exists(client).await,
-            None => self.put(client).await,
+        let timestamp = match self.expiration {
+            Some(expiration) => match expiration.timestamp() {
+                Some(ts) => ts,
+                None => return Ok(resp_err(RError::invalid_expire_time_error("set"))),
+            },
+            None => 0,
+        };
+        if self.condition.is_none() && !self.keep_ttl && !self.get {
+            return StringCommand::new(client)
+                .put(&self.key, &self.value, timestamp)
+                .await;
         }
-    }
-
-    async fn put_not_exists(&self, client: &RocksClient) -> RocksResult<Frame> {
-        StringCommand::new(client)
-            .put_not_exists(&self.key, &self.value)
-            .await
-    }
-
-    async fn put(&self, client: &RocksClient) -> RocksResult<Frame> {
-        let ttl = self.expire.map_or(-1, timestamp_from_ttl);
+        let timestamp = if self.keep_ttl { None } else { Some(timestamp) };
         StringCommand::new(client)
-            .put(&self.key, &self.value, ttl)
+            .set(&self.key, &self.value, timestamp, self.condition, self.get)
             .await
     }
 }
@@ -104,8 +172,10 @@ impl Invalid for Set {
         Set {
             key: "".to_owned(),
             value: Bytes::new(),
-            expire: None,
-            nx: None,
+            expiration: None,
+            condition: None,
+            keep_ttl: false,
+            get: false,
             valid: false,
         }
     }
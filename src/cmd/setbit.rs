@@ -0,0 +1,71 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::config::config_proto_max_bulk_len;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::{REDIS_BIT_NOT_INTEGER_ERR, REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments};
+
+/// Set or clear the bit at `offset` in the string value stored at `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Setbit {
+    key: String,
+    offset: i64,
+    value: i64,
+    valid: bool,
+}
+
+impl Setbit {
+    pub fn new(key: impl ToString, offset: i64, value: i64) -> Setbit {
+        Setbit {
+            key: key.to_string(),
+            offset,
+            value,
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        if self.offset < 0 || self.offset as u64 >= config_proto_max_bulk_len() as u64 * 8 {
+            return Ok(resp_err(REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR));
+        }
+        if self.value != 0 && self.value != 1 {
+            return Ok(resp_err(REDIS_BIT_NOT_INTEGER_ERR));
+        }
+        StringCommand::new(client)
+            .setbit(&self.key, self.offset as u64, self.value == 1)
+            .await
+    }
+}
+
+impl Invalid for Setbit {
+    fn new_invalid() -> Setbit {
+        Setbit {
+            key: "".to_owned(),
+            offset: 0,
+            value: 0,
+            valid: false,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::Frame;
+
+use crate::cmd::{Expiration, Invalid, Set};
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::RError;
+use bytes::Bytes;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::{resp_err, resp_invalid_arguments, timestamp_from_ttl};
+
+/// Set `key` to hold the string `value` and set `key` to timeout after a given
+/// number of seconds (SETEX) or milliseconds (PSETEX).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Setex {
+    key: String,
+    ttl: i64,
+    value: Bytes,
+    valid: bool,
+}
+
+impl Setex {
+    pub fn new(key: impl ToString, ttl: i64, value: impl ToString) -> Setex {
+        Setex {
+            key: key.to_string(),
+            ttl,
+            value: value.to_string().into(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    pub fn ttl(&self) -> i64 {
+        self.ttl
+    }
+
+    /// Return the SET of the value with the absolute expire time, none if the time is
+    /// invalid.
+    pub(crate) fn absolute(&self, is_millis: bool) -> Option<Set> {
+        if !self.valid {
+            return None;
+        }
+        let ttl = if is_millis {
+            Some(self.ttl)
+        } else {
+            self.ttl.checked_mul(1000)
+        };
+        match ttl {
+            Some(ttl) if ttl > 0 => Some(Set::with_expiration(
+                &self.key,
+                self.value.clone(),
+                Expiration::PxAt(timestamp_from_ttl(ttl)),
+            )),
+            _ => None,
+        }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>, is_millis: bool) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        let ttl = if is_millis {
+            Some(self.ttl)
+        } else {
+            self.ttl.checked_mul(1000)
+        };
+        match ttl {
+            Some(ttl) if ttl > 0 => {
+                StringCommand::new(client)
+                    .put(&self.key, &self.value, timestamp_from_ttl(ttl))
+                    .await
+            }
+            _ => {
+                let cmd = if is_millis { "psetex" } else { "setex" };
+                Ok(resp_err(RError::invalid_expire_time_error(cmd)))
+            }
+        }
+    }
+}
+
+impl Invalid for Setex {
+    fn new_invalid() -> Setex {
+        Setex {
+            key: "".to_owned(),
+            ttl: 0,
+            value: Bytes::new(),
+            valid: false,
+        }
+    }
+}
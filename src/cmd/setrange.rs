@@ -0,0 +1,64 @@
+use crate::Frame;
+
+use crate::cmd::Invalid;
+use crate::rocks::client::ClientRef;
+use bytes::Bytes;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Overwrite part of the string stored at `key`, starting at `offset`.
+///
+/// The string is padded with zero bytes if `offset` is larger than its
+/// current length. The time to live of an existing key is kept.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Setrange {
+    key: String,
+    offset: i64,
+    value: Bytes,
+    valid: bool,
+}
+
+impl Setrange {
+    pub fn new(key: impl ToString, offset: i64, value: impl ToString) -> Setrange {
+        Setrange {
+            key: key.to_string(),
+            offset,
+            value: value.to_string().into(),
+            valid: true,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client)
+            .setrange(&self.key, self.offset, &self.value)
+            .await
+    }
+}
+
+impl Invalid for Setrange {
+    fn new_invalid() -> Setrange {
+        Setrange {
+            key: "".to_owned(),
+            offset: 0,
+            value: Bytes::new(),
+            valid: false,
+        }
+    }
+}
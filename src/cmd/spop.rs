@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +13,10 @@ use crate::utils::resp_invalid_arguments;
 pub struct Spop {
     key: String,
     count: i64,
+    /// the members popped by the leader of the raft cluster, or when the command is recorded
+    /// in the journal, instead of the first members
+    #[serde(default)]
+    members: Option<Vec<String>>,
     valid: bool,
 }
 
@@ -21,16 +25,51 @@ impl Spop {
         Spop {
             key: key.to_string(),
             count,
+            members: None,
             valid: true,
         }
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Return the command popping exactly `members`, which are replied as the members popped
+    /// by this command.
+    pub(crate) fn with_members(&self, members: Vec<String>) -> Spop {
+        Spop {
+            members: Some(members),
+            ..self.clone()
+        }
+    }
+
+    /// Return the command popping the members in `frame`, the reply of this command, so it
+    /// pops the same members when it's executed again. None if the reply is an error, which
+    /// is given again.
+    pub(crate) fn with_popped(&self, frame: &Frame) -> Option<Spop> {
+        let frames = match frame {
+            Frame::Bulk(_) => std::slice::from_ref(frame),
+            Frame::Array(frames) => frames.as_slice(),
+            Frame::Null => &[],
+            _ => return None,
+        };
+        let members = frames
+            .iter()
+            .filter_map(|frame| match frame {
+                Frame::Bulk(member) => Some(String::from_utf8_lossy(member).into_owned()),
+                _ => None,
+            })
+            .collect();
+        Some(self.with_members(members))
+    }
+
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
         SetCommand::new(client)
-            .spop(&self.key, self.count as u64)
+            .spop(&self.key, self.count as u64, self.members.as_deref())
             .await
     }
 }
@@ -40,6 +79,7 @@ impl Invalid for Spop {
         Spop {
             key: "".to_string(),
             count: 0,
+            members: None,
             valid: false,
         }
     }
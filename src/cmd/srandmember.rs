@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 
 use serde::{Deserialize, Serialize};
 
@@ -25,25 +25,33 @@ impl Srandmember {
         }
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
         let mut count;
         let repeatable;
         let array_resp;
-        if self.count.is_none() {
-            repeatable = false;
-            count = 1;
-            array_resp = false;
-        } else {
-            array_resp = true;
-            count = self.count.unwrap();
-            if count > 0 {
+        match self.count {
+            None => {
                 repeatable = false;
-            } else {
-                repeatable = true;
-                count = -count;
+                count = 1;
+                array_resp = false;
+            }
+            Some(c) => {
+                array_resp = true;
+                count = c;
+                if count > 0 {
+                    repeatable = false;
+                } else {
+                    repeatable = true;
+                    count = -count;
+                }
             }
         }
         SetCommand::new(client)
@@ -1,6 +1,6 @@
 use crate::cmd::Invalid;
 
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 use crate::Frame;
 
 use serde::{Deserialize, Serialize};
@@ -27,7 +27,7 @@ impl Strlen {
         &self.key
     }
 
-    pub async fn execute(&mut self, client: &RocksClient) -> RocksResult<Frame> {
+    pub async fn execute(&mut self, client: ClientRef<'_>) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
@@ -0,0 +1,56 @@
+use crate::cmd::Invalid;
+
+use crate::rocks::client::ClientRef;
+use crate::Frame;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::string::StringCommand;
+use crate::rocks::Result as RocksResult;
+use crate::utils::resp_invalid_arguments;
+
+/// Return the number of existing keys, the access time of keys is not tracked,
+/// so it acts the same as EXISTS.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Touch {
+    keys: Vec<String>,
+    valid: bool,
+}
+
+impl Touch {
+    pub fn new(keys: &[impl ToString]) -> Touch {
+        Touch {
+            keys: keys.iter().map(|it| it.to_string()).collect(),
+            valid: true,
+        }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    /// Return the command with the keys at `indices`, it is used to split the command by shard.
+    pub(crate) fn select(&self, indices: &[usize]) -> Touch {
+        Touch {
+            keys: indices.iter().map(|i| self.keys[*i].clone()).collect(),
+            valid: self.valid,
+        }
+    }
+
+    pub async fn execute(&self, client: ClientRef<'_>) -> RocksResult<Frame> {
+        if !self.valid {
+            return Ok(resp_invalid_arguments());
+        }
+        StringCommand::new(client).exists(&self.keys).await
+    }
+}
+
+impl Invalid for Touch {
+    fn new_invalid() -> Touch {
+        Touch {
+            keys: vec![],
+            valid: false,
+        }
+    }
+}
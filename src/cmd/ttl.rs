@@ -1,7 +1,7 @@
 use crate::Frame;
 
 use crate::cmd::Invalid;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
 
 use serde::{Deserialize, Serialize};
 
@@ -28,11 +28,20 @@ impl TTL {
         &self.key
     }
 
-    pub async fn execute(&mut self, client: &RocksClient, is_millis: bool) -> RocksResult<Frame> {
+    /// Return the remaining time to live of the key, or the absolute unix expire time
+    /// for EXPIRETIME and PEXPIRETIME if `absolute` is true.
+    pub async fn execute(
+        &mut self,
+        client: ClientRef<'_>,
+        is_millis: bool,
+        absolute: bool,
+    ) -> RocksResult<Frame> {
         if !self.valid {
             return Ok(resp_invalid_arguments());
         }
-        StringCommand::new(client).ttl(&self.key, is_millis).await
+        StringCommand::new(client)
+            .ttl(&self.key, is_millis, absolute)
+            .await
     }
 }
 
@@ -6,3 +6,18 @@ pub fn config_meta_key_number_or_default() -> u16 {
 pub fn txn_retry_count() -> u32 {
     10
 }
+
+pub fn config_proto_max_bulk_len() -> usize {
+    // max size of a string value, same as the redis default
+    512 * 1024 * 1024
+}
+
+pub fn config_hll_sparse_max_bytes() -> usize {
+    // max size of a sparse encoded hyperloglog, same as the redis default
+    3000
+}
+
+pub fn config_script_time_limit() -> std::time::Duration {
+    // a script running longer is aborted and its writes are discarded
+    std::time::Duration::from_secs(5)
+}
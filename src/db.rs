@@ -1,23 +1,224 @@
+use std::net::SocketAddr;
 use std::path::Path;
 
 use std::sync::Arc;
 
 use crate::{
-    rocks::{client::RocksClient, new_client},
-    Result,
+    cluster::ClusterState,
+    journal::Journal,
+    pubsub::PubSub,
+    raft::{Raft, RaftConfig},
+    rocks::backup::Backup,
+    rocks::engine::MemoryEngine,
+    rocks::errors::{
+        INVALID_NOTIFY_EVENTS_ERR, IN_MEMORY_NOT_SUPPORTED_ERR, READ_ONLY_NOT_SUPPORTED_ERR,
+        SHARDS_NOT_SUPPORTED_ERR,
+    },
+    rocks::notify::{parse_notify_flags, Notifier},
+    rocks::replication::{self, ReplicationLog},
+    rocks::{client::RocksClient, new_client, new_read_only_client},
+    script::Scripts,
+    shard::key_shard,
+    watch::Watchers,
+    OpenOptions, Result,
 };
 
 pub(crate) struct DBInner {
     pub(crate) client: Arc<RocksClient>,
+    pub(crate) journal: Option<Journal>,
+    // address listened on for the replicas if the db is a primary
+    pub(crate) replication_addr: Option<SocketAddr>,
+    pub(crate) raft: Option<Arc<Raft>>,
+    // the first shard is `client`, there is only one shard if the db is not sharded
+    pub(crate) shards: Vec<Arc<RocksClient>>,
+    pub(crate) cluster: Option<ClusterState>,
+    pub(crate) pubsub: Arc<PubSub>,
+    pub(crate) watchers: Arc<Watchers>,
+    pub(crate) scripts: Arc<Scripts>,
+}
+
+impl Drop for DBInner {
+    fn drop(&mut self) {
+        if let Some(raft) = &self.raft {
+            raft.shutdown();
+        }
+    }
 }
 
 impl DBInner {
-    pub(crate) async fn open<P: AsRef<Path>>(
+    pub(crate) async fn open<P: AsRef<Path>>(path: P, options: &OpenOptions) -> Result<Self> {
+        if (options.read_only || options.secondary_of.is_some())
+            && (options.in_memory
+                || options.backup_dir.is_some()
+                || options.replication_listen.is_some()
+                || options.replica_of.is_some()
+                || options.raft_node.is_some())
+        {
+            return Err(READ_ONLY_NOT_SUPPORTED_ERR.into());
+        }
+        if options.in_memory
+            && (options.backup_dir.is_some()
+                || options.replication_listen.is_some()
+                || options.replica_of.is_some()
+                || options.raft_node.is_some())
+        {
+            return Err(IN_MEMORY_NOT_SUPPORTED_ERR.into());
+        }
+        let journal = match &options.journal_dir {
+            Some(dir) => Some(Journal::open(dir, options.journal_file_size)?),
+            None => None,
+        };
+        let backup = match &options.backup_dir {
+            Some(dir) => Some(Backup::new(dir, options.backup_retention)?),
+            None => None,
+        };
+        let pubsub = open_pubsub(options);
+        let notifier = open_notifier(options, &pubsub)?;
+        let watchers = Arc::new(Watchers::new(options.pubsub_buffer_size));
+        if options.shards > 1 {
+            return Self::open_shards(path, options, journal, backup, pubsub, notifier, watchers);
+        }
+        let raft_dir = path.as_ref().join("raft");
+        let mut client = open_client(path, options, backup)?.with_watchers(watchers.clone());
+        if let Some(notifier) = notifier {
+            client = client.with_notifier(notifier);
+        }
+        if options.change_capture {
+            client = client.with_change_log(options.change_retention)?;
+        }
+        if options.replication_listen.is_some() {
+            let log = ReplicationLog::open(&*client.db()?, options.replication_backlog_size)?;
+            client = client.with_replication(log);
+        }
+        if options.replica_of.is_some() {
+            client = client.with_read_only();
+        }
+        let client = Arc::new(client);
+
+        let replication_addr = match &options.replication_listen {
+            Some(addr) => Some(replication::listen(addr.as_str(), client.clone())?),
+            None => None,
+        };
+        if let Some(addr) = &options.replica_of {
+            replication::start_replica(addr.clone(), client.clone());
+        }
+        let raft = match &options.raft_node {
+            Some(node) => {
+                let config = RaftConfig {
+                    id: node.id,
+                    addr: node.addr.clone(),
+                    members: options.raft_members.clone(),
+                    snapshot_threshold: options.raft_snapshot_threshold,
+                    dir: raft_dir,
+                };
+                Some(Raft::start(config, client.clone()).await?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            cluster: open_cluster(options, &client)?,
+            pubsub,
+            watchers,
+            scripts: Arc::new(Scripts::new()),
+            shards: vec![client.clone()],
+            client,
+            journal,
+            replication_addr,
+            raft,
+        })
+    }
+
+    /// Open every shard in a sub directory of `path`.
+    fn open_shards<P: AsRef<Path>>(
         path: P,
-        async_deletion_enabled: bool,
+        options: &OpenOptions,
+        journal: Option<Journal>,
+        backup: Option<Backup>,
+        pubsub: Arc<PubSub>,
+        notifier: Option<Arc<Notifier>>,
+        watchers: Arc<Watchers>,
     ) -> Result<Self> {
-        let client = new_client(path, async_deletion_enabled)?;
-        let client = Arc::new(client);
-        Ok(Self { client })
+        if backup.is_some()
+            || options.replication_listen.is_some()
+            || options.replica_of.is_some()
+            || options.raft_node.is_some()
+            || options.change_capture
+            || options.secondary_of.is_some()
+        {
+            return Err(SHARDS_NOT_SUPPORTED_ERR.into());
+        }
+        let mut shards = vec![];
+        for i in 0..options.shards {
+            let shard_path = path.as_ref().join(format!("shard-{i}"));
+            let mut client =
+                open_client(shard_path, options, None)?.with_watchers(watchers.clone());
+            if let Some(notifier) = &notifier {
+                client = client.with_notifier(notifier.clone());
+            }
+            shards.push(Arc::new(client));
+        }
+        Ok(Self {
+            client: shards[0].clone(),
+            journal,
+            replication_addr: None,
+            raft: None,
+            cluster: open_cluster(options, &shards[0])?,
+            pubsub,
+            watchers,
+            scripts: Arc::new(Scripts::new()),
+            shards,
+        })
+    }
+
+    /// Return the shard of the key.
+    pub(crate) fn client_for_key(&self, key: &[u8]) -> &Arc<RocksClient> {
+        &self.shards[key_shard(key, self.shards.len())]
+    }
+}
+
+fn open_client<P: AsRef<Path>>(
+    path: P,
+    options: &OpenOptions,
+    backup: Option<Backup>,
+) -> Result<RocksClient> {
+    if options.in_memory {
+        let engine = Arc::new(MemoryEngine::new());
+        return Ok(RocksClient::new(engine, options.gc_enabled, backup)?);
+    }
+    if options.read_only || options.secondary_of.is_some() {
+        let primary_path = options.secondary_of.as_deref();
+        return Ok(new_read_only_client(
+            path,
+            primary_path,
+            options.gc_enabled,
+        )?);
+    }
+    Ok(new_client(path, options.gc_enabled, backup)?)
+}
+
+fn open_cluster(options: &OpenOptions, client: &Arc<RocksClient>) -> Result<Option<ClusterState>> {
+    match &options.cluster_node {
+        Some(node) => Ok(Some(ClusterState::open(
+            node,
+            &options.cluster_peers,
+            client.clone(),
+        )?)),
+        None => Ok(None),
+    }
+}
+
+fn open_pubsub(options: &OpenOptions) -> Arc<PubSub> {
+    Arc::new(PubSub::new(
+        options.pubsub_buffer_size,
+        options.pubsub_publish_timeout,
+    ))
+}
+
+fn open_notifier(options: &OpenOptions, pubsub: &Arc<PubSub>) -> Result<Option<Arc<Notifier>>> {
+    if options.notify_keyspace_events.is_empty() {
+        return Ok(None);
     }
+    let classes =
+        parse_notify_flags(&options.notify_keyspace_events).ok_or(INVALID_NOTIFY_EVENTS_ERR)?;
+    Ok(Some(Arc::new(Notifier::start(classes, pubsub.clone()))))
 }
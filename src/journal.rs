@@ -0,0 +1,178 @@
+//! Append-only journal of the write commands.
+//!
+//! Every successful write command executed by `Conn::execute` is appended to the journal as a
+//! line of JSON with a sequence number and the time it is executed. The journal is a directory
+//! of files named by the sequence number of their first entry, a new file is started when the
+//! current one grows over the size limit or the db is opened again.
+//!
+//! Commands are journaled in the order they complete, and the commands depending on the time
+//! or on randomness (relative expire time, SPOP) may give different results when replayed.
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::Command;
+use crate::db::DBInner;
+use crate::utils::now_timestamp_in_millis;
+use crate::Result;
+
+const JOURNAL_FILE_PREFIX: &str = "journal-";
+const JOURNAL_FILE_SUFFIX: &str = ".log";
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry<'a> {
+    seq: u64,
+    /// unix time in milliseconds
+    timestamp: i64,
+    command: Cow<'a, Command>,
+}
+
+struct JournalFile {
+    file: File,
+    size: u64,
+    next_seq: u64,
+}
+
+pub(crate) struct Journal {
+    dir: PathBuf,
+    max_file_size: u64,
+    current: Mutex<JournalFile>,
+}
+
+impl Journal {
+    /// Open the journal in `dir`, the sequence numbers continue from the existing files.
+    pub(crate) fn open<P: AsRef<Path>>(dir: P, max_file_size: u64) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let next_seq = match journal_files(&dir)?.last() {
+            Some((first_seq, path)) => {
+                let mut next_seq = *first_seq;
+                let valid_len = for_each_entry(path, |entry| {
+                    next_seq = entry.seq + 1;
+                    Ok(())
+                })?;
+                // drop the torn write, the entries are appended after it otherwise
+                let file = fs::OpenOptions::new().write(true).open(path)?;
+                if file.metadata()?.len() > valid_len {
+                    file.set_len(valid_len)?;
+                }
+                next_seq
+            }
+            None => 1,
+        };
+        let current = open_journal_file(&dir, next_seq)?;
+        Ok(Self {
+            dir,
+            max_file_size,
+            current: Mutex::new(current),
+        })
+    }
+
+    /// Append the command to the journal, returns its sequence number.
+    pub(crate) fn append(&self, cmd: &Command) -> Result<u64> {
+        let mut current = self.current.lock().unwrap();
+        if current.size >= self.max_file_size {
+            *current = open_journal_file(&self.dir, current.next_seq)?;
+        }
+
+        let seq = current.next_seq;
+        let entry = JournalEntry {
+            seq,
+            timestamp: now_timestamp_in_millis(),
+            command: Cow::Borrowed(cmd),
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        // write the line at once, so a crash never interleaves two entries
+        current.file.write_all(&line)?;
+        current.size += line.len() as u64;
+        current.next_seq += 1;
+        Ok(seq)
+    }
+}
+
+/// Execute the commands in the journal in `dir` from the sequence number `from_seq`.
+/// The replayed commands are not journaled again.
+/// Returns the number of the replayed commands.
+pub(crate) async fn replay<P: AsRef<Path>>(dir: P, from_seq: u64, db: &DBInner) -> Result<u64> {
+    let files = journal_files(dir.as_ref())?;
+    let mut commands = 0;
+    for (i, (_, path)) in files.iter().enumerate() {
+        // all the entries of this file are before `from_seq`
+        if matches!(files.get(i + 1), Some((next_first_seq, _)) if *next_first_seq <= from_seq) {
+            continue;
+        }
+        let mut entries = vec![];
+        for_each_entry(path, |entry| {
+            if entry.seq >= from_seq {
+                entries.push(entry.command.into_owned());
+            }
+            Ok(())
+        })?;
+        for cmd in entries {
+            cmd.execute(db).await?;
+            commands += 1;
+        }
+    }
+    Ok(commands)
+}
+
+/// Return the journal files in `dir` with the sequence number of their first entries,
+/// ordered by the sequence number.
+fn journal_files(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let mut files = vec![];
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        let first_seq = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(JOURNAL_FILE_PREFIX))
+            .and_then(|name| name.strip_suffix(JOURNAL_FILE_SUFFIX))
+            .and_then(|seq| seq.parse::<u64>().ok());
+        if let Some(first_seq) = first_seq {
+            files.push((first_seq, path));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn open_journal_file(dir: &Path, first_seq: u64) -> Result<JournalFile> {
+    let path = dir.join(format!(
+        "{JOURNAL_FILE_PREFIX}{first_seq:020}{JOURNAL_FILE_SUFFIX}"
+    ));
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let size = file.metadata()?.len();
+    Ok(JournalFile {
+        file,
+        size,
+        next_seq: first_seq,
+    })
+}
+
+/// Call `f` with every entry in the journal file, returns the length of the complete lines.
+/// The last line without newline is a torn write of a crash, it is ignored.
+fn for_each_entry<F>(path: &Path, mut f: F) -> Result<u64>
+where
+    F: FnMut(JournalEntry<'static>) -> Result<()>,
+{
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = String::new();
+    let mut valid_len = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+            return Ok(valid_len);
+        }
+        f(serde_json::from_str(&line)?)?;
+        valid_len += line.len() as u64;
+    }
+}
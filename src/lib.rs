@@ -1,20 +1,63 @@
+pub mod cluster;
 pub mod cmd;
 pub mod frame;
+pub mod rdb;
 
+mod atomic;
 mod config;
 mod db;
+mod journal;
+mod long_double;
+mod pubsub;
+mod raft;
 mod rocks;
+mod script;
+mod shard;
+mod snapshot;
 mod utils;
+mod watch;
 
+use cluster::ClusterNode;
 use cmd::{Command, Gc};
 
+pub use atomic::Tx;
+pub use pubsub::{Message, Subscription};
+pub use raft::{RaftMember, RaftRole, RaftStatus};
+pub use rocks::backup::BackupInfo;
+pub use rocks::cdc::{ChangeEvent, ChangeOp, ChangeStream};
+pub use rocks::encoding::DataType;
+pub use rocks::engine::{
+    KvBytes, KvIter, MemoryEngine, ReadOnlyEngine, RocksEngine, StorageEngine, StorageTxn,
+    CF_NAME_DEFAULT,
+};
+pub use rocks::errors::RError;
+pub use shard::{key_hash_slot, SLOT_COUNT};
+pub use snapshot::Snapshot;
+pub use watch::{Watch, WatchEvent};
+
 use db::DBInner;
 use frame::Frame;
-use std::{path::Path, sync::Arc, time::Duration};
+use raft::Raft;
+use rocks::backup::{checkpoint, Backup};
+use rocks::errors::{
+    CHANGE_CAPTURE_NOT_ENABLED_ERR, CHECKPOINT_EXISTS_ERR, RAFT_DIRECT_WRITE_ERR,
+    RAFT_NOT_ENABLED_ERR, REDIS_BACKUP_NOT_CONFIGURED_ERR, REDIS_READONLY_ERR,
+};
+use rocks::string::StringCommand;
+use std::{
+    net::SocketAddr,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
     spawn,
+    task::spawn_blocking,
     time::{interval, MissedTickBehavior},
 };
+use utils::{resp_err, resp_ok};
 
 /// Error returned by most functions.
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -31,6 +74,27 @@ pub type Result<T> = anyhow::Result<T, Error>;
 pub struct OpenOptions {
     pub(crate) gc_enabled: bool,
     pub(crate) gc_interval: u64,
+    pub(crate) journal_dir: Option<PathBuf>,
+    pub(crate) journal_file_size: u64,
+    pub(crate) backup_dir: Option<PathBuf>,
+    pub(crate) backup_retention: Option<usize>,
+    pub(crate) replication_listen: Option<String>,
+    pub(crate) replica_of: Option<String>,
+    pub(crate) replication_backlog_size: usize,
+    pub(crate) raft_node: Option<RaftMember>,
+    pub(crate) raft_members: Vec<RaftMember>,
+    pub(crate) raft_snapshot_threshold: u64,
+    pub(crate) shards: usize,
+    pub(crate) cluster_node: Option<ClusterNode>,
+    pub(crate) cluster_peers: Vec<ClusterNode>,
+    pub(crate) pubsub_buffer_size: usize,
+    pub(crate) pubsub_publish_timeout: Duration,
+    pub(crate) notify_keyspace_events: String,
+    pub(crate) change_capture: bool,
+    pub(crate) change_retention: u64,
+    pub(crate) in_memory: bool,
+    pub(crate) read_only: bool,
+    pub(crate) secondary_of: Option<PathBuf>,
 }
 
 impl OpenOptions {
@@ -50,13 +114,202 @@ impl OpenOptions {
         self
     }
 
+    /// Record the successful write commands in the journal in the directory. Default is no
+    /// journal.
+    pub fn journal<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.journal_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the size in bytes to start a new journal file. Default is 64MB.
+    pub fn journal_file_size(mut self, value: u64) -> Self {
+        self.journal_file_size = value;
+        self
+    }
+
+    /// Set the directory of the backups, it is required by `DB::backup` and BGSAVE.
+    /// Default is no backup.
+    ///
+    /// The backups are made from a staging db in the `staging` sub directory, which is a
+    /// full copy of the data, so the directory needs the space of the db plus the changes
+    /// kept by the backups. Every backup reads all the keys of the db to sync the staging
+    /// db, only the changed keys are written and backed up as new files.
+    pub fn backup_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.backup_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the number of latest backups to keep, the older backups are purged after a new
+    /// backup is created. Default is to keep all the backups.
+    pub fn backup_retention(mut self, value: usize) -> Self {
+        self.backup_retention = Some(value);
+        self
+    }
+
+    /// Make the db a primary, listen for the replicas on the address like "127.0.0.1:6380".
+    /// Default is no replication.
+    pub fn replication_listen<A: Into<String>>(mut self, addr: A) -> Self {
+        self.replication_listen = Some(addr.into());
+        self
+    }
+
+    /// Make the db a read only replica of the primary on the address. Default is no
+    /// replication.
+    pub fn replica_of<A: Into<String>>(mut self, addr: A) -> Self {
+        self.replica_of = Some(addr.into());
+        self
+    }
+
+    /// Set the size in bytes of the latest writes kept by the primary, a replica missing the
+    /// older writes syncs all the data again. Default is 16MB.
+    pub fn replication_backlog_size(mut self, value: usize) -> Self {
+        self.replication_backlog_size = value;
+        self
+    }
+
+    /// Run the db as the node `id` of a raft cluster, listen for the other members on `addr`.
+    /// The write commands are executed after they are committed by a majority of the
+    /// members, and only the leader serves the commands. Default is no raft.
+    pub fn raft_node<A: Into<String>>(mut self, id: u64, addr: A) -> Self {
+        self.raft_node = Some(RaftMember {
+            id,
+            addr: addr.into(),
+        });
+        self
+    }
+
+    /// Add a member of the initial raft cluster, the members include this node to bootstrap
+    /// the cluster. A node joining a running cluster is opened without members, and added by
+    /// `DB::raft_add_member` on the leader.
+    pub fn raft_member<A: Into<String>>(mut self, id: u64, addr: A) -> Self {
+        self.raft_members.push(RaftMember {
+            id,
+            addr: addr.into(),
+        });
+        self
+    }
+
+    /// Set the number of applied entries to compact the raft log. Default is 10000.
+    pub fn raft_snapshot_threshold(mut self, value: u64) -> Self {
+        self.raft_snapshot_threshold = value;
+        self
+    }
+
+    /// Split the keys by hash slot into `value` RocksDB instances in the sub directories of
+    /// the path, the db must be always opened with the same number of shards. Default is 1.
+    /// It can't be used with backup, replication or raft.
+    pub fn shards(mut self, value: usize) -> Self {
+        self.shards = value.max(1);
+        self
+    }
+
+    /// Open the db as the node `id` of a Redis Cluster, which is reached by the clients at
+    /// `addr` in `ip:port` form and owns `slots`. The slots assigned later by CLUSTER SETSLOT
+    /// NODE are saved in the db and replace the configured slots of all the nodes on reopen.
+    pub fn cluster_node<I, A>(mut self, id: I, addr: A, slots: Vec<RangeInclusive<u16>>) -> Self
+    where
+        I: Into<String>,
+        A: Into<String>,
+    {
+        self.cluster_node = Some(ClusterNode {
+            id: id.into(),
+            addr: addr.into(),
+            slots,
+        });
+        self
+    }
+
+    /// Add another node of the cluster, see `cluster_node`.
+    pub fn cluster_peer<I, A>(mut self, id: I, addr: A, slots: Vec<RangeInclusive<u16>>) -> Self
+    where
+        I: Into<String>,
+        A: Into<String>,
+    {
+        self.cluster_peers.push(ClusterNode {
+            id: id.into(),
+            addr: addr.into(),
+            slots,
+        });
+        self
+    }
+
+    /// Set the number of the messages buffered for a subscription, and the number of the
+    /// changes buffered for a watch. Default is 1024.
+    pub fn pubsub_buffer_size(mut self, value: usize) -> Self {
+        self.pubsub_buffer_size = value;
+        self
+    }
+
+    /// Set the time PUBLISH waits for a subscription with a full buffer, the subscription is
+    /// disconnected after the timeout. Default is 1 second.
+    pub fn pubsub_publish_timeout(mut self, value: Duration) -> Self {
+        self.pubsub_publish_timeout = value;
+        self
+    }
+
+    /// Publish the keyspace events of the classes in `value` after the writes are committed,
+    /// the classes are the characters of `notify-keyspace-events` of redis like "KEA" or
+    /// "Kx". The events are published to `__keyspace@0__:<key>` and `__keyevent@0__:<event>`.
+    /// Default is "", no event is published.
+    pub fn notify_keyspace_events<S: Into<String>>(mut self, value: S) -> Self {
+        self.notify_keyspace_events = value.into();
+        self
+    }
+
+    /// Set if capture the changes of the committed writes, which are read by
+    /// `DB::changes_since`. Default is false. It can't be used with shards.
+    pub fn change_capture(mut self, value: bool) -> Self {
+        self.change_capture = value;
+        self
+    }
+
+    /// Set the number of the latest write txns whose changes are kept. Default is 100000.
+    pub fn change_retention(mut self, value: u64) -> Self {
+        self.change_retention = value;
+        self
+    }
+
+    /// Keep the data in memory, the path of `open` is ignored, and `open_in_memory` opens the
+    /// db without a path. Every in-memory db is isolated, and its data is dropped with the db.
+    /// Unlike RocksDB, where the txns writing the same key wait for each other, the conflicts
+    /// are found at commit: the txn committing later fails, and the command or the atomic
+    /// closure is run again. It can't be used with backup, replication or raft. Default is on
+    /// disk.
+    pub fn in_memory(mut self) -> Self {
+        self.in_memory = true;
+        self
+    }
+
+    /// Open an existing db read only, the write commands fail with READONLY. The db can be
+    /// opened read only while it's written by another process, but the writes after it's
+    /// opened are not seen. It can't be used with in-memory, backup, replication or raft.
+    /// Default is read write.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Open the db as a secondary instance of the db in `primary_path`, which may be written
+    /// by another process at the same time. The secondary is read only like `read_only`,
+    /// it keeps its own files in the path of `open`, and reads the writes of the primary
+    /// after it's opened by `DB::try_catch_up`. It can't be used with shards.
+    pub fn secondary<P: AsRef<Path>>(mut self, primary_path: P) -> Self {
+        self.secondary_of = Some(primary_path.as_ref().to_path_buf());
+        self
+    }
+
     /// Open the db with a given path.
     /// It will create or open the fold in path which provide rocksdb storage.
     pub async fn open<P: AsRef<Path>>(self, path: P) -> Result<DB> {
-        let inner = DBInner::open(path, self.gc_enabled).await?;
+        let inner = DBInner::open(path, &self).await?;
         let inner = Arc::new(inner);
         Ok(DB { inner })
     }
+
+    /// Open an in-memory db, see `in_memory`.
+    pub async fn open_in_memory(self) -> Result<DB> {
+        self.in_memory().open("").await
+    }
 }
 
 impl Default for OpenOptions {
@@ -64,6 +317,27 @@ impl Default for OpenOptions {
         Self {
             gc_enabled: false,
             gc_interval: u64::MAX,
+            journal_dir: None,
+            journal_file_size: 64 * 1024 * 1024,
+            backup_dir: None,
+            backup_retention: None,
+            replication_listen: None,
+            replica_of: None,
+            replication_backlog_size: 16 * 1024 * 1024,
+            raft_node: None,
+            raft_members: vec![],
+            raft_snapshot_threshold: 10000,
+            shards: 1,
+            cluster_node: None,
+            cluster_peers: vec![],
+            pubsub_buffer_size: 1024,
+            pubsub_publish_timeout: Duration::from_secs(1),
+            notify_keyspace_events: String::new(),
+            change_capture: false,
+            change_retention: 100_000,
+            in_memory: false,
+            read_only: false,
+            secondary_of: None,
         }
     }
 }
@@ -97,19 +371,263 @@ impl DB {
         Ok(db)
     }
 
+    /// Execute the commands in the journal in `dir` from the sequence number `from_seq`,
+    /// the replayed commands are not recorded in the journal of this db.
+    /// Returns the number of the replayed commands.
+    pub async fn replay_journal<P: AsRef<Path>>(&self, dir: P, from_seq: u64) -> Result<u64> {
+        self.check_writable()?;
+        journal::replay(dir, from_seq, &self.inner).await
+    }
+
+    /// Create a consistent copy of the db in `path` while the db is online, the copy can be
+    /// opened as a db. `path` must not exist.
+    /// The keys are copied one by one instead of hard linking the files, so it takes the
+    /// time of reading and writing the whole db, and the space of a full copy.
+    /// Every shard of a sharded db is copied to its sub directory of `path` separately.
+    pub async fn checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        if self.inner.shards.len() == 1 {
+            let db = self.inner.client.db()?;
+            spawn_blocking(move || checkpoint(&db, path)).await??;
+            return Ok(());
+        }
+        if path.exists() {
+            return Err(CHECKPOINT_EXISTS_ERR.into());
+        }
+        for (i, shard) in self.inner.shards.iter().enumerate() {
+            let db = shard.db()?;
+            let shard_path = path.join(format!("shard-{i}"));
+            spawn_blocking(move || checkpoint(&db, shard_path)).await??;
+        }
+        Ok(())
+    }
+
+    /// Create an incremental backup in the backup directory, returns the id of the backup.
+    pub async fn backup(&self) -> Result<u32> {
+        let backup = self.backup_config()?;
+        let db = self.inner.client.db()?;
+        Ok(spawn_blocking(move || backup.create(&db)).await??)
+    }
+
+    /// Return the backups in the backup directory ordered by id.
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>> {
+        Ok(self.backup_config()?.list()?)
+    }
+
+    /// Restore the backup with `id` into `dir`, which can be opened as a db.
+    pub async fn restore_backup<P: AsRef<Path>>(&self, id: u32, dir: P) -> Result<()> {
+        let backup = self.backup_config()?;
+        let dir = dir.as_ref().to_path_buf();
+        spawn_blocking(move || backup.restore(id, dir)).await??;
+        Ok(())
+    }
+
+    /// Return the stream of the changes of the write txns after the sequence number `seq`,
+    /// starting from the oldest kept changes if `seq` is 0. The stream waits for the new
+    /// changes after the saved ones, pass the `seq` of the last received change to resume.
+    /// It fails if the changes after `seq` are no longer kept.
+    pub fn changes_since(&self, seq: u64) -> Result<ChangeStream> {
+        let log = self
+            .inner
+            .client
+            .change_log()
+            .ok_or(CHANGE_CAPTURE_NOT_ENABLED_ERR)?;
+        Ok(log.stream(self.inner.client.engine(), seq))
+    }
+
+    /// Read the writes of the primary since the db is opened or last caught up, if the db is
+    /// a secondary, see `OpenOptions::secondary`.
+    pub async fn try_catch_up(&self) -> Result<()> {
+        let client = self.inner.client.clone();
+        spawn_blocking(move || {
+            client.engine().try_catch_up()?;
+            // the keys written by the primary are not counted
+            client.reload_key_count()
+        })
+        .await??;
+        Ok(())
+    }
+
+    /// Return the address listened on for the replicas if the db is a primary.
+    pub fn replication_addr(&self) -> Option<SocketAddr> {
+        self.inner.replication_addr
+    }
+
+    /// A replica is only written by the replication.
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        if self.inner.client.is_read_only() {
+            return Err(REDIS_READONLY_ERR.into());
+        }
+        if self.inner.raft.is_some() {
+            return Err(RAFT_DIRECT_WRITE_ERR.into());
+        }
+        Ok(())
+    }
+
+    /// Return the status of this node if the db is in the raft mode.
+    pub fn raft_status(&self) -> Option<RaftStatus> {
+        self.inner.raft.as_ref().map(|raft| raft.status())
+    }
+
+    /// Add a member to the raft cluster, or change the address of a member.
+    /// It must be called on the leader, and only one member is changed at a time.
+    pub async fn raft_add_member<A: Into<String>>(&self, id: u64, addr: A) -> Result<()> {
+        let member = RaftMember {
+            id,
+            addr: addr.into(),
+        };
+        self.raft_config()?.add_member(member).await
+    }
+
+    /// Remove a member from the raft cluster, it must be called on the leader.
+    pub async fn raft_remove_member(&self, id: u64) -> Result<()> {
+        self.raft_config()?.remove_member(id).await
+    }
+
+    fn raft_config(&self) -> Result<&Arc<Raft>> {
+        self.inner
+            .raft
+            .as_ref()
+            .ok_or_else(|| RAFT_NOT_ENABLED_ERR.into())
+    }
+
+    fn backup_config(&self) -> Result<Arc<Backup>> {
+        self.inner
+            .client
+            .backup()
+            .ok_or_else(|| REDIS_BACKUP_NOT_CONFIGURED_ERR.into())
+    }
+
+    /// Take a read only snapshot of the db, the commands executed on it see the data at
+    /// this time until it's dropped.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self.inner.clone())
+    }
+
     pub fn conn(&self) -> Conn {
         Conn {
             inner: self.inner.clone(),
+            asking: AtomicBool::new(false),
         }
     }
 }
 
 pub struct Conn {
     pub(crate) inner: Arc<DBInner>,
+    // set by ASKING for the next command
+    asking: AtomicBool,
 }
 
 impl Conn {
+    /// Subscribe the channels, the messages are received from the returned stream.
+    pub fn subscribe(&self, channels: &[impl ToString]) -> Subscription {
+        let mut subscription = Subscription::new(self.inner.pubsub.clone());
+        subscription.subscribe(channels);
+        subscription
+    }
+
+    /// Subscribe the channels matching the glob-style patterns, the messages are received
+    /// from the returned stream.
+    pub fn psubscribe(&self, patterns: &[impl ToString]) -> Subscription {
+        let mut subscription = Subscription::new(self.inner.pubsub.clone());
+        subscription.psubscribe(patterns);
+        subscription
+    }
+
+    /// Watch the changes of the keys starting with `prefix`, the changes are received from
+    /// the returned stream after the txns are committed.
+    pub fn watch_prefix(&self, prefix: impl AsRef<[u8]>) -> Watch {
+        Watch::new(self.inner.watchers.clone(), prefix.as_ref().to_vec())
+    }
+
+    /// Same as `watch_prefix`, but the changes creating the existing keys starting with
+    /// `prefix` are received first, which build a view of the keys to apply the changes to.
+    pub fn watch_prefix_with_snapshot(&self, prefix: impl AsRef<[u8]>) -> Result<Watch> {
+        let prefix = prefix.as_ref().to_vec();
+        // watch before reading the snapshot, so no change is missed
+        let mut watch = Watch::new(self.inner.watchers.clone(), prefix.clone());
+        let mut snapshot = vec![];
+        for shard in &self.inner.shards {
+            snapshot.extend(StringCommand::new(shard).snapshot_changes(&prefix)?);
+        }
+        watch.set_snapshot(snapshot);
+        Ok(watch)
+    }
+
+    /// Run the async closure atomically in a single txn with typed access to the data, the
+    /// writes are discarded if the closure returns `Err`. The closure is run again if the txn
+    /// conflicts with another one, and the txn is committed once it succeeds.
+    ///
+    /// The writes are recorded in the journal as separate commands. In raft mode the closure
+    /// can only read, as the writes must be proposed as commands.
+    pub async fn atomic<T, F>(&self, f: F) -> Result<T>
+    where
+        F: AsyncFnMut(&mut Tx<'_>) -> Result<T>,
+    {
+        if let Some(raft) = &self.inner.raft {
+            raft.read_barrier().await?;
+        }
+        let (value, writes) = atomic::run(&self.inner, f).await?;
+        if let Some(journal) = &self.inner.journal {
+            for cmd in &writes {
+                journal.append(cmd)?;
+            }
+        }
+        Ok(value)
+    }
+
     pub async fn execute(&self, cmd: Command) -> crate::Result<Frame> {
-        cmd.execute(&self.inner).await
+        let asking = self.asking.swap(false, Ordering::Relaxed);
+        // the cached scripts are replaced by their code, so the journal and the raft log
+        // don't depend on the cache
+        let cmd = self.inner.scripts.resolve(cmd);
+        if let Some(cluster) = &self.inner.cluster {
+            if let Command::Asking(_) = cmd {
+                self.asking.store(true, Ordering::Relaxed);
+                return Ok(resp_ok());
+            }
+            if let Some(frame) = cluster.redirect(&cmd, asking, &self.inner).await? {
+                return Ok(frame);
+            }
+        }
+        if cmd.is_write() && self.inner.client.is_read_only() {
+            return Ok(resp_err(REDIS_READONLY_ERR));
+        }
+        if !cmd.is_write() {
+            if let Some(raft) = &self.inner.raft {
+                if let Err(e) = raft.read_barrier().await {
+                    return Ok(resp_err(e));
+                }
+            }
+            return cmd.execute(&self.inner).await;
+        }
+
+        // the relative expire times are made absolute like the entries of the raft log, so
+        // the journal sets the same ttls when it's replayed
+        let cmd = cmd.with_absolute_expire();
+        // the gc only removes the data of the deleted keys, the journal doesn't need it
+        let journaled = self
+            .inner
+            .journal
+            .as_ref()
+            .filter(|_| !matches!(cmd, Command::Gc(_)))
+            .map(|_| cmd.clone());
+        let frame = match &self.inner.raft {
+            Some(raft) => raft.propose(cmd).await?,
+            None => cmd.execute(&self.inner).await?,
+        };
+        if let (Some(journal), Some(cmd)) = (&self.inner.journal, journaled) {
+            if !matches!(frame, Frame::Error(_) | Frame::TxnFailed(_)) {
+                // SPOP is replayed with the members it popped
+                let cmd = match cmd {
+                    Command::Spop(spop) => spop
+                        .with_popped(&frame)
+                        .map_or(Command::Spop(spop), Command::Spop),
+                    cmd => cmd,
+                };
+                journal.append(&cmd)?;
+            }
+        }
+        Ok(frame)
     }
 }
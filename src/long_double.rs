@@ -0,0 +1,418 @@
+//! Software 80-bit extended precision floats, the `long double` of x86 used by Redis for
+//! INCRBYFLOAT and HINCRBYFLOAT.
+//!
+//! The values are parsed, added and formatted with the same rounding as the C library, so
+//! the results match Redis, like "0.3" for 0.1 plus 0.2 instead of "0.30000000000000004".
+//! The subnormal values are flushed to zero.
+
+use std::cmp::Ordering;
+
+// the value of a normalized float is `mant * 2^exp`, the top bit of `mant` is set
+const MAX_EXP: i32 = 16384 - 64;
+const MIN_EXP: i32 = -16382 - 63;
+
+// the decimal exponents far out of the range of the floats
+const MAX_EXP10: i64 = 5000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LongDouble {
+    neg: bool,
+    // 0 if the value is zero
+    mant: u64,
+    exp: i32,
+}
+
+impl LongDouble {
+    pub(crate) const ZERO: LongDouble = LongDouble {
+        neg: false,
+        mant: 0,
+        exp: 0,
+    };
+
+    /// Parse a decimal number like `strtold`, an optional sign, the digits with an optional
+    /// decimal point, and an optional exponent. Return None if it's not a number or it
+    /// overflows, the infinities and NaN are not accepted.
+    pub(crate) fn parse(s: &[u8]) -> Option<LongDouble> {
+        let (neg, s) = match s.first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let mut digits = Big::zero();
+        let mut exp10: i64 = 0;
+        let mut has_digits = false;
+        let mut i = 0;
+        let mut in_frac = false;
+        while i < s.len() {
+            match s[i] {
+                b'0'..=b'9' => {
+                    has_digits = true;
+                    // the digits beyond the precision only decide the rounding
+                    if digits.bit_len() < 20000 {
+                        digits.mul_small(10);
+                        digits.add_small((s[i] - b'0') as u32);
+                        if in_frac {
+                            exp10 -= 1;
+                        }
+                    } else if !in_frac {
+                        exp10 += 1;
+                    }
+                }
+                b'.' if !in_frac => in_frac = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        if !has_digits {
+            return None;
+        }
+        if i < s.len() {
+            if s[i] != b'e' && s[i] != b'E' {
+                return None;
+            }
+            let exp: i64 = std::str::from_utf8(&s[i + 1..]).ok()?.parse().ok()?;
+            exp10 = exp10.checked_add(exp)?;
+        }
+        if digits.is_zero() {
+            return Some(LongDouble { neg, ..Self::ZERO });
+        }
+        if exp10 > MAX_EXP10 {
+            return None;
+        }
+        // the value is below 10^(count + exp10) with the count of the significant digits, skip
+        // the long division when it's far below the smallest float
+        let count = digits.bit_len() as i64 * 30103 / 100000 + 1;
+        if exp10 + count < -MAX_EXP10 - 64 {
+            return Some(LongDouble { neg, ..Self::ZERO });
+        }
+        if exp10 >= 0 {
+            digits.mul_pow10(exp10 as u32);
+            return Self::round(neg, &digits, 0, false);
+        }
+        // divide by the power of 10 with at least 2 more bits than the mantissa
+        let mut den = Big::from_u64(1);
+        den.mul_pow10((-exp10) as u32);
+        let shift = (66 + den.bit_len()).saturating_sub(digits.bit_len());
+        digits.shl(shift);
+        let (quot, inexact) = digits.div_rem(&den);
+        Self::round(neg, &quot, -(shift as i32), inexact)
+    }
+
+    /// Convert the float with its shortest decimal representation, like parsing the number
+    /// received in a command. Return None if the float is not finite.
+    pub(crate) fn from_f64(f: f64) -> Option<LongDouble> {
+        if !f.is_finite() {
+            return None;
+        }
+        Self::parse(f.to_string().as_bytes())
+    }
+
+    /// Return the sum, None if it overflows.
+    pub(crate) fn add(self, other: LongDouble) -> Option<LongDouble> {
+        if other.mant == 0 {
+            return Some(self);
+        }
+        if self.mant == 0 {
+            return Some(other);
+        }
+        let (big, small) = if (self.exp, self.mant) >= (other.exp, other.mant) {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        // the smaller value is below the rounding of the larger one
+        if (big.exp as i64) - (small.exp as i64) > 130 {
+            return Some(big);
+        }
+        let exp = big.exp.min(small.exp);
+        let mut a = Big::from_u64(big.mant);
+        a.shl((big.exp - exp) as u32);
+        let mut b = Big::from_u64(small.mant);
+        b.shl((small.exp - exp) as u32);
+        if big.neg == small.neg {
+            a.add(&b);
+            return Self::round(big.neg, &a, exp, false);
+        }
+        match a.cmp(&b) {
+            Ordering::Equal => Some(Self::ZERO),
+            Ordering::Greater => {
+                a.sub(&b);
+                Self::round(big.neg, &a, exp, false)
+            }
+            Ordering::Less => {
+                b.sub(&a);
+                Self::round(small.neg, &b, exp, false)
+            }
+        }
+    }
+
+    /// Format like Redis, `%.17Lf` without the trailing zeros.
+    pub(crate) fn to_human_string(self) -> String {
+        if self.mant == 0 {
+            return "0".to_owned();
+        }
+        let mut scaled = Big::from_u64(self.mant);
+        scaled.mul_pow10(17);
+        if self.exp >= 0 {
+            scaled.shl(self.exp as u32);
+        } else {
+            let shift = (-self.exp) as u32;
+            let half = scaled.bit(shift - 1);
+            let rest = scaled.low_bits_nonzero(shift - 1);
+            scaled.shr(shift);
+            if half && (rest || scaled.bit(0)) {
+                scaled.add_small(1);
+            }
+        }
+        let digits = scaled.to_decimal();
+        let digits = format!("{digits:0>18}");
+        let (int, frac) = digits.split_at(digits.len() - 17);
+        let frac = frac.trim_end_matches('0');
+        if int == "0" && frac.is_empty() {
+            return "0".to_owned();
+        }
+        let sign = if self.neg { "-" } else { "" };
+        if frac.is_empty() {
+            format!("{sign}{int}")
+        } else {
+            format!("{sign}{int}.{frac}")
+        }
+    }
+
+    // round `n * 2^exp` to the nearest float, the ties to even, `inexact` is true if the
+    // exact value is a bit larger than `n * 2^exp`
+    fn round(neg: bool, n: &Big, exp: i32, inexact: bool) -> Option<LongDouble> {
+        let bits = n.bit_len();
+        if bits == 0 {
+            return Some(LongDouble { neg, ..Self::ZERO });
+        }
+        let (mut mant, mut exp) = if bits <= 64 {
+            (n.low_u64() << (64 - bits), exp - (64 - bits) as i32)
+        } else {
+            let shift = bits - 64;
+            let mut m = n.clone();
+            m.shr(shift);
+            let mut mant = m.low_u64();
+            let half = n.bit(shift - 1);
+            let rest = inexact || n.low_bits_nonzero(shift - 1);
+            let mut exp = exp + shift as i32;
+            if half && (rest || mant & 1 == 1) {
+                mant = mant.wrapping_add(1);
+                if mant == 0 {
+                    mant = 1 << 63;
+                    exp += 1;
+                }
+            }
+            (mant, exp)
+        };
+        if exp > MAX_EXP {
+            return None;
+        }
+        if exp < MIN_EXP {
+            mant = 0;
+            exp = 0;
+        }
+        Some(LongDouble { neg, mant, exp })
+    }
+}
+
+// an unsigned big integer of little endian 32-bit words
+#[derive(Clone, Debug)]
+struct Big(Vec<u32>);
+
+impl Big {
+    fn zero() -> Self {
+        Big(vec![])
+    }
+
+    fn from_u64(n: u64) -> Self {
+        let mut big = Big(vec![n as u32, (n >> 32) as u32]);
+        big.trim();
+        big
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn bit_len(&self) -> u32 {
+        match self.0.last() {
+            Some(top) => self.0.len() as u32 * 32 - top.leading_zeros(),
+            None => 0,
+        }
+    }
+
+    fn bit(&self, i: u32) -> bool {
+        let word = (i / 32) as usize;
+        word < self.0.len() && self.0[word] >> (i % 32) & 1 == 1
+    }
+
+    // whether any of the lowest `n` bits is set
+    fn low_bits_nonzero(&self, n: u32) -> bool {
+        let words = (n / 32) as usize;
+        if self.0.iter().take(words).any(|w| *w != 0) {
+            return true;
+        }
+        let bits = n % 32;
+        bits > 0 && words < self.0.len() && self.0[words] & ((1 << bits) - 1) != 0
+    }
+
+    fn low_u64(&self) -> u64 {
+        let lo = self.0.first().copied().unwrap_or(0) as u64;
+        let hi = self.0.get(1).copied().unwrap_or(0) as u64;
+        hi << 32 | lo
+    }
+
+    fn mul_small(&mut self, m: u32) {
+        let mut carry = 0u64;
+        for w in self.0.iter_mut() {
+            let v = *w as u64 * m as u64 + carry;
+            *w = v as u32;
+            carry = v >> 32;
+        }
+        if carry > 0 {
+            self.0.push(carry as u32);
+        }
+        self.trim();
+    }
+
+    fn add_small(&mut self, a: u32) {
+        let mut carry = a as u64;
+        for w in self.0.iter_mut() {
+            if carry == 0 {
+                break;
+            }
+            let v = *w as u64 + carry;
+            *w = v as u32;
+            carry = v >> 32;
+        }
+        if carry > 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    fn mul_pow10(&mut self, mut exp: u32) {
+        while exp >= 9 {
+            self.mul_small(1_000_000_000);
+            exp -= 9;
+        }
+        self.mul_small(10u32.pow(exp));
+    }
+
+    fn shl(&mut self, n: u32) {
+        if self.is_zero() {
+            return;
+        }
+        let (words, bits) = ((n / 32) as usize, n % 32);
+        if bits > 0 {
+            let mut carry = 0;
+            for w in self.0.iter_mut() {
+                let v = *w;
+                *w = v << bits | carry;
+                carry = v >> (32 - bits);
+            }
+            if carry > 0 {
+                self.0.push(carry);
+            }
+        }
+        self.0.splice(0..0, std::iter::repeat_n(0, words));
+    }
+
+    fn shr(&mut self, n: u32) {
+        let (words, bits) = ((n / 32) as usize, n % 32);
+        if words >= self.0.len() {
+            self.0.clear();
+            return;
+        }
+        self.0.drain(..words);
+        if bits > 0 {
+            let len = self.0.len();
+            for i in 0..len {
+                let hi = if i + 1 < len {
+                    self.0[i + 1] << (32 - bits)
+                } else {
+                    0
+                };
+                self.0[i] = self.0[i] >> bits | hi;
+            }
+        }
+        self.trim();
+    }
+
+    fn cmp(&self, other: &Big) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+
+    fn add(&mut self, other: &Big) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        let mut carry = 0u64;
+        for (i, w) in self.0.iter_mut().enumerate() {
+            let v = *w as u64 + other.0.get(i).copied().unwrap_or(0) as u64 + carry;
+            *w = v as u32;
+            carry = v >> 32;
+        }
+        if carry > 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    // `other` must not be larger than `self`
+    fn sub(&mut self, other: &Big) {
+        let mut borrow = 0i64;
+        for (i, w) in self.0.iter_mut().enumerate() {
+            let v = *w as i64 - other.0.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = (v < 0) as i64;
+            *w = v.rem_euclid(1 << 32) as u32;
+        }
+        self.trim();
+    }
+
+    // return the quotient, and whether the remainder is not zero
+    fn div_rem(&self, den: &Big) -> (Big, bool) {
+        let mut quot = Big(vec![0; self.0.len()]);
+        let mut rem = Big::zero();
+        for i in (0..self.bit_len()).rev() {
+            rem.shl(1);
+            if self.bit(i) {
+                rem.add_small(1);
+            }
+            if rem.cmp(den) != Ordering::Less {
+                rem.sub(den);
+                quot.0[(i / 32) as usize] |= 1 << (i % 32);
+            }
+        }
+        quot.trim();
+        (quot, !rem.is_zero())
+    }
+
+    fn to_decimal(&self) -> String {
+        let mut n = self.clone();
+        let mut chunks = vec![];
+        while !n.is_zero() {
+            let mut rem = 0u64;
+            for w in n.0.iter_mut().rev() {
+                let v = rem << 32 | *w as u64;
+                *w = (v / 1_000_000_000) as u32;
+                rem = v % 1_000_000_000;
+            }
+            n.trim();
+            chunks.push(rem as u32);
+        }
+        let mut s = chunks.pop().map(|c| c.to_string()).unwrap_or_default();
+        for c in chunks.iter().rev() {
+            s.push_str(&format!("{c:09}"));
+        }
+        s
+    }
+}
@@ -0,0 +1,317 @@
+//! In-process publish and subscribe.
+//!
+//! Every subscription has a bounded buffer of the messages. A publisher waits for the room in
+//! the buffer of a slow subscriber, and the subscriber is disconnected if it doesn't read in
+//! `OpenOptions::pubsub_publish_timeout`, its stream ends after the buffered messages.
+
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::Stream;
+use glob::Pattern;
+use tokio::sync::mpsc;
+
+use crate::utils::{resp_array, resp_bulk, resp_int};
+use crate::Frame;
+
+/// A message received by a subscription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub channel: String,
+    /// The pattern matching the channel if the message is received by PSUBSCRIBE
+    pub pattern: Option<String>,
+    pub payload: Bytes,
+}
+
+impl Message {
+    /// Return the message in the form pushed to the RESP clients.
+    pub fn frame(&self) -> Frame {
+        let mut frames = vec![];
+        match &self.pattern {
+            Some(pattern) => {
+                frames.push(resp_bulk(b"pmessage".to_vec()));
+                frames.push(resp_bulk(pattern.as_bytes().to_vec()));
+            }
+            None => frames.push(resp_bulk(b"message".to_vec())),
+        }
+        frames.push(resp_bulk(self.channel.as_bytes().to_vec()));
+        frames.push(Frame::Bulk(self.payload.clone()));
+        resp_array(frames)
+    }
+}
+
+#[derive(Default)]
+struct HubState {
+    subscribers: HashMap<u64, mpsc::Sender<Message>>,
+    channels: HashMap<String, HashSet<u64>>,
+    patterns: HashMap<String, (Pattern, HashSet<u64>)>,
+}
+
+impl HubState {
+    fn remove_subscriber(&mut self, id: u64) {
+        self.subscribers.remove(&id);
+        self.channels.retain(|_, ids| {
+            ids.remove(&id);
+            !ids.is_empty()
+        });
+        self.patterns.retain(|_, (_, ids)| {
+            ids.remove(&id);
+            !ids.is_empty()
+        });
+    }
+}
+
+pub(crate) struct PubSub {
+    state: Mutex<HubState>,
+    next_id: AtomicU64,
+    buffer_size: usize,
+    publish_timeout: Duration,
+}
+
+impl PubSub {
+    pub(crate) fn new(buffer_size: usize, publish_timeout: Duration) -> Self {
+        Self {
+            state: Mutex::new(HubState::default()),
+            next_id: AtomicU64::new(0),
+            buffer_size: buffer_size.max(1),
+            publish_timeout,
+        }
+    }
+
+    /// Send the message to the subscribers of the channel and the matching patterns.
+    /// Returns the number of the subscribers received the message.
+    pub(crate) async fn publish(&self, channel: &str, payload: Bytes) -> i64 {
+        let mut targets = vec![];
+        {
+            let state = self.state.lock().unwrap();
+            let message = Message {
+                channel: channel.to_string(),
+                pattern: None,
+                payload,
+            };
+            let mut add_target = |id: &u64, message: Message| {
+                if let Some(sender) = state.subscribers.get(id) {
+                    targets.push((*id, sender.clone(), message));
+                }
+            };
+            for id in state.channels.get(channel).into_iter().flatten() {
+                add_target(id, message.clone());
+            }
+            for (pattern, (matcher, ids)) in &state.patterns {
+                if !matcher.matches(channel) {
+                    continue;
+                }
+                for id in ids {
+                    let mut message = message.clone();
+                    message.pattern = Some(pattern.clone());
+                    add_target(id, message);
+                }
+            }
+        }
+
+        let mut receivers = 0;
+        let mut slow = vec![];
+        for (id, sender, message) in targets {
+            match sender.send_timeout(message, self.publish_timeout).await {
+                Ok(_) => receivers += 1,
+                Err(_) => slow.push(id),
+            }
+        }
+        if !slow.is_empty() {
+            let mut state = self.state.lock().unwrap();
+            for id in slow {
+                state.remove_subscriber(id);
+            }
+        }
+        receivers
+    }
+
+    /// Return the channels with subscribers matching the pattern.
+    pub(crate) fn channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let matcher = pattern.map(Pattern::new).transpose().ok().flatten();
+        let state = self.state.lock().unwrap();
+        let mut channels: Vec<String> = state
+            .channels
+            .keys()
+            .filter(|channel| matcher.as_ref().is_none_or(|m| m.matches(channel)))
+            .cloned()
+            .collect();
+        channels.sort();
+        channels
+    }
+
+    /// Return the number of the subscribers of the channel.
+    pub(crate) fn numsub(&self, channel: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state.channels.get(channel).map_or(0, HashSet::len)
+    }
+
+    /// Return the number of the subscribed patterns.
+    pub(crate) fn numpat(&self) -> usize {
+        self.state.lock().unwrap().patterns.len()
+    }
+}
+
+/// A stream of the messages of the subscribed channels and patterns, it is created by
+/// `Conn::subscribe` or `Conn::psubscribe`. The subscriptions are removed when it is dropped.
+///
+/// The methods return the confirmations of SUBSCRIBE, UNSUBSCRIBE, PSUBSCRIBE and
+/// PUNSUBSCRIBE in the form replied to the RESP clients.
+pub struct Subscription {
+    id: u64,
+    hub: Arc<PubSub>,
+    channels: HashSet<String>,
+    patterns: HashSet<String>,
+    receiver: mpsc::Receiver<Message>,
+}
+
+impl Subscription {
+    pub(crate) fn new(hub: Arc<PubSub>) -> Self {
+        let id = hub.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(hub.buffer_size);
+        hub.state.lock().unwrap().subscribers.insert(id, sender);
+        Self {
+            id,
+            hub,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            receiver,
+        }
+    }
+
+    /// Subscribe the channels.
+    pub fn subscribe(&mut self, channels: &[impl ToString]) -> Vec<Frame> {
+        let mut replies = vec![];
+        for channel in channels {
+            let channel = channel.to_string();
+            {
+                let mut state = self.hub.state.lock().unwrap();
+                // nothing is received after disconnected as a slow subscriber
+                if state.subscribers.contains_key(&self.id) {
+                    state
+                        .channels
+                        .entry(channel.clone())
+                        .or_default()
+                        .insert(self.id);
+                }
+            }
+            self.channels.insert(channel.clone());
+            replies.push(self.reply("subscribe", Some(channel)));
+        }
+        replies
+    }
+
+    /// Subscribe the channels matching the glob-style patterns.
+    pub fn psubscribe(&mut self, patterns: &[impl ToString]) -> Vec<Frame> {
+        let mut replies = vec![];
+        for pattern in patterns {
+            let pattern = pattern.to_string();
+            let Ok(matcher) = Pattern::new(&pattern) else {
+                // an invalid pattern matches nothing
+                self.patterns.insert(pattern.clone());
+                replies.push(self.reply("psubscribe", Some(pattern)));
+                continue;
+            };
+            {
+                let mut state = self.hub.state.lock().unwrap();
+                if state.subscribers.contains_key(&self.id) {
+                    state
+                        .patterns
+                        .entry(pattern.clone())
+                        .or_insert_with(|| (matcher, HashSet::new()))
+                        .1
+                        .insert(self.id);
+                }
+            }
+            self.patterns.insert(pattern.clone());
+            replies.push(self.reply("psubscribe", Some(pattern)));
+        }
+        replies
+    }
+
+    /// Unsubscribe the channels, all the channels are unsubscribed if `channels` is empty.
+    pub fn unsubscribe(&mut self, channels: &[impl ToString]) -> Vec<Frame> {
+        let channels: Vec<String> = if channels.is_empty() {
+            self.channels.iter().cloned().collect()
+        } else {
+            channels.iter().map(ToString::to_string).collect()
+        };
+        if channels.is_empty() {
+            return vec![self.reply("unsubscribe", None)];
+        }
+        let mut replies = vec![];
+        for channel in channels {
+            {
+                let mut state = self.hub.state.lock().unwrap();
+                if let Some(ids) = state.channels.get_mut(&channel) {
+                    ids.remove(&self.id);
+                    if ids.is_empty() {
+                        state.channels.remove(&channel);
+                    }
+                }
+            }
+            self.channels.remove(&channel);
+            replies.push(self.reply("unsubscribe", Some(channel)));
+        }
+        replies
+    }
+
+    /// Unsubscribe the patterns, all the patterns are unsubscribed if `patterns` is empty.
+    pub fn punsubscribe(&mut self, patterns: &[impl ToString]) -> Vec<Frame> {
+        let patterns: Vec<String> = if patterns.is_empty() {
+            self.patterns.iter().cloned().collect()
+        } else {
+            patterns.iter().map(ToString::to_string).collect()
+        };
+        if patterns.is_empty() {
+            return vec![self.reply("punsubscribe", None)];
+        }
+        let mut replies = vec![];
+        for pattern in patterns {
+            {
+                let mut state = self.hub.state.lock().unwrap();
+                if let Some((_, ids)) = state.patterns.get_mut(&pattern) {
+                    ids.remove(&self.id);
+                    if ids.is_empty() {
+                        state.patterns.remove(&pattern);
+                    }
+                }
+            }
+            self.patterns.remove(&pattern);
+            replies.push(self.reply("punsubscribe", Some(pattern)));
+        }
+        replies
+    }
+
+    /// Return the number of the subscribed channels and patterns.
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    fn reply(&self, kind: &str, name: Option<String>) -> Frame {
+        resp_array(vec![
+            resp_bulk(kind.as_bytes().to_vec()),
+            name.map_or(Frame::Null, |name| resp_bulk(name.into_bytes())),
+            resp_int(self.count() as i64),
+        ])
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.hub.state.lock().unwrap().remove_subscriber(self.id);
+    }
+}
@@ -0,0 +1,1307 @@
+//! Raft replicated mode.
+//!
+//! The write commands are proposed to a raft log, and executed on every node after their
+//! entries are committed by a majority of the members. Only the leader serves the commands.
+//! The reads are linearizable by the leader lease: the leader serves the reads only if a
+//! majority of the members acknowledged it within the lease, and a member doesn't vote for
+//! another node until the election timeout passes after it hears from the leader, or after
+//! it starts.
+//!
+//! The raft state and log are stored in the default column family of the db, the data is in
+//! the other column families. The executed commands are the state machine, so the log is
+//! compacted after a number of entries are applied, and a member behind the compacted log is
+//! sent a checkpoint of the db instead. A member is added or removed by an entry of the new
+//! members, one at a time.
+//!
+//! The commands are rewritten by the leader before they are proposed, so they give the same
+//! result on every member: the relative expire times become absolute, and SPOP pops the
+//! members it pops on the leader. The applied index is saved in the txn of the command, so a
+//! command is never applied twice after a crash.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+use rand::Rng;
+use rocksdb::{Direction, IteratorMode, TransactionDB, WriteBatchWithTransaction};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{self as aio, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, watch, Mutex as AsyncMutex, Notify};
+use tokio::task::spawn_blocking;
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::cmd::{Command, Spop};
+use crate::config::txn_retry_count;
+use crate::db::DBInner;
+use crate::pubsub::PubSub;
+use crate::rocks::backup::{checkpoint, open_plain_db, restore_db};
+use crate::rocks::client::{ClientRef, RocksClient};
+use crate::rocks::errors::{
+    RError, RAFT_MEMBERSHIP_CHANGING_ERR, RAFT_PROPOSAL_DROPPED_ERR, RAFT_PROPOSAL_TIMEOUT_ERR,
+    TXN_ERROR,
+};
+use crate::rocks::Result as RocksResult;
+use crate::script::Scripts;
+use crate::utils::{resp_err, resp_ok};
+use crate::watch::Watchers;
+use crate::{Frame, Result};
+
+// keys in the default column family, which is never used by the data
+const HARD_STATE_KEY: &[u8] = b"mapuche_raft_hard_state";
+const SNAPSHOT_META_KEY: &[u8] = b"mapuche_raft_snapshot";
+const APPLIED_INDEX_KEY: &[u8] = b"mapuche_raft_applied";
+const LOG_KEY_PREFIX: &[u8] = b"mapuche_raft_log_";
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(50);
+const ELECTION_TIMEOUT_MIN: Duration = Duration::from_millis(300);
+const ELECTION_TIMEOUT_MAX: Duration = Duration::from_millis(600);
+// shorter than the election timeout, so no other leader is elected in the lease
+const LEASE_DURATION: Duration = Duration::from_millis(250);
+const RPC_TIMEOUT: Duration = Duration::from_millis(200);
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(60);
+const PROPOSAL_TIMEOUT: Duration = Duration::from_secs(10);
+// max number of entries in an append request
+const MAX_APPEND_ENTRIES: usize = 1000;
+
+/// A member of the raft cluster.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RaftMember {
+    pub id: u64,
+    /// address the member listens on for the other members
+    pub addr: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaftRole {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// Status of a node in the raft cluster.
+#[derive(Debug, Clone)]
+pub struct RaftStatus {
+    pub id: u64,
+    pub role: RaftRole,
+    pub term: u64,
+    pub leader: Option<u64>,
+    pub commit_index: u64,
+    pub applied_index: u64,
+    pub members: Vec<RaftMember>,
+}
+
+pub(crate) struct RaftConfig {
+    pub(crate) id: u64,
+    pub(crate) addr: String,
+    // members of the initial cluster, used if there is no membership in the log
+    pub(crate) members: Vec<RaftMember>,
+    // number of applied entries to compact the log
+    pub(crate) snapshot_threshold: u64,
+    // directory of the checkpoints sent and received
+    pub(crate) dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry {
+    index: u64,
+    term: u64,
+    payload: Payload,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+enum Payload {
+    // appended by a new leader to commit the entries of the previous terms
+    Noop,
+    Command(Command),
+    Members(Vec<RaftMember>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct HardState {
+    term: u64,
+    voted_for: Option<u64>,
+}
+
+/// The last entry compacted from the log.
+#[derive(Serialize, Deserialize, Clone)]
+struct SnapshotMeta {
+    index: u64,
+    term: u64,
+    members: Vec<RaftMember>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Request {
+    Vote {
+        term: u64,
+        candidate: u64,
+        last_index: u64,
+        last_term: u64,
+    },
+    Append {
+        term: u64,
+        leader: u64,
+        prev_index: u64,
+        prev_term: u64,
+        entries: Vec<Entry>,
+        commit: u64,
+    },
+    // followed by the content of the files of the checkpoint
+    Snapshot {
+        term: u64,
+        leader: u64,
+        meta: SnapshotMeta,
+        files: Vec<(String, u64)>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum Response {
+    Vote {
+        term: u64,
+        granted: bool,
+    },
+    Append {
+        term: u64,
+        success: bool,
+        last_index: u64,
+    },
+    Snapshot {
+        term: u64,
+    },
+}
+
+struct RaftState {
+    role: RaftRole,
+    hard: HardState,
+    leader: Option<u64>,
+    snapshot: SnapshotMeta,
+    // entries after the snapshot
+    log: Vec<Entry>,
+    commit_index: u64,
+    applied_index: u64,
+    // first entry of the current leader, the reads wait until it is applied
+    term_start: u64,
+    next_index: HashMap<u64, u64>,
+    match_index: HashMap<u64, u64>,
+    // the time of the latest request acknowledged by the members
+    acked_at: HashMap<u64, Instant>,
+    // the time of the latest request from the leader
+    leader_seen_at: Option<Instant>,
+    election_deadline: Instant,
+}
+
+impl RaftState {
+    fn last_index(&self) -> u64 {
+        self.log
+            .last()
+            .map_or(self.snapshot.index, |entry| entry.index)
+    }
+
+    fn last_term(&self) -> u64 {
+        self.log
+            .last()
+            .map_or(self.snapshot.term, |entry| entry.term)
+    }
+
+    fn entry(&self, index: u64) -> Option<&Entry> {
+        if index <= self.snapshot.index {
+            return None;
+        }
+        self.log.get((index - self.snapshot.index - 1) as usize)
+    }
+
+    fn term_at(&self, index: u64) -> Option<u64> {
+        if index == self.snapshot.index {
+            return Some(self.snapshot.term);
+        }
+        self.entry(index).map(|entry| entry.term)
+    }
+
+    /// Return the latest members in the log up to `index`.
+    fn members_at(&self, index: u64) -> &[RaftMember] {
+        self.log
+            .iter()
+            .rev()
+            .filter(|entry| entry.index <= index)
+            .find_map(|entry| match &entry.payload {
+                Payload::Members(members) => Some(members.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&self.snapshot.members)
+    }
+
+    /// The members take effect once they are in the log.
+    fn members(&self) -> &[RaftMember] {
+        self.members_at(u64::MAX)
+    }
+
+    fn is_member(&self, id: u64) -> bool {
+        self.members().iter().any(|member| member.id == id)
+    }
+
+    fn membership_changing(&self) -> bool {
+        self.log.iter().any(|entry| {
+            entry.index > self.commit_index && matches!(entry.payload, Payload::Members(_))
+        })
+    }
+
+    fn reset_election_deadline(&mut self) {
+        let timeout = rand::thread_rng().gen_range(ELECTION_TIMEOUT_MIN..ELECTION_TIMEOUT_MAX);
+        self.election_deadline = Instant::now() + timeout;
+    }
+
+    fn become_follower(&mut self, term: u64) {
+        if term > self.hard.term {
+            self.hard.term = term;
+            self.hard.voted_for = None;
+            self.leader = None;
+        }
+        self.role = RaftRole::Follower;
+    }
+}
+
+struct Proposal {
+    term: u64,
+    tx: oneshot::Sender<Result<Frame>>,
+}
+
+struct Peer {
+    addr: String,
+    // one request is sent to a peer at a time
+    conn: AsyncMutex<Option<TcpStream>>,
+}
+
+pub(crate) struct Raft {
+    id: u64,
+    dir: PathBuf,
+    snapshot_threshold: u64,
+    db: Arc<TransactionDB>,
+    // executes the committed commands, without journal or raft
+    apply_db: DBInner,
+    state: Mutex<RaftState>,
+    // held while applying the entries or taking and installing a checkpoint
+    apply_lock: AsyncMutex<()>,
+    commit_notify: Notify,
+    applied: watch::Sender<u64>,
+    pending: Mutex<HashMap<u64, Proposal>>,
+    peers: Mutex<HashMap<u64, Arc<Peer>>>,
+    // cancelled when the db is dropped, the tasks of the node exit and release the db
+    shutdown: CancellationToken,
+    started_at: Instant,
+}
+
+impl Raft {
+    /// Load the raft state of the db, and start serving the other members.
+    pub(crate) async fn start(config: RaftConfig, client: Arc<RocksClient>) -> Result<Arc<Self>> {
+        let db = client.db()?;
+        let hard = get_json(&db, HARD_STATE_KEY)?.unwrap_or_default();
+        let snapshot = get_json(&db, SNAPSHOT_META_KEY)?.unwrap_or(SnapshotMeta {
+            index: 0,
+            term: 0,
+            members: config.members,
+        });
+        let log = load_log(&db, snapshot.index)?;
+        let applied_index = get_json::<u64>(&db, APPLIED_INDEX_KEY)?
+            .unwrap_or(0)
+            .max(snapshot.index);
+
+        let mut state = RaftState {
+            role: RaftRole::Follower,
+            hard,
+            leader: None,
+            snapshot,
+            log,
+            commit_index: applied_index,
+            applied_index,
+            term_start: 0,
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            acked_at: HashMap::new(),
+            leader_seen_at: None,
+            election_deadline: Instant::now(),
+        };
+        state.reset_election_deadline();
+
+        let listener = TcpListener::bind(&config.addr).await?;
+        let raft = Arc::new(Self {
+            id: config.id,
+            dir: config.dir,
+            snapshot_threshold: config.snapshot_threshold,
+            db,
+            apply_db: DBInner {
+                shards: vec![client.clone()],
+                cluster: None,
+                pubsub: Arc::new(PubSub::new(1, Duration::ZERO)),
+                watchers: Arc::new(Watchers::new(1)),
+                scripts: Arc::new(Scripts::deterministic()),
+                client,
+                journal: None,
+                replication_addr: None,
+                raft: None,
+            },
+            state: Mutex::new(state),
+            apply_lock: AsyncMutex::new(()),
+            commit_notify: Notify::new(),
+            applied: watch::channel(applied_index).0,
+            pending: Mutex::new(HashMap::new()),
+            peers: Mutex::new(HashMap::new()),
+            shutdown: CancellationToken::new(),
+            started_at: Instant::now(),
+        });
+        tokio::spawn(raft.clone().serve(listener));
+        tokio::spawn(raft.clone().tick());
+        tokio::spawn(raft.clone().apply());
+        Ok(raft)
+    }
+
+    /// Stop the node, it no longer serves the other members.
+    pub(crate) fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    pub(crate) fn status(&self) -> RaftStatus {
+        let state = self.state.lock().unwrap();
+        RaftStatus {
+            id: self.id,
+            role: state.role,
+            term: state.hard.term,
+            leader: state.leader,
+            commit_index: state.commit_index,
+            applied_index: state.applied_index,
+            members: state.members().to_vec(),
+        }
+    }
+
+    /// Commit the command by the cluster, returns the result of executing it on this node.
+    pub(crate) async fn propose(self: &Arc<Self>, cmd: Command) -> Result<Frame> {
+        let cmd = match cmd {
+            Command::Spop(spop) => self.resolve_spop(spop).await?,
+            cmd => cmd.with_absolute_expire(),
+        };
+        self.propose_with(|_| Ok(Payload::Command(cmd))).await
+    }
+
+    // pop the members of SPOP in a txn which is never committed, the members pop the same
+    // members as the leader
+    async fn resolve_spop(&self, spop: Spop) -> Result<Command> {
+        let client = &self.apply_db.client;
+        let txn = client.begin_txn(false);
+        let frame = Command::Spop(spop.clone())
+            .execute_on(ClientRef::with_txn(client, &txn))
+            .await?;
+        drop(txn);
+        // the error is given by SPOP on every member
+        Ok(Command::Spop(spop.with_popped(&frame).unwrap_or(spop)))
+    }
+
+    pub(crate) async fn add_member(self: &Arc<Self>, member: RaftMember) -> Result<()> {
+        self.change_members(|members| {
+            members.retain(|m| m.id != member.id);
+            members.push(member);
+        })
+        .await
+    }
+
+    pub(crate) async fn remove_member(self: &Arc<Self>, id: u64) -> Result<()> {
+        self.change_members(|members| members.retain(|m| m.id != id))
+            .await
+    }
+
+    async fn change_members<F>(self: &Arc<Self>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Vec<RaftMember>),
+    {
+        let frame = self
+            .propose_with(|state| {
+                if state.membership_changing() {
+                    return Err(RAFT_MEMBERSHIP_CHANGING_ERR);
+                }
+                let mut members = state.members().to_vec();
+                f(&mut members);
+                Ok(Payload::Members(members))
+            })
+            .await?;
+        match frame {
+            Frame::Error(e) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn propose_with<F>(self: &Arc<Self>, f: F) -> Result<Frame>
+    where
+        F: FnOnce(&RaftState) -> RocksResult<Payload>,
+    {
+        let rx = {
+            let mut state = self.state.lock().unwrap();
+            if state.role != RaftRole::Leader {
+                return Ok(resp_err(self.not_leader_error(&state)));
+            }
+            let payload = match f(&state) {
+                Ok(payload) => payload,
+                Err(e) => return Ok(resp_err(e)),
+            };
+            let entry = Entry {
+                index: state.last_index() + 1,
+                term: state.hard.term,
+                payload,
+            };
+            let (tx, rx) = oneshot::channel();
+            self.pending.lock().unwrap().insert(
+                entry.index,
+                Proposal {
+                    term: entry.term,
+                    tx,
+                },
+            );
+            self.append_to_log(&mut state, vec![entry])?;
+            self.advance_commit(&mut state);
+            rx
+        };
+        self.broadcast();
+        match timeout(PROPOSAL_TIMEOUT, rx).await {
+            Ok(Ok(res)) => res,
+            Ok(Err(_)) => Ok(resp_err(RAFT_PROPOSAL_DROPPED_ERR)),
+            Err(_) => Ok(resp_err(RAFT_PROPOSAL_TIMEOUT_ERR)),
+        }
+    }
+
+    /// Wait until the reads on this node are linearizable, which requires a valid lease of
+    /// the leader and all the committed entries applied.
+    pub(crate) async fn read_barrier(&self) -> RocksResult<()> {
+        let read_index = {
+            let state = self.state.lock().unwrap();
+            if state.role != RaftRole::Leader || !self.lease_valid(&state) {
+                return Err(self.not_leader_error(&state));
+            }
+            state.commit_index.max(state.term_start)
+        };
+        let mut applied = self.applied.subscribe();
+        let wait = timeout(
+            PROPOSAL_TIMEOUT,
+            applied.wait_for(|index| *index >= read_index),
+        )
+        .await;
+        match wait {
+            Ok(Ok(_)) => Ok(()),
+            _ => Err(RAFT_PROPOSAL_TIMEOUT_ERR),
+        }
+    }
+
+    fn lease_valid(&self, state: &RaftState) -> bool {
+        let now = Instant::now();
+        let members = state.members();
+        let acks = members
+            .iter()
+            .filter(|member| {
+                member.id == self.id
+                    || matches!(state.acked_at.get(&member.id), Some(at) if now < *at + LEASE_DURATION)
+            })
+            .count();
+        acks > members.len() / 2
+    }
+
+    fn not_leader_error(&self, state: &RaftState) -> RError {
+        let leader = state
+            .leader
+            .and_then(|id| state.members().iter().find(|member| member.id == id));
+        RError::not_raft_leader_error(leader.map(|member| member.addr.as_str()))
+    }
+
+    async fn tick(self: Arc<Self>) {
+        let mut ticker = interval(HEARTBEAT_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return,
+                _ = ticker.tick() => {}
+            }
+            let (role, election_due) = {
+                let state = self.state.lock().unwrap();
+                (state.role, Instant::now() >= state.election_deadline)
+            };
+            match role {
+                RaftRole::Leader => self.broadcast(),
+                _ if election_due => self.start_election().await,
+                _ => {}
+            }
+        }
+    }
+
+    async fn start_election(self: &Arc<Self>) {
+        let (request, term, peers, quorum) = {
+            let mut state = self.state.lock().unwrap();
+            state.reset_election_deadline();
+            // a node joining the cluster waits for the leader
+            if !state.is_member(self.id) {
+                return;
+            }
+            state.hard.term += 1;
+            state.hard.voted_for = Some(self.id);
+            state.role = RaftRole::Candidate;
+            state.leader = None;
+            if self.save_hard_state(&state).is_err() {
+                return;
+            }
+            let request = Request::Vote {
+                term: state.hard.term,
+                candidate: self.id,
+                last_index: state.last_index(),
+                last_term: state.last_term(),
+            };
+            let peers: Vec<RaftMember> = state
+                .members()
+                .iter()
+                .filter(|member| member.id != self.id)
+                .cloned()
+                .collect();
+            let quorum = state.members().len() / 2 + 1;
+            (request, state.hard.term, peers, quorum)
+        };
+
+        let responses = join_all(
+            peers
+                .iter()
+                .map(|member| self.call(member, &request, RPC_TIMEOUT)),
+        )
+        .await;
+        let mut state = self.state.lock().unwrap();
+        let mut votes = 1;
+        for response in responses {
+            if let Ok(Response::Vote {
+                term: vote_term,
+                granted,
+            }) = response
+            {
+                if vote_term > state.hard.term {
+                    state.become_follower(vote_term);
+                    let _ = self.save_hard_state(&state);
+                    return;
+                }
+                if granted && vote_term == term {
+                    votes += 1;
+                }
+            }
+        }
+        if state.role == RaftRole::Candidate && state.hard.term == term && votes >= quorum {
+            self.become_leader(&mut state);
+            drop(state);
+            self.broadcast();
+        }
+    }
+
+    fn become_leader(&self, state: &mut RaftState) {
+        state.role = RaftRole::Leader;
+        state.leader = Some(self.id);
+        let next_index = state.last_index() + 1;
+        state.next_index.clear();
+        state.match_index.clear();
+        state.acked_at.clear();
+        state.term_start = next_index;
+        let entry = Entry {
+            index: next_index,
+            term: state.hard.term,
+            payload: Payload::Noop,
+        };
+        if self.append_to_log(state, vec![entry]).is_err() {
+            state.role = RaftRole::Follower;
+            state.leader = None;
+            return;
+        }
+        self.advance_commit(state);
+    }
+
+    /// Send the new entries or a heartbeat to all the other members.
+    fn broadcast(self: &Arc<Self>) {
+        let peers: Vec<RaftMember> = {
+            let state = self.state.lock().unwrap();
+            state
+                .members()
+                .iter()
+                .filter(|member| member.id != self.id)
+                .cloned()
+                .collect()
+        };
+        for member in peers {
+            tokio::spawn(self.clone().replicate(member));
+        }
+    }
+
+    async fn replicate(self: Arc<Self>, member: RaftMember) {
+        let peer = self.peer(&member);
+        // the entries are sent after the request in flight
+        let mut conn = match peer.conn.try_lock() {
+            Ok(conn) => conn,
+            Err(_) => return,
+        };
+        loop {
+            if self.shutdown.is_cancelled() {
+                return;
+            }
+            let request = {
+                let state = self.state.lock().unwrap();
+                if state.role != RaftRole::Leader {
+                    return;
+                }
+                let next_index = state
+                    .next_index
+                    .get(&member.id)
+                    .copied()
+                    .unwrap_or(state.last_index() + 1);
+                if next_index <= state.snapshot.index {
+                    None
+                } else {
+                    let prev_index = next_index - 1;
+                    Some(Request::Append {
+                        term: state.hard.term,
+                        leader: self.id,
+                        prev_index,
+                        prev_term: state.term_at(prev_index).unwrap_or(0),
+                        entries: state
+                            .log
+                            .iter()
+                            .skip((next_index - state.snapshot.index - 1) as usize)
+                            .take(MAX_APPEND_ENTRIES)
+                            .cloned()
+                            .collect(),
+                        commit: state.commit_index,
+                    })
+                }
+            };
+            let request = match request {
+                Some(request) => request,
+                // the entries are compacted
+                None => match self.send_snapshot(&member, &mut conn).await {
+                    Ok(()) => continue,
+                    Err(_) => return,
+                },
+            };
+
+            let sent_at = Instant::now();
+            let response = match call_on(&peer.addr, &mut conn, &request, &[], RPC_TIMEOUT).await {
+                Ok(Response::Append {
+                    term,
+                    success,
+                    last_index,
+                }) => (term, success, last_index),
+                _ => return,
+            };
+            let mut state = self.state.lock().unwrap();
+            let (term, success, last_index) = response;
+            if term > state.hard.term {
+                state.become_follower(term);
+                let _ = self.save_hard_state(&state);
+                return;
+            }
+            if state.role != RaftRole::Leader || term != state.hard.term {
+                return;
+            }
+            state.acked_at.insert(member.id, sent_at);
+            if success {
+                let match_index = state.match_index.entry(member.id).or_default();
+                *match_index = (*match_index).max(last_index);
+                let next_index = *match_index + 1;
+                state.next_index.insert(member.id, next_index);
+                self.advance_commit(&mut state);
+                if next_index > state.last_index() {
+                    return;
+                }
+            } else {
+                // back off to the last entry of the member
+                let next_index = state
+                    .next_index
+                    .get(&member.id)
+                    .copied()
+                    .unwrap_or(state.last_index() + 1);
+                let next_index = (next_index - 1).min(last_index + 1).max(1);
+                state.next_index.insert(member.id, next_index);
+            }
+        }
+    }
+
+    /// Commit the entries of the current term acknowledged by a majority of the members.
+    fn advance_commit(&self, state: &mut RaftState) {
+        if state.role != RaftRole::Leader {
+            return;
+        }
+        let members = state.members().to_vec();
+        for index in (state.commit_index + 1..=state.last_index()).rev() {
+            if state.term_at(index) != Some(state.hard.term) {
+                break;
+            }
+            let acks = members
+                .iter()
+                .filter(|member| {
+                    member.id == self.id
+                        || state.match_index.get(&member.id).copied().unwrap_or(0) >= index
+                })
+                .count();
+            if acks > members.len() / 2 {
+                state.commit_index = index;
+                self.commit_notify.notify_one();
+                break;
+            }
+        }
+        // the leader removed from the cluster steps down after the removal is committed
+        if !members.iter().any(|member| member.id == self.id) && !state.membership_changing() {
+            state.role = RaftRole::Follower;
+            state.leader = None;
+        }
+    }
+
+    async fn send_snapshot(&self, member: &RaftMember, conn: &mut Option<TcpStream>) -> Result<()> {
+        let dir = self.dir.join(format!("snapshot-send-{}", member.id));
+        let (term, meta) = {
+            let _guard = self.apply_lock.lock().await;
+            let (term, meta) = {
+                let state = self.state.lock().unwrap();
+                let meta = SnapshotMeta {
+                    index: state.applied_index,
+                    term: state.term_at(state.applied_index).unwrap_or(0),
+                    members: state.members_at(state.applied_index).to_vec(),
+                };
+                (state.hard.term, meta)
+            };
+            if dir.exists() {
+                fs::remove_dir_all(&dir)?;
+            }
+            fs::create_dir_all(&self.dir)?;
+            let db = self.db.clone();
+            let path = dir.clone();
+            spawn_blocking(move || checkpoint(&db, path)).await??;
+            (term, meta)
+        };
+        let index = meta.index;
+        let files = list_files(&dir)?;
+        let request = Request::Snapshot {
+            term,
+            leader: self.id,
+            meta,
+            files: files
+                .iter()
+                .map(|(path, len)| (path.file_name().unwrap().to_string_lossy().into(), *len))
+                .collect(),
+        };
+        // the files are read while they are sent, the checkpoint is removed after
+        let response = call_on(&member.addr, conn, &request, &files, SNAPSHOT_TIMEOUT).await;
+        fs::remove_dir_all(&dir)?;
+        let response = response?;
+
+        let mut state = self.state.lock().unwrap();
+        if let Response::Snapshot { term } = response {
+            if term > state.hard.term {
+                state.become_follower(term);
+                self.save_hard_state(&state)?;
+                return Err(self.not_leader_error(&state).into());
+            }
+        }
+        let match_index = state.match_index.entry(member.id).or_default();
+        *match_index = (*match_index).max(index);
+        let next_index = *match_index + 1;
+        state.next_index.insert(member.id, next_index);
+        Ok(())
+    }
+
+    async fn serve(self: Arc<Self>, listener: TcpListener) {
+        loop {
+            let accepted = tokio::select! {
+                _ = self.shutdown.cancelled() => return,
+                accepted = listener.accept() => accepted,
+            };
+            if let Ok((stream, _)) = accepted {
+                let raft = self.clone();
+                tokio::spawn(async move {
+                    tokio::select! {
+                        _ = raft.shutdown.cancelled() => {}
+                        _ = raft.serve_conn(stream) => {}
+                    }
+                });
+            }
+        }
+    }
+
+    async fn serve_conn(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_nodelay(true)?;
+        loop {
+            let response = match read_message(&mut stream).await? {
+                Request::Vote {
+                    term,
+                    candidate,
+                    last_index,
+                    last_term,
+                } => self.handle_vote(term, candidate, last_index, last_term)?,
+                Request::Append {
+                    term,
+                    leader,
+                    prev_index,
+                    prev_term,
+                    entries,
+                    commit,
+                } => self.handle_append(term, leader, prev_index, prev_term, entries, commit)?,
+                Request::Snapshot {
+                    term,
+                    leader,
+                    meta,
+                    files,
+                } => {
+                    self.handle_snapshot(term, leader, meta, files, &mut stream)
+                        .await?
+                }
+            };
+            write_message(&mut stream, &response).await?;
+        }
+    }
+
+    fn handle_vote(
+        &self,
+        term: u64,
+        candidate: u64,
+        last_index: u64,
+        last_term: u64,
+    ) -> Result<Response> {
+        let mut state = self.state.lock().unwrap();
+        // don't vote while the leader is alive, so no other leader is elected in its lease.
+        // A restarted node doesn't know when it last heard from the leader, it waits for the
+        // election timeout as if it heard from the leader at the start
+        let leader_alive = state.role == RaftRole::Leader
+            || self.started_at.elapsed() < ELECTION_TIMEOUT_MIN
+            || (state.leader.is_some()
+                && matches!(state.leader_seen_at, Some(at) if at.elapsed() < ELECTION_TIMEOUT_MIN));
+        if leader_alive {
+            return Ok(Response::Vote {
+                term: state.hard.term,
+                granted: false,
+            });
+        }
+        if term > state.hard.term {
+            state.become_follower(term);
+        }
+        let up_to_date = (last_term, last_index) >= (state.last_term(), state.last_index());
+        let granted = term == state.hard.term
+            && up_to_date
+            && state.hard.voted_for.is_none_or(|id| id == candidate);
+        if granted {
+            state.hard.voted_for = Some(candidate);
+            state.reset_election_deadline();
+        }
+        self.save_hard_state(&state)?;
+        Ok(Response::Vote {
+            term: state.hard.term,
+            granted,
+        })
+    }
+
+    fn handle_append(
+        &self,
+        term: u64,
+        leader: u64,
+        prev_index: u64,
+        prev_term: u64,
+        entries: Vec<Entry>,
+        commit: u64,
+    ) -> Result<Response> {
+        let mut state = self.state.lock().unwrap();
+        if term < state.hard.term {
+            return Ok(Response::Append {
+                term: state.hard.term,
+                success: false,
+                last_index: state.last_index(),
+            });
+        }
+        if term > state.hard.term || state.role != RaftRole::Follower {
+            state.become_follower(term);
+            self.save_hard_state(&state)?;
+        }
+        state.leader = Some(leader);
+        state.leader_seen_at = Some(Instant::now());
+        state.reset_election_deadline();
+
+        if prev_index > state.last_index() {
+            return Ok(Response::Append {
+                term,
+                success: false,
+                last_index: state.last_index(),
+            });
+        }
+        // the entries up to the snapshot are committed, they always match
+        if prev_index > state.snapshot.index && state.term_at(prev_index) != Some(prev_term) {
+            return Ok(Response::Append {
+                term,
+                success: false,
+                last_index: prev_index - 1,
+            });
+        }
+
+        let last_new_index = prev_index + entries.len() as u64;
+        let mut new_entries = vec![];
+        for entry in entries {
+            if entry.index <= state.snapshot.index {
+                continue;
+            }
+            if new_entries.is_empty() {
+                match state.term_at(entry.index) {
+                    Some(term) if term == entry.term => continue,
+                    Some(_) => self.truncate_log(&mut state, entry.index)?,
+                    None => {}
+                }
+            }
+            new_entries.push(entry);
+        }
+        self.append_to_log(&mut state, new_entries)?;
+
+        let commit = commit.min(last_new_index);
+        if commit > state.commit_index {
+            state.commit_index = commit;
+            self.commit_notify.notify_one();
+        }
+        Ok(Response::Append {
+            term,
+            success: true,
+            last_index: last_new_index,
+        })
+    }
+
+    /// Install the checkpoint of the leader. The content of `files` follows the request on
+    /// `stream`, it is written to the files of the checkpoint as it is read.
+    async fn handle_snapshot(
+        &self,
+        term: u64,
+        leader: u64,
+        meta: SnapshotMeta,
+        files: Vec<(String, u64)>,
+        stream: &mut TcpStream,
+    ) -> Result<Response> {
+        let rejected = {
+            let mut state = self.state.lock().unwrap();
+            if term < state.hard.term {
+                Some(state.hard.term)
+            } else {
+                if term > state.hard.term || state.role != RaftRole::Follower {
+                    state.become_follower(term);
+                    self.save_hard_state(&state)?;
+                }
+                state.leader = Some(leader);
+                state.leader_seen_at = Some(Instant::now());
+                state.reset_election_deadline();
+                (meta.index <= state.applied_index).then_some(term)
+            }
+        };
+        if let Some(term) = rejected {
+            // skip the content of the files
+            let len = files.iter().map(|(_, len)| len).sum();
+            aio::copy(&mut stream.take(len), &mut aio::sink()).await?;
+            return Ok(Response::Snapshot { term });
+        }
+
+        let _guard = self.apply_lock.lock().await;
+        let dir = self.dir.join("snapshot-recv");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        for (name, len) in files {
+            // the names are sent by the leader, don't write outside of the directory
+            let name = Path::new(&name)
+                .file_name()
+                .ok_or("invalid snapshot file name")?;
+            let mut file = tokio::fs::File::create(dir.join(name)).await?;
+            if aio::copy(&mut (&mut *stream).take(len), &mut file).await? < len {
+                return Err("snapshot file is truncated".into());
+            }
+            file.flush().await?;
+        }
+        let db = self.db.clone();
+        let path = dir.clone();
+        spawn_blocking(move || -> RocksResult<()> {
+            let source = open_plain_db(path)?;
+            restore_db(&source, &db)
+        })
+        .await??;
+        fs::remove_dir_all(&dir)?;
+        self.apply_db.client.recount_keys()?;
+
+        let mut state = self.state.lock().unwrap();
+        let index = meta.index;
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        batch.put(APPLIED_INDEX_KEY, serde_json::to_vec(&index)?);
+        self.compact_log(&mut state, meta, batch)?;
+        state.commit_index = state.commit_index.max(index);
+        state.applied_index = index;
+        self.applied.send_replace(index);
+        Ok(Response::Snapshot { term })
+    }
+
+    async fn apply(self: Arc<Self>) {
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return,
+                _ = self.commit_notify.notified() => {}
+            }
+            let _ = self.apply_committed().await;
+        }
+    }
+
+    /// Execute the committed entries in order, and reply to the proposals of them.
+    async fn apply_committed(&self) -> Result<()> {
+        let _guard = self.apply_lock.lock().await;
+        loop {
+            let entry = {
+                let state = self.state.lock().unwrap();
+                if state.applied_index >= state.commit_index {
+                    return Ok(());
+                }
+                match state.entry(state.applied_index + 1) {
+                    Some(entry) => entry.clone(),
+                    None => return Ok(()),
+                }
+            };
+            let result = match entry.payload {
+                Payload::Command(cmd) => self.apply_command(cmd, entry.index).await,
+                _ => put_json(&self.db, APPLIED_INDEX_KEY, &entry.index).map(|_| resp_ok()),
+            };
+
+            {
+                let mut state = self.state.lock().unwrap();
+                state.applied_index = entry.index;
+                if entry.index - state.snapshot.index >= self.snapshot_threshold {
+                    let meta = SnapshotMeta {
+                        index: entry.index,
+                        term: entry.term,
+                        members: state.members_at(entry.index).to_vec(),
+                    };
+                    self.compact_log(&mut state, meta, WriteBatchWithTransaction::default())?;
+                }
+            }
+            self.applied.send_replace(entry.index);
+
+            let proposal = self.pending.lock().unwrap().remove(&entry.index);
+            if let Some(proposal) = proposal {
+                // another entry is committed at the index of the proposal
+                let result = if proposal.term == entry.term {
+                    result
+                } else {
+                    Ok(resp_err(RAFT_PROPOSAL_DROPPED_ERR))
+                };
+                let _ = proposal.tx.send(result);
+            }
+        }
+    }
+
+    /// Execute the command of the entry at `index`, the applied index is saved in the txn of
+    /// the command, so the writes and the index are committed together.
+    async fn apply_command(&self, cmd: Command, index: u64) -> Result<Frame> {
+        let client = &self.apply_db.client;
+        let applied = serde_json::to_vec(&index)?;
+        let mut result = Ok(resp_err(TXN_ERROR));
+        for _ in 0..txn_retry_count() {
+            let txn = client.begin_txn(false);
+            let shards = [ClientRef::with_txn(client, &txn)];
+            result = cmd.clone().execute_in(&self.apply_db, &shards).await;
+            if matches!(result, Ok(Frame::TxnFailed(_))) {
+                continue;
+            }
+            // the writes before the error are discarded with the txn
+            if matches!(result, Err(_) | Ok(Frame::Error(_))) {
+                break;
+            }
+            txn.put_default(APPLIED_INDEX_KEY, &applied)?;
+            match client.commit(txn) {
+                Ok(()) => return result,
+                Err(RError::Txn(msg)) => result = Ok(Frame::TxnFailed(msg.to_string())),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        // the entry is applied even if the command fails
+        put_json(&self.db, APPLIED_INDEX_KEY, &index)?;
+        result
+    }
+
+    fn peer(&self, member: &RaftMember) -> Arc<Peer> {
+        let mut peers = self.peers.lock().unwrap();
+        let peer = peers
+            .entry(member.id)
+            .or_insert_with(|| Peer::new(&member.addr));
+        if peer.addr != member.addr {
+            *peer = Peer::new(&member.addr);
+        }
+        peer.clone()
+    }
+
+    async fn call(
+        &self,
+        member: &RaftMember,
+        request: &Request,
+        wait: Duration,
+    ) -> Result<Response> {
+        let peer = self.peer(member);
+        let mut conn = peer.conn.lock().await;
+        call_on(&peer.addr, &mut conn, request, &[], wait).await
+    }
+
+    fn save_hard_state(&self, state: &RaftState) -> Result<()> {
+        put_json(&self.db, HARD_STATE_KEY, &state.hard)
+    }
+
+    fn append_to_log(&self, state: &mut RaftState, entries: Vec<Entry>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        for entry in &entries {
+            batch.put(log_key(entry.index), serde_json::to_vec(entry)?);
+        }
+        self.db.write(batch)?;
+        state.log.extend(entries);
+        Ok(())
+    }
+
+    /// Remove the entries from `index` to the end of the log.
+    fn truncate_log(&self, state: &mut RaftState, index: u64) -> Result<()> {
+        let pos = state.log.partition_point(|entry| entry.index < index);
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        for entry in &state.log[pos..] {
+            batch.delete(log_key(entry.index));
+        }
+        self.db.write(batch)?;
+        state.log.truncate(pos);
+        Ok(())
+    }
+
+    /// Replace the entries up to the index of `meta` with the snapshot, or all the entries if
+    /// the log doesn't match the snapshot. The snapshot is saved with the other writes of
+    /// `batch` in the batch removing the entries, so the log always follows the saved
+    /// snapshot, even after a crash.
+    fn compact_log(
+        &self,
+        state: &mut RaftState,
+        meta: SnapshotMeta,
+        mut batch: WriteBatchWithTransaction<true>,
+    ) -> Result<()> {
+        let pos = if state.term_at(meta.index) == Some(meta.term) {
+            state.log.partition_point(|entry| entry.index <= meta.index)
+        } else {
+            state.log.len()
+        };
+        for entry in &state.log[..pos] {
+            batch.delete(log_key(entry.index));
+        }
+        batch.put(SNAPSHOT_META_KEY, serde_json::to_vec(&meta)?);
+        self.db.write(batch)?;
+        state.log.drain(..pos);
+        state.snapshot = meta;
+        Ok(())
+    }
+}
+
+impl Peer {
+    fn new(addr: &str) -> Arc<Self> {
+        Arc::new(Self {
+            addr: addr.to_string(),
+            conn: AsyncMutex::new(None),
+        })
+    }
+}
+
+/// Send the request and the content of `files` following it on the connection, which is
+/// dropped on error. The files are given with their lengths, which are sent in the request.
+async fn call_on(
+    addr: &str,
+    conn: &mut Option<TcpStream>,
+    request: &Request,
+    files: &[(PathBuf, u64)],
+    wait: Duration,
+) -> Result<Response> {
+    let res = timeout(wait, async {
+        if conn.is_none() {
+            let stream = TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+            *conn = Some(stream);
+        }
+        let stream = conn.as_mut().unwrap();
+        write_message(stream, request).await?;
+        for (path, len) in files {
+            let file = tokio::fs::File::open(path).await?;
+            if aio::copy(&mut file.take(*len), stream).await? < *len {
+                return Err("snapshot file is truncated".into());
+            }
+        }
+        read_message(stream).await
+    })
+    .await;
+    match res {
+        Ok(Ok(response)) => Ok(response),
+        Ok(Err(e)) => {
+            *conn = None;
+            Err(e)
+        }
+        Err(_) => {
+            *conn = None;
+            Err("raft request timed out".into())
+        }
+    }
+}
+
+async fn write_message<T: Serialize>(stream: &mut TcpStream, message: &T) -> Result<()> {
+    let data = serde_json::to_vec(message)?;
+    stream.write_u32(data.len() as u32).await?;
+    stream.write_all(&data).await?;
+    Ok(())
+}
+
+async fn read_message<T: DeserializeOwned>(stream: &mut TcpStream) -> Result<T> {
+    let len = stream.read_u32().await?;
+    let mut data = vec![0; len as usize];
+    stream.read_exact(&mut data).await?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+fn log_key(index: u64) -> Vec<u8> {
+    let mut key = LOG_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
+
+fn load_log(db: &TransactionDB, snapshot_index: u64) -> Result<Vec<Entry>> {
+    let mut log = vec![];
+    for kv in db.iterator(IteratorMode::From(LOG_KEY_PREFIX, Direction::Forward)) {
+        let (key, value) = kv?;
+        if !key.starts_with(LOG_KEY_PREFIX) {
+            break;
+        }
+        let entry: Entry = serde_json::from_slice(&value)?;
+        if entry.index > snapshot_index {
+            log.push(entry);
+        }
+    }
+    Ok(log)
+}
+
+fn get_json<T: DeserializeOwned>(db: &TransactionDB, key: &[u8]) -> Result<Option<T>> {
+    match db.get(key)? {
+        Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+        None => Ok(None),
+    }
+}
+
+fn put_json<T: Serialize>(db: &TransactionDB, key: &[u8], value: &T) -> Result<()> {
+    db.put(key, serde_json::to_vec(value)?)?;
+    Ok(())
+}
+
+// the files in `dir` with their lengths
+fn list_files(dir: &Path) -> Result<Vec<(PathBuf, u64)>> {
+    let mut files = vec![];
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let metadata = dir_entry.metadata()?;
+        if metadata.is_file() {
+            files.push((dir_entry.path(), metadata.len()));
+        }
+    }
+    Ok(files)
+}
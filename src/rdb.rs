@@ -0,0 +1,736 @@
+//! Import and export of Redis RDB files, used to migrate data between Redis and mapuche.
+//!
+//! `import` loads the strings, lists, sets, sorted sets and hashes of RDB files created by
+//! Redis with RDB version 9 to 11, in both the plain encodings and the compact encodings
+//! (ziplist, listpack, intset, zipmap and quicklist). Streams and module types are not
+//! supported. Mapuche has a single keyspace, so the keys of all the databases in the file
+//! are imported into it. The keys that are not valid UTF-8 are imported as raw bytes, they
+//! are listed by KEYS and SCAN and exported again, but the commands taking `&str` keys
+//! can't address them.
+//!
+//! `export` writes the keys from a consistent snapshot of the db into an RDB file of
+//! version 9 with the plain encodings, which can be loaded by Redis 5.0 and later.
+//! `export_snapshot` writes the keys of a snapshot taken by `DB::snapshot`.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str;
+
+use crc::{Crc, Digest, CRC_64_REDIS};
+
+use crate::rocks::client::ClientRef;
+use crate::rocks::dump::{DumpPayload, DumpValue};
+use crate::rocks::errors::RError;
+use crate::rocks::string::StringCommand;
+use crate::{Error, Result, Snapshot, DB};
+
+const RDB_MAGIC: &[u8] = b"REDIS";
+const RDB_MIN_VERSION: u32 = 1;
+const RDB_MAX_VERSION: u32 = 11;
+const RDB_EXPORT_VERSION: u32 = 9;
+// checksum is appended to the file since version 5
+const RDB_CHECKSUM_VERSION: u32 = 5;
+static RDB_CRC: Crc<u64> = Crc::<u64>::new(&CRC_64_REDIS);
+
+const RDB_OPCODE_FUNCTION_PRE_GA: u8 = 0xF5;
+const RDB_OPCODE_FUNCTION2: u8 = 0xF6;
+const RDB_OPCODE_MODULE_AUX: u8 = 0xF7;
+const RDB_OPCODE_IDLE: u8 = 0xF8;
+const RDB_OPCODE_FREQ: u8 = 0xF9;
+const RDB_OPCODE_AUX: u8 = 0xFA;
+const RDB_OPCODE_RESIZEDB: u8 = 0xFB;
+const RDB_OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+const RDB_OPCODE_EXPIRETIME: u8 = 0xFD;
+const RDB_OPCODE_SELECTDB: u8 = 0xFE;
+const RDB_OPCODE_EOF: u8 = 0xFF;
+
+const RDB_TYPE_STRING: u8 = 0;
+const RDB_TYPE_LIST: u8 = 1;
+const RDB_TYPE_SET: u8 = 2;
+const RDB_TYPE_ZSET: u8 = 3;
+const RDB_TYPE_HASH: u8 = 4;
+const RDB_TYPE_ZSET_2: u8 = 5;
+const RDB_TYPE_HASH_ZIPMAP: u8 = 9;
+const RDB_TYPE_LIST_ZIPLIST: u8 = 10;
+const RDB_TYPE_SET_INTSET: u8 = 11;
+const RDB_TYPE_ZSET_ZIPLIST: u8 = 12;
+const RDB_TYPE_HASH_ZIPLIST: u8 = 13;
+const RDB_TYPE_LIST_QUICKLIST: u8 = 14;
+const RDB_TYPE_HASH_LISTPACK: u8 = 16;
+const RDB_TYPE_ZSET_LISTPACK: u8 = 17;
+const RDB_TYPE_LIST_QUICKLIST_2: u8 = 18;
+const RDB_TYPE_SET_LISTPACK: u8 = 20;
+
+// the two high bits of the first byte of a length
+const RDB_6BITLEN: u8 = 0;
+const RDB_14BITLEN: u8 = 1;
+const RDB_32BITLEN: u8 = 0x80;
+const RDB_64BITLEN: u8 = 0x81;
+const RDB_ENCVAL: u8 = 3;
+
+const RDB_ENC_INT8: u64 = 0;
+const RDB_ENC_INT16: u64 = 1;
+const RDB_ENC_INT32: u64 = 2;
+const RDB_ENC_LZF: u64 = 3;
+
+const QUICKLIST_NODE_CONTAINER_PLAIN: u64 = 1;
+
+/// Import the keys in the RDB file at `path` into `db`, the existing keys with the same
+/// names are replaced and the already expired keys are skipped. Only the keys of the db 0
+/// are imported, the keys of the other dbs are skipped.
+/// Returns the number of the imported keys.
+pub async fn import<P: AsRef<Path>>(path: P, db: &DB) -> Result<usize> {
+    db.check_writable()?;
+    let file = File::open(path)?;
+    let mut reader = RdbReader::new(BufReader::new(file));
+    let version = reader.read_header()?;
+
+    let mut count = 0;
+    // expire time of the next key in milliseconds, 0 means no ttl
+    let mut expire = 0;
+    // the db of the next keys
+    let mut db_index = 0;
+    loop {
+        match reader.read_u8()? {
+            RDB_OPCODE_EOF => break,
+            RDB_OPCODE_SELECTDB => {
+                db_index = reader.read_length()?;
+            }
+            RDB_OPCODE_RESIZEDB => {
+                reader.read_length()?;
+                reader.read_length()?;
+            }
+            RDB_OPCODE_AUX => {
+                reader.read_string()?;
+                reader.read_string()?;
+            }
+            RDB_OPCODE_EXPIRETIME => {
+                expire = reader.read_u32_le()? as i64 * 1000;
+            }
+            RDB_OPCODE_EXPIRETIME_MS => {
+                expire = reader.read_u64_le()? as i64;
+            }
+            RDB_OPCODE_FREQ => {
+                reader.read_u8()?;
+            }
+            RDB_OPCODE_IDLE => {
+                reader.read_length()?;
+            }
+            RDB_OPCODE_FUNCTION2 => {
+                // functions are not supported, skip the library code
+                reader.read_string()?;
+            }
+            RDB_OPCODE_MODULE_AUX | RDB_OPCODE_FUNCTION_PRE_GA => {
+                return Err(rdb_error("module and function data are not supported"));
+            }
+            rdb_type => {
+                // the keys are imported as raw bytes, they may be not valid utf-8
+                let key = reader.read_string()?;
+                let payload = reader.read_object(rdb_type)?;
+                if db_index == 0 {
+                    StringCommand::new(db.inner.client_for_key(&key))
+                        .import_value(&key, &DumpValue::new(expire, payload))?;
+                    count += 1;
+                }
+                expire = 0;
+            }
+        }
+    }
+
+    if version >= RDB_CHECKSUM_VERSION {
+        reader.check_checksum()?;
+    }
+    Ok(count)
+}
+
+/// Export all the keys in `db` to an RDB file at `path`, the keys are read from the same
+/// snapshot so the file is consistent even if the db is written concurrently.
+/// The shards of a sharded db are read from their own snapshots one after another.
+/// Returns the number of the exported keys.
+pub async fn export<P: AsRef<Path>>(path: P, db: &DB) -> Result<usize> {
+    let shards: Vec<ClientRef> = db.inner.shards.iter().map(ClientRef::from).collect();
+    write_rdb(path.as_ref(), &shards)
+}
+
+/// Export all the keys in the snapshot to an RDB file at `path`, which is a point-in-time
+/// backup of the db that can be loaded by `import`.
+/// Returns the number of the exported keys.
+pub async fn export_snapshot<P: AsRef<Path>>(path: P, snapshot: &Snapshot) -> Result<usize> {
+    write_rdb(path.as_ref(), &snapshot.shards())
+}
+
+fn write_rdb(path: &Path, shards: &[ClientRef<'_>]) -> Result<usize> {
+    // write to a temp file first, so a failed export never leaves a partial file at `path`
+    let tmp_path = path.with_extension("rdb.tmp");
+    let file = File::create(&tmp_path)?;
+    let mut writer = RdbWriter::new(BufWriter::new(file));
+    writer.write_header()?;
+
+    let mut count = 0;
+    for shard in shards {
+        count += StringCommand::new(*shard).export_values(|key, value| {
+            writer
+                .write_entry(key, &value)
+                .map_err(|e| RError::owned_error(e.to_string()))
+        })?;
+    }
+
+    writer.write_footer()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(count)
+}
+
+fn rdb_error(msg: &str) -> Error {
+    format!("invalid rdb file: {msg}").into()
+}
+
+struct RdbReader<R> {
+    inner: R,
+    digest: Digest<'static, u64>,
+}
+
+impl<R: Read> RdbReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            digest: RDB_CRC.digest(),
+        }
+    }
+
+    fn read_header(&mut self) -> Result<u32> {
+        let header = self.read_bytes(9)?;
+        if &header[..5] != RDB_MAGIC {
+            return Err(rdb_error("wrong signature"));
+        }
+        let version = str::from_utf8(&header[5..])
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or_else(|| rdb_error("wrong version"))?;
+        if !(RDB_MIN_VERSION..=RDB_MAX_VERSION).contains(&version) {
+            return Err(format!("unsupported rdb version {version}").into());
+        }
+        Ok(version)
+    }
+
+    // the checksum itself is not part of the digest, 0 means checksum is disabled
+    fn check_checksum(self) -> Result<()> {
+        let mut inner = self.inner;
+        let mut buf = [0; 8];
+        inner.read_exact(&mut buf)?;
+        let expected = u64::from_le_bytes(buf);
+        if expected != 0 && expected != self.digest.finalize() {
+            return Err(rdb_error("wrong checksum"));
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>> {
+        // the length is read from the file, the buffer grows with the bytes actually read
+        let mut buf = Vec::new();
+        self.inner.by_ref().take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.digest.update(&buf);
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32_le(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64_le(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    /// Read a length, returns the length and if it is a special encoding of string.
+    fn read_length_with_encoding(&mut self) -> Result<(u64, bool)> {
+        let first = self.read_u8()?;
+        match first >> 6 {
+            RDB_ENCVAL => Ok(((first & 0x3f) as u64, true)),
+            RDB_6BITLEN => Ok(((first & 0x3f) as u64, false)),
+            RDB_14BITLEN => {
+                let next = self.read_u8()?;
+                Ok(((((first & 0x3f) as u64) << 8) | next as u64, false))
+            }
+            _ => match first {
+                RDB_32BITLEN => {
+                    let len = u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap());
+                    Ok((len as u64, false))
+                }
+                RDB_64BITLEN => {
+                    let len = u64::from_be_bytes(self.read_bytes(8)?.try_into().unwrap());
+                    Ok((len, false))
+                }
+                _ => Err(rdb_error("unknown length encoding")),
+            },
+        }
+    }
+
+    fn read_length(&mut self) -> Result<u64> {
+        match self.read_length_with_encoding()? {
+            (len, false) => Ok(len),
+            (_, true) => Err(rdb_error("unexpected string encoding")),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Vec<u8>> {
+        let (len, encoded) = self.read_length_with_encoding()?;
+        if !encoded {
+            return self.read_bytes(len as usize);
+        }
+        match len {
+            RDB_ENC_INT8 => Ok((self.read_u8()? as i8).to_string().into_bytes()),
+            RDB_ENC_INT16 => {
+                let v = i16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap());
+                Ok(v.to_string().into_bytes())
+            }
+            RDB_ENC_INT32 => {
+                let v = i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap());
+                Ok(v.to_string().into_bytes())
+            }
+            RDB_ENC_LZF => {
+                let compressed_len = self.read_length()? as usize;
+                let len = self.read_length()? as usize;
+                let compressed = self.read_bytes(compressed_len)?;
+                lzf_decompress(&compressed, len)
+            }
+            _ => Err(rdb_error("unknown string encoding")),
+        }
+    }
+
+    fn read_strings(&mut self) -> Result<Vec<Vec<u8>>> {
+        let len = self.read_length()?;
+        (0..len).map(|_| self.read_string()).collect()
+    }
+
+    // score of RDB_TYPE_ZSET is saved as a length prefixed string
+    fn read_double_string(&mut self) -> Result<f64> {
+        match self.read_u8()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => parse_score(&self.read_bytes(len as usize)?),
+        }
+    }
+
+    fn read_object(&mut self, rdb_type: u8) -> Result<DumpPayload> {
+        let payload = match rdb_type {
+            RDB_TYPE_STRING => DumpPayload::String(self.read_string()?),
+            RDB_TYPE_LIST => DumpPayload::List(self.read_strings()?),
+            RDB_TYPE_SET => DumpPayload::Set(self.read_strings()?),
+            RDB_TYPE_ZSET | RDB_TYPE_ZSET_2 => {
+                let len = self.read_length()?;
+                let mut members = Vec::new();
+                for _ in 0..len {
+                    let member = self.read_string()?;
+                    let score = if rdb_type == RDB_TYPE_ZSET {
+                        self.read_double_string()?
+                    } else {
+                        f64::from_bits(self.read_u64_le()?)
+                    };
+                    members.push((member, score));
+                }
+                DumpPayload::Zset(members)
+            }
+            RDB_TYPE_HASH => {
+                let len = self.read_length()?;
+                let mut fields = Vec::new();
+                for _ in 0..len {
+                    fields.push((self.read_string()?, self.read_string()?));
+                }
+                DumpPayload::Hash(fields)
+            }
+            RDB_TYPE_HASH_ZIPMAP => DumpPayload::Hash(zipmap_entries(&self.read_string()?)?),
+            RDB_TYPE_LIST_ZIPLIST => DumpPayload::List(ziplist_entries(&self.read_string()?)?),
+            RDB_TYPE_SET_INTSET => DumpPayload::Set(intset_entries(&self.read_string()?)?),
+            RDB_TYPE_SET_LISTPACK => DumpPayload::Set(listpack_entries(&self.read_string()?)?),
+            RDB_TYPE_ZSET_ZIPLIST => {
+                DumpPayload::Zset(into_scores(ziplist_entries(&self.read_string()?)?)?)
+            }
+            RDB_TYPE_ZSET_LISTPACK => {
+                DumpPayload::Zset(into_scores(listpack_entries(&self.read_string()?)?)?)
+            }
+            RDB_TYPE_HASH_ZIPLIST => {
+                DumpPayload::Hash(into_pairs(ziplist_entries(&self.read_string()?)?)?)
+            }
+            RDB_TYPE_HASH_LISTPACK => {
+                DumpPayload::Hash(into_pairs(listpack_entries(&self.read_string()?)?)?)
+            }
+            RDB_TYPE_LIST_QUICKLIST => {
+                let len = self.read_length()?;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.extend(ziplist_entries(&self.read_string()?)?);
+                }
+                DumpPayload::List(items)
+            }
+            RDB_TYPE_LIST_QUICKLIST_2 => {
+                let len = self.read_length()?;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    let container = self.read_length()?;
+                    let node = self.read_string()?;
+                    if container == QUICKLIST_NODE_CONTAINER_PLAIN {
+                        items.push(node);
+                    } else {
+                        items.extend(listpack_entries(&node)?);
+                    }
+                }
+                DumpPayload::List(items)
+            }
+            _ => return Err(format!("unsupported rdb object type {rdb_type}").into()),
+        };
+        Ok(payload)
+    }
+}
+
+struct RdbWriter<W: Write> {
+    inner: W,
+    digest: Digest<'static, u64>,
+}
+
+impl<W: Write> RdbWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            digest: RDB_CRC.digest(),
+        }
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        self.write(RDB_MAGIC)?;
+        self.write(format!("{RDB_EXPORT_VERSION:04}").as_bytes())?;
+        self.write(&[RDB_OPCODE_SELECTDB])?;
+        self.write_length(0)
+    }
+
+    fn write_footer(mut self) -> io::Result<()> {
+        self.write(&[RDB_OPCODE_EOF])?;
+        let checksum = self.digest.finalize();
+        self.inner.write_all(&checksum.to_le_bytes())?;
+        self.inner.flush()
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.digest.update(buf);
+        self.inner.write_all(buf)
+    }
+
+    fn write_length(&mut self, len: u64) -> io::Result<()> {
+        if len < 1 << 6 {
+            self.write(&[len as u8])
+        } else if len < 1 << 14 {
+            self.write(&[(RDB_14BITLEN << 6) | (len >> 8) as u8, len as u8])
+        } else if len <= u32::MAX as u64 {
+            self.write(&[RDB_32BITLEN])?;
+            self.write(&(len as u32).to_be_bytes())
+        } else {
+            self.write(&[RDB_64BITLEN])?;
+            self.write(&len.to_be_bytes())
+        }
+    }
+
+    fn write_string(&mut self, s: &[u8]) -> io::Result<()> {
+        self.write_length(s.len() as u64)?;
+        self.write(s)
+    }
+
+    fn write_entry(&mut self, key: &[u8], value: &DumpValue) -> io::Result<()> {
+        if value.ttl > 0 {
+            self.write(&[RDB_OPCODE_EXPIRETIME_MS])?;
+            self.write(&value.ttl.to_le_bytes())?;
+        }
+        match &value.payload {
+            DumpPayload::String(v) => {
+                self.write(&[RDB_TYPE_STRING])?;
+                self.write_string(key)?;
+                self.write_string(v)
+            }
+            DumpPayload::List(items) | DumpPayload::Set(items) => {
+                let rdb_type = match value.payload {
+                    DumpPayload::List(_) => RDB_TYPE_LIST,
+                    _ => RDB_TYPE_SET,
+                };
+                self.write(&[rdb_type])?;
+                self.write_string(key)?;
+                self.write_length(items.len() as u64)?;
+                for item in items {
+                    self.write_string(item)?;
+                }
+                Ok(())
+            }
+            DumpPayload::Hash(fields) => {
+                self.write(&[RDB_TYPE_HASH])?;
+                self.write_string(key)?;
+                self.write_length(fields.len() as u64)?;
+                for (field, v) in fields {
+                    self.write_string(field)?;
+                    self.write_string(v)?;
+                }
+                Ok(())
+            }
+            DumpPayload::Zset(members) => {
+                self.write(&[RDB_TYPE_ZSET_2])?;
+                self.write_string(key)?;
+                self.write_length(members.len() as u64)?;
+                for (member, score) in members {
+                    self.write_string(member)?;
+                    self.write(&score.to_bits().to_le_bytes())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_score(s: &[u8]) -> Result<f64> {
+    str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or_else(|| rdb_error("invalid zset score"))
+}
+
+// elements of the compact encodings are saved as field, value, field, value...
+fn into_pairs(items: Vec<Vec<u8>>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    if !items.len().is_multiple_of(2) {
+        return Err(rdb_error("odd number of elements"));
+    }
+    let mut iter = items.into_iter();
+    let mut pairs = Vec::new();
+    while let (Some(field), Some(value)) = (iter.next(), iter.next()) {
+        pairs.push((field, value));
+    }
+    Ok(pairs)
+}
+
+fn into_scores(items: Vec<Vec<u8>>) -> Result<Vec<(Vec<u8>, f64)>> {
+    into_pairs(items)?
+        .into_iter()
+        .map(|(member, score)| Ok((member, parse_score(&score)?)))
+        .collect()
+}
+
+/// Decompress the data compressed by the LZF algorithm used by Redis.
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(len);
+    let mut ip = 0;
+    while ip < input.len() {
+        let ctrl = input[ip] as usize;
+        ip += 1;
+        if ctrl < 1 << 5 {
+            // literal run of ctrl + 1 bytes
+            let run = input
+                .get(ip..ip + ctrl + 1)
+                .ok_or_else(|| rdb_error("corrupted lzf data"))?;
+            output.extend_from_slice(run);
+            ip += ctrl + 1;
+        } else {
+            // back reference
+            let mut ref_len = ctrl >> 5;
+            if ref_len == 7 {
+                ref_len += *input
+                    .get(ip)
+                    .ok_or_else(|| rdb_error("corrupted lzf data"))?
+                    as usize;
+                ip += 1;
+            }
+            let low = *input
+                .get(ip)
+                .ok_or_else(|| rdb_error("corrupted lzf data"))? as usize;
+            ip += 1;
+            let offset = ((ctrl & 0x1f) << 8) + low + 1;
+            if offset > output.len() {
+                return Err(rdb_error("corrupted lzf data"));
+            }
+            let start = output.len() - offset;
+            // the reference may overlap the bytes being copied
+            for i in 0..ref_len + 2 {
+                output.push(output[start + i]);
+            }
+        }
+    }
+    if output.len() != len {
+        return Err(rdb_error("corrupted lzf data"));
+    }
+    Ok(output)
+}
+
+/// Cursor over the blob of a compact encoding.
+struct Blob<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Blob<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| rdb_error("unexpected end of encoded value"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn take_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn peek_u8(&self) -> Result<u8> {
+        self.data
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| rdb_error("unexpected end of encoded value"))
+    }
+
+    // signed little endian integer of `len` bytes
+    fn take_int(&mut self, len: usize) -> Result<i64> {
+        let bytes = self.take(len)?;
+        let mut buf = [0; 8];
+        buf[..len].copy_from_slice(bytes);
+        let shift = 64 - len * 8;
+        Ok((i64::from_le_bytes(buf) << shift) >> shift)
+    }
+}
+
+fn int_bytes(v: i64) -> Vec<u8> {
+    v.to_string().into_bytes()
+}
+
+fn ziplist_entries(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut blob = Blob::new(data);
+    // skip zlbytes, zltail and zllen
+    blob.take(10)?;
+    let mut items = Vec::new();
+    loop {
+        let prevlen = blob.take_u8()?;
+        if prevlen == 0xff {
+            return Ok(items);
+        }
+        if prevlen == 0xfe {
+            blob.take(4)?;
+        }
+        let enc = blob.take_u8()?;
+        let item = match enc >> 6 {
+            0 => blob.take((enc & 0x3f) as usize)?.to_vec(),
+            1 => {
+                let len = (((enc & 0x3f) as usize) << 8) | blob.take_u8()? as usize;
+                blob.take(len)?.to_vec()
+            }
+            2 => {
+                let len = u32::from_be_bytes(blob.take(4)?.try_into().unwrap());
+                blob.take(len as usize)?.to_vec()
+            }
+            _ => match enc {
+                0xc0 => int_bytes(blob.take_int(2)?),
+                0xd0 => int_bytes(blob.take_int(4)?),
+                0xe0 => int_bytes(blob.take_int(8)?),
+                0xf0 => int_bytes(blob.take_int(3)?),
+                0xfe => int_bytes(blob.take_int(1)?),
+                0xf1..=0xfd => int_bytes((enc & 0x0f) as i64 - 1),
+                _ => return Err(rdb_error("unknown ziplist encoding")),
+            },
+        };
+        items.push(item);
+    }
+}
+
+fn listpack_entries(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut blob = Blob::new(data);
+    // skip total bytes and number of elements
+    blob.take(6)?;
+    let mut items = Vec::new();
+    loop {
+        let start = blob.pos;
+        let enc = blob.peek_u8()?;
+        if enc == 0xff {
+            return Ok(items);
+        }
+        blob.take(1)?;
+        let item = if enc & 0x80 == 0 {
+            int_bytes(enc as i64)
+        } else if enc & 0xc0 == 0x80 {
+            blob.take((enc & 0x3f) as usize)?.to_vec()
+        } else if enc & 0xe0 == 0xc0 {
+            let v = (((enc & 0x1f) as i64) << 8) | blob.take_u8()? as i64;
+            int_bytes(if v >= 1 << 12 { v - (1 << 13) } else { v })
+        } else if enc & 0xf0 == 0xe0 {
+            let len = (((enc & 0x0f) as usize) << 8) | blob.take_u8()? as usize;
+            blob.take(len)?.to_vec()
+        } else {
+            match enc {
+                0xf0 => {
+                    let len = u32::from_le_bytes(blob.take(4)?.try_into().unwrap());
+                    blob.take(len as usize)?.to_vec()
+                }
+                0xf1 => int_bytes(blob.take_int(2)?),
+                0xf2 => int_bytes(blob.take_int(3)?),
+                0xf3 => int_bytes(blob.take_int(4)?),
+                0xf4 => int_bytes(blob.take_int(8)?),
+                _ => return Err(rdb_error("unknown listpack encoding")),
+            }
+        };
+        // skip the backlen, its size depends on the size of the entry like `lpEncodeBacklen`
+        let entry_len = blob.pos - start;
+        let backlen_size = match entry_len {
+            0..=127 => 1,
+            128..=16382 => 2,
+            16383..=2097150 => 3,
+            2097151..=268435454 => 4,
+            _ => 5,
+        };
+        blob.take(backlen_size)?;
+        items.push(item);
+    }
+}
+
+fn intset_entries(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut blob = Blob::new(data);
+    let width = u32::from_le_bytes(blob.take(4)?.try_into().unwrap()) as usize;
+    let len = u32::from_le_bytes(blob.take(4)?.try_into().unwrap());
+    if !matches!(width, 2 | 4 | 8) {
+        return Err(rdb_error("unknown intset encoding"));
+    }
+    (0..len)
+        .map(|_| Ok(int_bytes(blob.take_int(width)?)))
+        .collect()
+}
+
+fn zipmap_entries(data: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut blob = Blob::new(data);
+    // skip zmlen
+    blob.take(1)?;
+    let mut fields = Vec::new();
+    loop {
+        let field_len = match zipmap_len(&mut blob)? {
+            Some(len) => len,
+            None => return Ok(fields),
+        };
+        let field = blob.take(field_len)?.to_vec();
+        let value_len = zipmap_len(&mut blob)?.ok_or_else(|| rdb_error("corrupted zipmap"))?;
+        let free = blob.take_u8()? as usize;
+        let value = blob.take(value_len)?.to_vec();
+        blob.take(free)?;
+        fields.push((field, value));
+    }
+}
+
+// returns None at the end of the zipmap
+fn zipmap_len(blob: &mut Blob) -> Result<Option<usize>> {
+    match blob.take_u8()? {
+        0xff => Ok(None),
+        254 => Ok(Some(
+            u32::from_le_bytes(blob.take(4)?.try_into().unwrap()) as usize
+        )),
+        len => Ok(Some(len as usize)),
+    }
+}
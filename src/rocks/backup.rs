@@ -0,0 +1,294 @@
+//! Checkpoints and backups of the db while it is online.
+//!
+//! rust-rocksdb only supports `Checkpoint` and `BackupEngine` on a plain db, not on the
+//! `TransactionDB` we use. So a checkpoint is made by copying all the column families from a
+//! snapshot into a new db, and the backups are made by `BackupEngine` from a staging db which
+//! is synced with a snapshot before each backup. Only the changed keys are written to the
+//! staging db, so its unchanged sst files are shared by the backups and the backups are
+//! incremental.
+
+use std::cmp::Ordering as CmpOrdering;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+
+use rocksdb::backup::{BackupEngine, BackupEngineOptions, RestoreOptions};
+use rocksdb::{
+    Env, IteratorMode, Options, TransactionDB, WriteBatch, WriteBatchWithTransaction, DB,
+};
+use tokio::task::spawn_blocking;
+
+use crate::rocks::client::RocksClient;
+use crate::rocks::engine::KvBytes;
+use crate::rocks::errors::{
+    RError, CF_NOT_EXISTS_ERR, CHECKPOINT_EXISTS_ERR, REDIS_BACKUP_NOT_CONFIGURED_ERR,
+    REDIS_BGSAVE_IN_PROGRESS_ERR,
+};
+use crate::rocks::{Result as RocksResult, CF_NAMES};
+use crate::utils::{resp_err, resp_int, resp_str};
+use crate::Frame;
+
+// number of writes to the target db in a write batch when syncing
+pub(crate) const SYNC_BATCH_SIZE: usize = 1000;
+const STAGING_DIR: &str = "staging";
+
+/// Information of a backup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupInfo {
+    pub id: u32,
+    /// unix time in seconds when the backup is created
+    pub timestamp: i64,
+    /// size in bytes, the shared files are counted in every backup using them
+    pub size: u64,
+    pub num_files: u32,
+}
+
+pub struct Backup {
+    dir: PathBuf,
+    // number of latest backups to keep, all the backups are kept if it is None
+    retention: Option<usize>,
+    in_progress: AtomicBool,
+    // unix time in seconds of the last successful backup, 0 if there is no backup
+    last_save: AtomicI64,
+}
+
+impl Backup {
+    pub fn new<P: AsRef<Path>>(dir: P, retention: Option<usize>) -> RocksResult<Self> {
+        let backup = Self {
+            dir: dir.as_ref().to_path_buf(),
+            retention,
+            in_progress: AtomicBool::new(false),
+            last_save: AtomicI64::new(0),
+        };
+        let last_save = backup.list()?.iter().map(|info| info.timestamp).max();
+        backup
+            .last_save
+            .store(last_save.unwrap_or(0), Ordering::Relaxed);
+        Ok(backup)
+    }
+
+    fn open_engine(&self) -> RocksResult<BackupEngine> {
+        let opts = BackupEngineOptions::new(&self.dir)?;
+        Ok(BackupEngine::open(&opts, &Env::new()?)?)
+    }
+
+    /// Create a new backup of `db`, returns the id of the backup.
+    /// Only one backup can be created at a time.
+    pub fn create(&self, db: &TransactionDB) -> RocksResult<u32> {
+        if self.in_progress.swap(true, Ordering::AcqRel) {
+            return Err(REDIS_BGSAVE_IN_PROGRESS_ERR);
+        }
+        let res = self.create_in_progress(db);
+        self.in_progress.store(false, Ordering::Release);
+        res
+    }
+
+    fn create_in_progress(&self, db: &TransactionDB) -> RocksResult<u32> {
+        let staging = open_plain_db(self.dir.join(STAGING_DIR))?;
+        sync_db(db, &staging)?;
+
+        let mut engine = self.open_engine()?;
+        engine.create_new_backup_flush(&staging, true)?;
+        if let Some(retention) = self.retention {
+            engine.purge_old_backups(retention)?;
+        }
+        let latest = engine
+            .get_backup_info()
+            .into_iter()
+            .max_by_key(|info| info.backup_id)
+            .ok_or_else(|| RError::owned_error("ERR backup is not created"))?;
+        self.last_save.store(latest.timestamp, Ordering::Relaxed);
+        Ok(latest.backup_id)
+    }
+
+    /// Return the existing backups ordered by id.
+    pub fn list(&self) -> RocksResult<Vec<BackupInfo>> {
+        let mut backups: Vec<BackupInfo> = self
+            .open_engine()?
+            .get_backup_info()
+            .into_iter()
+            .map(|info| BackupInfo {
+                id: info.backup_id,
+                timestamp: info.timestamp,
+                size: info.size,
+                num_files: info.num_files,
+            })
+            .collect();
+        backups.sort_by_key(|info| info.id);
+        Ok(backups)
+    }
+
+    /// Restore the backup with `id` into `dir`, the restored db can be opened as usual.
+    pub fn restore<P: AsRef<Path>>(&self, id: u32, dir: P) -> RocksResult<()> {
+        let mut engine = self.open_engine()?;
+        engine.verify_backup(id)?;
+        engine.restore_from_backup(&dir, &dir, &RestoreOptions::default(), id)?;
+        Ok(())
+    }
+
+    pub fn last_save(&self) -> i64 {
+        self.last_save.load(Ordering::Relaxed)
+    }
+}
+
+/// Create a consistent copy of `db` in `path`, which must not exist.
+/// All the keys are copied from a snapshot, the files can't be hard linked like the
+/// checkpoints of a plain db.
+pub fn checkpoint<P: AsRef<Path>>(db: &TransactionDB, path: P) -> RocksResult<()> {
+    if path.as_ref().exists() {
+        return Err(CHECKPOINT_EXISTS_ERR);
+    }
+    let target = open_plain_db(path)?;
+    sync_db(db, &target)?;
+    target.flush()?;
+    Ok(())
+}
+
+pub(crate) fn open_plain_db<P: AsRef<Path>>(path: P) -> RocksResult<DB> {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    Ok(DB::open_cf(&opts, path, CF_NAMES)?)
+}
+
+/// Make the data of `target` the same as a snapshot of `db`, only the different keys are
+/// written to `target`.
+fn sync_db(db: &TransactionDB, target: &DB) -> RocksResult<()> {
+    let snapshot = db.snapshot();
+    for name in CF_NAMES {
+        let cf = db.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)?;
+        let target_cf = target.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)?;
+        let source = snapshot
+            .iterator_cf(&cf, IteratorMode::Start)
+            .map(|kv| kv.map_err(RError::from));
+        let dest = target.iterator_cf(&target_cf, IteratorMode::Start);
+
+        let mut batch = WriteBatch::default();
+        diff_sorted(source, dest, |op| {
+            match op {
+                SyncOp::Put(key, value) => batch.put_cf(&target_cf, key, value),
+                SyncOp::Delete(key) => batch.delete_cf(&target_cf, key),
+            }
+            if batch.len() >= SYNC_BATCH_SIZE {
+                target.write(std::mem::take(&mut batch))?;
+            }
+            Ok(())
+        })?;
+        target.write(batch)?;
+    }
+    Ok(())
+}
+
+/// Make the data of `db` the same as `source`, which is a checkpoint, only the different keys
+/// are written to `db`.
+pub(crate) fn restore_db(source: &DB, db: &TransactionDB) -> RocksResult<()> {
+    for name in CF_NAMES {
+        let source_cf = source.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)?;
+        let cf = db.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)?;
+        let src = source
+            .iterator_cf(&source_cf, IteratorMode::Start)
+            .map(|kv| kv.map_err(RError::from));
+        let dest = db.iterator_cf(&cf, IteratorMode::Start);
+
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        diff_sorted(src, dest, |op| {
+            match op {
+                SyncOp::Put(key, value) => batch.put_cf(&cf, key, value),
+                SyncOp::Delete(key) => batch.delete_cf(&cf, key),
+            }
+            if batch.len() >= SYNC_BATCH_SIZE {
+                db.write(std::mem::take(&mut batch))?;
+            }
+            Ok(())
+        })?;
+        db.write(batch)?;
+    }
+    Ok(())
+}
+
+pub(crate) enum SyncOp {
+    Put(Box<[u8]>, Box<[u8]>),
+    Delete(Box<[u8]>),
+}
+
+/// Call `f` with the writes to make the data in `dest` the same as `source`, both of them
+/// are ordered by key.
+pub(crate) fn diff_sorted<S, D, F>(source: S, dest: D, mut f: F) -> RocksResult<()>
+where
+    S: Iterator<Item = RocksResult<KvBytes>>,
+    D: Iterator<Item = Result<KvBytes, rocksdb::Error>>,
+    F: FnMut(SyncOp) -> RocksResult<()>,
+{
+    let mut source = source.peekable();
+    let mut dest = dest.map(|kv| kv.map_err(RError::from)).peekable();
+    loop {
+        let order = match (source.peek(), dest.peek()) {
+            (None, None) => return Ok(()),
+            (Some(_), None) => CmpOrdering::Less,
+            (None, Some(_)) => CmpOrdering::Greater,
+            (Some(Ok(s)), Some(Ok(d))) => s.0.cmp(&d.0),
+            // the error is returned by next_kv
+            _ => CmpOrdering::Equal,
+        };
+        match order {
+            CmpOrdering::Less => {
+                let (key, value) = next_kv(&mut source)?;
+                f(SyncOp::Put(key, value))?;
+            }
+            CmpOrdering::Greater => {
+                let (key, _) = next_kv(&mut dest)?;
+                f(SyncOp::Delete(key))?;
+            }
+            CmpOrdering::Equal => {
+                let (key, value) = next_kv(&mut source)?;
+                let (_, dest_value) = next_kv(&mut dest)?;
+                if value != dest_value {
+                    f(SyncOp::Put(key, value))?;
+                }
+            }
+        }
+    }
+}
+
+fn next_kv<I>(iter: &mut Peekable<I>) -> RocksResult<KvBytes>
+where
+    I: Iterator<Item = RocksResult<KvBytes>>,
+{
+    // the iterators are peeked before, they are not exhausted
+    iter.next().unwrap()
+}
+
+pub struct BackupCommand<'a> {
+    client: &'a RocksClient,
+}
+
+impl<'a> BackupCommand<'a> {
+    pub fn new(client: &'a RocksClient) -> Self {
+        Self { client }
+    }
+
+    /// Create a backup in the background.
+    pub async fn bgsave(self) -> RocksResult<Frame> {
+        let backup = match self.client.backup() {
+            Some(backup) => backup,
+            None => return Ok(resp_err(REDIS_BACKUP_NOT_CONFIGURED_ERR)),
+        };
+        if backup.in_progress.load(Ordering::Acquire) {
+            return Ok(resp_err(REDIS_BGSAVE_IN_PROGRESS_ERR));
+        }
+        let db = match self.client.db() {
+            Ok(db) => db,
+            Err(e) => return Ok(resp_err(e)),
+        };
+        spawn_blocking(move || backup.create(&db));
+        Ok(resp_str("Background saving started"))
+    }
+
+    /// Return the unix time in seconds of the last successful backup.
+    pub async fn lastsave(self) -> RocksResult<Frame> {
+        match self.client.backup() {
+            Some(backup) => Ok(resp_int(backup.last_save())),
+            None => Ok(resp_err(REDIS_BACKUP_NOT_CONFIGURED_ERR)),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Bit level helpers over the string value payload, bit 0 is the most significant bit
+//! of the first byte like redis.
+
+use crate::cmd::{BitOperation, BitfieldOverflow};
+
+/// Normalize the redis style inclusive range `[start, end]` for a value with `len` units,
+/// `None` is returned if the range is empty.
+pub fn normalize_range(mut start: i64, mut end: i64, len: i64) -> Option<(u64, u64)> {
+    if start < 0 {
+        start += len;
+    }
+    if end < 0 {
+        end += len;
+    }
+    if start < 0 {
+        start = 0;
+    }
+    if end < 0 {
+        end = 0;
+    }
+    if end >= len {
+        end = len - 1;
+    }
+    if len == 0 || start > end {
+        return None;
+    }
+    Some((start as u64, end as u64))
+}
+
+pub fn get_bit(data: &[u8], offset: u64) -> u8 {
+    let byte = (offset >> 3) as usize;
+    if byte >= data.len() {
+        return 0;
+    }
+    (data[byte] >> (7 - (offset & 7))) & 1
+}
+
+/// Set or clear the bit at `offset`, the value is zero extended if needed.
+/// Returns the original bit.
+pub fn set_bit(data: &mut Vec<u8>, offset: u64, on: bool) -> u8 {
+    let byte = (offset >> 3) as usize;
+    if byte >= data.len() {
+        data.resize(byte + 1, 0);
+    }
+    let shift = 7 - (offset & 7);
+    let old = (data[byte] >> shift) & 1;
+    if on {
+        data[byte] |= 1 << shift;
+    } else {
+        data[byte] &= !(1 << shift);
+    }
+    old
+}
+
+/// Count the set bits in the inclusive bit range `[start, end]`.
+pub fn count_bits(data: &[u8], start: u64, end: u64) -> i64 {
+    let first = (start >> 3) as usize;
+    let last = (end >> 3) as usize;
+    let mut count: i64 = data[first..=last]
+        .iter()
+        .map(|b| b.count_ones() as i64)
+        .sum();
+    // remove the bits out of range in the first and last byte
+    count -= ((data[first] as u32) >> (8 - (start & 7))).count_ones() as i64;
+    count -= ((data[last] as u32) & (0xff >> ((end & 7) + 1))).count_ones() as i64;
+    count
+}
+
+/// Find the first bit equals to `bit` in the inclusive bit range `[start, end]`.
+pub fn find_bit(data: &[u8], bit: bool, start: u64, end: u64) -> Option<u64> {
+    // bytes can be skipped if all bits are not the target
+    let skip = if bit { 0x00 } else { 0xff };
+    let mut pos = start;
+    while pos <= end {
+        let byte = data[(pos >> 3) as usize];
+        if pos & 7 == 0 && byte == skip && pos + 7 <= end {
+            pos += 8;
+            continue;
+        }
+        if ((byte >> (7 - (pos & 7))) & 1 == 1) == bit {
+            return Some(pos);
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Read `bits` bits at `offset` as an unsigned integer, bits out of the value are zero.
+pub fn get_unsigned(data: &[u8], offset: u64, bits: u8) -> u64 {
+    let mut value = 0;
+    for i in 0..bits as u64 {
+        value = (value << 1) | get_bit(data, offset + i) as u64;
+    }
+    value
+}
+
+/// Read `bits` bits at `offset` as a two's complement signed integer.
+pub fn get_signed(data: &[u8], offset: u64, bits: u8) -> i64 {
+    let value = get_unsigned(data, offset, bits);
+    if bits < 64 && value & (1 << (bits - 1)) != 0 {
+        // sign extend
+        (value | (u64::MAX << bits)) as i64
+    } else {
+        value as i64
+    }
+}
+
+/// Write the low `bits` bits of `value` at `offset`.
+pub fn set_unsigned(data: &mut Vec<u8>, offset: u64, bits: u8, value: u64) {
+    for i in 0..bits as u64 {
+        let on = (value >> (bits as u64 - 1 - i)) & 1 == 1;
+        set_bit(data, offset + i, on);
+    }
+}
+
+/// Apply the bitwise operation to the values, shorter values are zero extended.
+pub fn bitop(op: BitOperation, values: &[Vec<u8>]) -> Vec<u8> {
+    let max_len = values.iter().map(Vec::len).max().unwrap_or(0);
+    let mut result = vec![0; max_len];
+    for (i, res) in result.iter_mut().enumerate() {
+        let mut bytes = values.iter().map(|v| v.get(i).copied().unwrap_or(0));
+        *res = match op {
+            BitOperation::And => bytes.fold(0xff, |acc, b| acc & b),
+            BitOperation::Or => bytes.fold(0, |acc, b| acc | b),
+            BitOperation::Xor => bytes.fold(0, |acc, b| acc ^ b),
+            BitOperation::Not => !bytes.next().unwrap_or(0),
+        };
+    }
+    result
+}
+
+/// Check `value` against the range of the bitfield type with the overflow behavior,
+/// `None` is returned if the value overflows with `BitfieldOverflow::Fail`.
+pub fn handle_overflow(
+    value: i128,
+    signed: bool,
+    bits: u8,
+    overflow: BitfieldOverflow,
+) -> Option<i64> {
+    let (min, max) = if signed {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    };
+    if value >= min && value <= max {
+        return Some(value as i64);
+    }
+    match overflow {
+        BitfieldOverflow::Wrap => {
+            let wrapped = value.rem_euclid(1i128 << bits);
+            if signed && wrapped > max {
+                Some((wrapped - (1i128 << bits)) as i64)
+            } else {
+                Some(wrapped as i64)
+            }
+        }
+        BitfieldOverflow::Sat => Some(if value > max { max } else { min } as i64),
+        BitfieldOverflow::Fail => None,
+    }
+}
@@ -0,0 +1,356 @@
+//! Change data capture.
+//!
+//! rust-rocksdb doesn't expose `get_updates_since` on the `TransactionDB` we use, and a write
+//! batch can only be iterated for the default column family, so the WAL can't be followed.
+//! Instead every txn records the raw writes of the user keys, which are decoded by
+//! `KeyDecoder` into the logical changes when the txn is committed. The changes of a txn are
+//! saved with the next sequence number in the default column family in the same txn, so the
+//! log is as durable as the data and can be resumed after the db is reopened.
+//!
+//! The internal writes are not changes: the sub meta keys, the zset score keys and the gc
+//! keys are skipped, the element removals of a key deleted in the same txn are folded into
+//! its `Del`, and the txns of gc only remove the data of the deleted keys. The writes applied
+//! by the replication or restored from a backup are not captured.
+
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::rocks::encoding::encode::{
+    DATA_TYPE_GC_VERSION, DATA_TYPE_HASH, DATA_TYPE_LIST, DATA_TYPE_META, DATA_TYPE_SET,
+    DATA_TYPE_USER, DATA_TYPE_ZSET, TXN_KEY_PREFIX,
+};
+use crate::rocks::encoding::{DataType, KeyDecoder};
+use crate::rocks::engine::{StorageEngine, CF_NAME_DEFAULT};
+use crate::rocks::errors::{RError, CHANGES_TRIMMED_ERR};
+use crate::rocks::transaction::RocksTransaction;
+use crate::rocks::Result as RocksResult;
+
+// prefix of the keys in the default column family, followed by the sequence number
+const CHANGE_KEY_PREFIX: &[u8] = b"mapuche_changes_";
+// max number of the txns read from the log at a time
+const READ_BATCH_SIZE: usize = 256;
+
+/// A logical change of a key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Sequence number of the txn making the change, the changes of a txn share the number.
+    pub seq: u64,
+    pub key: Bytes,
+    pub data_type: DataType,
+    pub op: ChangeOp,
+    /// Expire time of the key in unix milliseconds after `Set` or `Meta`, 0 if the key has
+    /// no ttl or the change is of the other operations.
+    pub ttl: i64,
+}
+
+/// The operation of a change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChangeOp {
+    /// The string value is set.
+    Set {
+        value: Bytes,
+    },
+    /// The key is deleted or expired.
+    Del,
+    /// The meta of a hash, list, set or zset is written, when the key is created or its ttl
+    /// or bounds are changed.
+    Meta,
+    HashSet {
+        field: Bytes,
+        value: Bytes,
+    },
+    HashDel {
+        field: Bytes,
+    },
+    /// The element at `index` is set, the elements of the list are ordered by the index,
+    /// which is not the position from the head.
+    ListSet {
+        index: u64,
+        value: Bytes,
+    },
+    ListDel {
+        index: u64,
+    },
+    SetAdd {
+        member: Bytes,
+    },
+    SetRem {
+        member: Bytes,
+    },
+    ZsetAdd {
+        member: Bytes,
+        score: f64,
+    },
+    ZsetRem {
+        member: Bytes,
+    },
+}
+
+/// A write recorded in txn.
+pub struct RawChange {
+    pub key: Vec<u8>,
+    /// The value put, `None` for a delete
+    pub value: Option<Vec<u8>>,
+    /// The old value of a deleted meta key
+    pub old_meta: Option<Vec<u8>>,
+}
+
+impl RawChange {
+    /// Return true if the write of the key is recorded, the gc version keys are recorded to
+    /// tell the txns of gc.
+    pub fn is_captured(key: &[u8]) -> bool {
+        key.len() > 4
+            && key[0] == TXN_KEY_PREFIX
+            && (key[3] == DATA_TYPE_USER || key[3] == DATA_TYPE_GC_VERSION)
+    }
+}
+
+/// Decode the writes of a txn into the changes.
+pub(crate) fn decode_changes(changes: Vec<RawChange>) -> Vec<ChangeEvent> {
+    let mut parts = vec![];
+    for change in &changes {
+        // the txn of gc removes the data of the deleted keys
+        if change.key[3] == DATA_TYPE_GC_VERSION && change.value.is_none() {
+            return vec![];
+        }
+        if let Some((ukey, data_type, rest)) = KeyDecoder::decode_key_user_parts(&change.key) {
+            parts.push((ukey, data_type, rest, change));
+        }
+    }
+    let deleted: HashSet<&[u8]> = parts
+        .iter()
+        .filter(|(_, data_type, rest, change)| {
+            *data_type == DATA_TYPE_META && rest.is_empty() && change.value.is_none()
+        })
+        .map(|(ukey, ..)| ukey.as_slice())
+        .collect();
+
+    let mut events = vec![];
+    for (ukey, data_type, rest, change) in &parts {
+        let key = Bytes::from(ukey.clone());
+        if *data_type == DATA_TYPE_META {
+            if !rest.is_empty() {
+                continue;
+            }
+            let event = match (&change.value, &change.old_meta) {
+                (Some(value), _) => decode_meta_put(key, value),
+                (None, Some(old_meta)) => ChangeEvent {
+                    seq: 0,
+                    key,
+                    data_type: KeyDecoder::decode_key_type(old_meta),
+                    op: ChangeOp::Del,
+                    ttl: 0,
+                },
+                // the key doesn't exist
+                (None, None) => continue,
+            };
+            events.push(event);
+            continue;
+        }
+        if change.value.is_none() && deleted.contains(ukey.as_slice()) {
+            continue;
+        }
+        // skip the version and the place holder
+        let suffix = Bytes::copy_from_slice(&rest[3..]);
+        let value = change.value.as_deref().map(Bytes::copy_from_slice);
+        let (data_type, op) = match (*data_type, value) {
+            (DATA_TYPE_HASH, Some(value)) => (
+                DataType::Hash,
+                ChangeOp::HashSet {
+                    field: suffix,
+                    value,
+                },
+            ),
+            (DATA_TYPE_HASH, None) => (DataType::Hash, ChangeOp::HashDel { field: suffix }),
+            (DATA_TYPE_LIST, Some(value)) => (
+                DataType::List,
+                ChangeOp::ListSet {
+                    index: u64::from_be_bytes(suffix[..].try_into().unwrap()),
+                    value,
+                },
+            ),
+            (DATA_TYPE_LIST, None) => (
+                DataType::List,
+                ChangeOp::ListDel {
+                    index: u64::from_be_bytes(suffix[..].try_into().unwrap()),
+                },
+            ),
+            (DATA_TYPE_SET, Some(_)) => (DataType::Set, ChangeOp::SetAdd { member: suffix }),
+            (DATA_TYPE_SET, None) => (DataType::Set, ChangeOp::SetRem { member: suffix }),
+            (DATA_TYPE_ZSET, Some(value)) => (
+                DataType::Zset,
+                ChangeOp::ZsetAdd {
+                    member: suffix,
+                    score: KeyDecoder::decode_key_zset_data_value(&value),
+                },
+            ),
+            (DATA_TYPE_ZSET, None) => (DataType::Zset, ChangeOp::ZsetRem { member: suffix }),
+            // the zset score keys
+            _ => continue,
+        };
+        events.push(ChangeEvent {
+            seq: 0,
+            key,
+            data_type,
+            op,
+            ttl: 0,
+        });
+    }
+    events
+}
+
+fn decode_meta_put(key: Bytes, value: &[u8]) -> ChangeEvent {
+    let data_type = KeyDecoder::decode_key_type(value);
+    let ttl = KeyDecoder::decode_key_ttl(value).max(0);
+    let op = match data_type {
+        DataType::String => ChangeOp::Set {
+            value: Bytes::copy_from_slice(KeyDecoder::decode_key_string_slice(value)),
+        },
+        _ => ChangeOp::Meta,
+    };
+    ChangeEvent {
+        seq: 0,
+        key,
+        data_type,
+        op,
+        ttl,
+    }
+}
+
+fn change_key(seq: u64) -> Vec<u8> {
+    let mut key = CHANGE_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&seq.to_be_bytes());
+    key
+}
+
+/// Log of the changes of the committed txns.
+pub(crate) struct ChangeLog {
+    // number of the latest txns whose changes are kept
+    retention: u64,
+    // sequence number of the last logged txn, locked while committing
+    seq: Mutex<u64>,
+    latest: watch::Sender<u64>,
+}
+
+impl ChangeLog {
+    /// Open the log saved in `engine`, keeping the changes of the latest `retention` txns.
+    pub(crate) fn open(engine: &dyn StorageEngine, retention: u64) -> RocksResult<Self> {
+        let mut seq = 0;
+        let end = change_key(u64::MAX);
+        if let Some(kv) = engine.scan_reverse(CF_NAME_DEFAULT, &end, None)?.next() {
+            let (key, _) = kv?;
+            if key.starts_with(CHANGE_KEY_PREFIX) {
+                seq = u64::from_be_bytes(key[CHANGE_KEY_PREFIX.len()..].try_into().unwrap());
+            }
+        }
+        Ok(Self {
+            retention: retention.max(1),
+            seq: Mutex::new(seq),
+            latest: watch::channel(seq).0,
+        })
+    }
+
+    /// Save the changes of the txn in it and commit it by `commit`, the sequence number of
+    /// the changes is set. The log is locked while committing, so the changes are logged in
+    /// the order of commit.
+    pub(crate) fn commit<F>(
+        &self,
+        txn: RocksTransaction,
+        events: &mut [ChangeEvent],
+        commit: F,
+    ) -> RocksResult<()>
+    where
+        F: FnOnce(RocksTransaction) -> RocksResult<()>,
+    {
+        if events.is_empty() {
+            return commit(txn);
+        }
+        let mut seq = self.seq.lock().unwrap();
+        let next = *seq + 1;
+        for event in events.iter_mut() {
+            event.seq = next;
+        }
+        let value = serde_json::to_vec(&events).map_err(|e| RError::owned_error(e.to_string()))?;
+        txn.put_default(&change_key(next), &value)?;
+        if next > self.retention {
+            txn.del_default(&change_key(next - self.retention))?;
+        }
+        commit(txn)?;
+        *seq = next;
+        self.latest.send_replace(next);
+        Ok(())
+    }
+
+    /// Return the stream of the changes of the txns after `seq`.
+    pub(crate) fn stream(&self, engine: Arc<dyn StorageEngine>, mut seq: u64) -> ChangeStream {
+        let mut latest = self.latest.subscribe();
+        let inner = async_stream::stream! {
+            loop {
+                let entries = match read_changes(engine.as_ref(), seq) {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        yield Err(e.into());
+                        break;
+                    }
+                };
+                if entries.is_empty() {
+                    // the log lives as long as the engine held by the stream
+                    if latest.changed().await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                for (entry_seq, events) in entries {
+                    seq = entry_seq;
+                    for event in events {
+                        yield Ok(event);
+                    }
+                }
+            }
+        };
+        ChangeStream {
+            inner: Box::pin(inner),
+        }
+    }
+}
+
+/// Read the saved changes of the txns after `seq`.
+fn read_changes(engine: &dyn StorageEngine, seq: u64) -> RocksResult<Vec<(u64, Vec<ChangeEvent>)>> {
+    let start = change_key(seq + 1);
+    let mut entries = vec![];
+    for kv in engine.scan(CF_NAME_DEFAULT, &start, None)? {
+        let (key, value) = kv?;
+        if !key.starts_with(CHANGE_KEY_PREFIX) || entries.len() >= READ_BATCH_SIZE {
+            break;
+        }
+        let entry_seq = u64::from_be_bytes(key[CHANGE_KEY_PREFIX.len()..].try_into().unwrap());
+        // the changes are read from the oldest kept ones if `seq` is 0
+        if entries.is_empty() && seq != 0 && entry_seq != seq + 1 {
+            return Err(CHANGES_TRIMMED_ERR);
+        }
+        let events =
+            serde_json::from_slice(&value).map_err(|e| RError::owned_error(e.to_string()))?;
+        entries.push((entry_seq, events));
+    }
+    Ok(entries)
+}
+
+/// A stream of the changes returned by `DB::changes_since`, it ends after an error.
+pub struct ChangeStream {
+    inner: Pin<Box<dyn Stream<Item = crate::Result<ChangeEvent>> + Send>>,
+}
+
+impl Stream for ChangeStream {
+    type Item = crate::Result<ChangeEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
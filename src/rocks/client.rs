@@ -1,94 +1,349 @@
 use crate::config::config_meta_key_number_or_default;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
-use rocksdb::{
-    ColumnFamilyRef, TransactionDB, TransactionOptions, WriteBatchWithTransaction, WriteOptions,
-};
-use std::sync::atomic::AtomicU16;
+use rocksdb::{TransactionDB, WriteBatchWithTransaction};
+use std::ops::Deref;
 use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU16, AtomicU64};
+use std::sync::{Arc, Mutex};
+use std::vec;
 
-use crate::rocks::errors::{CF_NOT_EXISTS_ERR, KEY_VERSION_EXHUSTED_ERR, TXN_ERROR};
+use crate::rocks::backup::Backup;
+use crate::rocks::cdc::{decode_changes, ChangeLog};
+use crate::rocks::engine::{ColumnFamilyRef, StorageEngine, CF_NAME_DEFAULT};
+use crate::rocks::errors::{
+    CF_NOT_EXISTS_ERR, ENGINE_NOT_SUPPORTED_ERR, KEY_VERSION_EXHUSTED_ERR, TXN_ERROR,
+};
 use crate::rocks::kv::bound_range::BoundRange;
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::kvpair::KvPair;
 use crate::rocks::kv::value::Value;
+use crate::rocks::notify::Notifier;
+use crate::rocks::replication::ReplicationLog;
 use crate::rocks::transaction::RocksTransaction;
 use crate::rocks::Result as RocksResult;
+use crate::watch::Watchers;
+
+use super::encoding::{KeyDecoder, KeyEncoder};
+use super::{CF_NAMES, CF_NAME_META};
 
-use super::encoding::KeyEncoder;
+// prefix of the keys of the saved number of user keys in the default column family, the
+// base count is saved with the version 0, and every txn changing the count saves its delta
+// in a new key with the next version, so the txns never conflict or wait for each other
+// on the count, the deltas are folded into the base count from time to time
+const KEY_COUNT_PREFIX: &[u8] = b"mapuche_key_count_";
+
+// number of the saved deltas of the key count which are folded at a time
+const KEY_COUNT_FOLD_SIZE: u64 = 1024;
+
+fn key_count_key(version: u64) -> Vec<u8> {
+    [KEY_COUNT_PREFIX, &version.to_be_bytes()].concat()
+}
 
 pub struct RocksClient {
     index_count: AtomicU16,
-    client: Arc<TransactionDB>,
+    // number of user keys, expired keys are counted until they are removed
+    key_count: AtomicI64,
+    engine: Arc<dyn StorageEngine>,
     async_deletion_enabled: bool,
+    backup: Option<Arc<Backup>>,
+    // log of the committed writes to stream to the replicas
+    replication: Option<Arc<ReplicationLog>>,
+    // the db is a replica, which is only written by the replication, or a read-only db
+    read_only: bool,
+    // publisher of the keyspace events of the committed txns
+    notifier: Option<Arc<Notifier>>,
+    // log of the changes of the committed txns
+    change_log: Option<Arc<ChangeLog>>,
+    // watches of the changes of the committed txns
+    watchers: Option<Arc<Watchers>>,
+    // locked while committing a txn with changes, so they are delivered in the order of commit
+    change_lock: Mutex<()>,
+    // version of the next saved delta of the key count
+    count_version: AtomicU64,
+    // number of the deltas of the key count saved since they were last folded
+    unfolded_counts: AtomicU64,
+    // locked while folding the deltas of the key count or replacing the count
+    fold_lock: Mutex<()>,
 }
 
 impl RocksClient {
-    pub fn new(client: Arc<TransactionDB>, async_deletion_enabled: bool) -> Self {
+    pub fn new(
+        engine: Arc<dyn StorageEngine>,
+        async_deletion_enabled: bool,
+        backup: Option<Backup>,
+    ) -> RocksResult<Self> {
         let index_count = AtomicU16::new(SmallRng::from_entropy().gen_range(0..u16::MAX));
-        Self {
+        let client = Self {
             index_count,
-            client,
+            key_count: AtomicI64::new(0),
+            engine,
             async_deletion_enabled,
+            backup: backup.map(Arc::new),
+            replication: None,
+            read_only: false,
+            notifier: None,
+            change_log: None,
+            watchers: None,
+            change_lock: Mutex::new(()),
+            count_version: AtomicU64::new(1),
+            unfolded_counts: AtomicU64::new(0),
+            fold_lock: Mutex::new(()),
+        };
+        client.reload_key_count()?;
+        // a read-only db keeps the deltas
+        let _ = client.fold_key_counts();
+        Ok(client)
+    }
+
+    /// Record the committed writes in `log` to stream them to the replicas.
+    pub fn with_replication(mut self, log: ReplicationLog) -> Self {
+        self.replication = Some(Arc::new(log));
+        self
+    }
+
+    /// Make the db a replica or a read-only db, the writes except the replicated ones are
+    /// discarded.
+    pub fn with_read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Notify the keyspace events of the committed txns by `notifier`.
+    pub(crate) fn with_notifier(mut self, notifier: Arc<Notifier>) -> Self {
+        if notifier.is_enabled() {
+            self.notifier = Some(notifier);
+        }
+        self
+    }
+
+    /// Capture the changes of the committed txns, keeping the latest `retention` txns.
+    pub(crate) fn with_change_log(mut self, retention: u64) -> RocksResult<Self> {
+        self.change_log = Some(Arc::new(ChangeLog::open(self.engine.as_ref(), retention)?));
+        Ok(self)
+    }
+
+    pub(crate) fn change_log(&self) -> Option<Arc<ChangeLog>> {
+        self.change_log.clone()
+    }
+
+    /// Deliver the changes of the committed txns to the watches in `watchers`.
+    pub(crate) fn with_watchers(mut self, watchers: Arc<Watchers>) -> Self {
+        self.watchers = Some(watchers);
+        self
+    }
+
+    /// Return true if the changes of the txns are recorded.
+    fn records_changes(&self) -> bool {
+        self.change_log.is_some() || self.watchers.as_ref().is_some_and(|w| w.is_active())
+    }
+
+    // the saved key count and the last version of its deltas, None if it's not saved
+    fn saved_key_count(&self) -> RocksResult<Option<(u64, i64)>> {
+        let end = key_count_key(u64::MAX);
+        let mut saved = None;
+        for kv in self
+            .engine
+            .scan(CF_NAME_DEFAULT, KEY_COUNT_PREFIX, Some(&end))?
+        {
+            let (key, value) = kv?;
+            if let (Ok(version), Ok(count)) = (
+                key[KEY_COUNT_PREFIX.len()..].try_into(),
+                (*value).try_into(),
+            ) {
+                let (last, sum) = saved.unwrap_or((0, 0));
+                saved = Some((
+                    u64::max(last, u64::from_be_bytes(version)),
+                    sum + i64::from_be_bytes(count),
+                ));
+            }
+        }
+        Ok(saved)
+    }
+
+    // replace the saved base count and the deltas by `count`, or by their sum if it's None,
+    // the caller holds the fold lock
+    fn save_base_count(&self, count: Option<i64>) -> RocksResult<i64> {
+        let end = key_count_key(u64::MAX);
+        let mut keys = vec![];
+        let mut sum = 0;
+        for kv in self
+            .engine
+            .scan(CF_NAME_DEFAULT, KEY_COUNT_PREFIX, Some(&end))?
+        {
+            let (key, value) = kv?;
+            if let Ok(delta) = (*value).try_into() {
+                sum += i64::from_be_bytes(delta);
+            }
+            keys.push(key);
+        }
+        let count = count.unwrap_or(sum);
+        // the deltas saved after the scan are kept, they are folded the next time
+        let txn = self.engine.begin(false);
+        for key in &keys {
+            txn.del(CF_NAME_DEFAULT, key)?;
+        }
+        txn.put(CF_NAME_DEFAULT, &key_count_key(0), &count.to_be_bytes())?;
+        txn.commit()?;
+        Ok(count)
+    }
+
+    // fold the saved deltas of the key count into the base count, it's skipped if they are
+    // being folded by another txn
+    fn fold_key_counts(&self) -> RocksResult<()> {
+        let Ok(_guard) = self.fold_lock.try_lock() else {
+            return Ok(());
+        };
+        self.unfolded_counts.store(0, Ordering::Relaxed);
+        self.save_base_count(None)?;
+        Ok(())
+    }
+
+    // a delta of the key count is saved, the deltas are folded once there are enough of them
+    fn key_count_saved(&self, key_count_delta: i64) {
+        self.key_count.fetch_add(key_count_delta, Ordering::Relaxed);
+        if self.unfolded_counts.fetch_add(1, Ordering::Relaxed) + 1 >= KEY_COUNT_FOLD_SIZE {
+            let _ = self.fold_key_counts();
+        }
+    }
+
+    fn count_keys(&self) -> RocksResult<i64> {
+        let cf = self.cf_handle(CF_NAME_META)?;
+        let mut count = 0;
+        for kv in self.engine.scan(cf.name(), &[], None)? {
+            let (key, _) = kv?;
+            if KeyDecoder::is_meta_key(&key) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    pub fn engine(&self) -> Arc<dyn StorageEngine> {
+        self.engine.clone()
+    }
+
+    /// Return the RocksDB of the data, which is needed by the features built on RocksDB
+    /// like the backups and the replication.
+    pub fn db(&self) -> RocksResult<Arc<TransactionDB>> {
+        self.engine.rocksdb().ok_or(ENGINE_NOT_SUPPORTED_ERR)
+    }
+
+    pub fn backup(&self) -> Option<Arc<Backup>> {
+        self.backup.clone()
+    }
+
+    pub fn replication(&self) -> Option<Arc<ReplicationLog>> {
+        self.replication.clone()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn key_count(&self) -> i64 {
+        self.key_count.load(Ordering::Relaxed)
+    }
+
+    /// Write `batch` of the writes applied out of txn, the key count after adding their key
+    /// count delta is saved in the batch.
+    pub(crate) fn write_batch(
+        &self,
+        mut batch: WriteBatchWithTransaction<true>,
+        key_count_delta: i64,
+    ) -> RocksResult<()> {
+        let db = self.db()?;
+        if key_count_delta == 0 {
+            return Ok(db.write(batch)?);
         }
+        let version = self.count_version.fetch_add(1, Ordering::Relaxed);
+        batch.put(key_count_key(version), key_count_delta.to_be_bytes());
+        db.write(batch)?;
+        self.key_count_saved(key_count_delta);
+        Ok(())
+    }
+
+    /// Load the saved key count, after the data is written by the primary of a secondary db.
+    /// The keys of a db written before the count is saved are counted.
+    pub(crate) fn reload_key_count(&self) -> RocksResult<()> {
+        let _guard = self.fold_lock.lock().unwrap();
+        let key_count = match self.saved_key_count()? {
+            Some((version, key_count)) => {
+                self.count_version.fetch_max(version + 1, Ordering::Relaxed);
+                key_count
+            }
+            None => self.count_keys()?,
+        };
+        self.key_count.store(key_count, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Count the keys again and save the count after the data is replaced, the count isn't
+    /// copied with the data.
+    pub(crate) fn recount_keys(&self) -> RocksResult<()> {
+        let _guard = self.fold_lock.lock().unwrap();
+        let key_count = self.save_base_count(Some(self.count_keys()?))?;
+        self.unfolded_counts.store(0, Ordering::Relaxed);
+        self.key_count.store(key_count, Ordering::Relaxed);
+        Ok(())
     }
 
     pub fn get(&self, cf: ColumnFamilyRef, key: Key) -> RocksResult<Option<Value>> {
-        let client = self.client.as_ref();
         let key: Vec<u8> = key.into();
-        client.get_cf(&cf, key).map_err(|e| e.into())
+        self.engine.get(cf.name(), &key)
     }
 
     pub fn put(&self, cf: ColumnFamilyRef, key: Key, value: Value) -> RocksResult<()> {
-        let client = self.client.as_ref();
+        // meta keys are written in txn to keep the key count, and all the replicated or
+        // captured writes are written in txn to be recorded
+        if KeyDecoder::is_meta_key(key.as_ref()) || self.records_writes() {
+            return self.exec_txn(|txn| txn.put(cf, key, value));
+        }
         let key: Vec<u8> = key.into();
-        let value: Vec<u8> = value;
-        client.put_cf(&cf, key, value).map_err(|e| e.into())
+        self.engine.put(cf.name(), &key, &value)
     }
 
     pub fn del(&self, cf: ColumnFamilyRef, key: Key) -> RocksResult<()> {
-        let client = self.client.as_ref();
+        if KeyDecoder::is_meta_key(key.as_ref()) || self.records_writes() {
+            return self.exec_txn(|txn| txn.del(cf, key));
+        }
         let key: Vec<u8> = key.into();
-        client.delete_cf(&cf, key).map_err(|e| e.into())
+        self.engine.del(cf.name(), &key)
     }
 
-    pub fn batch_get(&self, cf: ColumnFamilyRef, keys: Vec<Key>) -> RocksResult<Vec<KvPair>> {
-        let client = self.client.as_ref();
+    fn records_writes(&self) -> bool {
+        self.replication.is_some() || self.read_only || self.records_changes()
+    }
 
-        let cf_key_pairs = keys
-            .clone()
+    pub fn batch_get(&self, cf: ColumnFamilyRef, keys: Vec<Key>) -> RocksResult<Vec<KvPair>> {
+        let raw_keys: Vec<&[u8]> = keys.iter().map(|key| key.as_ref()).collect();
+        let values = self.engine.batch_get(cf.name(), &raw_keys)?;
+        Ok(keys
             .into_iter()
-            .map(|k| (&cf, k))
-            .collect::<Vec<(&ColumnFamilyRef, Key)>>();
-
-        let results = client.multi_get_cf(cf_key_pairs);
-        let mut kvpairs = Vec::new();
-        for i in 0..results.len() {
-            if let Ok(opt) = results.get(i).unwrap() {
-                let key = keys.get(i).unwrap().clone();
-                let value = opt.clone();
-                if let Some(val) = value {
-                    let kvpair = KvPair::from((key, val));
-                    kvpairs.push(kvpair);
-                }
-            }
-        }
-        Ok(kvpairs)
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|value| KvPair::from((key, value))))
+            .collect())
     }
 
     pub fn batch_put(&self, cf: ColumnFamilyRef, kvs: Vec<KvPair>) -> RocksResult<()> {
-        let client = self.client.as_ref();
-
-        let mut write_batch = WriteBatchWithTransaction::default();
-        for kv in kvs {
-            write_batch.put_cf(&cf, kv.0, kv.1);
-        }
-        client.write(write_batch).map_err(|e| e.into())
+        // a write batch can't tell which keys are new, the txn locks the meta keys to count
+        // the new keys exactly and saves the count with them, the writes in txn are also
+        // recorded for the replicas and the changes
+        self.exec_txn(|txn| {
+            for kv in kvs {
+                txn.put(cf.clone(), kv.0, kv.1)?;
+            }
+            Ok(())
+        })
     }
 
-    pub fn cf_handle(&self, name: &str) -> RocksResult<ColumnFamilyRef> {
-        self.client.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)
+    pub fn cf_handle(&self, name: &str) -> RocksResult<ColumnFamilyRef<'_>> {
+        CF_NAMES
+            .into_iter()
+            .chain([CF_NAME_DEFAULT])
+            .find(|cf| *cf == name)
+            .map(ColumnFamilyRef::new)
+            .ok_or(CF_NOT_EXISTS_ERR)
     }
 
     pub fn scan(
@@ -96,33 +351,17 @@ impl RocksClient {
         cf_handle: ColumnFamilyRef,
         range: impl Into<BoundRange>,
         limit: u32,
-    ) -> RocksResult<impl Iterator<Item = KvPair>> {
+    ) -> RocksResult<vec::IntoIter<KvPair>> {
         let bound_range = range.into();
         let (start, end) = bound_range.into_keys();
-        let start: Vec<u8> = start.into();
-        let it = self.client.prefix_iterator_cf(&cf_handle, &start);
-        let end_it_key = end
-            .map(|e| {
-                let e_vec: Vec<u8> = e.into();
-                self.client.prefix_iterator_cf(&cf_handle, e_vec)
-            })
-            .and_then(|mut it| it.next())
-            .and_then(|res| res.ok())
-            .map(|kv| kv.0);
-
-        let mut kv_pairs: Vec<KvPair> = Vec::new();
-        for inner in it {
-            if let Ok(kv_bytes) = inner {
-                if Some(&kv_bytes.0) == end_it_key.as_ref() {
-                    break;
-                }
-                let pair: (Key, Value) = (kv_bytes.0.to_vec().into(), kv_bytes.1.to_vec());
-                kv_pairs.push(pair.into());
-            }
-            if kv_pairs.len() >= limit as usize {
-                break;
-            }
-        }
+        let end = end.map(Vec::<u8>::from);
+        let kv_pairs: Vec<KvPair> = self
+            .engine
+            .scan(cf_handle.name(), start.as_ref(), end.as_deref())?
+            .filter_map(|kv| kv.ok())
+            .take(limit as usize)
+            .map(|(key, value)| KvPair::from((Key::from(key.to_vec()), value.to_vec())))
+            .collect();
         Ok(kv_pairs.into_iter())
     }
 
@@ -131,16 +370,95 @@ impl RocksClient {
         T: Send + Sync + 'static,
         F: FnOnce(&RocksTransaction) -> RocksResult<T>,
     {
-        let client = self.client.as_ref();
-        let txn_opts = TransactionOptions::new();
-        // txn_opts.set_lock_timeout(50);
-        let txn = client.transaction_opt(&WriteOptions::default(), &txn_opts);
-        let rock_txn = RocksTransaction::new(txn);
-        let res = f(&rock_txn)?;
-        if rock_txn.commit().is_err() {
+        let txn = self.begin_txn(false);
+        let res = f(&txn)?;
+        self.commit(txn)?;
+        Ok(res)
+    }
+
+    /// Same as `exec_txn`, but all the reads in the txn see the db at the time the txn begins.
+    pub fn exec_snapshot_txn<T, F>(&self, f: F) -> RocksResult<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&RocksTransaction) -> RocksResult<T>,
+    {
+        let txn = self.begin_txn(true);
+        let res = f(&txn)?;
+        self.commit(txn)?;
+        Ok(res)
+    }
+
+    /// Create a read only txn of the current snapshot of the db, see
+    /// `RocksTransaction::read_only`.
+    pub(crate) fn read_only_txn(&self) -> RocksTransaction<'_> {
+        RocksTransaction::new(self.engine.begin(true)).read_only()
+    }
+
+    /// Begin a txn of the db, which is committed by `commit`. The reads see the snapshot
+    /// taken when the txn begins if `snapshot` is true, and writing a key changed after it
+    /// fails with `TXN_ERROR`, so a value read in the txn can't be lost by another write.
+    pub(crate) fn begin_txn(&self, snapshot: bool) -> RocksTransaction<'_> {
+        let rock_txn = RocksTransaction::new(self.engine.begin(snapshot));
+        let rock_txn = if self.replication.is_some() {
+            rock_txn.record_writes()
+        } else {
+            rock_txn
+        };
+        let rock_txn = if self.notifier.is_some() {
+            rock_txn.record_events()
+        } else {
+            rock_txn
+        };
+        if self.records_changes() {
+            rock_txn.record_changes()
+        } else {
+            rock_txn
+        }
+    }
+
+    /// Commit a txn begun by `begin_txn`, the writes are replicated, captured and notified
+    /// after they are committed.
+    pub(crate) fn commit(&self, rock_txn: RocksTransaction) -> RocksResult<()> {
+        // a replica keeps the data of the primary, and a read-only db can't be written, the
+        // local writes like removing the expired keys are rolled back when the txn is dropped
+        if self.read_only {
+            return Ok(());
+        }
+        let key_count_delta = rock_txn.key_count_delta();
+        // the delta of the key count is saved in the txn, the replicas save their own deltas
+        if key_count_delta != 0 {
+            let version = self.count_version.fetch_add(1, Ordering::Relaxed);
+            rock_txn.put_default(&key_count_key(version), &key_count_delta.to_be_bytes())?;
+        }
+        let events = rock_txn.take_events();
+        let commit = |txn: RocksTransaction| match &self.replication {
+            Some(log) => self.db().and_then(|db| log.commit(&db, txn)),
+            None => txn.commit(),
+        };
+        let mut changes = decode_changes(rock_txn.take_changes());
+        let committed = if changes.is_empty() {
+            commit(rock_txn)
+        } else {
+            let _guard = self.change_lock.lock().unwrap();
+            let committed = match &self.change_log {
+                Some(log) => log.commit(rock_txn, &mut changes, commit),
+                None => commit(rock_txn),
+            };
+            if let (Ok(_), Some(watchers)) = (&committed, &self.watchers) {
+                watchers.notify(&changes);
+            }
+            committed
+        };
+        if committed.is_err() {
             return Err(TXN_ERROR);
         }
-        Ok(res)
+        if key_count_delta != 0 {
+            self.key_count_saved(key_count_delta);
+        }
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(events);
+        }
+        Ok(())
     }
 
     pub(crate) fn gen_next_meta_index(&self) -> u16 {
@@ -162,8 +480,9 @@ impl RocksClient {
         txn: &RocksTransaction,
         gc_cf: ColumnFamilyRef,
         gc_version_cf: ColumnFamilyRef,
-        key: &str,
+        key: impl AsRef<[u8]>,
     ) -> RocksResult<u16> {
+        let key = key.as_ref();
         // check if async deletion is enabled, return ASAP if not
         if !self.async_deletion_enabled {
             return Ok(0);
@@ -187,3 +506,123 @@ impl RocksClient {
             .map_or_else(|| Ok(next_version), |_| Err(KEY_VERSION_EXHUSTED_ERR))
     }
 }
+
+/// The client of a db used by the commands. The reads and writes are executed in `txn` if
+/// it's given, so the commands executed with the same txn are committed together, otherwise
+/// every write is committed on its own.
+#[derive(Clone, Copy)]
+pub struct ClientRef<'a> {
+    client: &'a RocksClient,
+    txn: Option<&'a RocksTransaction<'a>>,
+}
+
+impl<'a> ClientRef<'a> {
+    pub fn with_txn(client: &'a RocksClient, txn: &'a RocksTransaction<'a>) -> Self {
+        Self {
+            client,
+            txn: Some(txn),
+        }
+    }
+
+    pub fn client(&self) -> &'a RocksClient {
+        self.client
+    }
+
+    pub fn txn(&self) -> Option<&'a RocksTransaction<'a>> {
+        self.txn
+    }
+
+    pub fn get(&self, cf: ColumnFamilyRef, key: Key) -> RocksResult<Option<Value>> {
+        match self.txn {
+            Some(txn) => txn.get(cf, key),
+            None => self.client.get(cf, key),
+        }
+    }
+
+    pub fn put(&self, cf: ColumnFamilyRef, key: Key, value: Value) -> RocksResult<()> {
+        match self.txn {
+            Some(txn) => txn.put(cf, key, value),
+            None => self.client.put(cf, key, value),
+        }
+    }
+
+    pub fn del(&self, cf: ColumnFamilyRef, key: Key) -> RocksResult<()> {
+        match self.txn {
+            Some(txn) => txn.del(cf, key),
+            None => self.client.del(cf, key),
+        }
+    }
+
+    pub fn batch_get(&self, cf: ColumnFamilyRef, keys: Vec<Key>) -> RocksResult<Vec<KvPair>> {
+        match self.txn {
+            Some(txn) => txn.batch_get(cf, keys),
+            None => self.client.batch_get(cf, keys),
+        }
+    }
+
+    pub fn batch_put(&self, cf: ColumnFamilyRef, kvs: Vec<KvPair>) -> RocksResult<()> {
+        match self.txn {
+            Some(txn) => kvs
+                .into_iter()
+                .try_for_each(|kv| txn.put(cf.clone(), kv.0, kv.1)),
+            None => self.client.batch_put(cf, kvs),
+        }
+    }
+
+    pub fn scan(
+        &self,
+        cf_handle: ColumnFamilyRef,
+        range: impl Into<BoundRange>,
+        limit: u32,
+    ) -> RocksResult<vec::IntoIter<KvPair>> {
+        match self.txn {
+            Some(txn) => Ok(txn
+                .scan(cf_handle, range, limit)?
+                .collect::<Vec<_>>()
+                .into_iter()),
+            None => self.client.scan(cf_handle, range, limit),
+        }
+    }
+
+    pub fn exec_txn<T, F>(&self, f: F) -> RocksResult<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&RocksTransaction) -> RocksResult<T>,
+    {
+        match self.txn {
+            Some(txn) => f(txn),
+            None => self.client.exec_txn(f),
+        }
+    }
+
+    pub fn exec_snapshot_txn<T, F>(&self, f: F) -> RocksResult<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&RocksTransaction) -> RocksResult<T>,
+    {
+        match self.txn {
+            Some(txn) => f(txn),
+            None => self.client.exec_snapshot_txn(f),
+        }
+    }
+}
+
+impl<'a> From<&'a RocksClient> for ClientRef<'a> {
+    fn from(client: &'a RocksClient) -> Self {
+        Self { client, txn: None }
+    }
+}
+
+impl<'a> From<&'a Arc<RocksClient>> for ClientRef<'a> {
+    fn from(client: &'a Arc<RocksClient>) -> Self {
+        Self::from(client.as_ref())
+    }
+}
+
+impl Deref for ClientRef<'_> {
+    type Target = RocksClient;
+
+    fn deref(&self) -> &RocksClient {
+        self.client
+    }
+}
@@ -0,0 +1,201 @@
+//! Serialization format of DUMP and RESTORE, it does not depend on the encoding of the keys
+//! in rocksdb, so the dumped values can be restored by other instances.
+//!
+//! The format is:
+//! "MPCH" magic, 1 byte format version, 1 byte data type, 8 bytes expire timestamp in
+//! milliseconds (0 means no ttl), the payload of the data type and 8 bytes crc64 of all the
+//! bytes before. All the numbers are in big endian.
+//!
+//! The payload of string is the length prefixed value, the payload of collections is the
+//! number of elements followed by the elements, byte strings are prefixed with 4 bytes length
+//! and zset scores are saved as the bits of f64.
+
+use crc::{Crc, CRC_64_REDIS};
+
+use crate::rocks::encoding::{DataType, KeyEncoder};
+use crate::rocks::errors::REDIS_DUMP_PAYLOAD_ERR;
+use crate::rocks::Result as RocksResult;
+
+const DUMP_MAGIC: &[u8] = b"MPCH";
+const DUMP_VERSION: u8 = 1;
+const DUMP_HDR_SIZE: usize = 14;
+const DUMP_CRC: Crc<u64> = Crc::<u64>::new(&CRC_64_REDIS);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DumpPayload {
+    String(Vec<u8>),
+    Hash(Vec<(Vec<u8>, Vec<u8>)>),
+    List(Vec<Vec<u8>>),
+    Set(Vec<Vec<u8>>),
+    Zset(Vec<(Vec<u8>, f64)>),
+}
+
+impl DumpPayload {
+    fn is_empty(&self) -> bool {
+        match self {
+            DumpPayload::String(_) => false,
+            DumpPayload::Hash(fields) => fields.is_empty(),
+            DumpPayload::List(items) | DumpPayload::Set(items) => items.is_empty(),
+            DumpPayload::Zset(members) => members.is_empty(),
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            DumpPayload::String(_) => DataType::String,
+            DumpPayload::Hash(_) => DataType::Hash,
+            DumpPayload::List(_) => DataType::List,
+            DumpPayload::Set(_) => DataType::Set,
+            DumpPayload::Zset(_) => DataType::Zset,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpValue {
+    /// expire timestamp in milliseconds, 0 means no ttl
+    pub ttl: i64,
+    pub payload: DumpPayload,
+}
+
+impl DumpValue {
+    pub fn new(ttl: i64, payload: DumpPayload) -> Self {
+        // SET without expire time saves -1 as ttl
+        DumpValue {
+            ttl: ttl.max(0),
+            payload,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(DUMP_HDR_SIZE + 8);
+        buf.extend_from_slice(DUMP_MAGIC);
+        buf.push(DUMP_VERSION);
+        buf.push(KeyEncoder::get_type_bytes(self.payload.data_type()));
+        buf.extend_from_slice(&self.ttl.to_be_bytes());
+
+        match &self.payload {
+            DumpPayload::String(value) => put_bytes(&mut buf, value),
+            DumpPayload::Hash(fields) => {
+                put_len(&mut buf, fields.len());
+                for (field, value) in fields {
+                    put_bytes(&mut buf, field);
+                    put_bytes(&mut buf, value);
+                }
+            }
+            DumpPayload::List(items) | DumpPayload::Set(items) => {
+                put_len(&mut buf, items.len());
+                for item in items {
+                    put_bytes(&mut buf, item);
+                }
+            }
+            DumpPayload::Zset(members) => {
+                put_len(&mut buf, members.len());
+                for (member, score) in members {
+                    put_bytes(&mut buf, member);
+                    buf.extend_from_slice(&score.to_bits().to_be_bytes());
+                }
+            }
+        }
+
+        let checksum = DUMP_CRC.checksum(&buf);
+        buf.extend_from_slice(&checksum.to_be_bytes());
+        buf
+    }
+
+    pub fn decode(data: &[u8]) -> RocksResult<Self> {
+        if data.len() < DUMP_HDR_SIZE + 8 || &data[..4] != DUMP_MAGIC || data[4] != DUMP_VERSION {
+            return Err(REDIS_DUMP_PAYLOAD_ERR);
+        }
+        let (body, checksum) = data.split_at(data.len() - 8);
+        if DUMP_CRC.checksum(body) != u64::from_be_bytes(checksum.try_into().unwrap()) {
+            return Err(REDIS_DUMP_PAYLOAD_ERR);
+        }
+
+        let data_type = body[5];
+        let ttl = i64::from_be_bytes(body[6..14].try_into().unwrap());
+        let mut reader = Reader {
+            data: &body[DUMP_HDR_SIZE..],
+        };
+        let payload = match data_type {
+            0 => DumpPayload::String(reader.bytes()?),
+            1 => {
+                let len = reader.len()?;
+                let mut fields = Vec::with_capacity(len.min(reader.remaining()));
+                for _ in 0..len {
+                    fields.push((reader.bytes()?, reader.bytes()?));
+                }
+                DumpPayload::Hash(fields)
+            }
+            2 | 3 => {
+                let len = reader.len()?;
+                let mut items = Vec::with_capacity(len.min(reader.remaining()));
+                for _ in 0..len {
+                    items.push(reader.bytes()?);
+                }
+                if data_type == 2 {
+                    DumpPayload::List(items)
+                } else {
+                    DumpPayload::Set(items)
+                }
+            }
+            4 => {
+                let len = reader.len()?;
+                let mut members = Vec::with_capacity(len.min(reader.remaining()));
+                for _ in 0..len {
+                    let member = reader.bytes()?;
+                    let score = f64::from_bits(reader.u64()?);
+                    members.push((member, score));
+                }
+                DumpPayload::Zset(members)
+            }
+            _ => return Err(REDIS_DUMP_PAYLOAD_ERR),
+        };
+        // empty collections are never dumped
+        if reader.remaining() != 0 || payload.is_empty() {
+            return Err(REDIS_DUMP_PAYLOAD_ERR);
+        }
+        Ok(DumpValue { ttl, payload })
+    }
+}
+
+fn put_len(buf: &mut Vec<u8>, len: usize) {
+    buf.extend_from_slice(&(len as u64).to_be_bytes());
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    fn take(&mut self, n: usize) -> RocksResult<&[u8]> {
+        if self.data.len() < n {
+            return Err(REDIS_DUMP_PAYLOAD_ERR);
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> RocksResult<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn len(&mut self) -> RocksResult<usize> {
+        Ok(self.u64()? as usize)
+    }
+
+    fn bytes(&mut self) -> RocksResult<Vec<u8>> {
+        let len = u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
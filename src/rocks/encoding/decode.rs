@@ -1,4 +1,4 @@
-use crate::rocks::encoding::encode::DATA_TYPE_META;
+use crate::rocks::encoding::encode::{DATA_TYPE_META, DATA_TYPE_USER, TXN_KEY_PREFIX};
 use crate::rocks::encoding::{DataType, ENC_GROUP_SIZE, ENC_MARKER, SIGN_MASK};
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::value::Value;
@@ -111,9 +111,41 @@ impl KeyDecoder {
         (ukey, key[idx] == DATA_TYPE_META && idx + 1 == key.len())
     }
 
+    /// Check if the raw key is the meta key of a user key, sub meta keys and data keys
+    /// share the same prefix but have more bytes after the meta type.
+    pub fn is_meta_key(key: &[u8]) -> bool {
+        if key.len() < 5 || key[0] != TXN_KEY_PREFIX || key[3] != DATA_TYPE_USER {
+            return false;
+        }
+        let idx = 4 + Self::encoded_bytes_len(&key[4..]);
+        idx + 1 == key.len() && key[idx] == DATA_TYPE_META
+    }
+
+    /// Split a key of the user keyspace into the user key, the type after it and the rest of
+    /// the key, which is empty for a meta key. `None` is returned for the other keys.
+    pub fn decode_key_user_parts(key: &[u8]) -> Option<(Vec<u8>, u8, &[u8])> {
+        if key.len() < 5 || key[0] != TXN_KEY_PREFIX || key[3] != DATA_TYPE_USER {
+            return None;
+        }
+        let idx = 4 + Self::encoded_bytes_len(&key[4..]);
+        if idx >= key.len() {
+            return None;
+        }
+        let ukey = Self::decode_bytes(&key[4..]);
+        Some((ukey, key[idx], &key[idx + 1..]))
+    }
+
+    /// Return the type specific part after the place holder of a data key.
+    pub fn decode_key_data_suffix(ukey: impl AsRef<[u8]>, key: Key) -> Vec<u8> {
+        let key: Vec<u8> = key.into();
+        let enc_ukey = encode_bytes(ukey.as_ref());
+        let idx = 8 + enc_ukey.len();
+        key[idx..].to_vec()
+    }
+
     pub fn decode_key_set_member_from_datakey(ukey: &str, key: Key) -> Vec<u8> {
         let key: Vec<u8> = key.into();
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let idx = 8 + enc_ukey.len();
         key[idx..].to_vec()
     }
@@ -139,35 +171,35 @@ impl KeyDecoder {
 
     pub fn decode_key_list_idx_from_datakey(ukey: &str, key: Key) -> u64 {
         let key: Vec<u8> = key.into();
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let idx = 8 + enc_ukey.len();
         u64::from_be_bytes(key[idx..].try_into().unwrap())
     }
 
     pub fn decode_key_hash_userkey_from_datakey(ukey: &str, key: Key) -> Vec<u8> {
         let key: Vec<u8> = key.into();
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let idx = 8 + enc_ukey.len();
         key[idx..].to_vec()
     }
 
     pub fn decode_key_zset_score_from_scorekey(ukey: &str, key: Key) -> f64 {
         let key: Vec<u8> = key.into();
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let idx = 8 + enc_ukey.len();
         Self::decode_cmp_uint64_to_f64(u64::from_be_bytes(key[idx..idx + 8].try_into().unwrap()))
     }
 
     pub fn decode_key_zset_member_from_scorekey(ukey: &str, key: Key) -> Vec<u8> {
         let key: Vec<u8> = key.into();
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let idx = 17 + enc_ukey.len();
         key[idx..].to_vec()
     }
 
-    pub fn decode_key_zset_member_from_datakey(ukey: &str, key: Key) -> Vec<u8> {
+    pub fn decode_key_zset_member_from_datakey(ukey: impl AsRef<[u8]>, key: Key) -> Vec<u8> {
         let key: Vec<u8> = key.into();
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let idx = 8 + enc_ukey.len();
         key[idx..].to_vec()
     }
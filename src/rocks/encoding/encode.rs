@@ -39,8 +39,8 @@ impl KeyEncoder {
         }
     }
 
-    pub fn encode_string(ukey: &str) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_string(ukey: impl AsRef<[u8]>) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(5 + enc_ukey.len());
 
         key.push(TXN_KEY_PREFIX);
@@ -73,9 +73,7 @@ impl KeyEncoder {
     }
 
     pub fn encode_strings(keys: &[String]) -> Vec<Key> {
-        keys.iter()
-            .map(|ukey| KeyEncoder::encode_string(ukey))
-            .collect()
+        keys.iter().map(KeyEncoder::encode_string).collect()
     }
 
     fn encode_meta_common_prefix(enc_ukey: &[u8], key: &mut Vec<u8>) {
@@ -86,8 +84,12 @@ impl KeyEncoder {
         key.push(DATA_TYPE_META);
     }
 
-    pub fn encode_meta_key(ukey: &str) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_meta_key(ukey: impl AsRef<[u8]>) -> Key {
+        KeyEncoder::encode_meta_key_from_bytes(ukey.as_ref())
+    }
+
+    pub fn encode_meta_key_from_bytes(ukey: &[u8]) -> Key {
+        let enc_ukey = encode_bytes(ukey);
         let mut key = Vec::with_capacity(5 + enc_ukey.len());
 
         KeyEncoder::encode_meta_common_prefix(&enc_ukey, &mut key);
@@ -102,8 +104,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_sub_meta_key(ukey: &str, version: u16, idx: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_sub_meta_key(ukey: impl AsRef<[u8]>, version: u16, idx: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(10 + enc_ukey.len());
 
         KeyEncoder::encode_meta_common_prefix(&enc_ukey, &mut key);
@@ -114,8 +116,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_sub_meta_key_start(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_sub_meta_key_start(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_meta_common_prefix(&enc_ukey, &mut key);
@@ -125,9 +127,9 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_sub_meta_key_end(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
-        let mut key = Vec::with_capacity(8 + ukey.len());
+    pub fn encode_sub_meta_key_end(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
+        let mut key = Vec::with_capacity(8 + ukey.as_ref().len());
 
         KeyEncoder::encode_meta_common_prefix(&enc_ukey, &mut key);
 
@@ -136,15 +138,16 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_sub_meta_key_range(key: &str, version: u16) -> BoundRange {
+    pub fn encode_sub_meta_key_range(key: impl AsRef<[u8]>, version: u16) -> BoundRange {
+        let key = key.as_ref();
         let sub_meta_key_start = KeyEncoder::encode_sub_meta_key_start(key, version);
         let sub_meta_key_end = KeyEncoder::encode_sub_meta_key_end(key, version);
         let range: Range<Key> = sub_meta_key_start..sub_meta_key_end;
         range.into()
     }
 
-    pub fn encode_gc_key_prefix(ukey: &str, data_type: u8, extra: usize) -> Vec<u8> {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_gc_key_prefix(ukey: impl AsRef<[u8]>, data_type: u8, extra: usize) -> Vec<u8> {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(extra + enc_ukey.len());
         key.push(TXN_KEY_PREFIX);
         key.extend_from_slice(INSTANCE_ID.as_slice());
@@ -154,11 +157,11 @@ impl KeyEncoder {
         key
     }
 
-    pub fn encode_gc_key(ukey: &str) -> Key {
+    pub fn encode_gc_key(ukey: impl AsRef<[u8]>) -> Key {
         KeyEncoder::encode_gc_key_prefix(ukey, DATA_TYPE_GC, 5).into()
     }
 
-    pub fn encode_gc_version_key(ukey: &str, version: u16) -> Key {
+    pub fn encode_gc_version_key(ukey: impl AsRef<[u8]>, version: u16) -> Key {
         let mut key = KeyEncoder::encode_gc_key_prefix(ukey, DATA_TYPE_GC_VERSION, 7);
         key.extend_from_slice(&version.to_be_bytes());
         key.into()
@@ -173,8 +176,40 @@ impl KeyEncoder {
         key.extend_from_slice(&version.to_be_bytes());
     }
 
-    pub fn encode_set_data_key_start(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    /// Encode the data key of `ukey` with the type specific `suffix` after the place holder,
+    /// used to move the data keys from one user key to another.
+    pub fn encode_type_data_key(
+        ukey: impl AsRef<[u8]>,
+        key_type: u8,
+        version: u16,
+        suffix: &[u8],
+    ) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
+        let mut key = Vec::with_capacity(8 + enc_ukey.len() + suffix.len());
+
+        KeyEncoder::encode_type_data_key_prefix(key_type, &enc_ukey, &mut key, version);
+        key.push(PLACE_HOLDER);
+        key.extend_from_slice(suffix);
+        key.into()
+    }
+
+    pub fn encode_type_data_key_range(
+        ukey: impl AsRef<[u8]>,
+        key_type: u8,
+        version: u16,
+    ) -> BoundRange {
+        let enc_ukey = encode_bytes(ukey.as_ref());
+        let mut start = Vec::with_capacity(8 + enc_ukey.len());
+        KeyEncoder::encode_type_data_key_prefix(key_type, &enc_ukey, &mut start, version);
+        let mut end = start.clone();
+        start.push(PLACE_HOLDER);
+        end.push(PLACE_HOLDER + 1);
+        let range: Range<Key> = start.into()..end.into();
+        range.into()
+    }
+
+    pub fn encode_set_data_key_start(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_SET, &enc_ukey, &mut key, version);
@@ -182,8 +217,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_set_data_key_end(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_set_data_key_end(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_SET, &enc_ukey, &mut key, version);
@@ -191,15 +226,16 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_set_data_key_range(key: &str, version: u16) -> BoundRange {
+    pub fn encode_set_data_key_range(key: impl AsRef<[u8]>, version: u16) -> BoundRange {
+        let key = key.as_ref();
         let data_key_start = KeyEncoder::encode_set_data_key_start(key, version);
         let data_key_end = KeyEncoder::encode_set_data_key_end(key, version);
         let range: Range<Key> = data_key_start..data_key_end;
         range.into()
     }
 
-    pub fn encode_set_data_key(ukey: &str, member: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_set_data_key(ukey: impl AsRef<[u8]>, member: &str, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len() + member.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_SET, &enc_ukey, &mut key, version);
@@ -248,8 +284,8 @@ impl KeyEncoder {
     /// left initial value  1<<32, left is point to the left element
     /// right initial value 1<<32, right is point to the next right position of right element
     /// list is indicated as null if left index equal to right
-    pub fn encode_list_data_key(ukey: &str, idx: u64, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_list_data_key(ukey: impl AsRef<[u8]>, idx: u64, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(16 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_LIST, &enc_ukey, &mut key, version);
@@ -259,19 +295,20 @@ impl KeyEncoder {
     }
 
     pub fn encode_list_data_key_idx_range(
-        key: &str,
+        key: impl AsRef<[u8]>,
         start: u64,
         end: u64,
         version: u16,
     ) -> BoundRange {
+        let key = key.as_ref();
         let data_key_start = KeyEncoder::encode_list_data_key(key, start, version);
         let data_key_end = KeyEncoder::encode_list_data_key(key, end, version);
         let range: RangeInclusive<Key> = data_key_start..=data_key_end;
         range.into()
     }
 
-    fn encode_list_data_key_start(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    fn encode_list_data_key_start(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_LIST, &enc_ukey, &mut key, version);
@@ -279,8 +316,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    fn encode_list_data_key_end(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    fn encode_list_data_key_end(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_LIST, &enc_ukey, &mut key, version);
@@ -288,7 +325,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_list_data_key_range(key: &str, version: u16) -> BoundRange {
+    pub fn encode_list_data_key_range(key: impl AsRef<[u8]>, version: u16) -> BoundRange {
+        let key = key.as_ref();
         let data_key_start = KeyEncoder::encode_list_data_key_start(key, version);
         let data_key_end = KeyEncoder::encode_list_data_key_end(key, version);
         let range: Range<Key> = data_key_start..data_key_end;
@@ -307,8 +345,8 @@ impl KeyEncoder {
         val
     }
 
-    pub fn encode_hash_data_key(ukey: &str, field: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_hash_data_key(ukey: impl AsRef<[u8]>, field: &str, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len() + field.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_HASH, &enc_ukey, &mut key, version);
@@ -317,8 +355,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_hash_data_key_start(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_hash_data_key_start(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_HASH, &enc_ukey, &mut key, version);
@@ -326,8 +364,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_hash_data_key_end(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_hash_data_key_end(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_HASH, &enc_ukey, &mut key, version);
@@ -335,7 +373,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_hash_data_key_range(key: &str, version: u16) -> BoundRange {
+    pub fn encode_hash_data_key_range(key: impl AsRef<[u8]>, version: u16) -> BoundRange {
+        let key = key.as_ref();
         let data_key_start = KeyEncoder::encode_hash_data_key_start(key, version);
         let data_key_end = KeyEncoder::encode_hash_data_key_end(key, version);
         let range: Range<Key> = data_key_start..data_key_end;
@@ -376,8 +415,8 @@ impl KeyEncoder {
         val
     }
 
-    pub fn encode_zset_data_key(ukey: &str, member: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_zset_data_key(ukey: impl AsRef<[u8]>, member: &str, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len() + member.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_ZSET, &enc_ukey, &mut key, version);
@@ -386,8 +425,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_zset_data_key_start(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_zset_data_key_start(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_ZSET, &enc_ukey, &mut key, version);
@@ -395,8 +434,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_zset_data_key_end(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_zset_data_key_end(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_ZSET, &enc_ukey, &mut key, version);
@@ -404,7 +443,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_zset_data_key_range(ukey: &str, version: u16) -> BoundRange {
+    pub fn encode_zset_data_key_range(ukey: impl AsRef<[u8]>, version: u16) -> BoundRange {
+        let ukey = ukey.as_ref();
         let data_key_start = KeyEncoder::encode_zset_data_key_start(ukey, version);
         let data_key_end = KeyEncoder::encode_zset_data_key_end(ukey, version);
         let range: Range<Key> = data_key_start..data_key_end;
@@ -429,8 +469,13 @@ impl KeyEncoder {
     }
 
     // encode the member to score key
-    pub fn encode_zset_score_key(ukey: &str, score: f64, member: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_zset_score_key(
+        ukey: impl AsRef<[u8]>,
+        score: f64,
+        member: &str,
+        version: u16,
+    ) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(17 + enc_ukey.len() + member.len());
         let score = KeyEncoder::encode_f64_to_cmp_uint64(score);
 
@@ -442,8 +487,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_zset_score_key_start(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_zset_score_key_start(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_SCORE, &enc_ukey, &mut key, version);
@@ -451,8 +496,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_zset_score_key_end(ukey: &str, version: u16) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+    pub fn encode_zset_score_key_end(ukey: impl AsRef<[u8]>, version: u16) -> Key {
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(8 + enc_ukey.len());
 
         KeyEncoder::encode_type_data_key_prefix(DATA_TYPE_SCORE, &enc_ukey, &mut key, version);
@@ -460,7 +505,8 @@ impl KeyEncoder {
         key.into()
     }
 
-    pub fn encode_zset_score_key_range(ukey: &str, version: u16) -> BoundRange {
+    pub fn encode_zset_score_key_range(ukey: impl AsRef<[u8]>, version: u16) -> BoundRange {
+        let ukey = ukey.as_ref();
         let range_start = KeyEncoder::encode_zset_score_key_start(ukey, version);
         let range_end = KeyEncoder::encode_zset_score_key_end(ukey, version);
         let range: Range<Key> = range_start..range_end;
@@ -468,12 +514,12 @@ impl KeyEncoder {
     }
 
     pub fn encode_zset_score_key_score_start(
-        ukey: &str,
+        ukey: impl AsRef<[u8]>,
         score: f64,
         with_frontier: bool,
         version: u16,
     ) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(17 + enc_ukey.len());
         let mut score = KeyEncoder::encode_f64_to_cmp_uint64(score);
         if !with_frontier {
@@ -489,12 +535,12 @@ impl KeyEncoder {
     }
 
     pub fn encode_zset_score_key_score_end(
-        ukey: &str,
+        ukey: impl AsRef<[u8]>,
         score: f64,
         with_frontier: bool,
         version: u16,
     ) -> Key {
-        let enc_ukey = encode_bytes(ukey.as_bytes());
+        let enc_ukey = encode_bytes(ukey.as_ref());
         let mut key = Vec::with_capacity(17 + enc_ukey.len());
         let mut score = KeyEncoder::encode_f64_to_cmp_uint64(score);
         if !with_frontier {
@@ -1,7 +1,7 @@
 pub mod decode;
 pub mod encode;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DataType {
     String,
     Hash,
@@ -24,6 +24,7 @@ impl fmt::Display for DataType {
     }
 }
 
+use serde::{Deserialize, Serialize};
 use std::fmt;
 pub use {decode::KeyDecoder, encode::KeyEncoder};
 
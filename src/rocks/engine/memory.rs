@@ -0,0 +1,439 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::Bound;
+use std::sync::RwLock;
+
+use crate::rocks::engine::{KvBytes, KvIter, StorageEngine, StorageTxn, CF_NAME_DEFAULT};
+use crate::rocks::errors::{CF_NOT_EXISTS_ERR, TXN_ERROR};
+use crate::rocks::{Result, CF_NAMES};
+
+// number of the key value pairs read at a time by a scan, the engine is locked while reading
+const SCAN_BATCH_SIZE: usize = 256;
+
+// a value of the key written by the commit with `seq`, None if the key is deleted
+struct Version {
+    seq: u64,
+    value: Option<Vec<u8>>,
+}
+
+// the versions of the keys ordered by seq, a key is removed if its only version is a delete
+type ColumnFamily = BTreeMap<Vec<u8>, Vec<Version>>;
+
+// the writes of a txn by column family, None if the key is deleted
+type TxnWrites = HashMap<String, BTreeMap<Vec<u8>, Option<Vec<u8>>>>;
+
+type KeyRange<'a> = (Bound<&'a [u8]>, Bound<&'a [u8]>);
+
+struct State {
+    // sequence number of the last commit
+    seq: u64,
+    cfs: HashMap<&'static str, ColumnFamily>,
+    // sequence numbers of the open snapshots and the number of txns reading each of them,
+    // the versions they read are kept until they are closed
+    snapshots: BTreeMap<u64, usize>,
+}
+
+impl State {
+    fn cf(&self, cf: &str) -> Result<&ColumnFamily> {
+        self.cfs.get(cf).ok_or(CF_NOT_EXISTS_ERR)
+    }
+
+    // sequence number of the last commit writing the key, 0 if the key doesn't exist
+    fn last_seq(&self, cf: &str, key: &[u8]) -> Result<u64> {
+        let versions = self.cf(cf)?.get(key);
+        Ok(versions.and_then(|v| v.last()).map_or(0, |v| v.seq))
+    }
+
+    // write the key in the commit with the current seq
+    fn write(&mut self, cf: &str, key: &[u8], value: Option<Vec<u8>>) -> Result<()> {
+        let oldest_snapshot = self.snapshots.keys().next().copied();
+        let seq = self.seq;
+        let data = self.cfs.get_mut(cf).ok_or(CF_NOT_EXISTS_ERR)?;
+        let versions = data.entry(key.to_vec()).or_default();
+        versions.push(Version { seq, value });
+        // drop the versions which are no longer read by any snapshot, the versions of the
+        // keys not written after a snapshot is closed are kept until they are written
+        let keep_from = match oldest_snapshot {
+            Some(snapshot) => versions
+                .iter()
+                .rposition(|v| v.seq <= snapshot)
+                .unwrap_or(0),
+            None => versions.len() - 1,
+        };
+        versions.drain(..keep_from);
+        if versions.len() == 1 && versions[0].value.is_none() {
+            data.remove(key);
+        }
+        Ok(())
+    }
+}
+
+// the value of the key read at `view`, or the latest one if `view` is None
+fn visible(versions: &[Version], view: Option<u64>) -> Option<&Vec<u8>> {
+    let version = match view {
+        Some(seq) => versions.iter().rev().find(|v| v.seq <= seq),
+        None => versions.last(),
+    };
+    version.and_then(|v| v.value.as_ref())
+}
+
+/// The engine keeping the data in memory, which is lost when the engine is dropped.
+///
+/// The keys are kept in a `BTreeMap` per column family with the versions read by the
+/// open snapshots. The txns buffer their writes, and check the keys read for update and
+/// the written keys for the writes of the other txns when they commit. Unlike RocksDB,
+/// the txns never wait for each other, the conflicting txns fail with `TXN_ERROR` instead.
+pub struct MemoryEngine {
+    state: RwLock<State>,
+}
+
+impl MemoryEngine {
+    pub fn new() -> Self {
+        let cfs = CF_NAMES
+            .into_iter()
+            .chain([CF_NAME_DEFAULT])
+            .map(|name| (name, ColumnFamily::new()))
+            .collect();
+        Self {
+            state: RwLock::new(State {
+                seq: 0,
+                cfs,
+                snapshots: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn write(&self, cf: &str, key: &[u8], value: Option<Vec<u8>>) -> Result<()> {
+        let mut state = self.state.write().unwrap();
+        state.cf(cf)?;
+        state.seq += 1;
+        state.write(cf, key, value)
+    }
+
+    fn read(&self, cf: &str, key: &[u8], view: Option<u64>) -> Result<Option<Vec<u8>>> {
+        let state = self.state.read().unwrap();
+        let versions = state.cf(cf)?.get(key);
+        Ok(versions.and_then(|v| visible(v, view)).cloned())
+    }
+
+    fn scan_iter<'a>(
+        &'a self,
+        cf: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+        reverse: bool,
+        view: Option<u64>,
+        writes: Option<&'a RefCell<TxnWrites>>,
+    ) -> Result<KvIter<'a>> {
+        self.state.read().unwrap().cf(cf)?;
+        Ok(Box::new(ScanIter {
+            engine: self,
+            writes,
+            cf: cf.to_string(),
+            view,
+            start: start.to_vec(),
+            end: end.map(|end| end.to_vec()),
+            reverse,
+            cursor: None,
+            buffer: VecDeque::new(),
+            done: false,
+        }))
+    }
+}
+
+impl Default for MemoryEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StorageEngine for MemoryEngine {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.read(cf, key, None)
+    }
+
+    fn put(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        self.write(cf, key, Some(value.to_vec()))
+    }
+
+    fn del(&self, cf: &str, key: &[u8]) -> Result<()> {
+        self.write(cf, key, None)
+    }
+
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let state = self.state.read().unwrap();
+        let data = state.cf(cf)?;
+        Ok(keys
+            .iter()
+            .map(|key| data.get(*key).and_then(|v| visible(v, None)).cloned())
+            .collect())
+    }
+
+    fn scan<'a>(&'a self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'a>> {
+        self.scan_iter(cf, start, end, false, None, None)
+    }
+
+    fn scan_reverse<'a>(
+        &'a self,
+        cf: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<KvIter<'a>> {
+        self.scan_iter(cf, start, end, true, None, None)
+    }
+
+    fn begin(&self, snapshot: bool) -> Box<dyn StorageTxn + '_> {
+        let snapshot = snapshot.then(|| {
+            let mut state = self.state.write().unwrap();
+            let seq = state.seq;
+            *state.snapshots.entry(seq).or_default() += 1;
+            seq
+        });
+        Box::new(MemoryTxn {
+            engine: self,
+            snapshot,
+            writes: RefCell::new(HashMap::new()),
+            tracked: RefCell::new(HashMap::new()),
+        })
+    }
+}
+
+struct MemoryTxn<'a> {
+    engine: &'a MemoryEngine,
+    // the reads see the commits up to the seq, or the latest commits if None
+    snapshot: Option<u64>,
+    writes: RefCell<TxnWrites>,
+    // the keys read for update or written, and the seq of their last commits when the txn
+    // reads or writes them first
+    tracked: RefCell<HashMap<(String, Vec<u8>), u64>>,
+}
+
+impl MemoryTxn<'_> {
+    // like RocksDB, a snapshot txn fails at once if the key is written after the snapshot
+    fn track(&self, state: &State, cf: &str, key: &[u8]) -> Result<()> {
+        let mut tracked = self.tracked.borrow_mut();
+        let tracked_key = (cf.to_string(), key.to_vec());
+        if tracked.contains_key(&tracked_key) {
+            return Ok(());
+        }
+        let seq = state.last_seq(cf, key)?;
+        if self.snapshot.is_some_and(|snapshot| seq > snapshot) {
+            return Err(TXN_ERROR);
+        }
+        tracked.insert(tracked_key, seq);
+        Ok(())
+    }
+
+    fn write(&self, cf: &str, key: &[u8], value: Option<Vec<u8>>) -> Result<()> {
+        let state = self.engine.state.read().unwrap();
+        self.track(&state, cf, key)?;
+        self.writes
+            .borrow_mut()
+            .entry(cf.to_string())
+            .or_default()
+            .insert(key.to_vec(), value);
+        Ok(())
+    }
+
+    fn read(&self, state: &State, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        if let Some(value) = self.writes.borrow().get(cf).and_then(|w| w.get(key)) {
+            return Ok(value.clone());
+        }
+        let versions = state.cf(cf)?.get(key);
+        Ok(versions.and_then(|v| visible(v, self.snapshot)).cloned())
+    }
+}
+
+impl StorageTxn for MemoryTxn<'_> {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let state = self.engine.state.read().unwrap();
+        self.read(&state, cf, key)
+    }
+
+    fn get_for_update(&self, cf: &str, key: &[u8], _exclusive: bool) -> Result<Option<Vec<u8>>> {
+        let state = self.engine.state.read().unwrap();
+        self.track(&state, cf, key)?;
+        self.read(&state, cf, key)
+    }
+
+    fn put(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        self.write(cf, key, Some(value.to_vec()))
+    }
+
+    fn del(&self, cf: &str, key: &[u8]) -> Result<()> {
+        self.write(cf, key, None)
+    }
+
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let state = self.engine.state.read().unwrap();
+        keys.iter().map(|key| self.read(&state, cf, key)).collect()
+    }
+
+    fn scan<'b>(&'b self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'b>> {
+        self.engine
+            .scan_iter(cf, start, end, false, self.snapshot, Some(&self.writes))
+    }
+
+    fn scan_reverse<'b>(
+        &'b self,
+        cf: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<KvIter<'b>> {
+        self.engine
+            .scan_iter(cf, start, end, true, self.snapshot, Some(&self.writes))
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        let mut state = self.engine.state.write().unwrap();
+        for ((cf, key), seq) in self.tracked.borrow().iter() {
+            if state.last_seq(cf, key)? != *seq {
+                return Err(TXN_ERROR);
+            }
+        }
+        let writes = self.writes.take();
+        if writes.is_empty() {
+            return Ok(());
+        }
+        state.seq += 1;
+        for (cf, writes) in writes {
+            for (key, value) in writes {
+                state.write(&cf, &key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MemoryTxn<'_> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot {
+            let mut state = self.engine.state.write().unwrap();
+            if let Some(count) = state.snapshots.get_mut(&snapshot) {
+                *count -= 1;
+                if *count == 0 {
+                    state.snapshots.remove(&snapshot);
+                }
+            }
+        }
+    }
+}
+
+// a scan reading the keys in batches, so the engine isn't locked while the keys are used
+struct ScanIter<'a> {
+    engine: &'a MemoryEngine,
+    // the writes of the txn of the scan, which replace the committed values
+    writes: Option<&'a RefCell<TxnWrites>>,
+    cf: String,
+    view: Option<u64>,
+    start: Vec<u8>,
+    end: Option<Vec<u8>>,
+    reverse: bool,
+    // the last key read, the next batch starts after it
+    cursor: Option<Vec<u8>>,
+    buffer: VecDeque<KvBytes>,
+    done: bool,
+}
+
+impl ScanIter<'_> {
+    // the range of the keys not read yet, None if there are none
+    fn range(&self) -> Option<KeyRange<'_>> {
+        let from = match &self.cursor {
+            Some(cursor) => Bound::Excluded(cursor.as_slice()),
+            None => Bound::Included(self.start.as_slice()),
+        };
+        let to = match &self.end {
+            Some(end) => Bound::Excluded(end.as_slice()),
+            None => Bound::Unbounded,
+        };
+        // an empty range makes `BTreeMap::range` panic
+        if let (Bound::Included(from) | Bound::Excluded(from), Bound::Excluded(to)) = (from, to) {
+            let empty = if self.reverse { from <= to } else { from >= to };
+            if empty {
+                return None;
+            }
+        }
+        Some(if self.reverse { (to, from) } else { (from, to) })
+    }
+
+    fn fill(&mut self) -> Result<()> {
+        let Some(range) = self.range() else {
+            self.done = true;
+            return Ok(());
+        };
+        let state = self.engine.state.read().unwrap();
+        let writes = self.writes.map(|writes| writes.borrow());
+        let own = writes.as_ref().and_then(|writes| writes.get(&self.cf));
+        let view = self.view;
+        let committed = state
+            .cf(&self.cf)?
+            .range::<[u8], _>(range)
+            .map(|(key, versions)| (key, visible(versions, view)));
+        let written = own
+            .into_iter()
+            .flat_map(|own| own.range::<[u8], _>(range))
+            .map(|(key, value)| (key, value.as_ref()));
+        type Entries<'b> = Box<dyn Iterator<Item = (&'b Vec<u8>, Option<&'b Vec<u8>>)> + 'b>;
+        let (committed, written): (Entries, Entries) = if self.reverse {
+            (Box::new(committed.rev()), Box::new(written.rev()))
+        } else {
+            (Box::new(committed), Box::new(written))
+        };
+        let (mut committed, mut written) = (committed.peekable(), written.peekable());
+
+        // merge the committed keys and the written keys of the txn in the order of the scan
+        let mut buffer = VecDeque::new();
+        let mut last = None;
+        let mut done = false;
+        while buffer.len() < SCAN_BATCH_SIZE {
+            let order = match (committed.peek(), written.peek()) {
+                (None, None) => {
+                    done = true;
+                    break;
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((a, _)), Some((b, _))) if self.reverse => b.cmp(a),
+                (Some((a, _)), Some((b, _))) => a.cmp(b),
+            };
+            let (key, value) = match order {
+                Ordering::Less => committed.next().unwrap(),
+                Ordering::Greater => written.next().unwrap(),
+                Ordering::Equal => {
+                    committed.next();
+                    written.next().unwrap()
+                }
+            };
+            if let Some(value) = value {
+                buffer.push_back((key.clone().into(), value.clone().into()));
+            }
+            last = Some(key);
+        }
+        let last = last.cloned();
+        drop(committed);
+        drop(written);
+        drop(writes);
+        drop(state);
+        if last.is_some() {
+            self.cursor = last;
+        }
+        self.buffer = buffer;
+        self.done = done;
+        Ok(())
+    }
+}
+
+impl Iterator for ScanIter<'_> {
+    type Item = Result<KvBytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.is_empty() && !self.done {
+            if let Err(e) = self.fill() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
@@ -0,0 +1,118 @@
+//! Storage engines of `RocksClient`.
+//!
+//! The data types are stored by `RocksClient` and `RocksTransaction`, which read and write
+//! the column families through the `StorageEngine` and `StorageTxn` traits. `RocksEngine`
+//! stores the data in RocksDB, `ReadOnlyEngine` reads a RocksDB opened read only or as a
+//! secondary instance, and `MemoryEngine` keeps the data in memory without touching the
+//! disk. The features built on RocksDB itself, like the backups, checkpoints, replication
+//! and raft, are only supported by `RocksEngine`, see `StorageEngine::rocksdb`.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use rocksdb::TransactionDB;
+
+use crate::rocks::errors::SECONDARY_NOT_ENABLED_ERR;
+use crate::rocks::Result;
+
+mod memory;
+mod rocks;
+
+pub use memory::MemoryEngine;
+pub use rocks::{ReadOnlyEngine, RocksEngine};
+
+/// The column family which is always created by the engines, it is used for the internal
+/// keys like the saved changes, not the data.
+pub const CF_NAME_DEFAULT: &str = "default";
+
+/// A key and its value read from a column family.
+pub type KvBytes = (Box<[u8]>, Box<[u8]>);
+
+/// The key value pairs of a scan ordered by key, descending for the reverse scans.
+pub type KvIter<'a> = Box<dyn Iterator<Item = Result<KvBytes>> + 'a>;
+
+/// A column family of the data, which is one of `CF_NAMES` or `CF_NAME_DEFAULT`. It
+/// borrows the client it's taken from like the column family handles of RocksDB.
+#[derive(Clone, Debug)]
+pub struct ColumnFamilyRef<'a> {
+    name: &'static str,
+    _client: PhantomData<&'a ()>,
+}
+
+impl ColumnFamilyRef<'_> {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _client: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// The storage of the column families.
+///
+/// The scans return the keys from `start`, including it, to `end`, excluding it, or to the
+/// end of the column family if `end` is None. The reverse scans return the keys from
+/// `start` down to `end` in the same way.
+pub trait StorageEngine: Send + Sync {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    fn put(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()>;
+
+    fn del(&self, cf: &str, key: &[u8]) -> Result<()>;
+
+    /// Get the values of `keys`, in the order of `keys`.
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>>;
+
+    fn scan<'a>(&'a self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'a>>;
+
+    fn scan_reverse<'a>(&'a self, cf: &str, start: &[u8], end: Option<&[u8]>)
+        -> Result<KvIter<'a>>;
+
+    /// Begin a txn, its reads see the db at the time it begins if `snapshot` is true,
+    /// otherwise they see the latest committed data.
+    fn begin(&self, snapshot: bool) -> Box<dyn StorageTxn + '_>;
+
+    /// Return the RocksDB of the engine if the data is stored in RocksDB.
+    fn rocksdb(&self) -> Option<Arc<TransactionDB>> {
+        None
+    }
+
+    /// Read the new writes of the primary if the engine is a secondary instance of RocksDB.
+    fn try_catch_up(&self) -> Result<()> {
+        Err(SECONDARY_NOT_ENABLED_ERR)
+    }
+}
+
+/// A txn of a `StorageEngine`, its writes are visible to its own reads and committed
+/// together, they are discarded if the txn is dropped without commit.
+///
+/// The keys read by `get_for_update` and the written keys are checked for the writes of
+/// the other txns. If one of them is written after it's read by the txn, or after the txn
+/// begins for a snapshot txn, the txn fails with `TXN_ERROR`. RocksDB checks it when the
+/// key is read or written, after waiting for the other txns writing the key to commit.
+/// `MemoryEngine` checks it optimistically when the txn commits.
+pub trait StorageTxn: Send {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Get the value of the key and check it for the writes of the other txns, the other
+    /// txns can't read it for update until this txn commits if `exclusive` is true.
+    fn get_for_update(&self, cf: &str, key: &[u8], exclusive: bool) -> Result<Option<Vec<u8>>>;
+
+    fn put(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()>;
+
+    fn del(&self, cf: &str, key: &[u8]) -> Result<()>;
+
+    /// Get the values of `keys`, in the order of `keys`.
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>>;
+
+    fn scan<'a>(&'a self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'a>>;
+
+    fn scan_reverse<'a>(&'a self, cf: &str, start: &[u8], end: Option<&[u8]>)
+        -> Result<KvIter<'a>>;
+
+    fn commit(self: Box<Self>) -> Result<()>;
+}
@@ -0,0 +1,320 @@
+use std::sync::Arc;
+
+use rocksdb::{
+    ColumnFamilyRef, Direction, IteratorMode, ReadOptions, Transaction, TransactionDB,
+    TransactionOptions, WriteOptions, DB,
+};
+
+use crate::rocks::engine::{KvIter, StorageEngine, StorageTxn};
+use crate::rocks::errors::{
+    RError, CF_NOT_EXISTS_ERR, REDIS_READONLY_ERR, SECONDARY_NOT_ENABLED_ERR, TXN_ERROR,
+};
+use crate::rocks::Result;
+
+/// The engine storing the data in a RocksDB `TransactionDB`.
+pub struct RocksEngine {
+    db: Arc<TransactionDB>,
+}
+
+impl RocksEngine {
+    pub fn new(db: Arc<TransactionDB>) -> Self {
+        Self { db }
+    }
+
+    fn cf_handle(&self, cf: &str) -> Result<ColumnFamilyRef<'_>> {
+        self.db.cf_handle(cf).ok_or(CF_NOT_EXISTS_ERR)
+    }
+}
+
+impl StorageEngine for RocksEngine {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let cf = self.cf_handle(cf)?;
+        self.db.get_cf(&cf, key).map_err(|e| e.into())
+    }
+
+    fn put(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        let cf = self.cf_handle(cf)?;
+        self.db.put_cf(&cf, key, value).map_err(|e| e.into())
+    }
+
+    fn del(&self, cf: &str, key: &[u8]) -> Result<()> {
+        let cf = self.cf_handle(cf)?;
+        self.db.delete_cf(&cf, key).map_err(|e| e.into())
+    }
+
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let cf = self.cf_handle(cf)?;
+        self.db
+            .multi_get_cf(keys.iter().map(|key| (&cf, key)))
+            .into_iter()
+            .map(|res| res.map_err(|e| e.into()))
+            .collect()
+    }
+
+    fn scan<'a>(&'a self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'a>> {
+        let cf = self.cf_handle(cf)?;
+        let it = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(start, Direction::Forward));
+        Ok(bounded(it, end, false))
+    }
+
+    fn scan_reverse<'a>(
+        &'a self,
+        cf: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<KvIter<'a>> {
+        let cf = self.cf_handle(cf)?;
+        let it = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(start, Direction::Reverse));
+        Ok(bounded(it, end, true))
+    }
+
+    fn begin(&self, snapshot: bool) -> Box<dyn StorageTxn + '_> {
+        let mut txn_opts = TransactionOptions::new();
+        txn_opts.set_snapshot(snapshot);
+        // txn_opts.set_lock_timeout(50);
+        let txn = self.db.transaction_opt(&WriteOptions::default(), &txn_opts);
+        Box::new(RocksTxn {
+            engine: self,
+            txn,
+            snapshot,
+        })
+    }
+
+    fn rocksdb(&self) -> Option<Arc<TransactionDB>> {
+        Some(self.db.clone())
+    }
+}
+
+// stop the iterator at `end`, which is excluded
+fn bounded<'a, I>(it: I, end: Option<&[u8]>, reverse: bool) -> KvIter<'a>
+where
+    I: Iterator<Item = std::result::Result<(Box<[u8]>, Box<[u8]>), rocksdb::Error>> + 'a,
+{
+    let end = end.map(|end| end.to_vec());
+    Box::new(
+        it.map(|kv| kv.map_err(RError::from))
+            .take_while(move |kv| match (kv, &end) {
+                (Ok((key, _)), Some(end)) if reverse => key[..] > end[..],
+                (Ok((key, _)), Some(end)) => key[..] < end[..],
+                _ => true,
+            }),
+    )
+}
+
+struct RocksTxn<'a> {
+    engine: &'a RocksEngine,
+    txn: Transaction<'a, TransactionDB>,
+    // read from the snapshot taken when the txn begins instead of the latest data
+    snapshot: bool,
+}
+
+impl RocksTxn<'_> {
+    fn read_opts(&self) -> ReadOptions {
+        let mut opts = ReadOptions::default();
+        if self.snapshot {
+            opts.set_snapshot(&self.txn.snapshot());
+        }
+        opts
+    }
+}
+
+impl StorageTxn for RocksTxn<'_> {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let cf = self.engine.cf_handle(cf)?;
+        self.txn
+            .get_cf_opt(&cf, key, &self.read_opts())
+            .map_err(|_| TXN_ERROR)
+    }
+
+    fn get_for_update(&self, cf: &str, key: &[u8], exclusive: bool) -> Result<Option<Vec<u8>>> {
+        let cf = self.engine.cf_handle(cf)?;
+        self.txn
+            .get_for_update_cf(&cf, key, exclusive)
+            .map_err(|_| TXN_ERROR)
+    }
+
+    fn put(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        let cf = self.engine.cf_handle(cf)?;
+        self.txn.put_cf(&cf, key, value).map_err(|_| TXN_ERROR)
+    }
+
+    fn del(&self, cf: &str, key: &[u8]) -> Result<()> {
+        let cf = self.engine.cf_handle(cf)?;
+        self.txn.delete_cf(&cf, key).map_err(|_| TXN_ERROR)
+    }
+
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let cf = self.engine.cf_handle(cf)?;
+        self.txn
+            .multi_get_cf_opt(keys.iter().map(|key| (&cf, key)), &self.read_opts())
+            .into_iter()
+            .map(|res| res.map_err(|_| TXN_ERROR))
+            .collect()
+    }
+
+    fn scan<'b>(&'b self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'b>> {
+        let cf = self.engine.cf_handle(cf)?;
+        let mut opts = self.read_opts();
+        opts.set_prefix_same_as_start(true);
+        let mode = IteratorMode::From(start, Direction::Forward);
+        Ok(bounded(
+            self.txn.iterator_cf_opt(&cf, opts, mode),
+            end,
+            false,
+        ))
+    }
+
+    fn scan_reverse<'b>(
+        &'b self,
+        cf: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<KvIter<'b>> {
+        let cf = self.engine.cf_handle(cf)?;
+        let mode = IteratorMode::From(start, Direction::Reverse);
+        Ok(bounded(
+            self.txn.iterator_cf_opt(&cf, self.read_opts(), mode),
+            end,
+            true,
+        ))
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        self.txn.commit().map_err(|_| TXN_ERROR)
+    }
+}
+
+/// The engine reading a RocksDB opened read only, or as a secondary instance of a RocksDB
+/// written by another process. The writes fail with `REDIS_READONLY_ERR`.
+///
+/// The data only changes when a secondary catches up with its primary, so the txns read
+/// the latest data instead of a snapshot, which isn't supported by the secondary instances.
+/// Only the column families existing in the db are opened, the missing ones are read as
+/// empty, like the ones added by a later version than the one creating the db.
+pub struct ReadOnlyEngine {
+    db: DB,
+    secondary: bool,
+}
+
+impl ReadOnlyEngine {
+    pub fn new(db: DB, secondary: bool) -> Self {
+        Self { db, secondary }
+    }
+
+    fn cf_handle(&self, cf: &str) -> Option<ColumnFamilyRef<'_>> {
+        self.db.cf_handle(cf)
+    }
+}
+
+impl StorageEngine for ReadOnlyEngine {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some(cf) = self.cf_handle(cf) else {
+            return Ok(None);
+        };
+        self.db.get_cf(&cf, key).map_err(|e| e.into())
+    }
+
+    fn put(&self, _cf: &str, _key: &[u8], _value: &[u8]) -> Result<()> {
+        Err(REDIS_READONLY_ERR)
+    }
+
+    fn del(&self, _cf: &str, _key: &[u8]) -> Result<()> {
+        Err(REDIS_READONLY_ERR)
+    }
+
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let Some(cf) = self.cf_handle(cf) else {
+            return Ok(vec![None; keys.len()]);
+        };
+        self.db
+            .multi_get_cf(keys.iter().map(|key| (&cf, key)))
+            .into_iter()
+            .map(|res| res.map_err(|e| e.into()))
+            .collect()
+    }
+
+    fn scan<'a>(&'a self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'a>> {
+        let Some(cf) = self.cf_handle(cf) else {
+            return Ok(Box::new(std::iter::empty()));
+        };
+        let it = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(start, Direction::Forward));
+        Ok(bounded(it, end, false))
+    }
+
+    fn scan_reverse<'a>(
+        &'a self,
+        cf: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<KvIter<'a>> {
+        let Some(cf) = self.cf_handle(cf) else {
+            return Ok(Box::new(std::iter::empty()));
+        };
+        let it = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(start, Direction::Reverse));
+        Ok(bounded(it, end, true))
+    }
+
+    fn begin(&self, _snapshot: bool) -> Box<dyn StorageTxn + '_> {
+        Box::new(ReadOnlyTxn { engine: self })
+    }
+
+    fn try_catch_up(&self) -> Result<()> {
+        if !self.secondary {
+            return Err(SECONDARY_NOT_ENABLED_ERR);
+        }
+        self.db.try_catch_up_with_primary().map_err(|e| e.into())
+    }
+}
+
+// the writes of the txn are discarded, the client of a read-only db never commits its
+// txns, like the local writes of a replica
+struct ReadOnlyTxn<'a> {
+    engine: &'a ReadOnlyEngine,
+}
+
+impl StorageTxn for ReadOnlyTxn<'_> {
+    fn get(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.engine.get(cf, key)
+    }
+
+    fn get_for_update(&self, cf: &str, key: &[u8], _exclusive: bool) -> Result<Option<Vec<u8>>> {
+        self.engine.get(cf, key)
+    }
+
+    fn put(&self, _cf: &str, _key: &[u8], _value: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn del(&self, _cf: &str, _key: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn batch_get(&self, cf: &str, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        self.engine.batch_get(cf, keys)
+    }
+
+    fn scan<'b>(&'b self, cf: &str, start: &[u8], end: Option<&[u8]>) -> Result<KvIter<'b>> {
+        self.engine.scan(cf, start, end)
+    }
+
+    fn scan_reverse<'b>(
+        &'b self,
+        cf: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<KvIter<'b>> {
+        self.engine.scan_reverse(cf, start, end)
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        Err(REDIS_READONLY_ERR)
+    }
+}
@@ -25,6 +25,39 @@ impl RError {
     pub fn is_not_integer_error<E>(_: E) -> RError {
         REDIS_VALUE_IS_NOT_INTEGER_ERR
     }
+
+    pub fn invalid_expire_time_error(cmd: &str) -> RError {
+        RError::Owned(format!("ERR invalid expire time in '{cmd}' command"))
+    }
+
+    pub fn not_raft_leader_error(leader: Option<&str>) -> RError {
+        match leader {
+            Some(addr) => RError::Owned(format!("ERR not the raft leader, the leader is {addr}")),
+            None => RError::String("ERR not the raft leader, the leader is unknown"),
+        }
+    }
+
+    pub fn wrong_arguments_error(cmd: &str) -> RError {
+        RError::Owned(format!("ERR wrong number of arguments for '{cmd}' command"))
+    }
+
+    pub fn moved_error(slot: u16, addr: &str) -> RError {
+        RError::Owned(format!("MOVED {slot} {addr}"))
+    }
+
+    pub fn ask_error(slot: u16, addr: &str) -> RError {
+        RError::Owned(format!("ASK {slot} {addr}"))
+    }
+
+    pub fn unknown_cluster_node_error(id: &str) -> RError {
+        RError::Owned(format!("ERR I don't know about node {id}"))
+    }
+
+    pub fn invalid_lonlat_error(lon: f64, lat: f64) -> RError {
+        RError::Owned(format!(
+            "ERR invalid longitude,latitude pair {lon:.6},{lat:.6}"
+        ))
+    }
 }
 
 impl From<RocksError> for RError {
@@ -33,6 +66,12 @@ impl From<RocksError> for RError {
     }
 }
 
+impl From<std::io::Error> for RError {
+    fn from(e: std::io::Error) -> Self {
+        RError::Owned(format!("ERR {e}"))
+    }
+}
+
 impl From<&'static str> for RError {
     fn from(e: &'static str) -> Self {
         RError::String(e)
@@ -58,8 +97,99 @@ pub const REDIS_VALUE_IS_NOT_INTEGER_ERR: RError =
 pub const REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR: RError =
     RError::String("ERR value is not a valid float");
 pub const REDIS_NO_SUCH_KEY_ERR: RError = RError::String("ERR no such key");
+pub const REDIS_SAME_OBJECT_ERR: RError =
+    RError::String("ERR source and destination objects are the same");
+pub const REDIS_DUMP_PAYLOAD_ERR: RError =
+    RError::String("ERR DUMP payload version or checksum are wrong");
+pub const REDIS_BUSY_KEY_ERR: RError = RError::String("BUSYKEY Target key name already exists.");
+pub const REDIS_INVALID_TTL_ERR: RError = RError::String("ERR Invalid TTL value, must be >= 0");
 pub const REDIS_INDEX_OUT_OF_RANGE_ERR: RError = RError::String("ERR index out of range");
+pub const REDIS_OFFSET_OUT_OF_RANGE_ERR: RError = RError::String("ERR offset is out of range");
+pub const REDIS_STRING_EXCEEDS_MAX_SIZE_ERR: RError =
+    RError::String("ERR string exceeds maximum allowed size (proto-max-bulk-len)");
+pub const REDIS_INCR_OR_DECR_OVERFLOW_ERR: RError =
+    RError::String("ERR increment or decrement would overflow");
+pub const REDIS_INCR_NAN_OR_INFINITY_ERR: RError =
+    RError::String("ERR increment would produce NaN or Infinity");
+pub const REDIS_BIT_OFFSET_OUT_OF_RANGE_ERR: RError =
+    RError::String("ERR bit offset is not an integer or out of range");
+pub const REDIS_BIT_NOT_INTEGER_ERR: RError =
+    RError::String("ERR bit is not an integer or out of range");
+pub const REDIS_BITPOS_BIT_ERR: RError = RError::String("ERR The bit argument must be 1 or 0.");
+pub const REDIS_BITOP_NOT_ERR: RError =
+    RError::String("ERR BITOP NOT must be called with a single source key.");
+pub const REDIS_BITFIELD_TYPE_ERR: RError = RError::String(
+    "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.",
+);
+pub const REDIS_INVALID_HLL_ERR: RError =
+    RError::String("WRONGTYPE Key is not a valid HyperLogLog string value.");
+pub const REDIS_CORRUPTED_HLL_ERR: RError =
+    RError::String("INVALIDOBJ Corrupted HLL object detected");
+pub const REDIS_GEO_MEMBER_NOT_FOUND_ERR: RError =
+    RError::String("ERR could not decode requested zset member");
+pub const REDIS_GEO_ANY_WITHOUT_COUNT_ERR: RError =
+    RError::String("ERR the ANY argument requires COUNT argument");
+pub const REDIS_GEO_COUNT_ERR: RError = RError::String("ERR COUNT must be > 0");
+pub const REDIS_BACKUP_NOT_CONFIGURED_ERR: RError =
+    RError::String("ERR backup directory is not configured");
+pub const REDIS_BGSAVE_IN_PROGRESS_ERR: RError =
+    RError::String("ERR Background save already in progress");
+pub const CHECKPOINT_EXISTS_ERR: RError = RError::String("ERR checkpoint directory already exists");
 pub const DECREMENT_OVERFLOW: RError = RError::String("Decrement would overflow");
 pub const TXN_ERROR: RError = RError::Txn("Txn commit failed");
 pub const KEY_VERSION_EXHUSTED_ERR: RError = RError::String("ERR key version exhausted");
 pub const CF_NOT_EXISTS_ERR: RError = RError::String("Column family not existed");
+pub const REDIS_CROSSSLOT_ERR: RError =
+    RError::String("CROSSSLOT Keys in request don't hash to the same slot");
+pub const REDIS_CLUSTER_DISABLED_ERR: RError =
+    RError::String("ERR This instance has cluster support disabled");
+pub const REDIS_CLUSTERDOWN_ERR: RError = RError::String("CLUSTERDOWN Hash slot not served");
+pub const REDIS_TRYAGAIN_ERR: RError =
+    RError::String("TRYAGAIN Multiple keys request during rehashing of slot");
+pub const REDIS_INVALID_SLOT_ERR: RError = RError::String("ERR Invalid or out of range slot");
+pub const SHARDS_NOT_SUPPORTED_ERR: RError =
+    RError::String("ERR shards can't be used with backup, replication, raft or change capture");
+pub const IN_MEMORY_NOT_SUPPORTED_ERR: RError =
+    RError::String("ERR the in-memory db can't be used with backup, replication or raft");
+pub const READ_ONLY_NOT_SUPPORTED_ERR: RError = RError::String(
+    "ERR the read-only db can't be used with in-memory, backup, replication or raft",
+);
+pub const SECONDARY_NOT_ENABLED_ERR: RError = RError::String("ERR the db is not a secondary");
+pub const INVALID_NOTIFY_EVENTS_ERR: RError =
+    RError::String("ERR Invalid event class character for notify-keyspace-events");
+pub const CHANGE_CAPTURE_NOT_ENABLED_ERR: RError =
+    RError::String("ERR change capture is not enabled");
+pub const CHANGES_TRIMMED_ERR: RError =
+    RError::String("ERR the changes after the sequence number are trimmed");
+pub const REDIS_READONLY_ERR: RError =
+    RError::String("READONLY You can't write against a read only replica.");
+pub const RAFT_DIRECT_WRITE_ERR: RError =
+    RError::String("ERR writes bypassing the raft log are not allowed in raft mode");
+pub const RAFT_NOT_ENABLED_ERR: RError = RError::String("ERR raft mode is not enabled");
+pub const RAFT_PROPOSAL_DROPPED_ERR: RError =
+    RError::String("ERR the write is dropped by a new raft leader");
+pub const RAFT_PROPOSAL_TIMEOUT_ERR: RError =
+    RError::String("ERR the write is not committed by the raft cluster in time");
+pub const RAFT_MEMBERSHIP_CHANGING_ERR: RError =
+    RError::String("ERR another raft membership change is in progress");
+pub const REDIS_SYNTAX_ERR: RError = RError::String("ERR syntax error");
+pub const REDIS_NOSCRIPT_ERR: RError =
+    RError::String("NOSCRIPT No matching script. Please use EVAL.");
+pub const REDIS_FUNCTION_NOT_FOUND_ERR: RError = RError::String("ERR Function not found");
+pub const REDIS_LIBRARY_NOT_FOUND_ERR: RError = RError::String("ERR Library not found");
+pub const REDIS_SCRIPT_WRITE_ERR: RError =
+    RError::String("ERR Write commands are not allowed from read-only scripts.");
+pub const REDIS_SCRIPT_RO_CALL_ERR: RError =
+    RError::String("ERR Can not execute a script with write flag using *_ro command.");
+pub const REDIS_SCRIPT_NON_LOCAL_KEY_ERR: RError =
+    RError::String("ERR Script attempted to access a non local key in a cluster node script");
+pub const REDIS_SCRIPT_NONDETERMINISTIC_ERR: RError =
+    RError::String("ERR Nondeterministic commands are not allowed from scripts in raft mode");
+pub const SCRIPT_TIMEOUT_ERR: RError =
+    RError::String("ERR Script exceeded the time limit and its changes are discarded");
+pub const ATOMIC_ABORTED_ERR: RError = RError::String("ERR the atomic closure is aborted");
+pub const UNEXPECTED_REPLY_ERR: RError = RError::String("ERR unexpected reply of the command");
+pub const SNAPSHOT_WRITE_ERR: RError =
+    RError::String("ERR write commands are not allowed on a snapshot");
+pub const ENGINE_NOT_SUPPORTED_ERR: RError =
+    RError::String("ERR not supported by the storage engine");
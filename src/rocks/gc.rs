@@ -1,4 +1,4 @@
-use rocksdb::ColumnFamilyRef;
+use crate::rocks::engine::ColumnFamilyRef;
 
 use crate::rocks::Result as RocksResult;
 use crate::{frame::Frame, utils::resp_nil};
@@ -54,7 +54,7 @@ impl<'a> GcCommand<'a> {
     }
 
     pub async fn run(&self) -> RocksResult<Frame> {
-        let client = self.client.clone();
+        let client = self.client;
         let gc_cfs = GcCF::new(client);
 
         let bound_range = KeyEncoder::encode_gc_version_key_range();
@@ -76,30 +76,30 @@ impl<'a> GcCommand<'a> {
 
             client.exec_txn(|txn| {
                 let task = task.clone();
-                let user_key = String::from_utf8_lossy(&task.user_key);
+                let user_key = &task.user_key[..];
                 let version = task.version;
                 match task.key_type {
                     DataType::String => {
                         panic!("string not support async deletion");
                     }
                     DataType::Set => {
-                        SetCommand::new(client).txn_gc(txn, &user_key, version)?;
+                        SetCommand::new(client).txn_gc(txn, user_key, version)?;
                     }
                     DataType::List => {
-                        ListCommand::new(client).txn_gc(txn, &user_key, version)?;
+                        ListCommand::new(client).txn_gc(txn, user_key, version)?;
                     }
                     DataType::Hash => {
-                        HashCommand::new(client).txn_gc(txn, &user_key, version)?;
+                        HashCommand::new(client).txn_gc(txn, user_key, version)?;
                     }
                     DataType::Zset => {
-                        ZsetCommand::new(client).txn_gc(txn, &user_key, version)?;
+                        ZsetCommand::new(client).txn_gc(txn, user_key, version)?;
                     }
                     DataType::Null => {
                         panic!("unknown data type to do async deletion");
                     }
                 }
                 // delete gc version key
-                let gc_version_key = KeyEncoder::encode_gc_version_key(&user_key, version);
+                let gc_version_key = KeyEncoder::encode_gc_version_key(user_key, version);
                 txn.del(gc_cfs.gc_version_cf.clone(), gc_version_key)?;
                 Ok(())
             })?;
@@ -107,9 +107,9 @@ impl<'a> GcCommand<'a> {
             // check the gc key in a small txn, avoid transaction confliction
             client.exec_txn(|txn| {
                 let task = task.clone();
-                let user_key = String::from_utf8_lossy(&task.user_key);
+                let user_key = &task.user_key[..];
                 // also delete gc key if version in gc key is same as task.version
-                let gc_key = KeyEncoder::encode_gc_key(&user_key);
+                let gc_key = KeyEncoder::encode_gc_key(user_key);
                 let version = task.version;
                 if let Some(v) = txn.get(gc_cfs.gc_cf.clone(), gc_key.clone())? {
                     let ver = u16::from_be_bytes(v[..2].try_into().unwrap());
@@ -0,0 +1,573 @@
+//! Geospatial index on top of sorted sets, the members are saved with the 52 bits
+//! interleaved geohash as score like redis.
+
+use crate::cmd::{GeoFrom, GeoSearchOptions, GeoShape, GeoSort, GeoUnit};
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::REDIS_GEO_MEMBER_NOT_FOUND_ERR;
+use crate::rocks::notify::{NOTIFY_GENERIC, NOTIFY_ZSET};
+use crate::rocks::string::StringCommand;
+use crate::rocks::transaction::RocksTransaction;
+use crate::rocks::zset::ZsetCommand;
+use crate::rocks::{Result as RocksResult, CF_NAME_GC, CF_NAME_GC_VERSION};
+use crate::utils::{resp_array, resp_bulk, resp_err, resp_int, resp_nil};
+use crate::Frame;
+
+pub const GEO_LONG_MIN: f64 = -180.0;
+pub const GEO_LONG_MAX: f64 = 180.0;
+pub const GEO_LAT_MIN: f64 = -85.05112878;
+pub const GEO_LAT_MAX: f64 = 85.05112878;
+
+const GEO_STEP_MAX: u8 = 26;
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct GeoHashBits {
+    bits: u64,
+    step: u8,
+}
+
+struct GeoHashArea {
+    lon_min: f64,
+    lon_max: f64,
+    lat_min: f64,
+    lat_max: f64,
+}
+
+/// Spread the low 32 bits of `v` to the even bits.
+fn spread(v: u32) -> u64 {
+    let mut x = v as u64;
+    x = (x | (x << 16)) & 0x0000ffff0000ffff;
+    x = (x | (x << 8)) & 0x00ff00ff00ff00ff;
+    x = (x | (x << 4)) & 0x0f0f0f0f0f0f0f0f;
+    x = (x | (x << 2)) & 0x3333333333333333;
+    x = (x | (x << 1)) & 0x5555555555555555;
+    x
+}
+
+/// Squash the even bits of `v` to the low 32 bits.
+fn squash(v: u64) -> u32 {
+    let mut x = v & 0x5555555555555555;
+    x = (x | (x >> 1)) & 0x3333333333333333;
+    x = (x | (x >> 2)) & 0x0f0f0f0f0f0f0f0f;
+    x = (x | (x >> 4)) & 0x00ff00ff00ff00ff;
+    x = (x | (x >> 8)) & 0x0000ffff0000ffff;
+    x = (x | (x >> 16)) & 0x00000000ffffffff;
+    x as u32
+}
+
+fn encode(lon: f64, lat: f64, step: u8) -> GeoHashBits {
+    let lat_offset = (lat - GEO_LAT_MIN) / (GEO_LAT_MAX - GEO_LAT_MIN);
+    let lon_offset = (lon - GEO_LONG_MIN) / (GEO_LONG_MAX - GEO_LONG_MIN);
+    let lat_offset = (lat_offset * (1u64 << step) as f64) as u32;
+    let lon_offset = (lon_offset * (1u64 << step) as f64) as u32;
+    GeoHashBits {
+        bits: spread(lat_offset) | (spread(lon_offset) << 1),
+        step,
+    }
+}
+
+fn decode(hash: GeoHashBits) -> GeoHashArea {
+    let lat = squash(hash.bits) as f64;
+    let lon = squash(hash.bits >> 1) as f64;
+    let cells = (1u64 << hash.step) as f64;
+    let lat_scale = GEO_LAT_MAX - GEO_LAT_MIN;
+    let lon_scale = GEO_LONG_MAX - GEO_LONG_MIN;
+    GeoHashArea {
+        lat_min: GEO_LAT_MIN + (lat / cells) * lat_scale,
+        lat_max: GEO_LAT_MIN + ((lat + 1.0) / cells) * lat_scale,
+        lon_min: GEO_LONG_MIN + (lon / cells) * lon_scale,
+        lon_max: GEO_LONG_MIN + ((lon + 1.0) / cells) * lon_scale,
+    }
+}
+
+/// Encode the coordinate to the score saved in zset.
+pub fn encode_score(lon: f64, lat: f64) -> f64 {
+    encode(lon, lat, GEO_STEP_MAX).bits as f64
+}
+
+/// Decode the score saved in zset to the center (longitude, latitude) of the cell.
+pub fn decode_score(score: f64) -> (f64, f64) {
+    let area = decode(GeoHashBits {
+        bits: score as u64,
+        step: GEO_STEP_MAX,
+    });
+    let lon = ((area.lon_min + area.lon_max) / 2.0).clamp(GEO_LONG_MIN, GEO_LONG_MAX);
+    let lat = ((area.lat_min + area.lat_max) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX);
+    (lon, lat)
+}
+
+fn move_x(hash: GeoHashBits, d: i8) -> GeoHashBits {
+    let mut x = hash.bits & 0xaaaaaaaaaaaaaaaa;
+    let y = hash.bits & 0x5555555555555555;
+    let zz = 0x5555555555555555u64 >> (64 - hash.step as u32 * 2);
+    if d > 0 {
+        x = x.wrapping_add(zz + 1);
+    } else {
+        x |= zz;
+        x = x.wrapping_sub(zz + 1);
+    }
+    x &= 0xaaaaaaaaaaaaaaaau64 >> (64 - hash.step as u32 * 2);
+    GeoHashBits {
+        bits: x | y,
+        step: hash.step,
+    }
+}
+
+fn move_y(hash: GeoHashBits, d: i8) -> GeoHashBits {
+    let x = hash.bits & 0xaaaaaaaaaaaaaaaa;
+    let mut y = hash.bits & 0x5555555555555555;
+    let zz = 0xaaaaaaaaaaaaaaaau64 >> (64 - hash.step as u32 * 2);
+    if d > 0 {
+        y = y.wrapping_add(zz + 1);
+    } else {
+        y |= zz;
+        y = y.wrapping_sub(zz + 1);
+    }
+    y &= 0x5555555555555555u64 >> (64 - hash.step as u32 * 2);
+    GeoHashBits {
+        bits: x | y,
+        step: hash.step,
+    }
+}
+
+fn lat_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (lat2.to_radians() - lat1.to_radians()).abs()
+}
+
+/// Haversine distance in meters.
+pub fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let v = ((lon2.to_radians() - lon1.to_radians()) / 2.0).sin();
+    // avoid the expensive math if the longitudes are practically the same
+    if v == 0.0 {
+        return lat_distance(lat1, lat2);
+    }
+    let lat1r = lat1.to_radians();
+    let lat2r = lat2.to_radians();
+    let u = ((lat2r - lat1r) / 2.0).sin();
+    let a = u * u + lat1r.cos() * lat2r.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+fn estimate_steps_by_radius(mut range_meters: f64, lat: f64) -> u8 {
+    if range_meters == 0.0 {
+        return GEO_STEP_MAX;
+    }
+    let mut step: i32 = 1;
+    while range_meters < MERCATOR_MAX {
+        range_meters *= 2.0;
+        step += 1;
+    }
+    // make sure range is included in most of the base cases
+    step -= 2;
+    // wider range towards the poles
+    if !(-66.0..=66.0).contains(&lat) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&lat) {
+            step -= 1;
+        }
+    }
+    step.clamp(1, GEO_STEP_MAX as i32) as u8
+}
+
+/// Return the bounding box (lon_min, lat_min, lon_max, lat_max) of the search shape.
+fn bounding_box(lon: f64, lat: f64, width: f64, height: f64) -> (f64, f64, f64, f64) {
+    let lat_delta = (height / 2.0 / EARTH_RADIUS_IN_METERS).to_degrees();
+    let lon_delta_top =
+        (width / 2.0 / EARTH_RADIUS_IN_METERS / (lat + lat_delta).to_radians().cos()).to_degrees();
+    let lon_delta_bottom =
+        (width / 2.0 / EARTH_RADIUS_IN_METERS / (lat - lat_delta).to_radians().cos()).to_degrees();
+    // the box is wider at the side closer to the equator
+    let lon_delta = if lat < 0.0 {
+        lon_delta_bottom
+    } else {
+        lon_delta_top
+    };
+    (
+        lon - lon_delta,
+        lat - lat_delta,
+        lon + lon_delta,
+        lat + lat_delta,
+    )
+}
+
+/// Return the score ranges `[min, max)` of the cells which cover the search shape.
+fn search_ranges(lon: f64, lat: f64, width: f64, height: f64, radius: f64) -> Vec<(u64, u64)> {
+    let (lon_min, lat_min, lon_max, lat_max) = bounding_box(lon, lat, width, height);
+    let mut step = estimate_steps_by_radius(radius, lat);
+    let mut hash = encode(lon, lat, step);
+    let mut neighbors = get_neighbors(hash);
+    let mut area = decode(hash);
+
+    // check if the step is enough at the limits of the covered area
+    let north = decode(neighbors[0]);
+    let south = decode(neighbors[1]);
+    let east = decode(neighbors[2]);
+    let west = decode(neighbors[3]);
+    let decrease_step = distance(lon, lat, lon, north.lat_max) < radius
+        || distance(lon, lat, lon, south.lat_min) < radius
+        || distance(lon, lat, east.lon_max, lat) < radius
+        || distance(lon, lat, west.lon_min, lat) < radius;
+    if step > 1 && decrease_step {
+        step -= 1;
+        hash = encode(lon, lat, step);
+        neighbors = get_neighbors(hash);
+        area = decode(hash);
+    }
+
+    // [north, south, east, west, north_east, north_west, south_east, south_west]
+    let mut useful = [true; 8];
+    if step >= 2 {
+        if area.lat_min < lat_min {
+            useful[1] = false;
+            useful[6] = false;
+            useful[7] = false;
+        }
+        if area.lat_max > lat_max {
+            useful[0] = false;
+            useful[4] = false;
+            useful[5] = false;
+        }
+        if area.lon_min < lon_min {
+            useful[3] = false;
+            useful[5] = false;
+            useful[7] = false;
+        }
+        if area.lon_max > lon_max {
+            useful[2] = false;
+            useful[4] = false;
+            useful[6] = false;
+        }
+    }
+
+    let shift = (GEO_STEP_MAX - step) as u32 * 2;
+    let mut cells = vec![hash];
+    for (neighbor, useful) in neighbors.iter().zip(useful) {
+        // the same cell may appear multiple times near the poles
+        if useful && !cells.contains(neighbor) {
+            cells.push(*neighbor);
+        }
+    }
+    cells
+        .into_iter()
+        .map(|cell| (cell.bits << shift, (cell.bits + 1) << shift))
+        .collect()
+}
+
+fn get_neighbors(hash: GeoHashBits) -> [GeoHashBits; 8] {
+    [
+        move_y(hash, 1),
+        move_y(hash, -1),
+        move_x(hash, 1),
+        move_x(hash, -1),
+        move_y(move_x(hash, 1), 1),
+        move_y(move_x(hash, -1), 1),
+        move_y(move_x(hash, 1), -1),
+        move_y(move_x(hash, -1), -1),
+    ]
+}
+
+impl GeoUnit {
+    fn to_meters(self) -> f64 {
+        match self {
+            GeoUnit::M => 1.0,
+            GeoUnit::Km => 1000.0,
+            GeoUnit::Ft => 0.3048,
+            GeoUnit::Mi => 1609.34,
+        }
+    }
+}
+
+struct GeoPoint {
+    member: Vec<u8>,
+    score: f64,
+    lon: f64,
+    lat: f64,
+    dist: f64,
+}
+
+fn format_distance(dist: f64) -> Frame {
+    resp_bulk(format!("{:.4}", dist).into_bytes())
+}
+
+fn format_coord(lon: f64, lat: f64) -> Frame {
+    resp_array(vec![
+        resp_bulk(lon.to_string().into_bytes()),
+        resp_bulk(lat.to_string().into_bytes()),
+    ])
+}
+
+pub struct GeoCommand<'a> {
+    client: ClientRef<'a>,
+}
+
+impl<'a> GeoCommand<'a> {
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        Self {
+            client: client.into(),
+        }
+    }
+
+    pub async fn geoadd(
+        self,
+        key: &str,
+        members: &Vec<String>,
+        coords: &[(f64, f64)],
+        exists: Option<bool>,
+        changed_only: bool,
+    ) -> RocksResult<Frame> {
+        let scores = coords
+            .iter()
+            .map(|(lon, lat)| encode_score(*lon, *lat))
+            .collect();
+        ZsetCommand::new(self.client)
+            .zadd(key, members, &scores, exists, changed_only, false)
+            .await
+    }
+
+    pub async fn geopos(self, key: &str, members: &[String]) -> RocksResult<Frame> {
+        let zset = ZsetCommand::new(self.client);
+        let resp = self.client.exec_txn(|txn| {
+            let version = zset.txn_zset_version(txn, key)?;
+            let mut resp = Vec::with_capacity(members.len());
+            for member in members {
+                let score = match version {
+                    Some(version) => zset.txn_zscore(txn, key, version, member)?,
+                    None => None,
+                };
+                resp.push(match score {
+                    Some(score) => {
+                        let (lon, lat) = decode_score(score);
+                        format_coord(lon, lat)
+                    }
+                    None => resp_nil(),
+                });
+            }
+            Ok(resp)
+        });
+
+        match resp {
+            Ok(resp) => Ok(resp_array(resp)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    pub async fn geodist(
+        self,
+        key: &str,
+        member1: &str,
+        member2: &str,
+        unit: GeoUnit,
+    ) -> RocksResult<Frame> {
+        let zset = ZsetCommand::new(self.client);
+        let resp = self.client.exec_txn(|txn| {
+            let version = match zset.txn_zset_version(txn, key)? {
+                Some(version) => version,
+                None => return Ok(None),
+            };
+            let score1 = zset.txn_zscore(txn, key, version, member1)?;
+            let score2 = zset.txn_zscore(txn, key, version, member2)?;
+            match (score1, score2) {
+                (Some(score1), Some(score2)) => {
+                    let (lon1, lat1) = decode_score(score1);
+                    let (lon2, lat2) = decode_score(score2);
+                    Ok(Some(distance(lon1, lat1, lon2, lat2) / unit.to_meters()))
+                }
+                _ => Ok(None),
+            }
+        });
+
+        match resp {
+            Ok(Some(dist)) => Ok(format_distance(dist)),
+            Ok(None) => Ok(resp_nil()),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    pub async fn geosearch(
+        self,
+        key: &str,
+        from: &GeoFrom,
+        shape: GeoShape,
+        unit: GeoUnit,
+        options: &GeoSearchOptions,
+    ) -> RocksResult<Frame> {
+        let resp = self
+            .client
+            .exec_txn(|txn| self.txn_geosearch(txn, key, from, shape, unit, options));
+
+        let points = match resp {
+            Ok(points) => points,
+            Err(e) => return Ok(resp_err(e)),
+        };
+        let with_any = options.with_coord || options.with_dist || options.with_hash;
+        let resp = points
+            .into_iter()
+            .map(|point| {
+                if !with_any {
+                    return resp_bulk(point.member);
+                }
+                let mut item = vec![resp_bulk(point.member)];
+                if options.with_dist {
+                    item.push(format_distance(point.dist / unit.to_meters()));
+                }
+                if options.with_hash {
+                    item.push(resp_int(point.score as i64));
+                }
+                if options.with_coord {
+                    item.push(format_coord(point.lon, point.lat));
+                }
+                resp_array(item)
+            })
+            .collect();
+        Ok(resp_array(resp))
+    }
+
+    /// Store the search result in `dest` as a zset, the score is the distance in `unit`
+    /// if `store_dist` is true, otherwise the geohash.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn geosearchstore(
+        self,
+        dest: &str,
+        key: &str,
+        from: &GeoFrom,
+        shape: GeoShape,
+        unit: GeoUnit,
+        options: &GeoSearchOptions,
+        store_dist: bool,
+    ) -> RocksResult<Frame> {
+        let client = self.client;
+        let zset = ZsetCommand::new(client);
+        let resp = client.exec_txn(|txn| {
+            let points = self.txn_geosearch(txn, key, from, shape, unit, options)?;
+            let dest_existed = StringCommand::new(client).txn_del_key(txn, dest)?;
+            if points.is_empty() {
+                if dest_existed {
+                    txn.notify(NOTIFY_GENERIC, "del", dest);
+                }
+                return Ok(0);
+            }
+
+            let mut members = Vec::with_capacity(points.len());
+            let mut scores = Vec::with_capacity(points.len());
+            for point in points {
+                members.push(String::from_utf8_lossy(&point.member).to_string());
+                scores.push(if store_dist {
+                    point.dist / unit.to_meters()
+                } else {
+                    point.score
+                });
+            }
+            let version = client.get_version_for_new(
+                txn,
+                client.cf_handle(CF_NAME_GC)?,
+                client.cf_handle(CF_NAME_GC_VERSION)?,
+                dest,
+            )?;
+            let idx = client.gen_next_meta_index();
+            zset.txn_zadd_new(txn, dest, version, idx, &members, &scores)?;
+            txn.notify(NOTIFY_ZSET, "geosearchstore", dest);
+            Ok(members.len() as i64)
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    fn txn_geosearch(
+        &self,
+        txn: &RocksTransaction,
+        key: &str,
+        from: &GeoFrom,
+        shape: GeoShape,
+        unit: GeoUnit,
+        options: &GeoSearchOptions,
+    ) -> RocksResult<Vec<GeoPoint>> {
+        let zset = ZsetCommand::new(self.client);
+        let version = match zset.txn_zset_version(txn, key)? {
+            Some(version) => version,
+            None => {
+                if let GeoFrom::Member(_) = from {
+                    return Err(REDIS_GEO_MEMBER_NOT_FOUND_ERR);
+                }
+                return Ok(vec![]);
+            }
+        };
+        let (lon, lat) = match from {
+            GeoFrom::Member(member) => match zset.txn_zscore(txn, key, version, member)? {
+                Some(score) => decode_score(score),
+                None => return Err(REDIS_GEO_MEMBER_NOT_FOUND_ERR),
+            },
+            GeoFrom::LonLat(lon, lat) => (*lon, *lat),
+        };
+
+        let factor = unit.to_meters();
+        let (width, height, radius) = match shape {
+            GeoShape::Radius(radius) => {
+                let radius = radius * factor;
+                (radius * 2.0, radius * 2.0, radius)
+            }
+            GeoShape::Box(width, height) => {
+                let (width, height) = (width * factor, height * factor);
+                (width, height, (width / 2.0).hypot(height / 2.0))
+            }
+        };
+
+        let limit = match options.count {
+            Some(count) if options.any => count as usize,
+            _ => usize::MAX,
+        };
+        let mut points = vec![];
+        'outer: for (min, max) in search_ranges(lon, lat, width, height, radius) {
+            for (score, member) in
+                zset.txn_zrange_by_score(txn, key, version, min as f64, max as f64)?
+            {
+                let (plon, plat) = decode_score(score);
+                let dist = match shape {
+                    GeoShape::Radius(_) => {
+                        let dist = distance(lon, lat, plon, plat);
+                        if dist > radius {
+                            continue;
+                        }
+                        dist
+                    }
+                    GeoShape::Box(..) => {
+                        if lat_distance(plat, lat) > height / 2.0
+                            || distance(plon, plat, lon, plat) > width / 2.0
+                        {
+                            continue;
+                        }
+                        distance(lon, lat, plon, plat)
+                    }
+                };
+                points.push(GeoPoint {
+                    member,
+                    score,
+                    lon: plon,
+                    lat: plat,
+                    dist,
+                });
+                if points.len() >= limit {
+                    break 'outer;
+                }
+            }
+        }
+
+        // COUNT without ANY needs the closest members
+        let sort = match options.sort {
+            None if options.count.is_some() && !options.any => Some(GeoSort::Asc),
+            sort => sort,
+        };
+        match sort {
+            Some(GeoSort::Asc) => points.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            Some(GeoSort::Desc) => points.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            None => {}
+        }
+        if let Some(count) = options.count {
+            points.truncate(count as usize);
+        }
+        Ok(points)
+    }
+}
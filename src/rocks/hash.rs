@@ -1,25 +1,32 @@
 use crate::config::config_meta_key_number_or_default;
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
+use crate::rocks::dump::DumpPayload;
 use crate::rocks::encoding::{DataType, KeyDecoder};
-use crate::rocks::errors::{REDIS_VALUE_IS_NOT_INTEGER_ERR, REDIS_WRONG_TYPE_ERR};
+use crate::rocks::engine::ColumnFamilyRef;
+use crate::rocks::errors::{
+    REDIS_DUMP_PAYLOAD_ERR, REDIS_INCR_OR_DECR_OVERFLOW_ERR, REDIS_VALUE_IS_NOT_INTEGER_ERR,
+    REDIS_WRONG_TYPE_ERR,
+};
 use crate::rocks::kv::bound_range::BoundRange;
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::kvpair::KvPair;
 use crate::rocks::kv::value::Value;
+use crate::rocks::notify::{NOTIFY_EXPIRED, NOTIFY_GENERIC, NOTIFY_HASH};
 use crate::rocks::transaction::RocksTransaction;
 use crate::rocks::{
-    Result as RocksResult, TxnCommand, CF_NAME_GC, CF_NAME_GC_VERSION, CF_NAME_HASH_DATA,
-    CF_NAME_HASH_SUB_META, CF_NAME_META,
+    txn_copy_data_keys, txn_for_each_data_key, Result as RocksResult, TxnCommand, CF_NAME_GC,
+    CF_NAME_GC_VERSION, CF_NAME_HASH_DATA, CF_NAME_HASH_SUB_META, CF_NAME_META,
 };
 use crate::utils::{
-    count_unique_keys, key_is_expired, resp_array, resp_bulk, resp_err, resp_int, resp_nil, resp_ok,
+    count_unique_keys, incr_float, key_is_expired, resp_array, resp_bulk, resp_err, resp_int,
+    resp_nil, resp_ok,
 };
 use crate::Frame;
-use rocksdb::ColumnFamilyRef;
 
 use std::collections::HashMap;
 use std::ops::Range;
 
+use super::encoding::encode::DATA_TYPE_HASH;
 use super::encoding::KeyEncoder;
 
 pub struct HashCF<'a> {
@@ -31,7 +38,8 @@ pub struct HashCF<'a> {
 }
 
 impl<'a> HashCF<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        let client = client.into().client();
         HashCF {
             meta_cf: client.cf_handle(CF_NAME_META).unwrap(),
             sub_meta_cf: client.cf_handle(CF_NAME_HASH_SUB_META).unwrap(),
@@ -43,12 +51,14 @@ impl<'a> HashCF<'a> {
 }
 
 pub struct HashCommand<'a> {
-    client: &'a RocksClient,
+    client: ClientRef<'a>,
 }
 
 impl<'a> HashCommand<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
-        Self { client }
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        Self {
+            client: client.into(),
+        }
     }
 
     pub async fn hset(
@@ -58,7 +68,7 @@ impl<'a> HashCommand<'a> {
         is_hmset: bool,
         is_nx: bool,
     ) -> RocksResult<Frame> {
-        let client = &self.client;
+        let client = self.client;
         let cfs = HashCF::new(client);
         let key = key.to_owned();
         let fvs_copy = fvs.to_vec();
@@ -67,7 +77,7 @@ impl<'a> HashCommand<'a> {
         let meta_key = KeyEncoder::encode_meta_key(&key);
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type is hash
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Hash) {
@@ -86,7 +96,7 @@ impl<'a> HashCommand<'a> {
                     let mut expired = false;
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         expired = true;
                         version = client.get_version_for_new(
                             txn,
@@ -96,7 +106,7 @@ impl<'a> HashCommand<'a> {
                         )?;
                     } else if is_nx {
                         // when is_nx == true, fvs_len must be 1
-                        let kv = fvs_copy.get(0).unwrap();
+                        let kv = fvs_copy.first().unwrap();
                         let field: Vec<u8> = kv.clone().0.into();
                         let data_key = KeyEncoder::encode_hash_data_key(
                             &key,
@@ -204,6 +214,7 @@ impl<'a> HashCommand<'a> {
                     )?;
                 }
             }
+            txn.notify(NOTIFY_HASH, "hset", &key);
             Ok(fvs_len)
         });
 
@@ -237,7 +248,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_nil());
                     }
 
@@ -269,7 +280,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_int(0));
                     }
 
@@ -301,7 +312,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_int(0));
                     }
 
@@ -338,7 +349,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_array(vec![]));
                     }
 
@@ -390,7 +401,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_int(0));
                     }
 
@@ -424,7 +435,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_nil());
                     }
 
@@ -469,7 +480,7 @@ impl<'a> HashCommand<'a> {
         let meta_key = KeyEncoder::encode_meta_key(&key);
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type is hash
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Hash) {
@@ -479,7 +490,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(0);
                     }
 
@@ -498,9 +509,13 @@ impl<'a> HashCommand<'a> {
                     // txn lock will be called in txnkv_sum_key_size, so release txn lock first
                     let old_size = self.sum_key_size(&key, version)?;
 
+                    if deleted > 0 {
+                        txn.notify(NOTIFY_HASH, "hdel", &key);
+                    }
                     // update sub meta key or clear all meta and sub meta key if needed
                     if old_size <= deleted {
                         txn.del(cfs.meta_cf.clone(), meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                         let bound_range = KeyEncoder::encode_sub_meta_key_range(&key, version);
                         let iter = txn.scan_keys(cfs.sub_meta_cf.clone(), bound_range, u32::MAX)?;
                         for k in iter {
@@ -538,6 +553,43 @@ impl<'a> HashCommand<'a> {
     }
 
     pub async fn hincrby(self, key: &str, field: &str, step: i64) -> RocksResult<Frame> {
+        let resp = self.txn_hincr(key, field, "hincrby", |prev| {
+            let prev_int = match prev {
+                // try to convert to int
+                Some(prev) => String::from_utf8_lossy(prev)
+                    .parse::<i64>()
+                    .map_err(|_| REDIS_VALUE_IS_NOT_INTEGER_ERR)?,
+                None => 0,
+            };
+            let new_int = prev_int
+                .checked_add(step)
+                .ok_or(REDIS_INCR_OR_DECR_OVERFLOW_ERR)?;
+            Ok((new_int.to_string().into_bytes(), new_int))
+        });
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    pub async fn hincrbyfloat(self, key: &str, field: &str, step: f64) -> RocksResult<Frame> {
+        let resp = self.txn_hincr(key, field, "hincrbyfloat", |prev| {
+            let new_value = incr_float(prev, step)?;
+            Ok((new_value.clone(), new_value))
+        });
+        match resp {
+            Ok(val) => Ok(resp_bulk(val)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Update the value of the hash field with `f` in txn, `f` accepts the current value
+    /// of the field and returns the new value to save and the result.
+    fn txn_hincr<T, F>(&self, key: &str, field: &str, event: &'static str, f: F) -> RocksResult<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(Option<&[u8]>) -> RocksResult<(Vec<u8>, T)>,
+    {
         let client = self.client;
         let cfs = HashCF::new(client);
         let key = key.to_owned();
@@ -545,10 +597,10 @@ impl<'a> HashCommand<'a> {
         let idx = self.client.gen_next_meta_index();
         let meta_key = KeyEncoder::encode_meta_key(&key);
 
-        let resp = client.exec_txn(|txn| {
-            let prev_int;
+        client.exec_txn(|txn| {
+            let prev_value;
             let data_key;
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type is hash
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Hash) {
@@ -559,7 +611,7 @@ impl<'a> HashCommand<'a> {
                     let (ttl, mut version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         expired = true;
                         version = client.get_version_for_new(
                             txn,
@@ -573,19 +625,11 @@ impl<'a> HashCommand<'a> {
 
                     match txn.get_for_update(cfs.data_cf.clone(), data_key.clone())? {
                         Some(data_value) => {
-                            // try to convert to int
-                            match String::from_utf8_lossy(&data_value).parse::<i64>() {
-                                Ok(ival) => {
-                                    prev_int = ival;
-                                }
-                                Err(_) => {
-                                    return Err(REDIS_VALUE_IS_NOT_INTEGER_ERR);
-                                }
-                            }
+                            prev_value = Some(data_value);
                         }
                         None => {
                             // filed not exist
-                            prev_int = 0;
+                            prev_value = None;
                             // add size to a random sub meta key
                             let sub_meta_key = KeyEncoder::encode_sub_meta_key(&key, version, idx);
 
@@ -622,7 +666,7 @@ impl<'a> HashCommand<'a> {
                         &key,
                     )?;
 
-                    prev_int = 0;
+                    prev_value = None;
                     // create new meta key first
                     let meta_size = config_meta_key_number_or_default();
                     let meta_value = KeyEncoder::encode_hash_meta_value(0, version, meta_size);
@@ -638,26 +682,19 @@ impl<'a> HashCommand<'a> {
                     data_key = KeyEncoder::encode_hash_data_key(&key, &field, version);
                 }
             }
-            let new_int = prev_int + step;
+            let (new_value, ret) = f(prev_value.as_deref())?;
             // update data key
-            txn.put(
-                cfs.data_cf.clone(),
-                data_key,
-                new_int.to_string().as_bytes().to_vec(),
-            )?;
+            txn.put(cfs.data_cf.clone(), data_key, new_value)?;
+            txn.notify(NOTIFY_HASH, event, &key);
 
-            Ok(new_int)
-        });
-        match resp {
-            Ok(n) => Ok(resp_int(n)),
-            Err(e) => Ok(resp_err(e)),
-        }
+            Ok(ret)
+        })
     }
 
-    fn sum_key_size(&self, key: &str, version: u16) -> RocksResult<i64> {
+    fn sum_key_size(&self, key: impl AsRef<[u8]>, version: u16) -> RocksResult<i64> {
         let client = self.client;
         let cfs = HashCF::new(client);
-        let key = key.to_owned();
+        let key = key.as_ref().to_owned();
 
         client.exec_txn(move |txn| {
             // check if meta key exists or already expired
@@ -683,12 +720,12 @@ impl<'a> HashCommand<'a> {
 }
 
 impl TxnCommand for HashCommand<'_> {
-    fn txn_del(&self, txn: &RocksTransaction, key: &str) -> RocksResult<()> {
+    fn txn_del(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<()> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = HashCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let (_, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                 let meta_size = self.sum_key_size(&key, version)?;
@@ -730,12 +767,12 @@ impl TxnCommand for HashCommand<'_> {
         }
     }
 
-    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &str) -> RocksResult<i64> {
+    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<i64> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = HashCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let (ttl, version, _meta_size) = KeyDecoder::decode_key_meta(&meta_value);
                 if !key_is_expired(ttl) {
@@ -774,6 +811,7 @@ impl TxnCommand for HashCommand<'_> {
 
                     txn.del(cfs.meta_cf.clone(), meta_key)?;
                 }
+                txn.notify(NOTIFY_EXPIRED, "expired", &String::from_utf8_lossy(&key));
                 Ok(1)
             }
             None => Ok(0),
@@ -783,7 +821,7 @@ impl TxnCommand for HashCommand<'_> {
     fn txn_expire(
         &self,
         txn: &RocksTransaction,
-        key: &str,
+        key: &[u8],
         timestamp: i64,
         meta_value: &Value,
     ) -> RocksResult<i64> {
@@ -794,13 +832,13 @@ impl TxnCommand for HashCommand<'_> {
             self.txn_expire_if_needed(txn, key)?;
             return Ok(0);
         }
-        let version = KeyDecoder::decode_key_version(meta_value);
-        let new_meta_value = KeyEncoder::encode_hash_meta_value(timestamp, version, 0);
+        let (_, version, index_size) = KeyDecoder::decode_key_meta(meta_value);
+        let new_meta_value = KeyEncoder::encode_hash_meta_value(timestamp, version, index_size);
         txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
         Ok(1)
     }
 
-    fn txn_gc(&self, txn: &RocksTransaction, key: &str, version: u16) -> RocksResult<()> {
+    fn txn_gc(&self, txn: &RocksTransaction, key: &[u8], version: u16) -> RocksResult<()> {
         let cfs = HashCF::new(self.client);
         // delete all sub meta key of this key and version
         let bound_range = KeyEncoder::encode_sub_meta_key_range(key, version);
@@ -816,4 +854,100 @@ impl TxnCommand for HashCommand<'_> {
         }
         Ok(())
     }
+
+    fn txn_copy(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        dest: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<()> {
+        let cfs = HashCF::new(self.client);
+        let (ttl, version, index_size) = KeyDecoder::decode_key_meta(meta_value);
+        let dest_version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            dest,
+        )?;
+        let size = txn_copy_data_keys(
+            txn,
+            cfs.data_cf.clone(),
+            DATA_TYPE_HASH,
+            key,
+            version,
+            dest,
+            dest_version,
+        )?;
+
+        // all the size is saved in one sub meta key of dest
+        let idx = self.client.gen_next_meta_index();
+        let sub_meta_key = KeyEncoder::encode_sub_meta_key(dest, dest_version, idx);
+        txn.put(cfs.sub_meta_cf, sub_meta_key, size.to_be_bytes().to_vec())?;
+
+        let meta_key = KeyEncoder::encode_meta_key(dest);
+        let new_meta_value = KeyEncoder::encode_hash_meta_value(ttl, dest_version, index_size);
+        txn.put(cfs.meta_cf, meta_key, new_meta_value)?;
+        Ok(())
+    }
+
+    fn txn_dump(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<DumpPayload> {
+        let cfs = HashCF::new(self.client);
+        let (_, version, _) = KeyDecoder::decode_key_meta(meta_value);
+        let mut items = vec![];
+        txn_for_each_data_key(
+            txn,
+            cfs.data_cf,
+            DATA_TYPE_HASH,
+            key,
+            version,
+            |suffix, value| {
+                items.push((suffix, value));
+                Ok(())
+            },
+        )?;
+        Ok(DumpPayload::Hash(items))
+    }
+
+    fn txn_restore(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        payload: &DumpPayload,
+    ) -> RocksResult<()> {
+        let items = match payload {
+            DumpPayload::Hash(items) => items,
+            _ => return Err(REDIS_DUMP_PAYLOAD_ERR),
+        };
+        let cfs = HashCF::new(self.client);
+        let version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            key,
+        )?;
+        for (field, value) in items {
+            let data_key = KeyEncoder::encode_type_data_key(key, DATA_TYPE_HASH, version, field);
+            txn.put(cfs.data_cf.clone(), data_key, value.clone())?;
+        }
+
+        let idx = self.client.gen_next_meta_index();
+        let sub_meta_key = KeyEncoder::encode_sub_meta_key(key, version, idx);
+        txn.put(
+            cfs.sub_meta_cf,
+            sub_meta_key,
+            (items.len() as i64).to_be_bytes().to_vec(),
+        )?;
+
+        let meta_key = KeyEncoder::encode_meta_key(key);
+        let meta_value = KeyEncoder::encode_hash_meta_value(timestamp, version, 0);
+        txn.put(cfs.meta_cf, meta_key, meta_value)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,340 @@
+//! HyperLogLog saved in the string value, the sparse and dense encodings are the same as
+//! redis so the values dumped from redis can be read directly.
+//!
+//! The value starts with a 16 bytes header:
+//! "HYLL" magic, 1 byte encoding, 3 unused bytes and 8 bytes cached cardinality in little
+//! endian, the most significant bit of the last byte means the cache is invalid.
+
+use crate::config::config_hll_sparse_max_bytes;
+use crate::rocks::errors::{REDIS_CORRUPTED_HLL_ERR, REDIS_INVALID_HLL_ERR};
+use crate::rocks::Result as RocksResult;
+
+const HLL_P: u32 = 14;
+const HLL_Q: u32 = 64 - HLL_P;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_P_MASK: u64 = HLL_REGISTERS as u64 - 1;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+const HLL_HDR_SIZE: usize = 16;
+const HLL_DENSE_SIZE: usize = HLL_HDR_SIZE + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+
+const HLL_SPARSE_VAL_MAX_VALUE: u8 = 32;
+const HLL_SPARSE_VAL_MAX_LEN: usize = 4;
+const HLL_SPARSE_ZERO_MAX_LEN: usize = 64;
+const HLL_SPARSE_XZERO_MAX_LEN: usize = 16384;
+
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+    dense: bool,
+    card: Option<u64>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HyperLogLog {
+    /// Create an empty sparse encoded HyperLogLog.
+    pub fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; HLL_REGISTERS],
+            dense: false,
+            card: Some(0),
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> RocksResult<Self> {
+        if data.len() < HLL_HDR_SIZE || &data[..4] != b"HYLL" {
+            return Err(REDIS_INVALID_HLL_ERR);
+        }
+        let card = if data[15] & (1 << 7) == 0 {
+            Some(u64::from_le_bytes(data[8..16].try_into().unwrap()))
+        } else {
+            None
+        };
+        let payload = &data[HLL_HDR_SIZE..];
+        let mut registers = vec![0; HLL_REGISTERS];
+        let dense = match data[4] {
+            HLL_DENSE => {
+                if data.len() != HLL_DENSE_SIZE {
+                    return Err(REDIS_INVALID_HLL_ERR);
+                }
+                for (idx, reg) in registers.iter_mut().enumerate() {
+                    *reg = dense_get_register(payload, idx);
+                }
+                true
+            }
+            HLL_SPARSE => {
+                let mut idx = 0;
+                let mut pos = 0;
+                while pos < payload.len() {
+                    let op = payload[pos];
+                    if op & 0xc0 == 0 {
+                        // ZERO: 00xxxxxx
+                        idx += (op & 0x3f) as usize + 1;
+                        pos += 1;
+                    } else if op & 0xc0 == 0x40 {
+                        // XZERO: 01xxxxxx yyyyyyyy
+                        if pos + 1 >= payload.len() {
+                            return Err(REDIS_CORRUPTED_HLL_ERR);
+                        }
+                        idx += (((op & 0x3f) as usize) << 8 | payload[pos + 1] as usize) + 1;
+                        pos += 2;
+                    } else {
+                        // VAL: 1vvvvvxx
+                        let val = ((op >> 2) & 0x1f) + 1;
+                        let len = (op & 0x3) as usize + 1;
+                        if idx + len > HLL_REGISTERS {
+                            return Err(REDIS_CORRUPTED_HLL_ERR);
+                        }
+                        registers[idx..idx + len].fill(val);
+                        idx += len;
+                        pos += 1;
+                    }
+                    if idx > HLL_REGISTERS {
+                        return Err(REDIS_CORRUPTED_HLL_ERR);
+                    }
+                }
+                if idx != HLL_REGISTERS {
+                    return Err(REDIS_CORRUPTED_HLL_ERR);
+                }
+                false
+            }
+            _ => return Err(REDIS_INVALID_HLL_ERR),
+        };
+        Ok(HyperLogLog {
+            registers,
+            dense,
+            card,
+        })
+    }
+
+    /// Encode to the sparse representation if possible, a dense HyperLogLog is never
+    /// converted back to sparse like redis.
+    pub fn encode(&self) -> Vec<u8> {
+        if !self.dense {
+            if let Some(sparse) = self.encode_sparse() {
+                return sparse;
+            }
+        }
+        let mut data = self.header(HLL_DENSE);
+        data.resize(HLL_DENSE_SIZE, 0);
+        for (idx, reg) in self.registers.iter().enumerate() {
+            dense_set_register(&mut data[HLL_HDR_SIZE..], idx, *reg);
+        }
+        data
+    }
+
+    fn encode_sparse(&self) -> Option<Vec<u8>> {
+        let mut data = self.header(HLL_SPARSE);
+        let mut idx = 0;
+        while idx < HLL_REGISTERS {
+            let val = self.registers[idx];
+            let run = self.registers[idx..]
+                .iter()
+                .take_while(|reg| **reg == val)
+                .count();
+            if val == 0 {
+                let mut left = run;
+                while left > 0 {
+                    if left > HLL_SPARSE_ZERO_MAX_LEN {
+                        let len = left.min(HLL_SPARSE_XZERO_MAX_LEN);
+                        data.push(0x40 | ((len - 1) >> 8) as u8);
+                        data.push(((len - 1) & 0xff) as u8);
+                        left -= len;
+                    } else {
+                        data.push((left - 1) as u8);
+                        left = 0;
+                    }
+                }
+            } else {
+                if val > HLL_SPARSE_VAL_MAX_VALUE {
+                    return None;
+                }
+                let mut left = run;
+                while left > 0 {
+                    let len = left.min(HLL_SPARSE_VAL_MAX_LEN);
+                    data.push(0x80 | ((val - 1) << 2) | (len - 1) as u8);
+                    left -= len;
+                }
+            }
+            if data.len() > config_hll_sparse_max_bytes() {
+                return None;
+            }
+            idx += run;
+        }
+        Some(data)
+    }
+
+    fn header(&self, encoding: u8) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HLL_DENSE_SIZE);
+        data.extend_from_slice(b"HYLL");
+        data.push(encoding);
+        data.extend_from_slice(&[0; 3]);
+        match self.card {
+            Some(card) => data.extend_from_slice(&card.to_le_bytes()),
+            None => {
+                let mut card = [0; 8];
+                card[7] = 1 << 7;
+                data.extend_from_slice(&card);
+            }
+        }
+        data
+    }
+
+    /// Add the element, returns true if any register is updated.
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let (idx, count) = pattern_len(element);
+        if self.registers[idx] < count {
+            self.registers[idx] = count;
+            self.card = None;
+            return true;
+        }
+        false
+    }
+
+    /// Merge the registers of `other` by taking the max value of each register.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (reg, other_reg) in self.registers.iter_mut().zip(other.registers.iter()) {
+            if *other_reg > *reg {
+                *reg = *other_reg;
+                self.card = None;
+            }
+        }
+    }
+
+    /// Convert to the dense encoding, used as the result of merge like redis.
+    pub fn set_dense(&mut self) {
+        self.dense = true;
+    }
+
+    pub fn is_count_cached(&self) -> bool {
+        self.card.is_some()
+    }
+
+    /// Estimate the cardinality and cache it in the header.
+    pub fn count(&mut self) -> u64 {
+        if let Some(card) = self.card {
+            return card;
+        }
+        let card = estimate(&self.registers);
+        self.card = Some(card);
+        card
+    }
+}
+
+fn dense_get_register(payload: &[u8], idx: usize) -> u8 {
+    let byte = idx * HLL_BITS / 8;
+    let fb = (idx * HLL_BITS) & 7;
+    let b0 = payload[byte] as u16;
+    let b1 = payload.get(byte + 1).copied().unwrap_or(0) as u16;
+    (((b0 >> fb) | (b1 << (8 - fb))) & HLL_REGISTER_MAX as u16) as u8
+}
+
+fn dense_set_register(payload: &mut [u8], idx: usize, val: u8) {
+    let byte = idx * HLL_BITS / 8;
+    let fb = (idx * HLL_BITS) & 7;
+    let val = val as u16;
+    let max = HLL_REGISTER_MAX as u16;
+    payload[byte] &= !((max << fb) as u8);
+    payload[byte] |= (val << fb) as u8;
+    if let Some(next) = payload.get_mut(byte + 1) {
+        *next &= !((max >> (8 - fb)) as u8);
+        *next |= (val >> (8 - fb)) as u8;
+    }
+}
+
+/// Return the register index and the number of leading zeros + 1 of the element hash.
+fn pattern_len(element: &[u8]) -> (usize, u8) {
+    let mut hash = murmurhash64a(element, 0xadc83b19);
+    let idx = (hash & HLL_P_MASK) as usize;
+    hash >>= HLL_P;
+    // make sure the loop terminates
+    hash |= 1 << HLL_Q;
+    (idx, hash.trailing_zeros() as u8 + 1)
+}
+
+fn murmurhash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, b) in tail.iter().enumerate() {
+            h ^= (*b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+/// The cardinality estimator from "New cardinality estimation algorithms for HyperLogLog
+/// sketches" by Otmar Ertl, which is also used by redis.
+fn estimate(registers: &[u8]) -> u64 {
+    let m = HLL_REGISTERS as f64;
+    let mut histo = [0u32; 64];
+    for reg in registers {
+        histo[*reg as usize] += 1;
+    }
+    let q = HLL_Q as usize;
+    let mut z = m * tau((m - histo[q + 1] as f64) / m);
+    for j in (1..=q).rev() {
+        z += histo[j] as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histo[0] as f64 / m);
+    (HLL_ALPHA_INF * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let z_prime = z;
+        z += x * y;
+        y += y;
+        if z_prime == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let z_prime = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z_prime == z {
+            return z / 3.0;
+        }
+    }
+}
@@ -152,6 +152,7 @@ impl<T: Into<Key> + Eq> From<(Bound<T>, Bound<T>)> for BoundRange {
     }
 }
 
+#[allow(dead_code)]
 pub trait IntoOwnedRange {
     /// Transform a borrowed range of some form into an owned `BoundRange`.
     fn into_owned(self) -> BoundRange;
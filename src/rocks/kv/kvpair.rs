@@ -82,8 +82,8 @@ impl fmt::Debug for KvPair {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let KvPair(key, value) = self;
         match str::from_utf8(value) {
-            Ok(s) => write!(f, "KvPair({:?}, {:?})", &key, s),
-            Err(_) => write!(f, "KvPair({:?}, {:?})", &key, &value),
+            Ok(s) => write!(f, "KvPair({:?}, {:?})", key, s),
+            Err(_) => write!(f, "KvPair({:?}, {:?})", key, value),
         }
     }
 }
@@ -1,22 +1,26 @@
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
+use crate::rocks::dump::DumpPayload;
 use crate::rocks::encoding::{DataType, KeyDecoder};
+use crate::rocks::engine::ColumnFamilyRef;
 use crate::rocks::errors::{
-    REDIS_INDEX_OUT_OF_RANGE_ERR, REDIS_NO_SUCH_KEY_ERR, REDIS_WRONG_TYPE_ERR,
+    REDIS_DUMP_PAYLOAD_ERR, REDIS_INDEX_OUT_OF_RANGE_ERR, REDIS_NO_SUCH_KEY_ERR,
+    REDIS_WRONG_TYPE_ERR,
 };
 use crate::rocks::kv::bound_range::BoundRange;
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::value::Value;
+use crate::rocks::notify::{NOTIFY_EXPIRED, NOTIFY_GENERIC, NOTIFY_LIST};
 use crate::rocks::transaction::RocksTransaction;
 use crate::rocks::{
-    Result as RocksResult, TxnCommand, CF_NAME_GC, CF_NAME_GC_VERSION, CF_NAME_LIST_DATA,
-    CF_NAME_META,
+    txn_copy_data_keys, txn_for_each_data_key, Result as RocksResult, TxnCommand, CF_NAME_GC,
+    CF_NAME_GC_VERSION, CF_NAME_LIST_DATA, CF_NAME_META,
 };
 use crate::utils::{key_is_expired, resp_array, resp_bulk, resp_err, resp_int, resp_nil, resp_ok};
 use crate::Frame;
 use bytes::Bytes;
-use rocksdb::ColumnFamilyRef;
 use std::ops::RangeFrom;
 
+use super::encoding::encode::DATA_TYPE_LIST;
 use super::encoding::KeyEncoder;
 
 const INIT_INDEX: u64 = 1 << 32;
@@ -29,7 +33,8 @@ pub struct ListCF<'a> {
 }
 
 impl<'a> ListCF<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        let client = client.into().client();
         ListCF {
             meta_cf: client.cf_handle(CF_NAME_META).unwrap(),
             gc_cf: client.cf_handle(CF_NAME_GC).unwrap(),
@@ -40,12 +45,14 @@ impl<'a> ListCF<'a> {
 }
 
 pub struct ListCommand<'a> {
-    client: &'a RocksClient,
+    client: ClientRef<'a>,
 }
 
 impl<'a> ListCommand<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
-        Self { client }
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        Self {
+            client: client.into(),
+        }
     }
 
     pub async fn push(self, key: &str, values: &Vec<Bytes>, op_left: bool) -> RocksResult<Frame> {
@@ -66,7 +73,7 @@ impl<'a> ListCommand<'a> {
                     let (ttl, mut version, mut left, mut right) =
                         KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         left = INIT_INDEX;
                         right = INIT_INDEX;
                         version = client.get_version_for_new(
@@ -95,6 +102,7 @@ impl<'a> ListCommand<'a> {
                     let new_meta_value =
                         KeyEncoder::encode_list_meta_value(ttl, version, left, right);
                     txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
+                    txn.notify(NOTIFY_LIST, push_event(op_left), &key);
 
                     Ok(right - left)
                 }
@@ -128,6 +136,7 @@ impl<'a> ListCommand<'a> {
                     // add meta key
                     let meta_value = KeyEncoder::encode_list_meta_value(0, version, left, right);
                     txn.put(cfs.meta_cf.clone(), meta_key, meta_value)?;
+                    txn.notify(NOTIFY_LIST, push_event(op_left), &key);
 
                     Ok(right - left)
                 }
@@ -157,7 +166,7 @@ impl<'a> ListCommand<'a> {
                     let (ttl, version, mut left, mut right) =
                         KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(values);
                     }
 
@@ -180,9 +189,11 @@ impl<'a> ListCommand<'a> {
 
                         txn.del(cfs.data_cf.clone(), data_key)?;
 
+                        txn.notify(NOTIFY_LIST, pop_event(op_left), &key);
                         if left == right {
                             // delete meta key
                             txn.del(cfs.meta_cf.clone(), meta_key)?;
+                            txn.notify(NOTIFY_GENERIC, "del", &key);
                         } else {
                             // update meta key
                             let new_meta_value =
@@ -212,9 +223,11 @@ impl<'a> ListCommand<'a> {
                             txn.del(cfs.data_cf.clone(), pair.0)?;
                         }
 
+                        txn.notify(NOTIFY_LIST, pop_event(op_left), &key);
                         if left == right {
                             // all elements popped, just delete meta key
                             txn.del(cfs.meta_cf.clone(), meta_key)?;
+                            txn.notify(NOTIFY_GENERIC, "del", &key);
                         } else {
                             // update meta key
                             let new_meta_value =
@@ -258,7 +271,7 @@ impl<'a> ListCommand<'a> {
                     let (ttl, version, mut left, mut right) =
                         KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(());
                     }
 
@@ -310,10 +323,12 @@ impl<'a> ListCommand<'a> {
                         right -= right_trim as u64;
                     }
 
+                    txn.notify(NOTIFY_LIST, "ltrim", &key);
                     // check key if empty
                     if left >= right {
                         // delete meta key
                         txn.del(cfs.meta_cf.clone(), meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                     } else {
                         // update meta key
                         let new_meta_value =
@@ -347,7 +362,7 @@ impl<'a> ListCommand<'a> {
                     }
                     let (ttl, version, left, right) = KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_array(vec![]));
                     }
 
@@ -404,7 +419,7 @@ impl<'a> ListCommand<'a> {
                     let (ttl, _version, left, right) =
                         KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_int(0));
                     }
 
@@ -431,7 +446,7 @@ impl<'a> ListCommand<'a> {
                     }
                     let (ttl, version, left, right) = KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_nil());
                     }
 
@@ -472,7 +487,7 @@ impl<'a> ListCommand<'a> {
                     }
                     let (ttl, version, left, right) = KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Err(REDIS_NO_SUCH_KEY_ERR);
                     }
 
@@ -489,6 +504,7 @@ impl<'a> ListCommand<'a> {
                     let data_key = KeyEncoder::encode_list_data_key(&key, uidx as u64, version);
                     // data keys exists, update it to new value
                     txn.put(cfs.data_cf.clone(), data_key, ele.to_vec())?;
+                    txn.notify(NOTIFY_LIST, "lset", &key);
                     Ok(())
                 }
                 None => Err(REDIS_NO_SUCH_KEY_ERR),
@@ -525,7 +541,7 @@ impl<'a> ListCommand<'a> {
                     let (ttl, version, mut left, mut right) =
                         KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(0);
                     }
 
@@ -612,6 +628,7 @@ impl<'a> ListCommand<'a> {
                         let new_meta_value =
                             KeyEncoder::encode_list_meta_value(ttl, version, left, right);
                         txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
+                        txn.notify(NOTIFY_LIST, "linsert", &key);
 
                         let len = (right - left) as i64;
                         Ok(len)
@@ -652,7 +669,7 @@ impl<'a> ListCommand<'a> {
                     }
                     let (ttl, version, left, right) = KeyDecoder::decode_key_list_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(0);
                     }
 
@@ -718,9 +735,13 @@ impl<'a> ListCommand<'a> {
                             }
                         }
 
+                        if removed_count > 0 {
+                            txn.notify(NOTIFY_LIST, "lrem", &key);
+                        }
                         // update meta key or delete it if no element left
                         if len == removed_count as u64 {
                             txn.del(cfs.meta_cf.clone(), meta_key)?;
+                            txn.notify(NOTIFY_GENERIC, "del", &key);
                         } else {
                             let new_meta_value = KeyEncoder::encode_list_meta_value(
                                 ttl,
@@ -761,9 +782,13 @@ impl<'a> ListCommand<'a> {
                             }
                         }
 
+                        if removed_count > 0 {
+                            txn.notify(NOTIFY_LIST, "lrem", &key);
+                        }
                         // update meta key or delete it if no element left
                         if len == removed_count as u64 {
                             txn.del(cfs.meta_cf.clone(), meta_key)?;
+                            txn.notify(NOTIFY_GENERIC, "del", &key);
                         } else {
                             let new_meta_value = KeyEncoder::encode_list_meta_value(
                                 ttl,
@@ -788,12 +813,12 @@ impl<'a> ListCommand<'a> {
 }
 
 impl TxnCommand for ListCommand<'_> {
-    fn txn_del(&self, txn: &RocksTransaction, key: &str) -> RocksResult<()> {
+    fn txn_del(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<()> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = ListCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let (_, version, left, right) = KeyDecoder::decode_key_list_meta(&meta_value);
                 let len = right - left;
@@ -827,12 +852,12 @@ impl TxnCommand for ListCommand<'_> {
         }
     }
 
-    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &str) -> RocksResult<i64> {
+    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<i64> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = ListCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let (ttl, version, left, right) = KeyDecoder::decode_key_list_meta(&meta_value);
                 if !key_is_expired(ttl) {
@@ -863,6 +888,7 @@ impl TxnCommand for ListCommand<'_> {
                     }
                     txn.del(cfs.meta_cf.clone(), meta_key)?;
                 }
+                txn.notify(NOTIFY_EXPIRED, "expired", &String::from_utf8_lossy(&key));
                 Ok(1)
             }
             None => Ok(0),
@@ -872,7 +898,7 @@ impl TxnCommand for ListCommand<'_> {
     fn txn_expire(
         &self,
         txn: &RocksTransaction,
-        key: &str,
+        key: &[u8],
         timestamp: i64,
         meta_value: &Value,
     ) -> RocksResult<i64> {
@@ -889,7 +915,7 @@ impl TxnCommand for ListCommand<'_> {
         Ok(1)
     }
 
-    fn txn_gc(&self, txn: &RocksTransaction, key: &str, version: u16) -> RocksResult<()> {
+    fn txn_gc(&self, txn: &RocksTransaction, key: &[u8], version: u16) -> RocksResult<()> {
         let cfs = ListCF::new(self.client);
         // delete all data key of this key and version
         let bound_range = KeyEncoder::encode_list_data_key_range(key, version);
@@ -899,4 +925,106 @@ impl TxnCommand for ListCommand<'_> {
         }
         Ok(())
     }
+
+    fn txn_copy(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        dest: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<()> {
+        let cfs = ListCF::new(self.client);
+        let (ttl, version, left, right) = KeyDecoder::decode_key_list_meta(meta_value);
+        let dest_version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            dest,
+        )?;
+        txn_copy_data_keys(
+            txn,
+            cfs.data_cf.clone(),
+            DATA_TYPE_LIST,
+            key,
+            version,
+            dest,
+            dest_version,
+        )?;
+
+        let meta_key = KeyEncoder::encode_meta_key(dest);
+        let new_meta_value = KeyEncoder::encode_list_meta_value(ttl, dest_version, left, right);
+        txn.put(cfs.meta_cf, meta_key, new_meta_value)?;
+        Ok(())
+    }
+
+    fn txn_dump(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<DumpPayload> {
+        let cfs = ListCF::new(self.client);
+        let (_, version, _, _) = KeyDecoder::decode_key_list_meta(meta_value);
+        let mut items = vec![];
+        // data keys are ordered by the index
+        txn_for_each_data_key(
+            txn,
+            cfs.data_cf,
+            DATA_TYPE_LIST,
+            key,
+            version,
+            |_, value| {
+                items.push(value);
+                Ok(())
+            },
+        )?;
+        Ok(DumpPayload::List(items))
+    }
+
+    fn txn_restore(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        payload: &DumpPayload,
+    ) -> RocksResult<()> {
+        let items = match payload {
+            DumpPayload::List(items) => items,
+            _ => return Err(REDIS_DUMP_PAYLOAD_ERR),
+        };
+        let cfs = ListCF::new(self.client);
+        let version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            key,
+        )?;
+        let left = INIT_INDEX;
+        let right = left + items.len() as u64;
+        for (idx, item) in (left..right).zip(items) {
+            let data_key = KeyEncoder::encode_list_data_key(key, idx, version);
+            txn.put(cfs.data_cf.clone(), data_key, item.clone())?;
+        }
+
+        let meta_key = KeyEncoder::encode_meta_key(key);
+        let meta_value = KeyEncoder::encode_list_meta_value(timestamp, version, left, right);
+        txn.put(cfs.meta_cf, meta_key, meta_value)?;
+        Ok(())
+    }
+}
+
+fn push_event(op_left: bool) -> &'static str {
+    if op_left {
+        "lpush"
+    } else {
+        "rpush"
+    }
+}
+
+fn pop_event(op_left: bool) -> &'static str {
+    if op_left {
+        "lpop"
+    } else {
+        "rpop"
+    }
 }
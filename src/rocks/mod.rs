@@ -1,20 +1,34 @@
+use crate::rocks::backup::Backup;
 use crate::rocks::client::RocksClient;
 
+use crate::rocks::dump::DumpPayload;
+use crate::rocks::encoding::{KeyDecoder, KeyEncoder};
+use crate::rocks::engine::{ColumnFamilyRef, ReadOnlyEngine, RocksEngine};
 use crate::rocks::errors::RError;
+use crate::rocks::kv::key::Key;
 use crate::rocks::kv::value::Value;
 use crate::rocks::transaction::RocksTransaction;
 
-use rocksdb::{MultiThreaded, Options, TransactionDB, TransactionDBOptions};
+use rocksdb::{MultiThreaded, Options, TransactionDB, TransactionDBOptions, DB};
 
 use std::{path::Path, sync::Arc};
 
+pub mod backup;
+pub mod bitmap;
+pub mod cdc;
 pub mod client;
+pub mod dump;
 pub mod encoding;
+pub mod engine;
 pub mod errors;
 pub mod gc;
+pub mod geo;
 pub mod hash;
+pub mod hyperloglog;
 pub mod kv;
 pub mod list;
+pub mod notify;
+pub mod replication;
 pub mod set;
 pub mod string;
 pub mod transaction;
@@ -31,28 +45,161 @@ pub const CF_NAME_HASH_DATA: &str = "hash_data";
 pub const CF_NAME_ZSET_SUB_META: &str = "zset_sub_meta";
 pub const CF_NAME_ZSET_DATA: &str = "zset_data";
 pub const CF_NAME_ZSET_SCORE: &str = "zset_score";
+pub const CF_NAME_FUNCTIONS: &str = "functions";
+
+pub const CF_NAMES: [&str; 12] = [
+    CF_NAME_META,
+    CF_NAME_GC,
+    CF_NAME_GC_VERSION,
+    CF_NAME_SET_SUB_META,
+    CF_NAME_SET_DATA,
+    CF_NAME_LIST_DATA,
+    CF_NAME_HASH_SUB_META,
+    CF_NAME_HASH_DATA,
+    CF_NAME_ZSET_SUB_META,
+    CF_NAME_ZSET_DATA,
+    CF_NAME_ZSET_SCORE,
+    CF_NAME_FUNCTIONS,
+];
 
 pub type Result<T> = anyhow::Result<T, RError>;
 
 pub trait TxnCommand {
-    fn txn_del(&self, txn: &RocksTransaction, key: &str) -> Result<()>;
+    fn txn_del(&self, txn: &RocksTransaction, key: &[u8]) -> Result<()>;
 
-    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &str) -> Result<i64>;
+    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &[u8]) -> Result<i64>;
 
     fn txn_expire(
         &self,
         txn: &RocksTransaction,
-        key: &str,
+        key: &[u8],
         timestamp: i64,
         meta_value: &Value,
     ) -> Result<i64>;
 
-    fn txn_gc(&self, txn: &RocksTransaction, key: &str, version: u16) -> Result<()>;
+    fn txn_gc(&self, txn: &RocksTransaction, key: &[u8], version: u16) -> Result<()>;
+
+    /// Copy the key with `meta_value` to `dest` with the same ttl, `dest` must not exist.
+    fn txn_copy(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        dest: &[u8],
+        meta_value: &Value,
+    ) -> Result<()>;
+
+    /// Load all the data of the key with `meta_value` for DUMP.
+    fn txn_dump(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        meta_value: &Value,
+    ) -> Result<DumpPayload>;
+
+    /// Create the key from the dumped `payload` with the expire `timestamp`,
+    /// the key must not exist.
+    fn txn_restore(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        payload: &DumpPayload,
+    ) -> Result<()>;
+}
+
+// number of data keys loaded at a time when iterating over all data keys of a key
+const DATA_KEYS_BATCH_SIZE: u32 = 1000;
+
+/// Call `f` with the type specific suffix and the value of each data key with `key_type` of
+/// `key`, the data keys are loaded in batches to handle large collections.
+pub(crate) fn txn_for_each_data_key<F>(
+    txn: &RocksTransaction,
+    cf: ColumnFamilyRef,
+    key_type: u8,
+    key: &[u8],
+    version: u16,
+    mut f: F,
+) -> Result<()>
+where
+    F: FnMut(Vec<u8>, Value) -> Result<()>,
+{
+    let (mut left_bound, end) =
+        KeyEncoder::encode_type_data_key_range(key, key_type, version).into_keys();
+    let end: Key = end.unwrap();
+    loop {
+        let iter = txn.scan(
+            cf.clone(),
+            left_bound.clone()..end.clone(),
+            DATA_KEYS_BATCH_SIZE,
+        )?;
+        let mut iter_count = 0;
+        for kv in iter {
+            iter_count += 1;
+            // skip the left bound key, it is handled in the last round
+            if kv.0 == left_bound {
+                continue;
+            }
+            left_bound = kv.0.clone();
+            f(KeyDecoder::decode_key_data_suffix(key, kv.0), kv.1)?;
+        }
+        if iter_count < DATA_KEYS_BATCH_SIZE {
+            return Ok(());
+        }
+    }
 }
 
-pub fn new_client<P: AsRef<Path>>(path: P, async_deletion_enabled: bool) -> Result<RocksClient> {
+/// Copy the data keys with `key_type` from `key` to `dest`, the data keys need to be
+/// re-encoded because the user key is part of them.
+/// Returns the number of copied data keys.
+pub(crate) fn txn_copy_data_keys(
+    txn: &RocksTransaction,
+    cf: ColumnFamilyRef,
+    key_type: u8,
+    key: &[u8],
+    version: u16,
+    dest: &[u8],
+    dest_version: u16,
+) -> Result<i64> {
+    let mut count = 0;
+    txn_for_each_data_key(txn, cf.clone(), key_type, key, version, |suffix, value| {
+        let dest_key = KeyEncoder::encode_type_data_key(dest, key_type, dest_version, &suffix);
+        count += 1;
+        txn.put(cf.clone(), dest_key, value)
+    })?;
+    Ok(count)
+}
+
+pub fn new_client<P: AsRef<Path>>(
+    path: P,
+    async_deletion_enabled: bool,
+    backup: Option<Backup>,
+) -> Result<RocksClient> {
     let db: TransactionDB = new_db(path)?;
-    Ok(RocksClient::new(Arc::new(db), async_deletion_enabled))
+    let engine = RocksEngine::new(Arc::new(db));
+    RocksClient::new(Arc::new(engine), async_deletion_enabled, backup)
+}
+
+/// Open the existing db in `path` read only, or as a secondary instance of the db in
+/// `primary_path` if it's given, keeping the files of the secondary in `path`.
+pub fn new_read_only_client<P: AsRef<Path>>(
+    path: P,
+    primary_path: Option<&Path>,
+    async_deletion_enabled: bool,
+) -> Result<RocksClient> {
+    let mut opts = Options::default();
+    // open the column families existing in the db, the db may be created by a version
+    // without some of them
+    let cfs = DB::list_cf(&opts, primary_path.unwrap_or(path.as_ref()))?;
+    let db = match primary_path {
+        Some(primary_path) => {
+            // the secondary instances need to keep all the files of the primary open
+            opts.set_max_open_files(-1);
+            DB::open_cf_as_secondary(&opts, primary_path, path.as_ref(), cfs)?
+        }
+        None => DB::open_cf_for_read_only(&opts, path, cfs, false)?,
+    };
+    let engine = ReadOnlyEngine::new(db, primary_path.is_some());
+    Ok(RocksClient::new(Arc::new(engine), async_deletion_enabled, None)?.with_read_only())
 }
 
 fn new_db<P: AsRef<Path>>(path: P) -> Result<TransactionDB<MultiThreaded>> {
@@ -61,19 +208,5 @@ fn new_db<P: AsRef<Path>>(path: P) -> Result<TransactionDB<MultiThreaded>> {
     opts.create_if_missing(true);
     opts.create_missing_column_families(true);
 
-    let cf_names = vec![
-        CF_NAME_META,
-        CF_NAME_GC,
-        CF_NAME_GC_VERSION,
-        CF_NAME_SET_SUB_META,
-        CF_NAME_SET_DATA,
-        CF_NAME_LIST_DATA,
-        CF_NAME_HASH_SUB_META,
-        CF_NAME_HASH_DATA,
-        CF_NAME_ZSET_SUB_META,
-        CF_NAME_ZSET_DATA,
-        CF_NAME_ZSET_SCORE,
-    ];
-
-    TransactionDB::open_cf(&opts, &transaction_opts, path, cf_names).map_err(|e| e.into())
+    TransactionDB::open_cf(&opts, &transaction_opts, path, CF_NAMES).map_err(|e| e.into())
 }
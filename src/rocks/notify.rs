@@ -0,0 +1,117 @@
+//! Keyspace and keyevent notifications.
+//!
+//! The events of the writes are recorded in the txn and published to the pub/sub channels
+//! `__keyspace@0__:<key>` and `__keyevent@0__:<event>` after the txn is committed, in the
+//! order of the commits. The classes of the events to publish are configured by a string
+//! with the flags of `notify-keyspace-events` of redis. The db has no eviction and no streams,
+//! and key misses are not tracked, so the events of the classes `e`, `t` and `m` are never
+//! published.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+
+use crate::pubsub::PubSub;
+
+pub const NOTIFY_KEYSPACE: u16 = 1 << 0;
+pub const NOTIFY_KEYEVENT: u16 = 1 << 1;
+pub const NOTIFY_GENERIC: u16 = 1 << 2;
+pub const NOTIFY_STRING: u16 = 1 << 3;
+pub const NOTIFY_LIST: u16 = 1 << 4;
+pub const NOTIFY_SET: u16 = 1 << 5;
+pub const NOTIFY_HASH: u16 = 1 << 6;
+pub const NOTIFY_ZSET: u16 = 1 << 7;
+pub const NOTIFY_EXPIRED: u16 = 1 << 8;
+pub const NOTIFY_EVICTED: u16 = 1 << 9;
+pub const NOTIFY_STREAM: u16 = 1 << 10;
+pub const NOTIFY_KEY_MISS: u16 = 1 << 11;
+pub const NOTIFY_NEW: u16 = 1 << 12;
+// the classes of the `A` flag
+const NOTIFY_ALL: u16 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM;
+
+/// Parse the flags of `notify-keyspace-events`, `None` is returned if there is any unknown
+/// flag.
+pub fn parse_notify_flags(flags: &str) -> Option<u16> {
+    let mut classes = 0;
+    for c in flags.chars() {
+        classes |= match c {
+            'K' => NOTIFY_KEYSPACE,
+            'E' => NOTIFY_KEYEVENT,
+            'g' => NOTIFY_GENERIC,
+            '$' => NOTIFY_STRING,
+            'l' => NOTIFY_LIST,
+            's' => NOTIFY_SET,
+            'h' => NOTIFY_HASH,
+            'z' => NOTIFY_ZSET,
+            'x' => NOTIFY_EXPIRED,
+            'e' => NOTIFY_EVICTED,
+            't' => NOTIFY_STREAM,
+            'm' => NOTIFY_KEY_MISS,
+            'n' => NOTIFY_NEW,
+            'A' => NOTIFY_ALL,
+            _ => return None,
+        };
+    }
+    Some(classes)
+}
+
+/// An event of a key recorded in txn.
+#[derive(Debug, Clone)]
+pub struct KeyEvent {
+    pub class: u16,
+    pub event: &'static str,
+    pub key: String,
+}
+
+pub(crate) struct Notifier {
+    classes: u16,
+    sender: mpsc::UnboundedSender<Vec<KeyEvent>>,
+}
+
+impl Notifier {
+    /// Start publishing the events of `classes` to `hub` in a task, it must be called in the
+    /// tokio runtime.
+    pub(crate) fn start(classes: u16, hub: Arc<PubSub>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<KeyEvent>>();
+        tokio::spawn(async move {
+            while let Some(events) = receiver.recv().await {
+                for event in events {
+                    if classes & NOTIFY_KEYSPACE != 0 {
+                        let channel = format!("__keyspace@0__:{}", event.key);
+                        hub.publish(&channel, Bytes::from_static(event.event.as_bytes()))
+                            .await;
+                    }
+                    if classes & NOTIFY_KEYEVENT != 0 {
+                        let channel = format!("__keyevent@0__:{}", event.event);
+                        hub.publish(&channel, Bytes::from(event.key)).await;
+                    }
+                }
+            }
+        });
+        Self { classes, sender }
+    }
+
+    /// Return true if any event is published.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.classes & (NOTIFY_KEYSPACE | NOTIFY_KEYEVENT) != 0
+            && self.classes & !(NOTIFY_KEYSPACE | NOTIFY_KEYEVENT) != 0
+    }
+
+    /// Publish the events of the committed txn.
+    pub(crate) fn notify(&self, mut events: Vec<KeyEvent>) {
+        events.retain(|event| event.class & self.classes != 0);
+        if !events.is_empty() {
+            // the receiver is only dropped when the runtime shuts down
+            let _ = self.sender.send(events);
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! Primary-replica replication.
+//!
+//! rust-rocksdb doesn't expose `get_updates_since` on the `TransactionDB` we use, so the WAL
+//! can't be tailed. Instead the primary records the writes of every committed txn as a write
+//! batch in a bounded in-memory log, and streams the batches to the replicas over TCP in the
+//! order of commit. A replica applies the batches in order and stores the id of the primary
+//! and the sequence number of the last applied batch with the data. The primary stores its id
+//! and the sequence number of the last batch with every commit too, so the replicas continue
+//! from the same sequence number after the primary restarts. When the replica connects to
+//! another primary, or the batches it misses are dropped from the log, including the ones
+//! logged before the primary restarts, the primary sends all the data from a snapshot
+//! instead.
+//!
+//! The column families are created in the same order in every db, so the batches recorded
+//! by the primary can be applied by the replica as they are.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
+use std::thread;
+use std::time::Duration;
+
+use rocksdb::{Direction, IteratorMode, TransactionDB, WriteBatchWithTransaction};
+use uuid::Uuid;
+
+use crate::rocks::backup::{diff_sorted, SyncOp, SYNC_BATCH_SIZE};
+use crate::rocks::client::RocksClient;
+use crate::rocks::engine::KvBytes;
+use crate::rocks::errors::{RError, CF_NOT_EXISTS_ERR};
+use crate::rocks::transaction::RocksTransaction;
+use crate::rocks::{Result as RocksResult, CF_NAMES};
+
+// replica -> primary: replication id and the sequence number of the last applied batch
+// primary -> replica: the messages below, every message starts with its type
+const MSG_CONTINUE: u8 = 1;
+const MSG_FULLSYNC: u8 = 2;
+const MSG_KV: u8 = 3;
+const MSG_CF_END: u8 = 4;
+const MSG_BATCH: u8 = 5;
+const MSG_PING: u8 = 6;
+
+// keys in the default column family, which is never used by the data
+const REPLICATION_STATE_KEY: &[u8] = b"mapuche_replication_state";
+// the primary saves the id in a new key for every sequence number, so a snapshot txn saving
+// it never conflicts with the txns committed after the snapshot, the older key is removed
+// after the txn is committed
+const PRIMARY_STATE_PREFIX: &[u8] = b"mapuche_replication_primary_";
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+// the connection is closed if nothing is received from the primary in the time
+const PRIMARY_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+struct LogEntry {
+    seq: u64,
+    key_count_delta: i64,
+    data: Arc<[u8]>,
+}
+
+struct LogState {
+    // sequence number of the last logged batch
+    seq: u64,
+    // sequence number of the last batch assigned to a txn
+    assigned: u64,
+    entries: VecDeque<LogEntry>,
+    bytes: usize,
+    // the batches committed before the ones with the smaller sequence numbers, they are
+    // logged after them
+    pending: BTreeMap<u64, LogEntry>,
+}
+
+enum LogRead {
+    Entries(Vec<LogEntry>),
+    // no new entries in the timeout
+    Empty,
+    // some of the entries are dropped from the log
+    Trimmed,
+}
+
+/// Log of the committed write batches on the primary.
+pub struct ReplicationLog {
+    // id of the primary, it's kept with the sequence number when the primary restarts
+    replid: String,
+    max_bytes: usize,
+    state: Mutex<LogState>,
+    cond: Condvar,
+    // held shared while a txn is committed and logged, and exclusively while taking the
+    // snapshot of a full sync, so the snapshot has the batches up to the logged seq
+    commit_lock: RwLock<()>,
+}
+
+impl ReplicationLog {
+    /// Create the log of the primary `db` keeping the latest batches up to `max_bytes`, the
+    /// id and the sequence number saved in `db` are loaded, a new id is created for a new db.
+    pub fn open(db: &TransactionDB, max_bytes: usize) -> RocksResult<Self> {
+        let (replid, seq) = match load_primary_state(db)? {
+            Some((replid, seq)) => (String::from_utf8_lossy(&replid).into(), seq),
+            None => (Uuid::new_v4().simple().to_string(), 0),
+        };
+        Ok(Self {
+            replid,
+            max_bytes,
+            state: Mutex::new(LogState {
+                seq,
+                assigned: seq,
+                entries: VecDeque::new(),
+                bytes: 0,
+                pending: BTreeMap::new(),
+            }),
+            cond: Condvar::new(),
+            commit_lock: RwLock::new(()),
+        })
+    }
+
+    /// Commit the txn of `db` and append its writes to the log. The sequence number of the
+    /// batch is assigned before committing, while the txn holds the locks of the keys it
+    /// writes, so the txns writing the same keys are logged in the order of commit. The
+    /// sequence number is saved in the txn, and the log is only locked to assign it and to
+    /// append the batch.
+    pub(crate) fn commit(&self, db: &TransactionDB, txn: RocksTransaction) -> RocksResult<()> {
+        let key_count_delta = txn.key_count_delta();
+        let writes = txn.take_writes().unwrap_or_default();
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        for (name, key, value) in writes {
+            let cf = db.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)?;
+            match value {
+                Some(value) => batch.put_cf(&cf, key, value),
+                None => batch.delete_cf(&cf, key),
+            }
+        }
+        if batch.is_empty() {
+            return txn.commit();
+        }
+        let _commit = self.commit_lock.read().unwrap();
+        let seq = {
+            let mut state = self.state.lock().unwrap();
+            state.assigned += 1;
+            state.assigned
+        };
+        let committed = txn
+            .put_default(&primary_state_key(seq), self.replid.as_bytes())
+            .and_then(|_| txn.commit());
+        // a failed txn logs an empty batch, so the sequence numbers of the log have no gaps
+        let entry = match committed {
+            Ok(_) => {
+                let _ = db.delete(primary_state_key(seq - 1));
+                LogEntry {
+                    seq,
+                    key_count_delta,
+                    data: batch.data().into(),
+                }
+            }
+            Err(_) => LogEntry {
+                seq,
+                key_count_delta: 0,
+                data: WriteBatchWithTransaction::<true>::default().data().into(),
+            },
+        };
+
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        state.pending.insert(seq, entry);
+        while let Some(entry) = state.pending.remove(&(state.seq + 1)) {
+            state.seq = entry.seq;
+            state.bytes += entry.data.len();
+            state.entries.push_back(entry);
+        }
+        while state.bytes > self.max_bytes && state.entries.len() > 1 {
+            let dropped = state.entries.pop_front().unwrap();
+            state.bytes -= dropped.data.len();
+        }
+        self.cond.notify_all();
+        committed
+    }
+
+    /// Return if the entries from `seq` are all in the log.
+    fn contains_from(&self, seq: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        match state.entries.front() {
+            _ if seq == state.seq + 1 => true,
+            Some(front) => front.seq <= seq && seq <= state.seq,
+            None => false,
+        }
+    }
+
+    /// Return the entries from `seq`, wait for them in `timeout` if there are none.
+    fn read_from(&self, seq: u64, timeout: Duration) -> LogRead {
+        let mut state = self.state.lock().unwrap();
+        if seq > state.seq {
+            state = self.cond.wait_timeout(state, timeout).unwrap().0;
+            if seq > state.seq {
+                return LogRead::Empty;
+            }
+        }
+        match state.entries.front() {
+            Some(front) if front.seq <= seq => LogRead::Entries(
+                state
+                    .entries
+                    .iter()
+                    .skip((seq - front.seq) as usize)
+                    .cloned()
+                    .collect(),
+            ),
+            _ => LogRead::Trimmed,
+        }
+    }
+}
+
+/// Listen for the replicas on `addr`, returns the address listened on.
+/// Every replica is served by a thread. The threads only keep a weak reference to the
+/// client, the replicas are disconnected after the db is dropped.
+pub fn listen<A: ToSocketAddrs>(addr: A, client: Arc<RocksClient>) -> RocksResult<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+    let client = Arc::downgrade(&client);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if client.strong_count() == 0 {
+                break;
+            }
+            let client = client.clone();
+            // the replica reconnects if it is disconnected by an error
+            thread::spawn(move || serve_replica(stream, &client));
+        }
+    });
+    Ok(local_addr)
+}
+
+fn serve_replica(stream: TcpStream, client: &Weak<RocksClient>) -> RocksResult<()> {
+    let log = upgrade(client)?
+        .replication()
+        .ok_or_else(|| RError::owned_error("ERR replication is not enabled"))?;
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    let replid = read_bytes(&mut reader)?;
+    let applied_seq = read_u64(&mut reader)?;
+    let mut next_seq = applied_seq + 1;
+    if replid == log.replid.as_bytes() && log.contains_from(next_seq) {
+        writer.write_all(&[MSG_CONTINUE])?;
+    } else {
+        next_seq = full_sync(&mut writer, &*upgrade(client)?, &log)? + 1;
+    }
+    writer.flush()?;
+
+    while client.strong_count() > 0 {
+        match log.read_from(next_seq, PING_INTERVAL) {
+            LogRead::Entries(entries) => {
+                for entry in entries {
+                    writer.write_all(&[MSG_BATCH])?;
+                    write_u64(&mut writer, entry.seq)?;
+                    write_u64(&mut writer, entry.key_count_delta as u64)?;
+                    write_bytes(&mut writer, &entry.data)?;
+                    next_seq = entry.seq + 1;
+                }
+            }
+            LogRead::Empty => writer.write_all(&[MSG_PING])?,
+            LogRead::Trimmed => next_seq = full_sync(&mut writer, &*upgrade(client)?, &log)? + 1,
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn upgrade(client: &Weak<RocksClient>) -> RocksResult<Arc<RocksClient>> {
+    client
+        .upgrade()
+        .ok_or_else(|| RError::owned_error("ERR the db is closed"))
+}
+
+/// Send all the data from a snapshot, returns the sequence number of the snapshot.
+fn full_sync<W: Write>(
+    writer: &mut W,
+    client: &RocksClient,
+    log: &ReplicationLog,
+) -> RocksResult<u64> {
+    let db = client.db()?;
+    // no batch is committed while taking the snapshot, so it is at the sequence number
+    let (snapshot, seq) = {
+        let _commits = log.commit_lock.write().unwrap();
+        let state = log.state.lock().unwrap();
+        (db.snapshot(), state.seq)
+    };
+    writer.write_all(&[MSG_FULLSYNC])?;
+    write_bytes(writer, log.replid.as_bytes())?;
+    write_u64(writer, seq)?;
+    for name in CF_NAMES {
+        let cf = db.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)?;
+        for kv in snapshot.iterator_cf(&cf, IteratorMode::Start) {
+            let (key, value) = kv?;
+            writer.write_all(&[MSG_KV])?;
+            write_bytes(writer, &key)?;
+            write_bytes(writer, &value)?;
+        }
+        writer.write_all(&[MSG_CF_END])?;
+    }
+    Ok(seq)
+}
+
+/// Replicate the primary at `addr` in the background, the replica reconnects after it is
+/// disconnected.
+pub fn start_replica(addr: String, client: Arc<RocksClient>) {
+    thread::spawn(move || loop {
+        let _ = replicate(&addr, &client);
+        thread::sleep(RECONNECT_INTERVAL);
+    });
+}
+
+fn replicate(addr: &str, client: &RocksClient) -> RocksResult<()> {
+    let db = client.db()?;
+    let (mut replid, mut applied_seq) = load_state(&db, REPLICATION_STATE_KEY)?;
+
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(PRIMARY_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    write_bytes(&mut writer, &replid)?;
+    write_u64(&mut writer, applied_seq)?;
+    writer.flush()?;
+
+    loop {
+        match read_u8(&mut reader)? {
+            MSG_CONTINUE | MSG_PING => {}
+            MSG_FULLSYNC => {
+                replid = read_bytes(&mut reader)?;
+                applied_seq = read_u64(&mut reader)?;
+                // the data is inconsistent until the full sync is done, clear the state
+                // to start over if it is interrupted
+                save_state(&db, &[], 0)?;
+                apply_full_sync(&mut reader, &db)?;
+                client.recount_keys()?;
+                save_state(&db, &replid, applied_seq)?;
+            }
+            MSG_BATCH => {
+                let seq = read_u64(&mut reader)?;
+                let key_count_delta = read_u64(&mut reader)? as i64;
+                let data = read_bytes(&mut reader)?;
+                if seq != applied_seq + 1 {
+                    return Err(RError::owned_error(format!(
+                        "ERR replication batch {seq} is out of order, last applied {applied_seq}"
+                    )));
+                }
+                // the state and the key count are written atomically with the batch
+                let mut batch = WriteBatchWithTransaction::<true>::from_data(&data);
+                batch.put(REPLICATION_STATE_KEY, encode_state(&replid, seq));
+                client.write_batch(batch, key_count_delta)?;
+                applied_seq = seq;
+            }
+            msg => {
+                return Err(RError::owned_error(format!(
+                    "ERR unknown replication message {msg}"
+                )))
+            }
+        }
+    }
+}
+
+/// Make the data the same as the data sent by the primary, only the different keys are
+/// written.
+fn apply_full_sync<R: Read>(reader: &mut R, db: &TransactionDB) -> RocksResult<()> {
+    for name in CF_NAMES {
+        let cf = db.cf_handle(name).ok_or(CF_NOT_EXISTS_ERR)?;
+        let source = KvReader {
+            reader,
+            done: false,
+        };
+        let dest = db.iterator_cf(&cf, IteratorMode::Start);
+
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        diff_sorted(source, dest, |op| {
+            match op {
+                SyncOp::Put(key, value) => batch.put_cf(&cf, key, value),
+                SyncOp::Delete(key) => batch.delete_cf(&cf, key),
+            }
+            if batch.len() >= SYNC_BATCH_SIZE {
+                db.write(std::mem::take(&mut batch))?;
+            }
+            Ok(())
+        })?;
+        db.write(batch)?;
+    }
+    Ok(())
+}
+
+/// Iterator of the key-value pairs of a column family in the full sync.
+struct KvReader<'a, R> {
+    reader: &'a mut R,
+    done: bool,
+}
+
+impl<R: Read> KvReader<'_, R> {
+    fn read_kv(&mut self) -> RocksResult<Option<KvBytes>> {
+        match read_u8(self.reader)? {
+            MSG_KV => {
+                let key = read_bytes(self.reader)?;
+                let value = read_bytes(self.reader)?;
+                Ok(Some((key.into(), value.into())))
+            }
+            MSG_CF_END => Ok(None),
+            msg => Err(RError::owned_error(format!(
+                "ERR unexpected replication message {msg} in full sync"
+            ))),
+        }
+    }
+}
+
+impl<R: Read> Iterator for KvReader<'_, R> {
+    type Item = RocksResult<KvBytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let kv = self.read_kv().transpose();
+        // stop at the end of the column family or the first error
+        self.done = !matches!(kv, Some(Ok(_)));
+        kv
+    }
+}
+
+fn primary_state_key(seq: u64) -> Vec<u8> {
+    [PRIMARY_STATE_PREFIX, &seq.to_be_bytes()].concat()
+}
+
+// the id and the sequence number saved by the primary, the older keys left when the primary
+// is closed before they are removed are removed
+fn load_primary_state(db: &TransactionDB) -> RocksResult<Option<(Vec<u8>, u64)>> {
+    let start = primary_state_key(u64::MAX);
+    let mut state = None;
+    for kv in db.iterator(IteratorMode::From(&start, Direction::Reverse)) {
+        let (key, value) = kv?;
+        let Some(seq) = key.strip_prefix(PRIMARY_STATE_PREFIX) else {
+            break;
+        };
+        match (&state, seq.try_into()) {
+            (None, Ok(seq)) => state = Some((value.to_vec(), u64::from_be_bytes(seq))),
+            _ => db.delete(&key)?,
+        }
+    }
+    Ok(state)
+}
+
+fn load_state(db: &TransactionDB, key: &[u8]) -> RocksResult<(Vec<u8>, u64)> {
+    match db.get(key)? {
+        Some(state) if state.len() >= 8 => {
+            let (seq, replid) = state.split_at(8);
+            Ok((replid.to_vec(), u64::from_be_bytes(seq.try_into().unwrap())))
+        }
+        _ => Ok((vec![], 0)),
+    }
+}
+
+fn save_state(db: &TransactionDB, replid: &[u8], seq: u64) -> RocksResult<()> {
+    db.put(REPLICATION_STATE_KEY, encode_state(replid, seq))?;
+    Ok(())
+}
+
+fn encode_state(replid: &[u8], seq: u64) -> Vec<u8> {
+    let mut state = seq.to_be_bytes().to_vec();
+    state.extend_from_slice(replid);
+    state
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_u64(reader)? as usize;
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_be_bytes())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, value: &[u8]) -> io::Result<()> {
+    write_u64(writer, value.len() as u64)?;
+    writer.write_all(value)
+}
@@ -1,12 +1,15 @@
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
+use crate::rocks::dump::DumpPayload;
 use crate::rocks::encoding::{DataType, KeyDecoder};
-use crate::rocks::errors::REDIS_WRONG_TYPE_ERR;
+use crate::rocks::engine::ColumnFamilyRef;
+use crate::rocks::errors::{REDIS_DUMP_PAYLOAD_ERR, REDIS_WRONG_TYPE_ERR};
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::value::Value;
+use crate::rocks::notify::{NOTIFY_EXPIRED, NOTIFY_GENERIC, NOTIFY_SET};
 use crate::rocks::transaction::RocksTransaction;
 use crate::rocks::{
-    Result as RocksResult, TxnCommand, CF_NAME_GC, CF_NAME_GC_VERSION, CF_NAME_META,
-    CF_NAME_SET_DATA, CF_NAME_SET_SUB_META,
+    txn_copy_data_keys, txn_for_each_data_key, Result as RocksResult, TxnCommand, CF_NAME_GC,
+    CF_NAME_GC_VERSION, CF_NAME_META, CF_NAME_SET_DATA, CF_NAME_SET_SUB_META,
 };
 use crate::utils::{
     count_unique_keys, key_is_expired, resp_array, resp_bulk, resp_err, resp_int, resp_nil,
@@ -15,10 +18,10 @@ use crate::Frame;
 use rand::rngs::SmallRng;
 use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
-use rocksdb::ColumnFamilyRef;
 
 use std::collections::HashMap;
 
+use super::encoding::encode::DATA_TYPE_SET;
 use super::encoding::KeyEncoder;
 
 const RANDOM_BASE: i64 = 100;
@@ -32,7 +35,8 @@ pub struct SetCF<'a> {
 }
 
 impl<'a> SetCF<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        let client = client.into().client();
         SetCF {
             meta_cf: client.cf_handle(CF_NAME_META).unwrap(),
             sub_meta_cf: client.cf_handle(CF_NAME_SET_SUB_META).unwrap(),
@@ -44,12 +48,14 @@ impl<'a> SetCF<'a> {
 }
 
 pub struct SetCommand<'a> {
-    client: &'a RocksClient,
+    client: ClientRef<'a>,
 }
 
 impl<'a> SetCommand<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
-        Self { client }
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        Self {
+            client: client.into(),
+        }
     }
 
     pub async fn sadd(self, key: &str, members: &Vec<String>) -> RocksResult<Frame> {
@@ -61,7 +67,7 @@ impl<'a> SetCommand<'a> {
         let rand_idx = self.client.gen_next_meta_index();
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Set) {
                         return Err(REDIS_WRONG_TYPE_ERR);
@@ -76,7 +82,7 @@ impl<'a> SetCommand<'a> {
                         txn.get_for_update(cfs.sub_meta_cf.clone(), sub_meta_key.clone())?;
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         expired = true;
                         version = client.get_version_for_new(
                             txn,
@@ -121,6 +127,9 @@ impl<'a> SetCommand<'a> {
                         txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
                     }
 
+                    if added > 0 {
+                        txn.notify(NOTIFY_SET, "sadd", &key);
+                    }
                     Ok(added)
                 }
                 None => {
@@ -153,6 +162,7 @@ impl<'a> SetCommand<'a> {
                         sub_meta_key,
                         added.to_be_bytes().to_vec(),
                     )?;
+                    txn.notify(NOTIFY_SET, "sadd", &key);
                     Ok(added)
                 }
             }
@@ -180,7 +190,7 @@ impl<'a> SetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_int(0));
                     }
 
@@ -215,7 +225,7 @@ impl<'a> SetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         if !resp_in_arr {
                             return Ok(resp_int(0));
                         } else {
@@ -288,7 +298,7 @@ impl<'a> SetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_array(vec![]));
                     }
 
@@ -364,7 +374,7 @@ impl<'a> SetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_array(vec![]));
                     }
 
@@ -394,7 +404,7 @@ impl<'a> SetCommand<'a> {
         let rand_idx = self.client.gen_next_meta_index();
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Set) {
@@ -404,7 +414,7 @@ impl<'a> SetCommand<'a> {
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
 
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(0);
                     }
 
@@ -420,9 +430,13 @@ impl<'a> SetCommand<'a> {
                         removed += 1;
                     }
 
+                    if removed > 0 {
+                        txn.notify(NOTIFY_SET, "srem", &key);
+                    }
                     // check if all items cleared, delete meta key and all sub meta keys if needed
                     if removed >= size {
                         txn.del(cfs.meta_cf, meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                         let meta_bound_range = KeyEncoder::encode_sub_meta_key_range(&key, version);
                         let iter =
                             txn.scan_keys(cfs.sub_meta_cf.clone(), meta_bound_range, u32::MAX)?;
@@ -460,8 +474,13 @@ impl<'a> SetCommand<'a> {
         }
     }
 
-    /// spop will pop members by alphabetical order
-    pub async fn spop(self, key: &str, count: u64) -> RocksResult<Frame> {
+    /// spop will pop members by alphabetical order, or pop `members` if it is some
+    pub async fn spop(
+        self,
+        key: &str,
+        count: u64,
+        members: Option<&[String]>,
+    ) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = SetCF::new(client);
         let meta_key = KeyEncoder::encode_meta_key(key);
@@ -469,7 +488,7 @@ impl<'a> SetCommand<'a> {
         let rand_idx = self.client.gen_next_meta_index();
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Set) {
@@ -478,16 +497,37 @@ impl<'a> SetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(vec![]);
                     }
 
-                    let bound_range = KeyEncoder::encode_set_data_key_range(&key, version);
-                    let iter =
-                        txn.scan_keys(cfs.data_cf.clone(), bound_range, count.try_into().unwrap())?;
+                    let data_keys: Vec<Key> = match members {
+                        Some(members) => {
+                            let data_keys = members
+                                .iter()
+                                .map(|member| {
+                                    KeyEncoder::encode_set_data_key(&key, member, version)
+                                })
+                                .collect();
+                            txn.batch_get_for_update(cfs.data_cf.clone(), data_keys)?
+                                .into_iter()
+                                .map(|kv| kv.0)
+                                .collect()
+                        }
+                        None => {
+                            let bound_range = KeyEncoder::encode_set_data_key_range(&key, version);
+                            txn.scan_keys(
+                                cfs.data_cf.clone(),
+                                bound_range,
+                                count.try_into().unwrap(),
+                            )?
+                            .collect()
+                        }
+                    };
 
                     let mut data_key_to_delete = vec![];
-                    let resp = iter
+                    let resp = data_keys
+                        .into_iter()
                         .map(|k| {
                             data_key_to_delete.push(k.clone());
                             // decode member from data key
@@ -504,10 +544,14 @@ impl<'a> SetCommand<'a> {
                     // txn will be lock inner txnkv_sum_key_size, so release it first
                     let size = self.sum_key_size(&key, version)?;
 
+                    if poped_count > 0 {
+                        txn.notify(NOTIFY_SET, "spop", &key);
+                    }
                     // update or delete meta key
                     if poped_count >= size {
                         // delete meta key
                         txn.del(cfs.meta_cf, meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                         // delete all sub meta keys
                         let meta_bound_range = KeyEncoder::encode_sub_meta_key_range(&key, version);
                         let iter =
@@ -555,10 +599,10 @@ impl<'a> SetCommand<'a> {
         }
     }
 
-    fn sum_key_size(&self, key: &str, version: u16) -> RocksResult<i64> {
+    fn sum_key_size(&self, key: impl AsRef<[u8]>, version: u16) -> RocksResult<i64> {
         let client = self.client;
         let cfs = SetCF::new(client);
-        let key = key.to_owned();
+        let key = key.as_ref().to_owned();
 
         client.exec_txn(move |txn| {
             // check if meta key exists or already expired
@@ -582,12 +626,12 @@ impl<'a> SetCommand<'a> {
 }
 
 impl TxnCommand for SetCommand<'_> {
-    fn txn_del(&self, txn: &RocksTransaction, key: &str) -> RocksResult<()> {
+    fn txn_del(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<()> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = SetCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let version = KeyDecoder::decode_key_version(&meta_value);
                 let size = self.sum_key_size(&key, version)?;
@@ -626,12 +670,12 @@ impl TxnCommand for SetCommand<'_> {
         }
     }
 
-    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &str) -> RocksResult<i64> {
+    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<i64> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = SetCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                 if !key_is_expired(ttl) {
@@ -667,6 +711,7 @@ impl TxnCommand for SetCommand<'_> {
 
                     txn.del(cfs.meta_cf.clone(), meta_key)?;
                 }
+                txn.notify(NOTIFY_EXPIRED, "expired", &String::from_utf8_lossy(&key));
                 Ok(1)
             }
             None => Ok(0),
@@ -676,7 +721,7 @@ impl TxnCommand for SetCommand<'_> {
     fn txn_expire(
         &self,
         txn: &RocksTransaction,
-        key: &str,
+        key: &[u8],
         timestamp: i64,
         meta_value: &Value,
     ) -> RocksResult<i64> {
@@ -687,13 +732,13 @@ impl TxnCommand for SetCommand<'_> {
             self.txn_expire_if_needed(txn, key)?;
             return Ok(0);
         }
-        let version = KeyDecoder::decode_key_version(meta_value);
-        let new_meta_value = KeyEncoder::encode_set_meta_value(timestamp, version, 0);
+        let (_, version, index_size) = KeyDecoder::decode_key_meta(meta_value);
+        let new_meta_value = KeyEncoder::encode_set_meta_value(timestamp, version, index_size);
         txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
         Ok(1)
     }
 
-    fn txn_gc(&self, txn: &RocksTransaction, key: &str, version: u16) -> RocksResult<()> {
+    fn txn_gc(&self, txn: &RocksTransaction, key: &[u8], version: u16) -> RocksResult<()> {
         let cfs = SetCF::new(self.client);
         // delete all sub meta key of this key and version
         let bound_range = KeyEncoder::encode_sub_meta_key_range(key, version);
@@ -709,4 +754,100 @@ impl TxnCommand for SetCommand<'_> {
         }
         Ok(())
     }
+
+    fn txn_copy(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        dest: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<()> {
+        let cfs = SetCF::new(self.client);
+        let (ttl, version, index_size) = KeyDecoder::decode_key_meta(meta_value);
+        let dest_version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            dest,
+        )?;
+        let size = txn_copy_data_keys(
+            txn,
+            cfs.data_cf.clone(),
+            DATA_TYPE_SET,
+            key,
+            version,
+            dest,
+            dest_version,
+        )?;
+
+        // all the size is saved in one sub meta key of dest
+        let idx = self.client.gen_next_meta_index();
+        let sub_meta_key = KeyEncoder::encode_sub_meta_key(dest, dest_version, idx);
+        txn.put(cfs.sub_meta_cf, sub_meta_key, size.to_be_bytes().to_vec())?;
+
+        let meta_key = KeyEncoder::encode_meta_key(dest);
+        let new_meta_value = KeyEncoder::encode_set_meta_value(ttl, dest_version, index_size);
+        txn.put(cfs.meta_cf, meta_key, new_meta_value)?;
+        Ok(())
+    }
+
+    fn txn_dump(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<DumpPayload> {
+        let cfs = SetCF::new(self.client);
+        let (_, version, _) = KeyDecoder::decode_key_meta(meta_value);
+        let mut items = vec![];
+        txn_for_each_data_key(
+            txn,
+            cfs.data_cf,
+            DATA_TYPE_SET,
+            key,
+            version,
+            |suffix, _| {
+                items.push(suffix);
+                Ok(())
+            },
+        )?;
+        Ok(DumpPayload::Set(items))
+    }
+
+    fn txn_restore(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        payload: &DumpPayload,
+    ) -> RocksResult<()> {
+        let items = match payload {
+            DumpPayload::Set(items) => items,
+            _ => return Err(REDIS_DUMP_PAYLOAD_ERR),
+        };
+        let cfs = SetCF::new(self.client);
+        let version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            key,
+        )?;
+        for member in items {
+            let data_key = KeyEncoder::encode_type_data_key(key, DATA_TYPE_SET, version, member);
+            txn.put(cfs.data_cf.clone(), data_key, vec![0])?;
+        }
+
+        let idx = self.client.gen_next_meta_index();
+        let sub_meta_key = KeyEncoder::encode_sub_meta_key(key, version, idx);
+        txn.put(
+            cfs.sub_meta_cf,
+            sub_meta_key,
+            (items.len() as i64).to_be_bytes().to_vec(),
+        )?;
+
+        let meta_key = KeyEncoder::encode_meta_key(key);
+        let meta_value = KeyEncoder::encode_set_meta_value(timestamp, version, 0);
+        txn.put(cfs.meta_cf, meta_key, meta_value)?;
+        Ok(())
+    }
 }
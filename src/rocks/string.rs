@@ -3,30 +3,48 @@ use std::str;
 
 use bytes::Bytes;
 use glob::Pattern;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
 use regex::bytes::Regex;
 
-use crate::rocks::client::RocksClient;
+use crate::cmd::{BitOperation, BitfieldOp, BitfieldOverflow, ExpireCondition, SetCondition};
+use crate::config::config_proto_max_bulk_len;
+use crate::rocks::bitmap;
+use crate::rocks::cdc::{decode_changes, ChangeEvent, RawChange};
+use crate::rocks::client::ClientRef;
+use crate::rocks::dump::{DumpPayload, DumpValue};
 use crate::rocks::encoding::{DataType, KeyDecoder};
-use crate::rocks::errors::{RError, REDIS_WRONG_TYPE_ERR};
+use crate::rocks::engine::ColumnFamilyRef;
+use crate::rocks::errors::{
+    RError, REDIS_BUSY_KEY_ERR, REDIS_DUMP_PAYLOAD_ERR, REDIS_INCR_OR_DECR_OVERFLOW_ERR,
+    REDIS_INVALID_TTL_ERR, REDIS_NO_SUCH_KEY_ERR, REDIS_OFFSET_OUT_OF_RANGE_ERR,
+    REDIS_SAME_OBJECT_ERR, REDIS_STRING_EXCEEDS_MAX_SIZE_ERR, REDIS_WRONG_TYPE_ERR,
+};
 use crate::rocks::hash::HashCommand;
+use crate::rocks::hyperloglog::HyperLogLog;
 use crate::rocks::kv::bound_range::BoundRange;
-use crate::rocks::{TxnCommand, CF_NAME_META};
+use crate::rocks::{
+    TxnCommand, CF_NAME_HASH_DATA, CF_NAME_LIST_DATA, CF_NAME_META, CF_NAME_SET_DATA,
+    CF_NAME_ZSET_DATA,
+};
+use crate::shard::key_hash_slot;
 use crate::Frame;
-use rocksdb::ColumnFamilyRef;
 
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::kvpair::KvPair;
 use crate::rocks::kv::value::Value;
 use crate::rocks::list::ListCommand;
+use crate::rocks::notify::{NOTIFY_EXPIRED, NOTIFY_GENERIC, NOTIFY_STRING};
 use crate::rocks::set::SetCommand;
 use crate::rocks::transaction::RocksTransaction;
 use crate::rocks::zset::ZsetCommand;
 use crate::rocks::Result as RocksResult;
 use crate::utils::{
-    key_is_expired, resp_array, resp_bulk, resp_err, resp_int, resp_nil, resp_ok, resp_str,
-    ttl_from_timestamp,
+    incr_float, key_is_expired, now_timestamp_in_millis, resp_array, resp_bulk, resp_err, resp_int,
+    resp_nil, resp_ok, resp_str, ttl_from_timestamp,
 };
 
+use super::encoding::encode::{DATA_TYPE_HASH, DATA_TYPE_LIST, DATA_TYPE_SET, DATA_TYPE_ZSET};
 use super::encoding::KeyEncoder;
 
 pub struct StringCF<'a> {
@@ -34,7 +52,8 @@ pub struct StringCF<'a> {
 }
 
 impl<'a> StringCF<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        let client = client.into().client();
         StringCF {
             meta_cf: client.cf_handle(CF_NAME_META).unwrap(),
         }
@@ -42,12 +61,14 @@ impl<'a> StringCF<'a> {
 }
 
 pub struct StringCommand<'a> {
-    client: &'a RocksClient,
+    client: ClientRef<'a>,
 }
 
 impl<'a> StringCommand<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
-        Self { client }
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        Self {
+            client: client.into(),
+        }
     }
 
     pub async fn get(&self, key: &str) -> RocksResult<Frame> {
@@ -63,8 +84,7 @@ impl<'a> StringCommand<'a> {
                 // ttl saved in milliseconds
                 let ttl = KeyDecoder::decode_key_ttl(&val);
                 if key_is_expired(ttl) {
-                    // delete key
-                    client.del(cfs.meta_cf, ekey)?;
+                    self.del_expired(key.as_bytes(), ekey)?;
                     return Ok(resp_nil());
                 }
                 let data = KeyDecoder::decode_key_string_value(&val);
@@ -83,8 +103,7 @@ impl<'a> StringCommand<'a> {
                 // ttl saved in milliseconds
                 let ttl = KeyDecoder::decode_key_ttl(&val);
                 if key_is_expired(ttl) {
-                    // delete key
-                    client.del(cfs.meta_cf.clone(), ekey)?;
+                    self.del_expired(key.as_bytes(), ekey)?;
                     return Ok(resp_str(&DataType::Null.to_string()));
                 }
                 Ok(resp_str(&KeyDecoder::decode_key_type(&val).to_string()))
@@ -106,8 +125,7 @@ impl<'a> StringCommand<'a> {
                 // ttl saved in milliseconds
                 let ttl = KeyDecoder::decode_key_ttl(&val);
                 if key_is_expired(ttl) {
-                    // delete key
-                    client.del(cfs.meta_cf, ekey)?;
+                    self.del_expired(key.as_bytes(), ekey)?;
                     return Ok(resp_int(0));
                 }
                 let data = KeyDecoder::decode_key_string_value(&val);
@@ -122,7 +140,14 @@ impl<'a> StringCommand<'a> {
         let cfs = StringCF::new(client);
         let ekey = KeyEncoder::encode_string(key);
         let eval = KeyEncoder::encode_string_value(&mut val.to_vec(), timestamp);
-        client.put(cfs.meta_cf, ekey, eval)?;
+        client.exec_txn(|txn| {
+            txn.put(cfs.meta_cf, ekey, eval)?;
+            txn.notify(NOTIFY_STRING, "set", key);
+            if timestamp > 0 {
+                txn.notify(NOTIFY_GENERIC, "expire", key);
+            }
+            Ok(())
+        })?;
         Ok(resp_ok())
     }
 
@@ -135,16 +160,15 @@ impl<'a> StringCommand<'a> {
 
         let values: Vec<Frame> = ekeys
             .into_iter()
-            .map(|k| {
+            .zip(keys)
+            .map(|(k, key)| {
                 let data = ret.get(&k);
                 match data {
                     Some(val) => {
                         // ttl saved in milliseconds
                         let ttl = KeyDecoder::decode_key_ttl(val);
                         if key_is_expired(ttl) {
-                            // delete key
-                            client
-                                .del(cfs.meta_cf.clone(), k)
+                            self.del_expired(key.as_bytes(), k)
                                 .expect("remove outdated data failed");
                             resp_nil()
                         } else {
@@ -162,330 +186,1269 @@ impl<'a> StringCommand<'a> {
     pub async fn batch_put(self, kvs: Vec<KvPair>) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
-        client.batch_put(cfs.meta_cf, kvs)?;
+        client.exec_txn(|txn| {
+            for kv in kvs {
+                let key = KeyDecoder::decode_key_userkey_from_metakey(&kv.0).0;
+                txn.put(cfs.meta_cf.clone(), kv.0, kv.1)?;
+                txn.notify(NOTIFY_STRING, "set", &String::from_utf8_lossy(&key));
+            }
+            Ok(())
+        })?;
         Ok(resp_ok())
     }
 
-    pub async fn put_not_exists(self, key: &str, value: &Bytes) -> RocksResult<Frame> {
+    /// Set the string value of the key with the conditional options of SET.
+    ///
+    /// `timestamp` is the expire time in milliseconds, `0` means no ttl and `None` retains
+    /// the ttl of the existing key. If `get` is true the old string value is returned,
+    /// otherwise OK or nil if the value is not set because of `condition`.
+    pub async fn set(
+        self,
+        key: &str,
+        value: &Bytes,
+        timestamp: Option<i64>,
+        condition: Option<SetCondition>,
+        get: bool,
+    ) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
         let ekey = KeyEncoder::encode_string(key);
-        let eval = KeyEncoder::encode_string_value(&mut value.to_vec(), -1);
 
         let resp = client.exec_txn(|txn| {
-            match txn.get_for_update(cfs.meta_cf.clone(), ekey.clone())? {
-                Some(ref v) => {
-                    let ttl = KeyDecoder::decode_key_ttl(v);
+            let mut old_ttl = 0;
+            let mut old_value = None;
+            let meta_value = match txn.get_for_update(cfs.meta_cf.clone(), ekey.clone())? {
+                Some(meta_value) => {
+                    let ttl = KeyDecoder::decode_key_ttl(&meta_value);
                     if key_is_expired(ttl) {
-                        // no need to delete, just overwrite
-                        txn.put(cfs.meta_cf, ekey, eval)?;
-                        Ok(1)
+                        self.txn_expire_any(txn, key.as_bytes(), &ekey, &meta_value)?;
+                        None
                     } else {
-                        Ok(0)
+                        Some(meta_value)
                     }
                 }
-                None => {
-                    txn.put(cfs.meta_cf, ekey, eval)?;
-                    Ok(1)
+                None => None,
+            };
+
+            if let Some(ref meta_value) = meta_value {
+                let is_string = matches!(KeyDecoder::decode_key_type(meta_value), DataType::String);
+                if get && !is_string {
+                    return Err(REDIS_WRONG_TYPE_ERR);
+                }
+                if is_string {
+                    old_value = Some(KeyDecoder::decode_key_string_value(meta_value));
                 }
+                old_ttl = KeyDecoder::decode_key_ttl(meta_value);
             }
-        });
 
-        match resp {
-            Ok(n) => {
-                if n == 0 {
-                    Ok(resp_nil())
-                } else {
-                    Ok(resp_ok())
+            let exists = meta_value.is_some();
+            let skip = match condition {
+                Some(SetCondition::Nx) => exists,
+                Some(SetCondition::Xx) => !exists,
+                None => false,
+            };
+            if skip {
+                return Ok((false, old_value));
+            }
+
+            // values of other types must be removed before overwrite
+            if let Some(ref meta_value) = meta_value {
+                if !matches!(KeyDecoder::decode_key_type(meta_value), DataType::String) {
+                    self.txn_del_any(txn, key.as_bytes(), &ekey, meta_value)?;
                 }
             }
+            let eval = KeyEncoder::encode_string_slice(value, timestamp.unwrap_or(old_ttl));
+            txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+            txn.notify(NOTIFY_STRING, "set", key);
+            if timestamp.is_some_and(|ts| ts > 0) {
+                txn.notify(NOTIFY_GENERIC, "expire", key);
+            }
+            Ok((true, old_value))
+        });
+
+        match resp {
+            Ok((_, old_value)) if get => match old_value {
+                Some(data) => Ok(resp_bulk(data)),
+                None => Ok(resp_nil()),
+            },
+            Ok((true, _)) => Ok(resp_ok()),
+            Ok((false, _)) => Ok(resp_nil()),
             Err(e) => Ok(resp_err(e)),
         }
     }
 
-    pub async fn exists(self, keys: &[String]) -> RocksResult<Frame> {
+    pub async fn append(self, key: &str, value: &Bytes) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
-        let ekeys = KeyEncoder::encode_strings(keys);
-        let result = client.batch_get(cfs.meta_cf.clone(), ekeys.clone())?;
-        let ret: HashMap<Key, Value> = result.into_iter().map(|pair| (pair.0, pair.1)).collect();
-        let mut nums = 0;
-        for k in ekeys {
-            let data = ret.get(&k);
-            if let Some(val) = data {
-                // ttl saved in milliseconds
-                let ttl = KeyDecoder::decode_key_ttl(val);
-                if key_is_expired(ttl) {
-                    // delete key
-                    client.del(cfs.meta_cf.clone(), k)?;
-                } else {
-                    nums += 1;
-                }
+        let ekey = KeyEncoder::encode_string(key);
+
+        let resp = client.exec_txn(|txn| {
+            // keep the ttl of the existing key
+            let (mut data, ttl) = match self.txn_get_string(txn, key, &ekey, true)? {
+                Some(val) => (
+                    KeyDecoder::decode_key_string_value(&val),
+                    KeyDecoder::decode_key_ttl(&val),
+                ),
+                None => (vec![], 0),
+            };
+            if data.len() + value.len() > config_proto_max_bulk_len() {
+                return Err(REDIS_STRING_EXCEEDS_MAX_SIZE_ERR);
             }
+            data.extend_from_slice(value);
+            let new_len = data.len() as i64;
+            let eval = KeyEncoder::encode_string_value(&mut data, ttl);
+            txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+            txn.notify(NOTIFY_STRING, "append", key);
+            Ok(new_len)
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
         }
-        Ok(resp_int(nums as i64))
     }
 
-    // TODO: All actions should in txn
-    pub async fn incr(self, key: &str, step: i64) -> RocksResult<Frame> {
+    pub async fn getrange(self, key: &str, mut start: i64, mut end: i64) -> RocksResult<Frame> {
         let client = self.client;
-        let cfs = StringCF::new(client);
         let ekey = KeyEncoder::encode_string(key);
-        let the_key = ekey.clone();
 
-        client.exec_txn(|txn| {
-            let pair = match txn.get_for_update(cfs.meta_cf.clone(), the_key.clone())? {
-                Some(val) => {
-                    let dt = KeyDecoder::decode_key_type(&val);
-                    if !matches!(dt, DataType::String) {
-                        return Err(REDIS_WRONG_TYPE_ERR);
-                    }
-                    // ttl saved in milliseconds
-                    let ttl = KeyDecoder::decode_key_ttl(&val);
-                    if key_is_expired(ttl) {
-                        // delete key
-                        txn.del(cfs.meta_cf.clone(), the_key)?;
-                        (0, None)
-                    } else {
-                        let current_value = KeyDecoder::decode_key_string_slice(&val);
-                        let prev_int = str::from_utf8(current_value)
-                            .map_err(RError::is_not_integer_error)?
-                            .parse::<i64>()?;
-                        let prev = Some(val.clone());
-                        (prev_int, prev)
-                    }
-                }
-                None => (0, None),
+        let resp = client.exec_txn(|txn| {
+            let val = match self.txn_get_string(txn, key, &ekey, false)? {
+                Some(val) => val,
+                None => return Ok(vec![]),
             };
+            let data = KeyDecoder::decode_key_string_slice(&val);
+            let len = data.len() as i64;
+            if start < 0 && end < 0 && start > end {
+                return Ok(vec![]);
+            }
+            // convert index to positive if negtive
+            if start < 0 {
+                start = (len + start).max(0);
+            }
+            if end < 0 {
+                end = (len + end).max(0);
+            }
+            if end >= len {
+                end = len - 1;
+            }
+            if len == 0 || start > end {
+                return Ok(vec![]);
+            }
+            Ok(data[start as usize..=end as usize].to_vec())
+        });
+
+        match resp {
+            Ok(data) => Ok(resp_bulk(data)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
 
-            let (prev_int, _) = pair;
+    pub async fn setrange(self, key: &str, offset: i64, value: &Bytes) -> RocksResult<Frame> {
+        if offset < 0 {
+            return Ok(resp_err(REDIS_OFFSET_OUT_OF_RANGE_ERR));
+        }
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(key);
+        let offset = offset as usize;
 
-            let new_int = prev_int + step;
-            let new_val = new_int.to_string();
-            let eval = KeyEncoder::encode_string_value(&mut new_val.as_bytes().to_vec(), 0);
-            txn.put(cfs.meta_cf, ekey, eval)?;
-            Ok(resp_int(new_int))
-        })
+        let resp = client.exec_txn(|txn| {
+            // keep the ttl of the existing key
+            let (mut data, ttl) = match self.txn_get_string(txn, key, &ekey, true)? {
+                Some(val) => (
+                    KeyDecoder::decode_key_string_value(&val),
+                    KeyDecoder::decode_key_ttl(&val),
+                ),
+                None => (vec![], 0),
+            };
+            // nothing to set, key will not be created if not exists
+            if value.is_empty() {
+                return Ok(data.len() as i64);
+            }
+            if offset + value.len() > config_proto_max_bulk_len() {
+                return Err(REDIS_STRING_EXCEEDS_MAX_SIZE_ERR);
+            }
+            // pad with zero bytes if the offset is beyond the current length
+            if data.len() < offset + value.len() {
+                data.resize(offset + value.len(), 0);
+            }
+            data[offset..offset + value.len()].copy_from_slice(value);
+            let new_len = data.len() as i64;
+            let eval = KeyEncoder::encode_string_value(&mut data, ttl);
+            txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+            txn.notify(NOTIFY_STRING, "setrange", key);
+            Ok(new_len)
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
     }
 
-    pub async fn expire(self, key: &str, timestamp: i64) -> RocksResult<Frame> {
+    pub async fn getset(self, key: &str, value: &Bytes) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
-        let key = key.to_owned();
-        let timestamp = timestamp;
-        let ekey = KeyEncoder::encode_string(&key);
+        let ekey = KeyEncoder::encode_string(key);
+
         let resp = client.exec_txn(|txn| {
-            match txn.get_for_update(cfs.meta_cf.clone(), ekey.clone())? {
-                Some(meta_value) => {
-                    if timestamp == 0 {
-                        return Ok(0);
-                    }
-                    let dt = KeyDecoder::decode_key_type(&meta_value);
-                    match dt {
-                        DataType::String => {
-                            let ttl = KeyDecoder::decode_key_ttl(&meta_value);
-                            // check key expired
-                            if key_is_expired(ttl) {
-                                self.txn_expire_if_needed(txn, &ekey, &meta_value)?;
-                                return Ok(0);
-                            }
-                            let value = KeyDecoder::decode_key_string_slice(&meta_value);
-                            let new_meta_value = KeyEncoder::encode_string_slice(value, timestamp);
-                            txn.put(cfs.meta_cf.clone(), ekey, new_meta_value)?;
-                            Ok(1)
-                        }
-                        DataType::Set => SetCommand::new(self.client).txn_expire(
-                            txn,
-                            &key,
-                            timestamp,
-                            &meta_value,
-                        ),
-                        DataType::List => ListCommand::new(self.client).txn_expire(
-                            txn,
-                            &key,
-                            timestamp,
-                            &meta_value,
-                        ),
-                        DataType::Hash => HashCommand::new(self.client).txn_expire(
-                            txn,
-                            &key,
-                            timestamp,
-                            &meta_value,
-                        ),
-                        DataType::Zset => ZsetCommand::new(self.client).txn_expire(
-                            txn,
-                            &key,
-                            timestamp,
-                            &meta_value,
-                        ),
-                        _ => Ok(0),
-                    }
-                }
-                None => Ok(0),
+            let old = self
+                .txn_get_string(txn, key, &ekey, true)?
+                .map(|val| KeyDecoder::decode_key_string_value(&val));
+            // any previous ttl is discarded
+            let eval = KeyEncoder::encode_string_slice(value, 0);
+            txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+            txn.notify(NOTIFY_STRING, "set", key);
+            Ok(old)
+        });
+
+        match resp {
+            Ok(Some(data)) => Ok(resp_bulk(data)),
+            Ok(None) => Ok(resp_nil()),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    pub async fn getdel(self, key: &str) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(key);
+
+        let resp = client.exec_txn(|txn| match self.txn_get_string(txn, key, &ekey, true)? {
+            Some(val) => {
+                txn.del(cfs.meta_cf.clone(), ekey.clone())?;
+                txn.notify(NOTIFY_GENERIC, "del", key);
+                Ok(Some(KeyDecoder::decode_key_string_value(&val)))
             }
+            None => Ok(None),
         });
+
         match resp {
-            Ok(v) => Ok(resp_int(v)),
+            Ok(Some(data)) => Ok(resp_bulk(data)),
+            Ok(None) => Ok(resp_nil()),
             Err(e) => Ok(resp_err(e)),
         }
     }
 
-    pub async fn ttl(self, key: &str, is_millis: bool) -> RocksResult<Frame> {
+    /// Get the value of key and optionally update its expiration.
+    ///
+    /// `timestamp` is the new expire time in milliseconds, `Some(0)` removes the ttl
+    /// and `None` keeps the key untouched.
+    pub async fn getex(self, key: &str, timestamp: Option<i64>) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
-        let key = key.to_owned();
-        let ekey = KeyEncoder::encode_string(&key);
-        client.exec_txn(|txn| match txn.get(cfs.meta_cf.clone(), ekey.clone())? {
-            Some(meta_value) => {
-                let dt = KeyDecoder::decode_key_type(&meta_value);
-                let ttl = KeyDecoder::decode_key_ttl(&meta_value);
-                if key_is_expired(ttl) {
-                    match dt {
-                        DataType::String => {
-                            self.txn_expire_if_needed(txn, &ekey, &meta_value)?;
-                        }
-                        DataType::Set => {
-                            SetCommand::new(self.client).txn_expire_if_needed(txn, &key)?;
-                        }
-                        DataType::List => {
-                            ListCommand::new(self.client).txn_expire_if_needed(txn, &key)?;
-                        }
-                        DataType::Hash => {
-                            HashCommand::new(self.client).txn_expire_if_needed(txn, &key)?;
-                        }
-                        DataType::Zset => {
-                            ZsetCommand::new(self.client).txn_expire_if_needed(txn, &key)?;
-                        }
-                        _ => {}
-                    }
-                    return Ok(resp_int(-2));
+        let ekey = KeyEncoder::encode_string(key);
+
+        let resp = client.exec_txn(|txn| {
+            let val = match self.txn_get_string(txn, key, &ekey, timestamp.is_some())? {
+                Some(val) => val,
+                None => return Ok(None),
+            };
+            let data = KeyDecoder::decode_key_string_value(&val);
+            match timestamp {
+                // expire time in the past, delete the key
+                Some(ts) if ts > 0 && ts <= now_timestamp_in_millis() => {
+                    txn.del(cfs.meta_cf.clone(), ekey.clone())?;
+                    txn.notify(NOTIFY_GENERIC, "del", key);
                 }
-                if ttl == 0 {
-                    Ok(resp_int(-1))
-                } else {
-                    let mut ttl = ttl_from_timestamp(ttl);
-                    if !is_millis {
-                        ttl /= 1000;
-                    }
-                    Ok(resp_int(ttl))
+                Some(ts) => {
+                    let eval = KeyEncoder::encode_string_slice(&data, ts);
+                    txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+                    let event = if ts > 0 { "expire" } else { "persist" };
+                    txn.notify(NOTIFY_GENERIC, event, key);
                 }
+                None => {}
             }
-            None => Ok(resp_int(-2)),
-        })
+            Ok(Some(data))
+        });
+
+        match resp {
+            Ok(Some(data)) => Ok(resp_bulk(data)),
+            Ok(None) => Ok(resp_nil()),
+            Err(e) => Ok(resp_err(e)),
+        }
     }
 
-    pub async fn del(self, keys: &Vec<String>) -> RocksResult<Frame> {
+    pub async fn msetnx(self, keys: &[String], vals: &[Bytes]) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
-        let keys = keys.to_owned();
-        let resp = client.exec_txn(|txn| {
-            let ekeys = KeyEncoder::encode_strings(&keys);
-            let ekey_map: HashMap<Key, String> = ekeys.clone().into_iter().zip(keys).collect();
-            let cf = cfs.meta_cf.clone();
-            let pairs = txn.batch_get(cf, ekeys.clone())?;
-            let dts: HashMap<Key, DataType> = pairs
-                .into_iter()
-                .map(|pair| (pair.0, KeyDecoder::decode_key_type(pair.1.as_slice())))
-                .collect();
+        let ekeys = KeyEncoder::encode_strings(keys);
 
-            let mut resp = 0;
-            for ekey in ekeys {
-                match dts.get(&ekey) {
-                    Some(DataType::String) => {
-                        txn.del(cfs.meta_cf.clone(), ekey.clone())?;
-                        resp += 1;
-                    }
-                    Some(DataType::Set) => {
-                        SetCommand::new(self.client).txn_del(txn, &ekey_map[&ekey])?;
-                        resp += 1;
-                    }
-                    Some(DataType::List) => {
-                        ListCommand::new(self.client).txn_del(txn, &ekey_map[&ekey])?;
-                        resp += 1;
-                    }
-                    Some(DataType::Hash) => {
-                        HashCommand::new(self.client).txn_del(txn, &ekey_map[&ekey])?;
-                        resp += 1;
-                    }
-                    Some(DataType::Zset) => {
-                        ZsetCommand::new(self.client).txn_del(txn, &ekey_map[&ekey])?;
-                        resp += 1;
+        let resp = client.exec_txn(|txn| {
+            for (key, ekey) in keys.iter().zip(ekeys.iter()) {
+                if let Some(meta_value) = txn.get_for_update(cfs.meta_cf.clone(), ekey.clone())? {
+                    let ttl = KeyDecoder::decode_key_ttl(&meta_value);
+                    if !key_is_expired(ttl) {
+                        return Ok(0);
                     }
-                    _ => {}
+                    self.txn_expire_any(txn, key.as_bytes(), ekey, &meta_value)?;
                 }
             }
-            Ok(resp)
+            for ((key, ekey), val) in keys.iter().zip(ekeys.iter()).zip(vals) {
+                let eval = KeyEncoder::encode_string_slice(val, 0);
+                txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+                txn.notify(NOTIFY_STRING, "set", key);
+            }
+            Ok(1)
         });
+
         match resp {
-            Ok(v) => Ok(resp_int(v)),
+            Ok(n) => Ok(resp_int(n)),
             Err(e) => Ok(resp_err(e)),
         }
     }
 
-    pub async fn keys(self, regex: &str) -> RocksResult<Frame> {
+    pub async fn setbit(self, key: &str, offset: u64, on: bool) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
-        let ekey = KeyEncoder::encode_string("");
-        let re = Pattern::new(regex).unwrap();
-
-        client.exec_txn(|txn| {
-            let mut keys = vec![];
-            let mut last_round_iter_count = 1;
-
-            let mut left_bound = ekey.clone();
+        let ekey = KeyEncoder::encode_string(key);
 
-            loop {
-                if last_round_iter_count == 0 {
-                    break;
-                }
-                let range = left_bound.clone()..KeyEncoder::encode_keyspace_end();
-                let bound_range: BoundRange = range.into();
+        let resp = client.exec_txn(|txn| {
+            let (mut data, ttl) = match self.txn_get_string(txn, key, &ekey, true)? {
+                Some(val) => (
+                    KeyDecoder::decode_key_string_value(&val),
+                    KeyDecoder::decode_key_ttl(&val),
+                ),
+                None => (vec![], 0),
+            };
+            let old = bitmap::set_bit(&mut data, offset, on);
+            let eval = KeyEncoder::encode_string_slice(&data, ttl);
+            txn.put(cfs.meta_cf, ekey.clone(), eval)?;
+            txn.notify(NOTIFY_STRING, "setbit", key);
+            Ok(old as i64)
+        });
 
-                let iter = txn.scan(cfs.meta_cf.clone(), bound_range, 100)?;
-                // reset count to zero
-                last_round_iter_count = 0;
-                for kv in iter {
-                    // skip the left bound key, this should be exclusive
-                    if kv.0 == left_bound {
-                        continue;
-                    }
-                    left_bound = kv.0.clone();
-                    // left bound key is exclusive
-                    last_round_iter_count += 1;
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
 
-                    let (userkey, is_meta_key) = KeyDecoder::decode_key_userkey_from_metakey(&kv.0);
+    pub async fn getbit(self, key: &str, offset: u64) -> RocksResult<Frame> {
+        let ekey = KeyEncoder::encode_string(key);
+        let resp =
+            self.client
+                .exec_txn(|txn| match self.txn_get_string(txn, key, &ekey, false)? {
+                    Some(val) => Ok(bitmap::get_bit(
+                        KeyDecoder::decode_key_string_slice(&val),
+                        offset,
+                    ) as i64),
+                    None => Ok(0),
+                });
 
-                    // skip it if it is not a meta key
-                    if !is_meta_key {
-                        continue;
-                    }
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
 
-                    let ttl = KeyDecoder::decode_key_ttl(&kv.1);
-                    // delete it if it is expired
-                    if key_is_expired(ttl) {
-                        continue;
-                    }
-                    if re.matches(str::from_utf8(&userkey).unwrap()) {
-                        keys.push(resp_bulk(userkey));
+    /// Count the set bits of the value, the range is in bits if `is_bit` is true,
+    /// otherwise in bytes.
+    pub async fn bitcount(
+        self,
+        key: &str,
+        range: Option<(i64, i64)>,
+        is_bit: bool,
+    ) -> RocksResult<Frame> {
+        let ekey = KeyEncoder::encode_string(key);
+        let resp = self.client.exec_txn(|txn| {
+            let val = match self.txn_get_string(txn, key, &ekey, false)? {
+                Some(val) => val,
+                None => return Ok(0),
+            };
+            let data = KeyDecoder::decode_key_string_slice(&val);
+            let byte_len = data.len() as i64;
+            let (start, end) = match range {
+                Some((start, end)) if is_bit => {
+                    match bitmap::normalize_range(start, end, byte_len * 8) {
+                        Some(range) => range,
+                        None => return Ok(0),
                     }
                 }
+                Some((start, end)) => match bitmap::normalize_range(start, end, byte_len) {
+                    Some((start, end)) => (start * 8, end * 8 + 7),
+                    None => return Ok(0),
+                },
+                None if byte_len == 0 => return Ok(0),
+                None => (0, byte_len as u64 * 8 - 1),
+            };
+            Ok(bitmap::count_bits(data, start, end))
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Return the position of the first bit set to 1 or 0 in the value, the range is in
+    /// bits if `is_bit` is true, otherwise in bytes.
+    pub async fn bitpos(
+        self,
+        key: &str,
+        bit: bool,
+        start: Option<i64>,
+        end: Option<i64>,
+        is_bit: bool,
+    ) -> RocksResult<Frame> {
+        let ekey = KeyEncoder::encode_string(key);
+        let resp = self.client.exec_txn(|txn| {
+            let val = match self.txn_get_string(txn, key, &ekey, false)? {
+                Some(val) => val,
+                // missing key is regarded as an empty string
+                None => return Ok(if bit { -1 } else { 0 }),
+            };
+            let data = KeyDecoder::decode_key_string_slice(&val);
+            let byte_len = data.len() as i64;
+            let end_given = end.is_some();
+            let len = if is_bit { byte_len * 8 } else { byte_len };
+            let (start, end) =
+                match bitmap::normalize_range(start.unwrap_or(0), end.unwrap_or(-1), len) {
+                    Some((start, end)) if is_bit => (start, end),
+                    Some((start, end)) => (start * 8, end * 8 + 7),
+                    None => return Ok(-1),
+                };
+            match bitmap::find_bit(data, bit, start, end) {
+                Some(pos) => Ok(pos as i64),
+                // the right of the value is regarded as zero padded if no end is given
+                None if !bit && !end_given => Ok(end as i64 + 1),
+                None => Ok(-1),
             }
-            Ok(resp_array(keys))
-        })
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
     }
 
-    pub async fn scan(self, start: &str, count: u32, regex: &str) -> RocksResult<Frame> {
+    /// Apply the bitwise operation between `keys` and store the result in `dest`,
+    /// returns the length of the result.
+    pub async fn bitop(self, op: BitOperation, dest: &str, keys: &[String]) -> RocksResult<Frame> {
         let client = self.client;
         let cfs = StringCF::new(client);
-        let ekey = KeyEncoder::encode_string(start);
-        let re = Regex::new(regex).unwrap();
+        let ekeys = KeyEncoder::encode_strings(keys);
+        let dest_ekey = KeyEncoder::encode_string(dest);
 
-        client.exec_txn(|txn| {
-            let mut keys = vec![];
-            let mut retrieved_key_count = 0;
-            let mut next_key = vec![];
+        let resp = client.exec_txn(|txn| {
+            let mut values = Vec::with_capacity(keys.len());
+            for (key, ekey) in keys.iter().zip(ekeys.iter()) {
+                let data = self
+                    .txn_get_string(txn, key, ekey, false)?
+                    .map_or_else(Vec::new, |val| KeyDecoder::decode_key_string_value(&val));
+                values.push(data);
+            }
+            let result = bitmap::bitop(op, &values);
 
-            let mut left_bound = ekey.clone();
+            let dest_meta = txn.get_for_update(cfs.meta_cf.clone(), dest_ekey.clone())?;
+            if let Some(meta_value) = &dest_meta {
+                self.txn_del_any(txn, dest.as_bytes(), &dest_ekey, meta_value)?;
+            }
+            if !result.is_empty() {
+                let eval = KeyEncoder::encode_string_slice(&result, 0);
+                txn.put(cfs.meta_cf.clone(), dest_ekey.clone(), eval)?;
+                txn.notify(NOTIFY_STRING, "set", dest);
+            } else if dest_meta.is_some() {
+                txn.notify(NOTIFY_GENERIC, "del", dest);
+            }
+            Ok(result.len() as i64)
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Execute the bitfield operations in order, the value is only saved if it is changed.
+    pub async fn bitfield(self, key: &str, ops: &[BitfieldOp]) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(key);
+        let read_only = ops.iter().all(|op| matches!(op, BitfieldOp::Get { .. }));
+
+        let resp = client.exec_txn(|txn| {
+            let (mut data, ttl) = match self.txn_get_string(txn, key, &ekey, !read_only)? {
+                Some(val) => (
+                    KeyDecoder::decode_key_string_value(&val),
+                    KeyDecoder::decode_key_ttl(&val),
+                ),
+                None => (vec![], 0),
+            };
+
+            let mut overflow = BitfieldOverflow::Wrap;
+            let mut changed = false;
+            let mut results = vec![];
+            for op in ops {
+                match *op {
+                    BitfieldOp::Get { ty, offset } => {
+                        results.push(resp_int(ty.get(&data, offset)));
+                    }
+                    BitfieldOp::Set { ty, offset, value } => {
+                        let old = ty.get(&data, offset);
+                        match bitmap::handle_overflow(value as i128, ty.signed, ty.bits, overflow) {
+                            Some(value) => {
+                                bitmap::set_unsigned(&mut data, offset, ty.bits, value as u64);
+                                changed = true;
+                                results.push(resp_int(old));
+                            }
+                            None => results.push(resp_nil()),
+                        }
+                    }
+                    BitfieldOp::Incrby {
+                        ty,
+                        offset,
+                        increment,
+                    } => {
+                        let old = ty.get(&data, offset);
+                        let value = old as i128 + increment as i128;
+                        match bitmap::handle_overflow(value, ty.signed, ty.bits, overflow) {
+                            Some(value) => {
+                                bitmap::set_unsigned(&mut data, offset, ty.bits, value as u64);
+                                changed = true;
+                                results.push(resp_int(value));
+                            }
+                            None => results.push(resp_nil()),
+                        }
+                    }
+                    BitfieldOp::Overflow(o) => overflow = o,
+                }
+            }
+
+            if changed {
+                let eval = KeyEncoder::encode_string_slice(&data, ttl);
+                txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+                txn.notify(NOTIFY_STRING, "setbit", key);
+            }
+            Ok(results)
+        });
+
+        match resp {
+            Ok(results) => Ok(resp_array(results)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Add the elements to the HyperLogLog, returns 1 if the HyperLogLog is created or
+    /// any internal register is altered.
+    pub async fn pfadd(self, key: &str, elements: &[Bytes]) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(key);
+
+        let resp = client.exec_txn(|txn| {
+            let (mut hll, ttl, created) = match self.txn_get_string(txn, key, &ekey, true)? {
+                Some(val) => (
+                    HyperLogLog::decode(KeyDecoder::decode_key_string_slice(&val))?,
+                    KeyDecoder::decode_key_ttl(&val),
+                    false,
+                ),
+                None => (HyperLogLog::new(), 0, true),
+            };
+            let mut updated = false;
+            for ele in elements {
+                if hll.add(ele) {
+                    updated = true;
+                }
+            }
+            if !updated && !created {
+                return Ok(0);
+            }
+            let eval = KeyEncoder::encode_string_slice(&hll.encode(), ttl);
+            txn.put(cfs.meta_cf, ekey.clone(), eval)?;
+            txn.notify(NOTIFY_STRING, "pfadd", key);
+            Ok(1)
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Return the approximated cardinality of the union of the HyperLogLogs, the
+    /// cardinality is cached in the value if only one key is given.
+    pub async fn pfcount(self, keys: &[String]) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekeys = KeyEncoder::encode_strings(keys);
+
+        let resp = client.exec_txn(|txn| {
+            if keys.len() == 1 {
+                let (key, ekey) = (&keys[0], &ekeys[0]);
+                let val = match self.txn_get_string(txn, key, ekey, true)? {
+                    Some(val) => val,
+                    None => return Ok(0),
+                };
+                let mut hll = HyperLogLog::decode(KeyDecoder::decode_key_string_slice(&val))?;
+                let cached = hll.is_count_cached();
+                let card = hll.count();
+                // update the cached cardinality
+                if !cached {
+                    let ttl = KeyDecoder::decode_key_ttl(&val);
+                    let eval = KeyEncoder::encode_string_slice(&hll.encode(), ttl);
+                    txn.put(cfs.meta_cf.clone(), ekey.clone(), eval)?;
+                }
+                return Ok(card as i64);
+            }
+
+            let mut merged = HyperLogLog::new();
+            for (key, ekey) in keys.iter().zip(ekeys.iter()) {
+                if let Some(val) = self.txn_get_string(txn, key, ekey, false)? {
+                    let hll = HyperLogLog::decode(KeyDecoder::decode_key_string_slice(&val))?;
+                    merged.merge(&hll);
+                }
+            }
+            Ok(merged.count() as i64)
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Merge the HyperLogLogs of `keys` into `dest`, `dest` is also merged if it exists.
+    pub async fn pfmerge(self, dest: &str, keys: &[String]) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekeys = KeyEncoder::encode_strings(keys);
+        let dest_ekey = KeyEncoder::encode_string(dest);
+
+        let resp = client.exec_txn(|txn| {
+            let (mut merged, ttl) = match self.txn_get_string(txn, dest, &dest_ekey, true)? {
+                Some(val) => (
+                    HyperLogLog::decode(KeyDecoder::decode_key_string_slice(&val))?,
+                    KeyDecoder::decode_key_ttl(&val),
+                ),
+                None => (HyperLogLog::new(), 0),
+            };
+            for (key, ekey) in keys.iter().zip(ekeys.iter()) {
+                if let Some(val) = self.txn_get_string(txn, key, ekey, false)? {
+                    let hll = HyperLogLog::decode(KeyDecoder::decode_key_string_slice(&val))?;
+                    merged.merge(&hll);
+                }
+            }
+            merged.set_dense();
+            let eval = KeyEncoder::encode_string_slice(&merged.encode(), ttl);
+            txn.put(cfs.meta_cf.clone(), dest_ekey.clone(), eval)?;
+            txn.notify(NOTIFY_STRING, "pfadd", dest);
+            Ok(())
+        });
+
+        match resp {
+            Ok(_) => Ok(resp_ok()),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    pub async fn exists(self, keys: &[String]) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekeys = KeyEncoder::encode_strings(keys);
+        let result = client.batch_get(cfs.meta_cf.clone(), ekeys.clone())?;
+        let ret: HashMap<Key, Value> = result.into_iter().map(|pair| (pair.0, pair.1)).collect();
+        let mut nums = 0;
+        for (k, key) in ekeys.into_iter().zip(keys) {
+            let data = ret.get(&k);
+            if let Some(val) = data {
+                // ttl saved in milliseconds
+                let ttl = KeyDecoder::decode_key_ttl(val);
+                if key_is_expired(ttl) {
+                    self.del_expired(key.as_bytes(), k)?;
+                } else {
+                    nums += 1;
+                }
+            }
+        }
+        Ok(resp_int(nums as i64))
+    }
+
+    pub async fn incr(self, key: &str, step: i64) -> RocksResult<Frame> {
+        let resp = self.txn_incr(key, "incrby", |prev| {
+            let prev_int = match prev {
+                Some(prev) => str::from_utf8(prev)
+                    .map_err(RError::is_not_integer_error)?
+                    .parse::<i64>()?,
+                None => 0,
+            };
+            let new_int = prev_int
+                .checked_add(step)
+                .ok_or(REDIS_INCR_OR_DECR_OVERFLOW_ERR)?;
+            Ok((new_int.to_string().into_bytes(), new_int))
+        });
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    pub async fn incr_by_float(self, key: &str, step: f64) -> RocksResult<Frame> {
+        let resp = self.txn_incr(key, "incrbyfloat", |prev| {
+            let new_val = incr_float(prev, step)?;
+            Ok((new_val.clone(), new_val))
+        });
+        match resp {
+            Ok(val) => Ok(resp_bulk(val)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Set the expire `timestamp` in milliseconds of the key with any type, an expire time
+    /// in the past deletes the key. The ttl is only updated if `condition` is satisfied.
+    pub async fn expire(
+        self,
+        key: &str,
+        timestamp: i64,
+        condition: Option<ExpireCondition>,
+    ) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(key);
+        let resp = client.exec_txn(|txn| {
+            match txn.get_for_update(cfs.meta_cf.clone(), ekey.clone())? {
+                Some(meta_value) => {
+                    let ttl = KeyDecoder::decode_key_ttl(&meta_value);
+                    // check key expired
+                    if key_is_expired(ttl) {
+                        self.txn_expire_any(txn, key.as_bytes(), &ekey, &meta_value)?;
+                        return Ok(0);
+                    }
+                    if let Some(condition) = condition {
+                        if !condition.check(ttl, timestamp) {
+                            return Ok(0);
+                        }
+                    }
+                    if timestamp <= now_timestamp_in_millis() {
+                        self.txn_del_any(txn, key.as_bytes(), &ekey, &meta_value)?;
+                        txn.notify(NOTIFY_GENERIC, "del", key);
+                        return Ok(1);
+                    }
+                    let n =
+                        self.txn_expire_with_type(txn, key.as_bytes(), timestamp, &meta_value)?;
+                    if n > 0 {
+                        txn.notify(NOTIFY_GENERIC, "expire", key);
+                    }
+                    Ok(n)
+                }
+                None => Ok(0),
+            }
+        });
+        match resp {
+            Ok(v) => Ok(resp_int(v)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Remove the existing time to live of the key with any type.
+    pub async fn persist(self, key: &str) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(key);
+        let resp =
+            client.exec_txn(
+                |txn| match txn.get_for_update(cfs.meta_cf.clone(), ekey.clone())? {
+                    Some(meta_value) => {
+                        let ttl = KeyDecoder::decode_key_ttl(&meta_value);
+                        if key_is_expired(ttl) {
+                            self.txn_expire_any(txn, key.as_bytes(), &ekey, &meta_value)?;
+                            return Ok(0);
+                        }
+                        if ttl <= 0 {
+                            return Ok(0);
+                        }
+                        let n = self.txn_expire_with_type(txn, key.as_bytes(), 0, &meta_value)?;
+                        if n > 0 {
+                            txn.notify(NOTIFY_GENERIC, "persist", key);
+                        }
+                        Ok(n)
+                    }
+                    None => Ok(0),
+                },
+            );
+        match resp {
+            Ok(v) => Ok(resp_int(v)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Get the remaining time to live of the key, or the absolute unix expire time
+    /// if `absolute` is true.
+    pub async fn ttl(self, key: &str, is_millis: bool, absolute: bool) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let key = key.to_owned();
+        let ekey = KeyEncoder::encode_string(&key);
+        client.exec_txn(|txn| match txn.get(cfs.meta_cf.clone(), ekey.clone())? {
+            Some(meta_value) => {
+                let ttl = KeyDecoder::decode_key_ttl(&meta_value);
+                if key_is_expired(ttl) {
+                    self.txn_expire_any(txn, key.as_bytes(), &ekey, &meta_value)?;
+                    return Ok(resp_int(-2));
+                }
+                // SET without expire time saves -1 as ttl
+                if ttl <= 0 {
+                    Ok(resp_int(-1))
+                } else {
+                    let mut ttl = if absolute {
+                        ttl
+                    } else {
+                        ttl_from_timestamp(ttl)
+                    };
+                    if !is_millis {
+                        ttl /= 1000;
+                    }
+                    Ok(resp_int(ttl))
+                }
+            }
+            None => Ok(resp_int(-2)),
+        })
+    }
+
+    pub async fn del(self, keys: &Vec<String>) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let keys = keys.to_owned();
+        let resp = client.exec_txn(|txn| {
+            let ekeys = KeyEncoder::encode_strings(&keys);
+            let ekey_map: HashMap<Key, String> = ekeys.clone().into_iter().zip(keys).collect();
+            let cf = cfs.meta_cf.clone();
+            let pairs = txn.batch_get(cf, ekeys.clone())?;
+            let dts: HashMap<Key, DataType> = pairs
+                .into_iter()
+                .map(|pair| (pair.0, KeyDecoder::decode_key_type(pair.1.as_slice())))
+                .collect();
+
+            let mut resp = 0;
+            for ekey in ekeys {
+                let key = &ekey_map[&ekey];
+                match dts.get(&ekey) {
+                    Some(DataType::String) => {
+                        txn.del(cfs.meta_cf.clone(), ekey.clone())?;
+                        txn.notify(NOTIFY_GENERIC, "del", key);
+                        resp += 1;
+                    }
+                    Some(DataType::Set) => {
+                        SetCommand::new(self.client).txn_del(txn, key.as_bytes())?;
+                        txn.notify(NOTIFY_GENERIC, "del", key);
+                        resp += 1;
+                    }
+                    Some(DataType::List) => {
+                        ListCommand::new(self.client).txn_del(txn, key.as_bytes())?;
+                        txn.notify(NOTIFY_GENERIC, "del", key);
+                        resp += 1;
+                    }
+                    Some(DataType::Hash) => {
+                        HashCommand::new(self.client).txn_del(txn, key.as_bytes())?;
+                        txn.notify(NOTIFY_GENERIC, "del", key);
+                        resp += 1;
+                    }
+                    Some(DataType::Zset) => {
+                        ZsetCommand::new(self.client).txn_del(txn, key.as_bytes())?;
+                        txn.notify(NOTIFY_GENERIC, "del", key);
+                        resp += 1;
+                    }
+                    _ => {}
+                }
+            }
+            Ok(resp)
+        });
+        match resp {
+            Ok(v) => Ok(resp_int(v)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Rename `key` of any type to `newkey` with the ttl kept, `newkey` is overwritten if it
+    /// exists, unless `nx` is set.
+    pub async fn rename(self, key: &str, newkey: &str, nx: bool) -> RocksResult<Frame> {
+        let client = self.client;
+        let ekey = KeyEncoder::encode_string(key);
+        let resp = client.exec_txn(|txn| {
+            let meta_value = match self.txn_get_alive(txn, key.as_bytes(), &ekey)? {
+                Some(meta_value) => meta_value,
+                None => return Err(REDIS_NO_SUCH_KEY_ERR),
+            };
+            if key == newkey {
+                return Ok(!nx);
+            }
+            if !self.txn_prepare_dest(txn, newkey.as_bytes(), !nx)? {
+                return Ok(false);
+            }
+            self.txn_copy_any(txn, key.as_bytes(), newkey.as_bytes(), &meta_value)?;
+            // large collections are deleted asynchronously
+            self.txn_del_any(txn, key.as_bytes(), &ekey, &meta_value)?;
+            txn.notify(NOTIFY_GENERIC, "rename_from", key);
+            txn.notify(NOTIFY_GENERIC, "rename_to", newkey);
+            Ok(true)
+        });
+
+        match resp {
+            Ok(renamed) if nx => Ok(resp_int(renamed as i64)),
+            Ok(_) => Ok(resp_ok()),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Copy `src` of any type to `dest` with the ttl kept, `dest` is overwritten only if
+    /// `replace` is set.
+    pub async fn copy(self, src: &str, dest: &str, replace: bool) -> RocksResult<Frame> {
+        if src == dest {
+            return Ok(resp_err(REDIS_SAME_OBJECT_ERR));
+        }
+        let client = self.client;
+        let ekey = KeyEncoder::encode_string(src);
+        let resp = client.exec_txn(|txn| {
+            let meta_value = match self.txn_get_alive(txn, src.as_bytes(), &ekey)? {
+                Some(meta_value) => meta_value,
+                None => return Ok(0),
+            };
+            if !self.txn_prepare_dest(txn, dest.as_bytes(), replace)? {
+                return Ok(0);
+            }
+            self.txn_copy_any(txn, src.as_bytes(), dest.as_bytes(), &meta_value)?;
+            txn.notify(NOTIFY_GENERIC, "copy_to", dest);
+            Ok(1)
+        });
+
+        match resp {
+            Ok(n) => Ok(resp_int(n)),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Return a random key. The key is picked by seeking to a random position between the
+    /// first and the last key, so the keys are not returned with the same probability.
+    pub async fn randomkey(self) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let resp = client.exec_txn(|txn| {
+            let mut rng = SmallRng::from_entropy();
+            // give up after some tries if most of the keys are expired
+            for _ in 0..RANDOMKEY_MAX_TRIES {
+                let first = match self.txn_next_meta(txn, KeyEncoder::encode_string(""))? {
+                    Some(kv) => kv.0,
+                    None => return Ok(None),
+                };
+                let last = txn
+                    .scan_keys_reverse(cfs.meta_cf.clone(), KeyEncoder::encode_keyspace_end().., 1)?
+                    .next()
+                    .unwrap_or_else(|| first.clone());
+                let low = key_prefix(&KeyDecoder::decode_key_userkey_from_metakey(&first).0);
+                let high = key_prefix(&KeyDecoder::decode_key_userkey_from_metakey(&last).0);
+                let mut target = rng.gen_range(low..=high).to_be_bytes().to_vec();
+                while target.last() == Some(&0) {
+                    target.pop();
+                }
+
+                let start = KeyEncoder::encode_meta_key_from_bytes(&target);
+                let (ekey, meta_value) = match self.txn_next_meta(txn, start)? {
+                    Some(kv) => kv,
+                    None => self.txn_next_meta(txn, first)?.unwrap(),
+                };
+                let key = KeyDecoder::decode_key_userkey_from_metakey(&ekey).0;
+                if !key_is_expired(KeyDecoder::decode_key_ttl(&meta_value)) {
+                    return Ok(Some(key));
+                }
+                self.txn_expire_any(txn, &key, &ekey, &meta_value)?;
+            }
+            Ok(None)
+        });
+
+        match resp {
+            Ok(Some(key)) => Ok(resp_bulk(key)),
+            Ok(None) => Ok(resp_nil()),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Serialize the key of any type with its ttl, see `DumpValue` for the format.
+    pub async fn dump(self, key: &str) -> RocksResult<Frame> {
+        let client = self.client;
+        let ekey = KeyEncoder::encode_string(key);
+        let resp = client.exec_txn(
+            |txn| match self.txn_get_alive(txn, key.as_bytes(), &ekey)? {
+                Some(meta_value) => {
+                    let payload = self.txn_dump_any(txn, key.as_bytes(), &meta_value)?;
+                    let ttl = KeyDecoder::decode_key_ttl(&meta_value);
+                    Ok(Some(DumpValue::new(ttl, payload).encode()))
+                }
+                None => Ok(None),
+            },
+        );
+
+        match resp {
+            Ok(Some(value)) => Ok(resp_bulk(value)),
+            Ok(None) => Ok(resp_nil()),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Create the key from the value produced by DUMP. The key expires in `ttl` milliseconds
+    /// if it is not 0, otherwise the ttl saved in the dumped value is used.
+    pub async fn restore(
+        self,
+        key: &str,
+        ttl: i64,
+        value: &[u8],
+        replace: bool,
+    ) -> RocksResult<Frame> {
+        if ttl < 0 {
+            return Ok(resp_err(REDIS_INVALID_TTL_ERR));
+        }
+        let dump = match DumpValue::decode(value) {
+            Ok(dump) => dump,
+            Err(e) => return Ok(resp_err(e)),
+        };
+        let now = now_timestamp_in_millis();
+        let timestamp = match ttl {
+            0 => dump.ttl,
+            _ => match now.checked_add(ttl) {
+                Some(timestamp) => timestamp,
+                None => return Ok(resp_err(RError::invalid_expire_time_error("restore"))),
+            },
+        };
+
+        let resp = self.client.exec_txn(|txn| {
+            if !self.txn_prepare_dest(txn, key.as_bytes(), replace)? {
+                return Err(REDIS_BUSY_KEY_ERR);
+            }
+            // the key is already expired, nothing to create
+            if timestamp > 0 && timestamp <= now {
+                return Ok(());
+            }
+            self.txn_restore_any(txn, key.as_bytes(), timestamp, &dump.payload)?;
+            txn.notify(NOTIFY_GENERIC, "restore", key);
+            Ok(())
+        });
+
+        match resp {
+            Ok(_) => Ok(resp_ok()),
+            Err(e) => Ok(resp_err(e)),
+        }
+    }
+
+    /// Call `f` with the dumped value of every alive key, all the keys are read from the same
+    /// snapshot. Returns the number of the keys.
+    pub fn export_values<F>(&self, mut f: F) -> RocksResult<usize>
+    where
+        F: FnMut(&[u8], DumpValue) -> RocksResult<()>,
+    {
+        let cfs = StringCF::new(self.client);
+        self.client.exec_snapshot_txn(|txn| {
+            let mut count = 0;
+            let mut left_bound = KeyEncoder::encode_string("");
+            loop {
+                let range = left_bound.clone()..KeyEncoder::encode_keyspace_end();
+                let mut iter_count = 0;
+                for kv in txn.scan(cfs.meta_cf.clone(), range, DUMP_KEYS_BATCH_SIZE)? {
+                    iter_count += 1;
+                    // skip the left bound key, it is handled in the last round
+                    if kv.0 == left_bound {
+                        continue;
+                    }
+                    left_bound = kv.0.clone();
+                    if !KeyDecoder::is_meta_key(kv.0.as_ref()) {
+                        continue;
+                    }
+                    let ttl = KeyDecoder::decode_key_ttl(&kv.1);
+                    if key_is_expired(ttl) {
+                        continue;
+                    }
+                    let key = KeyDecoder::decode_key_userkey_from_metakey(&kv.0).0;
+                    let payload = self.txn_dump_any(txn, &key, &kv.1)?;
+                    f(&key, DumpValue::new(ttl, payload))?;
+                    count += 1;
+                }
+                if iter_count < DUMP_KEYS_BATCH_SIZE {
+                    return Ok(count);
+                }
+            }
+        })
+    }
+
+    /// Create the key from the dumped value, the existing key is replaced.
+    pub fn import_value(&self, key: &[u8], value: &DumpValue) -> RocksResult<()> {
+        let now = now_timestamp_in_millis();
+        self.client.exec_txn(|txn| {
+            self.txn_prepare_dest(txn, key, true)?;
+            // the key is already expired, nothing to create
+            if value.ttl > 0 && value.ttl <= now {
+                return Ok(());
+            }
+            self.txn_restore_any(txn, key, value.ttl, &value.payload)
+        })
+    }
+
+    /// Return the number of keys in O(1) by the key counter.
+    pub async fn dbsize(self) -> RocksResult<Frame> {
+        Ok(resp_int(self.client.key_count()))
+    }
+
+    pub async fn keys(self, regex: &str) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string("");
+        let re = Pattern::new(regex).unwrap();
+
+        client.exec_txn(|txn| {
+            let mut keys = vec![];
+            let mut last_round_iter_count = 1;
+
+            let mut left_bound = ekey.clone();
+
+            loop {
+                if last_round_iter_count == 0 {
+                    break;
+                }
+                let range = left_bound.clone()..KeyEncoder::encode_keyspace_end();
+                let bound_range: BoundRange = range.into();
+
+                let iter = txn.scan(cfs.meta_cf.clone(), bound_range, 100)?;
+                // reset count to zero
+                last_round_iter_count = 0;
+                for kv in iter {
+                    // skip the left bound key, this should be exclusive
+                    if kv.0 == left_bound {
+                        continue;
+                    }
+                    left_bound = kv.0.clone();
+                    // left bound key is exclusive
+                    last_round_iter_count += 1;
+
+                    let (userkey, is_meta_key) = KeyDecoder::decode_key_userkey_from_metakey(&kv.0);
+
+                    // skip it if it is not a meta key
+                    if !is_meta_key {
+                        continue;
+                    }
+
+                    let ttl = KeyDecoder::decode_key_ttl(&kv.1);
+                    // delete it if it is expired
+                    if key_is_expired(ttl) {
+                        continue;
+                    }
+                    if re.matches(&String::from_utf8_lossy(&userkey)) {
+                        keys.push(resp_bulk(userkey));
+                    }
+                }
+            }
+            Ok(resp_array(keys))
+        })
+    }
+
+    /// Return at most `count` keys in the hash slot, all the keys are iterated to find them.
+    pub fn slot_keys(&self, slot: u16, count: usize) -> RocksResult<Vec<Vec<u8>>> {
+        let cfs = StringCF::new(self.client);
+        self.client.exec_snapshot_txn(|txn| {
+            let mut keys = vec![];
+            let mut left_bound = KeyEncoder::encode_string("");
+            loop {
+                let range = left_bound.clone()..KeyEncoder::encode_keyspace_end();
+                let mut iter_count = 0;
+                for kv in txn.scan(cfs.meta_cf.clone(), range, DUMP_KEYS_BATCH_SIZE)? {
+                    iter_count += 1;
+                    // skip the left bound key, it is handled in the last round
+                    if kv.0 == left_bound {
+                        continue;
+                    }
+                    left_bound = kv.0.clone();
+                    if !KeyDecoder::is_meta_key(kv.0.as_ref()) {
+                        continue;
+                    }
+                    if key_is_expired(KeyDecoder::decode_key_ttl(&kv.1)) {
+                        continue;
+                    }
+                    let key = KeyDecoder::decode_key_userkey_from_metakey(&kv.0).0;
+                    if key_hash_slot(&key) != slot {
+                        continue;
+                    }
+                    if keys.len() == count {
+                        return Ok(keys);
+                    }
+                    keys.push(key);
+                }
+                if iter_count < DUMP_KEYS_BATCH_SIZE {
+                    return Ok(keys);
+                }
+            }
+        })
+    }
+
+    /// Return the changes creating every alive key with `prefix`, all the keys are read from
+    /// the same snapshot.
+    pub fn snapshot_changes(&self, prefix: &[u8]) -> RocksResult<Vec<ChangeEvent>> {
+        let cfs = StringCF::new(self.client);
+        self.client.exec_snapshot_txn(|txn| {
+            let mut changes = vec![];
+            let mut left_bound = KeyEncoder::encode_string("");
+            loop {
+                let range = left_bound.clone()..KeyEncoder::encode_keyspace_end();
+                let mut iter_count = 0;
+                for kv in txn.scan(cfs.meta_cf.clone(), range, DUMP_KEYS_BATCH_SIZE)? {
+                    iter_count += 1;
+                    // skip the left bound key, it is handled in the last round
+                    if kv.0 == left_bound {
+                        continue;
+                    }
+                    left_bound = kv.0.clone();
+                    if !KeyDecoder::is_meta_key(kv.0.as_ref()) {
+                        continue;
+                    }
+                    if key_is_expired(KeyDecoder::decode_key_ttl(&kv.1)) {
+                        continue;
+                    }
+                    let key = KeyDecoder::decode_key_userkey_from_metakey(&kv.0).0;
+                    if !key.starts_with(prefix) {
+                        continue;
+                    }
+                    // the elements of a collection are in its data column family
+                    let data = match KeyDecoder::decode_key_type(&kv.1) {
+                        DataType::Hash => Some((CF_NAME_HASH_DATA, DATA_TYPE_HASH)),
+                        DataType::List => Some((CF_NAME_LIST_DATA, DATA_TYPE_LIST)),
+                        DataType::Set => Some((CF_NAME_SET_DATA, DATA_TYPE_SET)),
+                        DataType::Zset => Some((CF_NAME_ZSET_DATA, DATA_TYPE_ZSET)),
+                        _ => None,
+                    };
+                    let version = KeyDecoder::decode_key_version(&kv.1);
+                    changes.push(RawChange {
+                        key: kv.0.into(),
+                        value: Some(kv.1),
+                        old_meta: None,
+                    });
+                    let Some((cf_name, key_type)) = data else {
+                        continue;
+                    };
+                    let range = KeyEncoder::encode_type_data_key_range(&key, key_type, version);
+                    let data_cf = self.client.cf_handle(cf_name)?;
+                    for data in txn.scan(data_cf, range, u32::MAX)? {
+                        changes.push(RawChange {
+                            key: data.0.into(),
+                            value: Some(data.1),
+                            old_meta: None,
+                        });
+                    }
+                }
+                if iter_count < DUMP_KEYS_BATCH_SIZE {
+                    return Ok(decode_changes(changes));
+                }
+            }
+        })
+    }
+
+    pub async fn scan(self, start: &str, count: u32, regex: &str) -> RocksResult<Frame> {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(start);
+        let re = Regex::new(regex).unwrap();
+
+        client.exec_txn(|txn| {
+            let mut keys = vec![];
+            let mut retrieved_key_count = 0;
+            let mut next_key = vec![];
+
+            let mut left_bound = ekey.clone();
 
             // set to a non-zore value before loop
             let mut last_round_iter_count = 1;
@@ -545,9 +1508,253 @@ impl<'a> StringCommand<'a> {
         })
     }
 
-    fn txn_expire_if_needed(
+    /// Update the string value of the key with `f` in txn, `f` accepts the current value
+    /// of the key and returns the new value to save and the result. The ttl is retained.
+    fn txn_incr<T, F>(&self, key: &str, event: &'static str, f: F) -> RocksResult<T>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(Option<&[u8]>) -> RocksResult<(Vec<u8>, T)>,
+    {
+        let client = self.client;
+        let cfs = StringCF::new(client);
+        let ekey = KeyEncoder::encode_string(key);
+
+        client.exec_txn(|txn| {
+            let meta_value = self.txn_get_string(txn, key, &ekey, true)?;
+            let (new_val, ret) = f(meta_value
+                .as_deref()
+                .map(KeyDecoder::decode_key_string_slice))?;
+            let ttl = meta_value.as_deref().map_or(0, KeyDecoder::decode_key_ttl);
+            let eval = KeyEncoder::encode_string_slice(&new_val, ttl);
+            txn.put(cfs.meta_cf, ekey, eval)?;
+            txn.notify(NOTIFY_STRING, event, key);
+            Ok(ret)
+        })
+    }
+
+    /// Get the string meta value of `ekey` in txn, `None` is returned if the key does not
+    /// exist or is already expired. An expired key of any type is removed in the same txn.
+    fn txn_get_string(
+        &self,
+        txn: &RocksTransaction,
+        key: &str,
+        ekey: &Key,
+        for_update: bool,
+    ) -> RocksResult<Option<Value>> {
+        let cfs = StringCF::new(self.client);
+        let meta_value = if for_update {
+            txn.get_for_update(cfs.meta_cf, ekey.clone())?
+        } else {
+            txn.get(cfs.meta_cf, ekey.clone())?
+        };
+        match meta_value {
+            Some(meta_value) => {
+                let ttl = KeyDecoder::decode_key_ttl(&meta_value);
+                if key_is_expired(ttl) {
+                    self.txn_expire_any(txn, key.as_bytes(), ekey, &meta_value)?;
+                    return Ok(None);
+                }
+                if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::String) {
+                    return Err(REDIS_WRONG_TYPE_ERR);
+                }
+                Ok(Some(meta_value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Delete the key of any type in txn, returns true if the key existed.
+    pub(crate) fn txn_del_key(&self, txn: &RocksTransaction, key: &str) -> RocksResult<bool> {
+        let cfs = StringCF::new(self.client);
+        let ekey = KeyEncoder::encode_string(key);
+        match txn.get_for_update(cfs.meta_cf, ekey.clone())? {
+            Some(meta_value) => {
+                self.txn_del_any(txn, key.as_bytes(), &ekey, &meta_value)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Get the meta value of the key of any type for update, `None` is returned if the key
+    /// does not exist or is expired.
+    fn txn_get_alive(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        ekey: &Key,
+    ) -> RocksResult<Option<Value>> {
+        let cfs = StringCF::new(self.client);
+        match txn.get_for_update(cfs.meta_cf, ekey.clone())? {
+            Some(meta_value) => {
+                if key_is_expired(KeyDecoder::decode_key_ttl(&meta_value)) {
+                    self.txn_expire_any(txn, key, ekey, &meta_value)?;
+                    return Ok(None);
+                }
+                Ok(Some(meta_value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Make sure `dest` does not exist before copying to it, the existing `dest` is only
+    /// deleted if `replace` is set. Returns false if `dest` exists and is kept.
+    fn txn_prepare_dest(
+        &self,
+        txn: &RocksTransaction,
+        dest: &[u8],
+        replace: bool,
+    ) -> RocksResult<bool> {
+        let ekey = KeyEncoder::encode_string(dest);
+        match self.txn_get_alive(txn, dest, &ekey)? {
+            Some(_) if !replace => Ok(false),
+            Some(meta_value) => {
+                self.txn_del_any(txn, dest, &ekey, &meta_value)?;
+                Ok(true)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Return the first meta key and value from `start`.
+    fn txn_next_meta(
+        &self,
+        txn: &RocksTransaction,
+        start: Key,
+    ) -> RocksResult<Option<(Key, Value)>> {
+        let cfs = StringCF::new(self.client);
+        let range = start..KeyEncoder::encode_keyspace_end();
+        Ok(txn
+            .scan(cfs.meta_cf, range, 1)?
+            .next()
+            .map(|kv| (kv.0, kv.1)))
+    }
+
+    /// Copy the key of any type to `dest` in txn.
+    fn txn_copy_any(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        dest: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<()> {
+        match KeyDecoder::decode_key_type(meta_value) {
+            DataType::String => self.txn_copy(txn, key, dest, meta_value),
+            DataType::Set => SetCommand::new(self.client).txn_copy(txn, key, dest, meta_value),
+            DataType::List => ListCommand::new(self.client).txn_copy(txn, key, dest, meta_value),
+            DataType::Hash => HashCommand::new(self.client).txn_copy(txn, key, dest, meta_value),
+            DataType::Zset => ZsetCommand::new(self.client).txn_copy(txn, key, dest, meta_value),
+            DataType::Null => Ok(()),
+        }
+    }
+
+    fn txn_dump_any(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<DumpPayload> {
+        match KeyDecoder::decode_key_type(meta_value) {
+            DataType::String => self.txn_dump(txn, key, meta_value),
+            DataType::Set => SetCommand::new(self.client).txn_dump(txn, key, meta_value),
+            DataType::List => ListCommand::new(self.client).txn_dump(txn, key, meta_value),
+            DataType::Hash => HashCommand::new(self.client).txn_dump(txn, key, meta_value),
+            DataType::Zset => ZsetCommand::new(self.client).txn_dump(txn, key, meta_value),
+            DataType::Null => Err(REDIS_WRONG_TYPE_ERR),
+        }
+    }
+
+    fn txn_restore_any(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        payload: &DumpPayload,
+    ) -> RocksResult<()> {
+        match payload {
+            DumpPayload::String(_) => self.txn_restore(txn, key, timestamp, payload),
+            DumpPayload::Set(_) => {
+                SetCommand::new(self.client).txn_restore(txn, key, timestamp, payload)
+            }
+            DumpPayload::List(_) => {
+                ListCommand::new(self.client).txn_restore(txn, key, timestamp, payload)
+            }
+            DumpPayload::Hash(_) => {
+                HashCommand::new(self.client).txn_restore(txn, key, timestamp, payload)
+            }
+            DumpPayload::Zset(_) => {
+                ZsetCommand::new(self.client).txn_restore(txn, key, timestamp, payload)
+            }
+        }
+    }
+
+    /// Delete the key of any type in txn.
+    fn txn_del_any(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        ekey: &Key,
+        meta_value: &Value,
+    ) -> RocksResult<()> {
+        let cfs = StringCF::new(self.client);
+        match KeyDecoder::decode_key_type(meta_value) {
+            DataType::String => txn.del(cfs.meta_cf, ekey.to_owned()),
+            DataType::Set => SetCommand::new(self.client).txn_del(txn, key),
+            DataType::List => ListCommand::new(self.client).txn_del(txn, key),
+            DataType::Hash => HashCommand::new(self.client).txn_del(txn, key),
+            DataType::Zset => ZsetCommand::new(self.client).txn_del(txn, key),
+            DataType::Null => Ok(()),
+        }
+    }
+
+    /// Update the expire timestamp of the key of any type in txn.
+    fn txn_expire_with_type(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        meta_value: &Value,
+    ) -> RocksResult<i64> {
+        match KeyDecoder::decode_key_type(meta_value) {
+            DataType::String => self.txn_expire(txn, key, timestamp, meta_value),
+            DataType::Set => {
+                SetCommand::new(self.client).txn_expire(txn, key, timestamp, meta_value)
+            }
+            DataType::List => {
+                ListCommand::new(self.client).txn_expire(txn, key, timestamp, meta_value)
+            }
+            DataType::Hash => {
+                HashCommand::new(self.client).txn_expire(txn, key, timestamp, meta_value)
+            }
+            DataType::Zset => {
+                ZsetCommand::new(self.client).txn_expire(txn, key, timestamp, meta_value)
+            }
+            DataType::Null => Ok(0),
+        }
+    }
+
+    /// Remove the key of any type if it is expired.
+    fn txn_expire_any(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        ekey: &Key,
+        meta_value: &Value,
+    ) -> RocksResult<i64> {
+        match KeyDecoder::decode_key_type(meta_value) {
+            DataType::String => self.txn_del_if_expired(txn, key, ekey, meta_value),
+            DataType::Set => SetCommand::new(self.client).txn_expire_if_needed(txn, key),
+            DataType::List => ListCommand::new(self.client).txn_expire_if_needed(txn, key),
+            DataType::Hash => HashCommand::new(self.client).txn_expire_if_needed(txn, key),
+            DataType::Zset => ZsetCommand::new(self.client).txn_expire_if_needed(txn, key),
+            DataType::Null => Ok(0),
+        }
+    }
+
+    fn txn_del_if_expired(
         &self,
         txn: &RocksTransaction,
+        key: &[u8],
         ekey: &Key,
         meta_value: &Value,
     ) -> RocksResult<i64> {
@@ -555,8 +1762,115 @@ impl<'a> StringCommand<'a> {
         let ttl = KeyDecoder::decode_key_ttl(meta_value);
         if key_is_expired(ttl) {
             txn.del(cfs.meta_cf.clone(), ekey.to_owned())?;
+            txn.notify(NOTIFY_EXPIRED, "expired", &String::from_utf8_lossy(key));
             return Ok(1);
         }
         Ok(0)
     }
+
+    /// Remove the expired key read out of txn.
+    fn del_expired(&self, key: &[u8], ekey: Key) -> RocksResult<()> {
+        let cfs = StringCF::new(self.client);
+        self.client.exec_txn(|txn| {
+            txn.del(cfs.meta_cf, ekey)?;
+            txn.notify(NOTIFY_EXPIRED, "expired", &String::from_utf8_lossy(key));
+            Ok(())
+        })
+    }
+}
+
+impl TxnCommand for StringCommand<'_> {
+    fn txn_del(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<()> {
+        let cfs = StringCF::new(self.client);
+        let ekey = KeyEncoder::encode_string(key);
+        txn.del(cfs.meta_cf, ekey)
+    }
+
+    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<i64> {
+        let cfs = StringCF::new(self.client);
+        let ekey = KeyEncoder::encode_string(key);
+        match txn.get_for_update(cfs.meta_cf, ekey.clone())? {
+            Some(meta_value) => self.txn_del_if_expired(txn, key, &ekey, &meta_value),
+            None => Ok(0),
+        }
+    }
+
+    fn txn_expire(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        meta_value: &Value,
+    ) -> RocksResult<i64> {
+        let cfs = StringCF::new(self.client);
+        let ekey = KeyEncoder::encode_string(key);
+        let ttl = KeyDecoder::decode_key_ttl(meta_value);
+        if key_is_expired(ttl) {
+            self.txn_del_if_expired(txn, key, &ekey, meta_value)?;
+            return Ok(0);
+        }
+        let value = KeyDecoder::decode_key_string_slice(meta_value);
+        let new_meta_value = KeyEncoder::encode_string_slice(value, timestamp);
+        txn.put(cfs.meta_cf, ekey, new_meta_value)?;
+        Ok(1)
+    }
+
+    // string has no data keys, nothing to gc
+    fn txn_gc(&self, _txn: &RocksTransaction, _key: &[u8], _version: u16) -> RocksResult<()> {
+        Ok(())
+    }
+
+    fn txn_copy(
+        &self,
+        txn: &RocksTransaction,
+        _key: &[u8],
+        dest: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<()> {
+        let cfs = StringCF::new(self.client);
+        let ekey = KeyEncoder::encode_string(dest);
+        txn.put(cfs.meta_cf, ekey, meta_value.to_owned())
+    }
+
+    fn txn_dump(
+        &self,
+        _txn: &RocksTransaction,
+        _key: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<DumpPayload> {
+        Ok(DumpPayload::String(KeyDecoder::decode_key_string_value(
+            meta_value,
+        )))
+    }
+
+    fn txn_restore(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        payload: &DumpPayload,
+    ) -> RocksResult<()> {
+        let value = match payload {
+            DumpPayload::String(value) => value,
+            _ => return Err(REDIS_DUMP_PAYLOAD_ERR),
+        };
+        let cfs = StringCF::new(self.client);
+        let ekey = KeyEncoder::encode_string(key);
+        txn.put(
+            cfs.meta_cf,
+            ekey,
+            KeyEncoder::encode_string_slice(value, timestamp),
+        )
+    }
+}
+
+const RANDOMKEY_MAX_TRIES: usize = 10;
+const DUMP_KEYS_BATCH_SIZE: u32 = 1000;
+
+// the first 8 bytes of the key as a big endian number, used to pick a random key
+fn key_prefix(key: &[u8]) -> u64 {
+    let mut prefix = [0; 8];
+    let len = key.len().min(8);
+    prefix[..len].copy_from_slice(&key[..len]);
+    u64::from_be_bytes(prefix)
 }
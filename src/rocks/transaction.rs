@@ -1,66 +1,245 @@
-use rocksdb::{ColumnFamilyRef, Direction, IteratorMode, Transaction, TransactionDB};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
 
-use crate::rocks::errors::TXN_ERROR;
+use crate::rocks::cdc::RawChange;
+use crate::rocks::encoding::KeyDecoder;
+use crate::rocks::engine::{ColumnFamilyRef, KvIter, StorageTxn, CF_NAME_DEFAULT};
 use crate::rocks::kv::bound_range::BoundRange;
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::kvpair::KvPair;
 use crate::rocks::kv::value::Value;
+use crate::rocks::notify::{KeyEvent, NOTIFY_NEW};
 use crate::rocks::Result as RocksResult;
 
+/// A write recorded by `RocksTransaction::record_writes`, the column family, the key and
+/// the value, which is None for a delete.
+pub type RecordedWrite = (&'static str, Vec<u8>, Option<Vec<u8>>);
+
+/// A txn of the db, it's shared by the commands executed in it, so its state is locked.
 pub struct RocksTransaction<'a> {
-    inner_txn: Transaction<'a, TransactionDB>,
+    inner_txn: Box<dyn LockedTxn + 'a>,
+    // number of user keys created minus deleted in this txn
+    key_count_delta: AtomicI64,
+    // the meta keys read for update or written in this txn and their values, the keys are
+    // locked, so the values are used to count the keys without reading them again
+    metas: Mutex<HashMap<Vec<u8>, Option<Value>>>,
+    // copy of the writes in this txn, recorded only if the writes are replicated
+    writes: Option<Mutex<Vec<RecordedWrite>>>,
+    // keyspace events of this txn, recorded only if the events are notified
+    events: Option<Mutex<Vec<KeyEvent>>>,
+    // writes of the user keys in this txn, recorded only if the changes are captured
+    changes: Option<Mutex<Vec<RawChange>>>,
+    // the txn is never committed, see `read_only`
+    read_only: bool,
 }
 
 impl<'a> RocksTransaction<'a> {
-    pub fn new(txn: Transaction<'a, TransactionDB>) -> Self {
-        Self { inner_txn: txn }
+    pub fn new(txn: Box<dyn StorageTxn + 'a>) -> Self {
+        Self {
+            inner_txn: Box::new(Mutex::new(txn)),
+            key_count_delta: AtomicI64::new(0),
+            metas: Mutex::new(HashMap::new()),
+            writes: None,
+            events: None,
+            changes: None,
+            read_only: false,
+        }
+    }
+
+    /// Make the txn read only, which never locks the keys, so it can be kept open without
+    /// blocking the other txns. The writes of the read commands, like removing the expired
+    /// keys, are dropped.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Record a copy of the writes in this txn, which can be taken by `take_writes`.
+    pub fn record_writes(mut self) -> Self {
+        self.writes = Some(Mutex::new(vec![]));
+        self
+    }
+
+    pub fn take_writes(&self) -> Option<Vec<RecordedWrite>> {
+        self.writes
+            .as_ref()
+            .map(|writes| std::mem::take(&mut *writes.lock().unwrap()))
+    }
+
+    /// Record the keyspace events in this txn, which can be taken by `take_events`.
+    pub fn record_events(mut self) -> Self {
+        self.events = Some(Mutex::new(vec![]));
+        self
+    }
+
+    pub fn take_events(&self) -> Vec<KeyEvent> {
+        self.events
+            .as_ref()
+            .map(|events| std::mem::take(&mut *events.lock().unwrap()))
+            .unwrap_or_default()
+    }
+
+    /// Record the writes of the user keys in this txn, which can be taken by `take_changes`.
+    pub fn record_changes(mut self) -> Self {
+        self.changes = Some(Mutex::new(vec![]));
+        self
+    }
+
+    pub fn take_changes(&self) -> Vec<RawChange> {
+        self.changes
+            .as_ref()
+            .map(|changes| std::mem::take(&mut *changes.lock().unwrap()))
+            .unwrap_or_default()
+    }
+
+    /// Write the default column family, the write is neither recorded nor replicated.
+    pub(crate) fn put_default(&self, key: &[u8], value: &[u8]) -> RocksResult<()> {
+        self.inner(|txn| txn.put(CF_NAME_DEFAULT, key, value))
+    }
+
+    pub(crate) fn del_default(&self, key: &[u8]) -> RocksResult<()> {
+        self.inner(|txn| txn.del(CF_NAME_DEFAULT, key))
+    }
+
+    /// Record an event of the key, it is notified after the txn is committed.
+    pub fn notify(&self, class: u16, event: &'static str, key: &str) {
+        if let Some(events) = &self.events {
+            events.lock().unwrap().push(KeyEvent {
+                class,
+                event,
+                key: key.to_string(),
+            });
+        }
+    }
+
+    // the key value pairs of the scan up to `limit`, the failed reads are skipped
+    fn collect_pairs(it: KvIter, limit: u32) -> Vec<KvPair> {
+        it.filter_map(|kv| kv.ok())
+            .take(limit as usize)
+            .map(|(key, value)| KvPair::from((Key::from(key.to_vec()), value.to_vec())))
+            .collect()
+    }
+
+    fn inner<T>(&self, f: impl FnOnce(&dyn StorageTxn) -> T) -> T {
+        let mut f = Some(f);
+        let mut res = None;
+        self.inner_txn
+            .with_txn(&mut |txn| res = f.take().map(|f| f(txn)));
+        res.unwrap()
+    }
+
+    pub fn key_count_delta(&self) -> i64 {
+        self.key_count_delta.load(Ordering::Relaxed)
     }
 
     pub fn get(&self, cf: ColumnFamilyRef, key: Key) -> RocksResult<Option<Value>> {
         let key: Vec<u8> = key.into();
-        self.inner_txn.get_cf(&cf, key).map_err(|_| TXN_ERROR)
+        self.inner(|txn| txn.get(cf.name(), &key))
     }
 
     pub fn get_for_update(&self, cf: ColumnFamilyRef, key: Key) -> RocksResult<Option<Value>> {
+        if self.read_only {
+            return self.get(cf, key);
+        }
         let key: Vec<u8> = key.into();
-        self.inner_txn
-            .get_for_update_cf(&cf, key, false)
-            .map_err(|_| TXN_ERROR)
+        if KeyDecoder::is_meta_key(&key) {
+            return self.meta_value_for_update(&cf, &key);
+        }
+        self.inner(|txn| txn.get_for_update(cf.name(), &key, false))
     }
 
     pub fn put(&self, cf: ColumnFamilyRef, key: Key, value: impl Into<Value>) -> RocksResult<()> {
+        if self.read_only {
+            return Ok(());
+        }
         let key: Vec<u8> = key.into();
         let value: Vec<u8> = value.into();
-        self.inner_txn
-            .put_cf(&cf, key, value)
-            .map_err(|_| TXN_ERROR)
+        if KeyDecoder::is_meta_key(&key) {
+            if self.meta_value_for_update(&cf, &key)?.is_none() {
+                self.key_count_delta.fetch_add(1, Ordering::Relaxed);
+                if self.events.is_some() {
+                    let ukey = KeyDecoder::decode_bytes(&key[4..]);
+                    self.notify(NOTIFY_NEW, "new", &String::from_utf8_lossy(&ukey));
+                }
+            }
+            self.metas
+                .lock()
+                .unwrap()
+                .insert(key.clone(), Some(value.clone()));
+        }
+        if let Some(writes) = &self.writes {
+            writes
+                .lock()
+                .unwrap()
+                .push((cf.name(), key.clone(), Some(value.clone())));
+        }
+        if let Some(changes) = &self.changes {
+            if RawChange::is_captured(&key) {
+                changes.lock().unwrap().push(RawChange {
+                    key: key.clone(),
+                    value: Some(value.clone()),
+                    old_meta: None,
+                });
+            }
+        }
+        self.inner(|txn| txn.put(cf.name(), &key, &value))
     }
 
     pub fn del(&self, cf: ColumnFamilyRef, key: Key) -> RocksResult<()> {
+        if self.read_only {
+            return Ok(());
+        }
         let key: Vec<u8> = key.into();
-        self.inner_txn.delete_cf(&cf, key).map_err(|_| TXN_ERROR)
+        let mut old_meta = None;
+        if KeyDecoder::is_meta_key(&key) {
+            old_meta = self.meta_value_for_update(&cf, &key)?;
+            if old_meta.is_some() {
+                self.key_count_delta.fetch_add(-1, Ordering::Relaxed);
+            }
+            self.metas.lock().unwrap().insert(key.clone(), None);
+        }
+        if let Some(writes) = &self.writes {
+            writes.lock().unwrap().push((cf.name(), key.clone(), None));
+        }
+        if let Some(changes) = &self.changes {
+            if RawChange::is_captured(&key) {
+                changes.lock().unwrap().push(RawChange {
+                    key: key.clone(),
+                    value: None,
+                    old_meta,
+                });
+            }
+        }
+        self.inner(|txn| txn.del(cf.name(), &key))
+    }
+
+    // the meta key is locked exclusively when it's first read, so the key count stays
+    // accurate with concurrent txns, the later reads and writes of it use the value in `metas`
+    fn meta_value_for_update(
+        &self,
+        cf: &ColumnFamilyRef,
+        key: &[u8],
+    ) -> RocksResult<Option<Value>> {
+        if let Some(value) = self.metas.lock().unwrap().get(key) {
+            return Ok(value.clone());
+        }
+        let value = self.inner(|txn| txn.get_for_update(cf.name(), key, true))?;
+        self.metas
+            .lock()
+            .unwrap()
+            .insert(key.to_vec(), value.clone());
+        Ok(value)
     }
 
     pub fn batch_get(&self, cf: ColumnFamilyRef, keys: Vec<Key>) -> RocksResult<Vec<KvPair>> {
-        let cf_key_pairs = keys
-            .clone()
+        let raw_keys: Vec<&[u8]> = keys.iter().map(|key| key.as_ref()).collect();
+        let values = self.inner(|txn| txn.batch_get(cf.name(), &raw_keys))?;
+        Ok(keys
             .into_iter()
-            .map(|k| (&cf, k))
-            .collect::<Vec<(&ColumnFamilyRef, Key)>>();
-
-        let results = self.inner_txn.multi_get_cf(cf_key_pairs);
-        let mut kvpairs = Vec::new();
-        for i in 0..results.len() {
-            if let Ok(opt) = results.get(i).unwrap() {
-                let key = keys.get(i).unwrap().clone();
-                let value = opt.clone();
-                if let Some(val) = value {
-                    let kvpair = KvPair::from((key, val));
-                    kvpairs.push(kvpair);
-                }
-            }
-        }
-        Ok(kvpairs)
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|value| KvPair::from((key, value))))
+            .collect())
     }
 
     pub fn batch_get_for_update(
@@ -68,36 +247,25 @@ impl<'a> RocksTransaction<'a> {
         cf: ColumnFamilyRef,
         keys: Vec<Key>,
     ) -> RocksResult<Vec<KvPair>> {
-        let cf_key_pairs = keys
-            .clone()
-            .into_iter()
-            .map(|k| (&cf, k))
-            .collect::<Vec<(&ColumnFamilyRef, Key)>>();
-
-        let mut results = Vec::new();
-        for cf_key_pair in cf_key_pairs {
-            let res = self
-                .inner_txn
-                .get_for_update_cf(cf_key_pair.0, cf_key_pair.1, false)
-                .map_err(|_| TXN_ERROR)?;
-            results.push(res);
+        if self.read_only {
+            return self.batch_get(cf, keys);
         }
-
         let mut kvpairs = Vec::new();
-        for i in 0..results.len() {
-            let opt = results.get(i).unwrap();
-            let key = keys.get(i).unwrap().clone();
-            let value = opt.clone();
-            if let Some(val) = value {
-                let kvpair = KvPair::from((key, val));
-                kvpairs.push(kvpair);
+        for key in keys {
+            let value = if KeyDecoder::is_meta_key(key.as_ref()) {
+                self.meta_value_for_update(&cf, key.as_ref())?
+            } else {
+                self.inner(|txn| txn.get_for_update(cf.name(), key.as_ref(), false))?
+            };
+            if let Some(value) = value {
+                kvpairs.push(KvPair::from((key, value)));
             }
         }
         Ok(kvpairs)
     }
 
     pub fn commit(self) -> RocksResult<()> {
-        self.inner_txn.commit().map_err(|_| TXN_ERROR)
+        self.inner_txn.commit()
     }
 
     pub fn scan(
@@ -106,33 +274,13 @@ impl<'a> RocksTransaction<'a> {
         range: impl Into<BoundRange>,
         limit: u32,
     ) -> RocksResult<impl Iterator<Item = KvPair>> {
-        let bound_range = range.into();
-        let (start, end) = bound_range.into_keys();
-        let start: Vec<u8> = start.into();
-        let it = self.inner_txn.prefix_iterator_cf(&cf_handle, &start);
-        let end_it_key = end
-            .map(|e| {
-                let e_vec: Vec<u8> = e.into();
-                self.inner_txn.prefix_iterator_cf(&cf_handle, e_vec)
-            })
-            .and_then(|mut it| it.next())
-            .and_then(|res| res.ok())
-            .map(|kv| kv.0);
-
-        let mut kv_pairs: Vec<KvPair> = Vec::new();
-        for inner in it {
-            if let Ok(kv_bytes) = inner {
-                if Some(&kv_bytes.0) == end_it_key.as_ref() {
-                    break;
-                }
-                let pair: (Key, Value) = (kv_bytes.0.to_vec().into(), kv_bytes.1.to_vec());
-                kv_pairs.push(pair.into());
-            }
-            if kv_pairs.len() >= limit as usize {
-                break;
-            }
-        }
-        Ok(kv_pairs.into_iter())
+        let (start, end) = range.into().into_keys();
+        let end = end.map(Vec::<u8>::from);
+        let pairs = self.inner(|txn| -> RocksResult<_> {
+            let it = txn.scan(cf_handle.name(), start.as_ref(), end.as_deref())?;
+            Ok(Self::collect_pairs(it, limit))
+        })?;
+        Ok(pairs.into_iter())
     }
 
     pub fn scan_reverse(
@@ -141,36 +289,13 @@ impl<'a> RocksTransaction<'a> {
         range: impl Into<BoundRange>,
         limit: u32,
     ) -> RocksResult<impl Iterator<Item = KvPair>> {
-        let bound_range = range.into();
-        let (start, end) = bound_range.into_keys();
-        let start: Vec<u8> = start.into();
-        let it = self
-            .inner_txn
-            .iterator_cf(&cf_handle, IteratorMode::From(&start, Direction::Reverse));
-        let end_it_key = end
-            .map(|e| {
-                let e_vec: Vec<u8> = e.into();
-                self.inner_txn
-                    .iterator_cf(&cf_handle, IteratorMode::From(&e_vec, Direction::Reverse))
-            })
-            .and_then(|mut it| it.next())
-            .and_then(|res| res.ok())
-            .map(|kv| kv.0);
-
-        let mut kv_pairs: Vec<KvPair> = Vec::new();
-        for inner in it {
-            if let Ok(kv_bytes) = inner {
-                if Some(&kv_bytes.0) == end_it_key.as_ref() {
-                    break;
-                }
-                let pair: (Key, Value) = (kv_bytes.0.to_vec().into(), kv_bytes.1.to_vec());
-                kv_pairs.push(pair.into());
-            }
-            if kv_pairs.len() >= limit as usize {
-                break;
-            }
-        }
-        Ok(kv_pairs.into_iter())
+        let (start, end) = range.into().into_keys();
+        let end = end.map(Vec::<u8>::from);
+        let pairs = self.inner(|txn| -> RocksResult<_> {
+            let it = txn.scan_reverse(cf_handle.name(), start.as_ref(), end.as_deref())?;
+            Ok(Self::collect_pairs(it, limit))
+        })?;
+        Ok(pairs.into_iter())
     }
 
     pub fn scan_keys(
@@ -179,32 +304,7 @@ impl<'a> RocksTransaction<'a> {
         range: impl Into<BoundRange>,
         limit: u32,
     ) -> RocksResult<impl Iterator<Item = Key>> {
-        let bound_range = range.into();
-        let (start, end) = bound_range.into_keys();
-        let start: Vec<u8> = start.into();
-        let it = self.inner_txn.prefix_iterator_cf(&cf_handle, &start);
-        let end_it_key = end
-            .map(|e| {
-                let e_vec: Vec<u8> = e.into();
-                self.inner_txn.prefix_iterator_cf(&cf_handle, e_vec)
-            })
-            .and_then(|mut it| it.next())
-            .and_then(|res| res.ok())
-            .map(|kv| kv.0);
-
-        let mut keys: Vec<Key> = Vec::new();
-        for inner in it {
-            if let Ok(kv_bytes) = inner {
-                if Some(&kv_bytes.0) == end_it_key.as_ref() {
-                    break;
-                }
-                keys.push(kv_bytes.0.to_vec().into());
-            }
-            if keys.len() >= limit as usize {
-                break;
-            }
-        }
-        Ok(keys.into_iter())
+        Ok(self.scan(cf_handle, range, limit)?.map(|kv| kv.0))
     }
 
     pub fn scan_keys_reverse(
@@ -213,34 +313,25 @@ impl<'a> RocksTransaction<'a> {
         range: impl Into<BoundRange>,
         limit: u32,
     ) -> RocksResult<impl Iterator<Item = Key>> {
-        let bound_range = range.into();
-        let (start, end) = bound_range.into_keys();
-        let start: Vec<u8> = start.into();
-        let it = self
-            .inner_txn
-            .iterator_cf(&cf_handle, IteratorMode::From(&start, Direction::Reverse));
-        let end_it_key = end
-            .map(|e| {
-                let e_vec: Vec<u8> = e.into();
-                self.inner_txn
-                    .iterator_cf(&cf_handle, IteratorMode::From(&e_vec, Direction::Reverse))
-            })
-            .and_then(|mut it| it.next())
-            .and_then(|res| res.ok())
-            .map(|kv| kv.0);
-
-        let mut keys: Vec<Key> = Vec::new();
-        for inner in it {
-            if let Ok(kv_bytes) = inner {
-                if Some(&kv_bytes.0) == end_it_key.as_ref() {
-                    break;
-                }
-                keys.push(kv_bytes.0.to_vec().into());
-            }
-            if keys.len() >= limit as usize {
-                break;
-            }
-        }
-        Ok(keys.into_iter())
+        Ok(self.scan_reverse(cf_handle, range, limit)?.map(|kv| kv.0))
+    }
+}
+
+// the txn of the engine behind a lock, as it's not shared by the threads. It's boxed as a
+// trait object, which keeps `RocksTransaction` covariant in the lifetime of the engine, so
+// a txn can be borrowed for shorter than the engine.
+trait LockedTxn: Send + Sync {
+    fn with_txn(&self, f: &mut dyn FnMut(&dyn StorageTxn));
+
+    fn commit(self: Box<Self>) -> RocksResult<()>;
+}
+
+impl LockedTxn for Mutex<Box<dyn StorageTxn + '_>> {
+    fn with_txn(&self, f: &mut dyn FnMut(&dyn StorageTxn)) {
+        f(self.lock().unwrap().as_ref())
+    }
+
+    fn commit(self: Box<Self>) -> RocksResult<()> {
+        self.into_inner().unwrap().commit()
     }
 }
@@ -1,19 +1,24 @@
-use crate::rocks::client::RocksClient;
+use crate::rocks::client::ClientRef;
+use crate::rocks::dump::DumpPayload;
 use crate::rocks::encoding::{DataType, KeyDecoder};
-use crate::rocks::errors::{REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR, REDIS_WRONG_TYPE_ERR};
+use crate::rocks::engine::ColumnFamilyRef;
+use crate::rocks::errors::{
+    REDIS_DUMP_PAYLOAD_ERR, REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR, REDIS_WRONG_TYPE_ERR,
+};
 use crate::rocks::kv::bound_range::BoundRange;
 use crate::rocks::kv::key::Key;
 use crate::rocks::kv::value::Value;
+use crate::rocks::notify::{NOTIFY_EXPIRED, NOTIFY_GENERIC, NOTIFY_ZSET};
 use crate::rocks::transaction::RocksTransaction;
 use crate::rocks::{
-    Result as RocksResult, TxnCommand, CF_NAME_GC, CF_NAME_GC_VERSION, CF_NAME_META,
-    CF_NAME_ZSET_DATA, CF_NAME_ZSET_SCORE, CF_NAME_ZSET_SUB_META,
+    txn_copy_data_keys, txn_for_each_data_key, Result as RocksResult, TxnCommand, CF_NAME_GC,
+    CF_NAME_GC_VERSION, CF_NAME_META, CF_NAME_ZSET_DATA, CF_NAME_ZSET_SCORE, CF_NAME_ZSET_SUB_META,
 };
 use crate::utils::{key_is_expired, resp_array, resp_bulk, resp_err, resp_int, resp_nil};
 use crate::Frame;
-use rocksdb::ColumnFamilyRef;
 use std::collections::HashMap;
 
+use super::encoding::encode::{DATA_TYPE_SCORE, DATA_TYPE_ZSET, PLACE_HOLDER};
 use super::encoding::KeyEncoder;
 
 pub struct ZsetCF<'a> {
@@ -26,7 +31,8 @@ pub struct ZsetCF<'a> {
 }
 
 impl<'a> ZsetCF<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        let client = client.into().client();
         ZsetCF {
             meta_cf: client.cf_handle(CF_NAME_META).unwrap(),
             sub_meta_cf: client.cf_handle(CF_NAME_ZSET_SUB_META).unwrap(),
@@ -39,12 +45,14 @@ impl<'a> ZsetCF<'a> {
 }
 
 pub struct ZsetCommand<'a> {
-    client: &'a RocksClient,
+    client: ClientRef<'a>,
 }
 
 impl<'a> ZsetCommand<'a> {
-    pub fn new(client: &'a RocksClient) -> Self {
-        Self { client }
+    pub fn new(client: impl Into<ClientRef<'a>>) -> Self {
+        Self {
+            client: client.into(),
+        }
     }
 
     pub async fn zadd(
@@ -65,7 +73,7 @@ impl<'a> ZsetCommand<'a> {
         let rand_idx = self.client.gen_next_meta_index();
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Zset) {
@@ -80,7 +88,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let mut expired = false;
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         expired = true;
                         version = client.get_version_for_new(
                             txn,
@@ -224,6 +232,7 @@ impl<'a> ZsetCommand<'a> {
                         txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
                     }
 
+                    txn.notify(NOTIFY_ZSET, "zadd", &key);
                     if changed_only {
                         Ok(updated_count)
                     } else {
@@ -250,30 +259,9 @@ impl<'a> ZsetCommand<'a> {
                         }
                     }
                     // create new key
-                    for idx in 0..members.len() {
-                        let data_key =
-                            KeyEncoder::encode_zset_data_key(&key, &members[idx], version);
-                        let score = scores[idx];
-                        let member = members[idx].clone();
-                        let score_key =
-                            KeyEncoder::encode_zset_score_key(&key, score, &member, version);
-                        // add data key and score key
-                        let data_value = KeyEncoder::encode_zset_data_value(score);
-                        txn.put(cfs.data_cf.clone(), data_key, data_value)?;
-                        // TODO check old score key exists, in case of zadd same field with different scores?
-                        txn.put(cfs.score_cf.clone(), score_key, member)?;
-                    }
-
-                    txn.put(
-                        cfs.sub_meta_cf.clone(),
-                        sub_meta_key,
-                        (members.len() as i64).to_be_bytes().to_vec(),
-                    )?;
-                    // add meta key
-                    let size = members.len() as i64;
-                    let new_meta_value = KeyEncoder::encode_zset_meta_value(0, version, 0);
-                    txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
-                    Ok(size)
+                    self.txn_zadd_new(txn, &key, version, rand_idx, &members, &scores)?;
+                    txn.notify(NOTIFY_ZSET, "zadd", &key);
+                    Ok(members.len() as i64)
                 }
             }
         });
@@ -300,7 +288,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_int(0));
                     }
 
@@ -329,7 +317,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_nil());
                     }
 
@@ -370,7 +358,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_int(0));
                     }
 
@@ -425,7 +413,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_array(resp));
                     }
 
@@ -511,7 +499,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_array(resp));
                     }
 
@@ -575,7 +563,7 @@ impl<'a> ZsetCommand<'a> {
         let rand_idx = self.client.gen_next_meta_index();
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Zset) {
@@ -584,7 +572,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(vec![]);
                     }
 
@@ -647,6 +635,13 @@ impl<'a> ZsetCommand<'a> {
 
                     let size = self.sum_key_size(&key, version)?;
 
+                    if poped_count > 0 {
+                        txn.notify(
+                            NOTIFY_ZSET,
+                            if from_min { "zpopmin" } else { "zpopmax" },
+                            &key,
+                        );
+                    }
                     // delete all sub meta keys and meta key if all members poped
                     if poped_count >= size {
                         let bound_range = KeyEncoder::encode_sub_meta_key_range(&key, version);
@@ -656,6 +651,7 @@ impl<'a> ZsetCommand<'a> {
                         }
 
                         txn.del(cfs.meta_cf.clone(), meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                     } else {
                         // update size to a random sub meta key
                         let sub_meta_key = KeyEncoder::encode_sub_meta_key(&key, version, rand_idx);
@@ -704,7 +700,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(resp_nil());
                     }
 
@@ -753,7 +749,7 @@ impl<'a> ZsetCommand<'a> {
             let prev_score;
             let data_key;
             let mut version;
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Zset) {
@@ -765,7 +761,7 @@ impl<'a> ZsetCommand<'a> {
                     let (ttl, ver, _) = KeyDecoder::decode_key_meta(&meta_value);
                     version = ver;
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         expired = true;
                         version = client.get_version_for_new(
                             txn,
@@ -846,6 +842,7 @@ impl<'a> ZsetCommand<'a> {
             let data_value = KeyEncoder::encode_zset_data_value(new_score);
             txn.put(cfs.data_cf.clone(), data_key, data_value)?;
             txn.put(cfs.score_cf.clone(), score_key, member)?;
+            txn.notify(NOTIFY_ZSET, "zincr", &key);
 
             Ok(new_score)
         });
@@ -865,7 +862,7 @@ impl<'a> ZsetCommand<'a> {
         let rand_idx = self.client.gen_next_meta_index();
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Zset) {
@@ -874,7 +871,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(0);
                     }
 
@@ -906,6 +903,9 @@ impl<'a> ZsetCommand<'a> {
                     let removed_count = data_map.len() as i64;
 
                     let size = self.sum_key_size(&key, version)?;
+                    if removed_count > 0 {
+                        txn.notify(NOTIFY_ZSET, "zrem", &key);
+                    }
                     // clear all sub meta keys and meta key if all members removed
                     if removed_count >= size {
                         let bound_range = KeyEncoder::encode_sub_meta_key_range(&key, version);
@@ -914,6 +914,7 @@ impl<'a> ZsetCommand<'a> {
                             txn.del(cfs.sub_meta_cf.clone(), k)?;
                         }
                         txn.del(cfs.meta_cf.clone(), meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                     } else {
                         let sub_meta_key = KeyEncoder::encode_sub_meta_key(&key, version, rand_idx);
                         let new_sub_meta_value = txn
@@ -959,7 +960,7 @@ impl<'a> ZsetCommand<'a> {
 
         let resp = client.exec_txn(|txn| {
             let mut removed_count = 0;
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Zset) {
@@ -968,7 +969,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(0);
                     }
 
@@ -1006,6 +1007,9 @@ impl<'a> ZsetCommand<'a> {
                         removed_count += 1;
                     }
 
+                    if removed_count > 0 {
+                        txn.notify(NOTIFY_ZSET, "zremrangebyrank", &key);
+                    }
                     // update sub meta key
                     // clear all sub meta keys and meta key if all members removed
                     if removed_count >= size {
@@ -1015,6 +1019,7 @@ impl<'a> ZsetCommand<'a> {
                             txn.del(cfs.sub_meta_cf.clone(), k)?;
                         }
                         txn.del(cfs.meta_cf.clone(), meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                     } else {
                         let sub_meta_key = KeyEncoder::encode_sub_meta_key(&key, version, rand_idx);
                         let new_sub_meta_value = txn
@@ -1054,7 +1059,7 @@ impl<'a> ZsetCommand<'a> {
         let rand_idx = self.client.gen_next_meta_index();
 
         let resp = client.exec_txn(|txn| {
-            match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+            match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
                 Some(meta_value) => {
                     // check key type and ttl
                     if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Zset) {
@@ -1063,7 +1068,7 @@ impl<'a> ZsetCommand<'a> {
 
                     let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
                     if key_is_expired(ttl) {
-                        self.txn_expire_if_needed(txn, &key)?;
+                        self.txn_expire_if_needed(txn, key.as_bytes())?;
                         return Ok(0);
                     }
 
@@ -1100,6 +1105,9 @@ impl<'a> ZsetCommand<'a> {
                     }
 
                     let size = self.sum_key_size(&key, version)?;
+                    if removed_count > 0 {
+                        txn.notify(NOTIFY_ZSET, "zremrangebyscore", &key);
+                    }
                     // delete all sub meta keys and meta key if all members removed
                     if removed_count >= size {
                         let bound_range = KeyEncoder::encode_sub_meta_key_range(&key, version);
@@ -1108,6 +1116,7 @@ impl<'a> ZsetCommand<'a> {
                             txn.del(cfs.sub_meta_cf.clone(), k)?;
                         }
                         txn.del(cfs.meta_cf.clone(), meta_key)?;
+                        txn.notify(NOTIFY_GENERIC, "del", &key);
                     } else {
                         // update a random sub meta key
                         let sub_meta_key = KeyEncoder::encode_sub_meta_key(&key, version, rand_idx);
@@ -1140,10 +1149,107 @@ impl<'a> ZsetCommand<'a> {
         }
     }
 
-    fn sum_key_size(&self, key: &str, version: u16) -> RocksResult<i64> {
+    /// Create a new zset with the members in txn, the key must not exist.
+    pub(crate) fn txn_zadd_new(
+        &self,
+        txn: &RocksTransaction,
+        key: &str,
+        version: u16,
+        idx: u16,
+        members: &[String],
+        scores: &[f64],
+    ) -> RocksResult<()> {
+        let cfs = ZsetCF::new(self.client);
+        for (member, score) in members.iter().zip(scores) {
+            let data_key = KeyEncoder::encode_zset_data_key(key, member, version);
+            let score_key = KeyEncoder::encode_zset_score_key(key, *score, member, version);
+            // add data key and score key
+            let data_value = KeyEncoder::encode_zset_data_value(*score);
+            txn.put(cfs.data_cf.clone(), data_key, data_value)?;
+            // TODO check old score key exists, in case of zadd same field with different scores?
+            txn.put(cfs.score_cf.clone(), score_key, member.clone())?;
+        }
+
+        let sub_meta_key = KeyEncoder::encode_sub_meta_key(key, version, idx);
+        txn.put(
+            cfs.sub_meta_cf.clone(),
+            sub_meta_key,
+            (members.len() as i64).to_be_bytes().to_vec(),
+        )?;
+        // add meta key
+        let meta_key = KeyEncoder::encode_meta_key(key);
+        let new_meta_value = KeyEncoder::encode_zset_meta_value(0, version, 0);
+        txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
+        Ok(())
+    }
+
+    /// Return the version of the zset in txn, `None` is returned if the key does not exist
+    /// or is expired.
+    pub(crate) fn txn_zset_version(
+        &self,
+        txn: &RocksTransaction,
+        key: &str,
+    ) -> RocksResult<Option<u16>> {
+        let cfs = ZsetCF::new(self.client);
+        let meta_key = KeyEncoder::encode_meta_key(key);
+        match txn.get(cfs.meta_cf.clone(), meta_key)? {
+            Some(meta_value) => {
+                // check key type and ttl
+                if !matches!(KeyDecoder::decode_key_type(&meta_value), DataType::Zset) {
+                    return Err(REDIS_WRONG_TYPE_ERR);
+                }
+                let (ttl, version, _) = KeyDecoder::decode_key_meta(&meta_value);
+                if key_is_expired(ttl) {
+                    self.txn_expire_if_needed(txn, key.as_bytes())?;
+                    return Ok(None);
+                }
+                Ok(Some(version))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn txn_zscore(
+        &self,
+        txn: &RocksTransaction,
+        key: &str,
+        version: u16,
+        member: &str,
+    ) -> RocksResult<Option<f64>> {
+        let cfs = ZsetCF::new(self.client);
+        let data_key = KeyEncoder::encode_zset_data_key(key, member, version);
+        Ok(txn
+            .get(cfs.data_cf, data_key)?
+            .map(|data_value| KeyDecoder::decode_key_zset_data_value(&data_value)))
+    }
+
+    /// Return the (score, member) pairs with score in `[min, max)` in txn.
+    pub(crate) fn txn_zrange_by_score(
+        &self,
+        txn: &RocksTransaction,
+        key: &str,
+        version: u16,
+        min: f64,
+        max: f64,
+    ) -> RocksResult<Vec<(f64, Vec<u8>)>> {
+        let cfs = ZsetCF::new(self.client);
+        let start_key = KeyEncoder::encode_zset_score_key_score_start(key, min, true, version);
+        let end_key = KeyEncoder::encode_zset_score_key_score_end(key, max, false, version);
+        let range = start_key..end_key;
+        let bound_range: BoundRange = range.into();
+        let iter = txn.scan(cfs.score_cf, bound_range, u32::MAX)?;
+        Ok(iter
+            .map(|kv| {
+                let score = KeyDecoder::decode_key_zset_score_from_scorekey(key, kv.0);
+                (score, kv.1)
+            })
+            .collect())
+    }
+
+    fn sum_key_size(&self, key: impl AsRef<[u8]>, version: u16) -> RocksResult<i64> {
         let client = self.client;
         let cfs = ZsetCF::new(client);
-        let key = key.to_owned();
+        let key = key.as_ref().to_owned();
 
         client.exec_txn(move |txn| {
             // check if meta key exists or already expired
@@ -1168,12 +1274,12 @@ impl<'a> ZsetCommand<'a> {
 }
 
 impl TxnCommand for ZsetCommand<'_> {
-    fn txn_del(&self, txn: &RocksTransaction, key: &str) -> RocksResult<()> {
+    fn txn_del(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<()> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = ZsetCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let version = KeyDecoder::decode_key_version(&meta_value);
                 let size = self.sum_key_size(&key, version)?;
@@ -1226,12 +1332,12 @@ impl TxnCommand for ZsetCommand<'_> {
         }
     }
 
-    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &str) -> RocksResult<i64> {
+    fn txn_expire_if_needed(&self, txn: &RocksTransaction, key: &[u8]) -> RocksResult<i64> {
         let key = key.to_owned();
         let meta_key = KeyEncoder::encode_meta_key(&key);
         let cfs = ZsetCF::new(self.client);
 
-        match txn.get(cfs.meta_cf.clone(), meta_key.clone())? {
+        match txn.get_for_update(cfs.meta_cf.clone(), meta_key.clone())? {
             Some(meta_value) => {
                 let ttl = KeyDecoder::decode_key_ttl(&meta_value);
                 if !key_is_expired(ttl) {
@@ -1284,6 +1390,7 @@ impl TxnCommand for ZsetCommand<'_> {
                     }
                     txn.del(cfs.meta_cf.clone(), meta_key)?;
                 }
+                txn.notify(NOTIFY_EXPIRED, "expired", &String::from_utf8_lossy(&key));
                 Ok(1)
             }
             None => Ok(0),
@@ -1293,7 +1400,7 @@ impl TxnCommand for ZsetCommand<'_> {
     fn txn_expire(
         &self,
         txn: &RocksTransaction,
-        key: &str,
+        key: &[u8],
         timestamp: i64,
         meta_value: &Value,
     ) -> RocksResult<i64> {
@@ -1304,13 +1411,13 @@ impl TxnCommand for ZsetCommand<'_> {
             self.txn_expire_if_needed(txn, key)?;
             return Ok(0);
         }
-        let version = KeyDecoder::decode_key_version(meta_value);
-        let new_meta_value = KeyEncoder::encode_zset_meta_value(timestamp, version, 0);
+        let (_, version, index_size) = KeyDecoder::decode_key_meta(meta_value);
+        let new_meta_value = KeyEncoder::encode_zset_meta_value(timestamp, version, index_size);
         txn.put(cfs.meta_cf.clone(), meta_key, new_meta_value)?;
         Ok(1)
     }
 
-    fn txn_gc(&self, txn: &RocksTransaction, key: &str, version: u16) -> RocksResult<()> {
+    fn txn_gc(&self, txn: &RocksTransaction, key: &[u8], version: u16) -> RocksResult<()> {
         let cfs = ZsetCF::new(self.client);
         // delete all sub meta key of this key and version
         let bound_range = KeyEncoder::encode_sub_meta_key_range(key, version);
@@ -1334,4 +1441,117 @@ impl TxnCommand for ZsetCommand<'_> {
         }
         Ok(())
     }
+
+    fn txn_copy(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        dest: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<()> {
+        let cfs = ZsetCF::new(self.client);
+        let (ttl, version, index_size) = KeyDecoder::decode_key_meta(meta_value);
+        let dest_version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            dest,
+        )?;
+        let size = txn_copy_data_keys(
+            txn,
+            cfs.data_cf.clone(),
+            DATA_TYPE_ZSET,
+            key,
+            version,
+            dest,
+            dest_version,
+        )?;
+        txn_copy_data_keys(
+            txn,
+            cfs.score_cf.clone(),
+            DATA_TYPE_SCORE,
+            key,
+            version,
+            dest,
+            dest_version,
+        )?;
+
+        // all the size is saved in one sub meta key of dest
+        let idx = self.client.gen_next_meta_index();
+        let sub_meta_key = KeyEncoder::encode_sub_meta_key(dest, dest_version, idx);
+        txn.put(cfs.sub_meta_cf, sub_meta_key, size.to_be_bytes().to_vec())?;
+
+        let meta_key = KeyEncoder::encode_meta_key(dest);
+        let new_meta_value = KeyEncoder::encode_zset_meta_value(ttl, dest_version, index_size);
+        txn.put(cfs.meta_cf, meta_key, new_meta_value)?;
+        Ok(())
+    }
+
+    fn txn_dump(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        meta_value: &Value,
+    ) -> RocksResult<DumpPayload> {
+        let cfs = ZsetCF::new(self.client);
+        let (_, version, _) = KeyDecoder::decode_key_meta(meta_value);
+        let mut items = vec![];
+        txn_for_each_data_key(
+            txn,
+            cfs.data_cf,
+            DATA_TYPE_ZSET,
+            key,
+            version,
+            |suffix, value| {
+                items.push((suffix, KeyDecoder::decode_key_zset_data_value(&value)));
+                Ok(())
+            },
+        )?;
+        Ok(DumpPayload::Zset(items))
+    }
+
+    fn txn_restore(
+        &self,
+        txn: &RocksTransaction,
+        key: &[u8],
+        timestamp: i64,
+        payload: &DumpPayload,
+    ) -> RocksResult<()> {
+        let items = match payload {
+            DumpPayload::Zset(items) => items,
+            _ => return Err(REDIS_DUMP_PAYLOAD_ERR),
+        };
+        let cfs = ZsetCF::new(self.client);
+        let version = self.client.get_version_for_new(
+            txn,
+            cfs.gc_cf.clone(),
+            cfs.gc_version_cf.clone(),
+            key,
+        )?;
+        for (member, score) in items {
+            let data_key = KeyEncoder::encode_type_data_key(key, DATA_TYPE_ZSET, version, member);
+            let data_value = KeyEncoder::encode_zset_data_value(*score);
+            // the score key is ordered by score and then member
+            let mut score_suffix = data_value.clone();
+            score_suffix.push(PLACE_HOLDER);
+            score_suffix.extend_from_slice(member);
+            let score_key =
+                KeyEncoder::encode_type_data_key(key, DATA_TYPE_SCORE, version, &score_suffix);
+            txn.put(cfs.data_cf.clone(), data_key, data_value)?;
+            txn.put(cfs.score_cf.clone(), score_key, member.clone())?;
+        }
+
+        let idx = self.client.gen_next_meta_index();
+        let sub_meta_key = KeyEncoder::encode_sub_meta_key(key, version, idx);
+        txn.put(
+            cfs.sub_meta_cf,
+            sub_meta_key,
+            (items.len() as i64).to_be_bytes().to_vec(),
+        )?;
+
+        let meta_key = KeyEncoder::encode_meta_key(key);
+        let meta_value = KeyEncoder::encode_zset_meta_value(timestamp, version, 0);
+        txn.put(cfs.meta_cf, meta_key, meta_value)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,281 @@
+//! Function libraries of FUNCTION LOAD, which are stored in the functions column family of
+//! the first shard, so they are kept by the backups and the replicas.
+//!
+//! A library is stored at `lib:<name>` with its code and the names and flags of its functions,
+//! and every function is indexed at `fn:<function>` with the name of its library.
+
+use crate::rocks::engine::ColumnFamilyRef;
+use serde::{Deserialize, Serialize};
+
+use crate::db::DBInner;
+use crate::rocks::client::{ClientRef, RocksClient};
+use crate::rocks::errors::{
+    RError, REDIS_FUNCTION_NOT_FOUND_ERR, REDIS_LIBRARY_NOT_FOUND_ERR, REDIS_SCRIPT_RO_CALL_ERR,
+};
+use crate::rocks::kv::key::Key;
+use crate::rocks::transaction::RocksTransaction;
+use crate::rocks::{Result as RocksResult, CF_NAME_FUNCTIONS};
+use crate::utils::{resp_array, resp_bulk, resp_ok, resp_str};
+use crate::Frame;
+
+use super::{is_valid_name, load_functions, run_in_shard, Entry};
+
+const LIBRARY_PREFIX: &str = "lib:";
+const FUNCTION_PREFIX: &str = "fn:";
+
+#[derive(Serialize, Deserialize)]
+struct LibraryMeta {
+    name: String,
+    code: String,
+    functions: Vec<FunctionMeta>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionMeta {
+    name: String,
+    flags: Vec<String>,
+}
+
+fn library_key(name: &str) -> Key {
+    format!("{LIBRARY_PREFIX}{name}").into()
+}
+
+fn function_key(name: &str) -> Key {
+    format!("{FUNCTION_PREFIX}{name}").into()
+}
+
+/// Parse the `#!lua name=<library>` header of the code, return the name of the library.
+fn parse_header(code: &str) -> Result<String, RError> {
+    let header = code.lines().next().unwrap_or_default();
+    let Some(header) = header.strip_prefix("#!") else {
+        return Err(RError::String("ERR Missing library metadata"));
+    };
+    let mut parts = header.split_whitespace();
+    let engine = parts.next().unwrap_or_default();
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(RError::Owned(format!("ERR Engine '{engine}' not found")));
+    }
+    let mut name = None;
+    for part in parts {
+        match part.strip_prefix("name=") {
+            Some(value) => name = Some(value.to_string()),
+            None => {
+                return Err(RError::Owned(format!(
+                    "ERR Invalid metadata value given: {part}"
+                )))
+            }
+        }
+    }
+    let name = name.ok_or(RError::String("ERR Library name was not given"))?;
+    if !is_valid_name(&name) {
+        return Err(RError::String(
+            "ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long",
+        ));
+    }
+    Ok(name)
+}
+
+fn get_library(
+    txn: &RocksTransaction,
+    cf: &ColumnFamilyRef,
+    name: &str,
+) -> RocksResult<Option<LibraryMeta>> {
+    match txn.get(cf.clone(), library_key(name))? {
+        Some(value) => Ok(Some(decode_library(&value)?)),
+        None => Ok(None),
+    }
+}
+
+fn decode_library(value: &[u8]) -> RocksResult<LibraryMeta> {
+    serde_json::from_slice(value)
+        .map_err(|e| RError::Owned(format!("ERR invalid function library: {e}")))
+}
+
+fn delete_library(
+    txn: &RocksTransaction,
+    cf: &ColumnFamilyRef,
+    library: &LibraryMeta,
+) -> RocksResult<()> {
+    for function in &library.functions {
+        txn.del(cf.clone(), function_key(&function.name))?;
+    }
+    txn.del(cf.clone(), library_key(&library.name))
+}
+
+/// Load the library of `code`, an existing library of the same name is replaced if
+/// `replace` is true. Return the name of the library.
+pub(crate) fn function_load(inner_db: &DBInner, code: &str, replace: bool) -> RocksResult<Frame> {
+    let name = parse_header(code)?;
+    let functions = load_functions(code)?;
+    if functions.is_empty() {
+        return Err(RError::String("ERR No functions registered"));
+    }
+    let library = LibraryMeta {
+        name: name.clone(),
+        code: code.to_string(),
+        functions: functions
+            .into_iter()
+            .map(|f| FunctionMeta {
+                name: f.name,
+                flags: f.flags,
+            })
+            .collect(),
+    };
+    let value = serde_json::to_vec(&library).unwrap();
+    let cf = inner_db.client.cf_handle(CF_NAME_FUNCTIONS)?;
+    inner_db.client.exec_txn(move |txn| {
+        if let Some(old) = get_library(txn, &cf, &library.name)? {
+            if !replace {
+                return Err(RError::Owned(format!(
+                    "ERR Library '{}' already exists",
+                    library.name
+                )));
+            }
+            delete_library(txn, &cf, &old)?;
+        }
+        for function in &library.functions {
+            if txn.get(cf.clone(), function_key(&function.name))?.is_some() {
+                return Err(RError::Owned(format!(
+                    "ERR Function {} already exists",
+                    function.name
+                )));
+            }
+            txn.put(
+                cf.clone(),
+                function_key(&function.name),
+                library.name.as_bytes().to_vec(),
+            )?;
+        }
+        txn.put(cf, library_key(&library.name), value)?;
+        Ok(resp_bulk(name.into_bytes()))
+    })
+}
+
+/// Delete the library and its functions.
+pub(crate) fn function_delete(inner_db: &DBInner, name: &str) -> RocksResult<Frame> {
+    let name = name.to_string();
+    let cf = inner_db.client.cf_handle(CF_NAME_FUNCTIONS)?;
+    inner_db.client.exec_txn(move |txn| {
+        let library = get_library(txn, &cf, &name)?.ok_or(REDIS_LIBRARY_NOT_FOUND_ERR)?;
+        delete_library(txn, &cf, &library)?;
+        Ok(resp_ok())
+    })
+}
+
+/// Delete all the libraries.
+pub(crate) fn function_flush(inner_db: &DBInner) -> RocksResult<Frame> {
+    let libraries = list_libraries(&inner_db.client)?;
+    let cf = inner_db.client.cf_handle(CF_NAME_FUNCTIONS)?;
+    inner_db.client.exec_txn(move |txn| {
+        for library in &libraries {
+            delete_library(txn, &cf, library)?;
+        }
+        Ok(resp_ok())
+    })
+}
+
+fn list_libraries(client: &RocksClient) -> RocksResult<Vec<LibraryMeta>> {
+    let cf = client.cf_handle(CF_NAME_FUNCTIONS)?;
+    let start: Key = LIBRARY_PREFIX.to_string().into();
+    // ';' is the byte after ':'
+    let end: Key = "lib;".to_string().into();
+    client
+        .scan(cf, start..end, u32::MAX)?
+        .filter(|kv| kv.0.as_ref().starts_with(LIBRARY_PREFIX.as_bytes()))
+        .map(|kv| decode_library(&kv.1))
+        .collect()
+}
+
+/// List the libraries whose names match the glob-style `pattern`, with their code if
+/// `with_code` is true.
+pub(crate) fn function_list(
+    inner_db: &DBInner,
+    pattern: Option<&str>,
+    with_code: bool,
+) -> RocksResult<Frame> {
+    let pattern = match pattern {
+        Some(pattern) => {
+            Some(glob::Pattern::new(pattern).map_err(|_| RError::String("ERR invalid pattern"))?)
+        }
+        None => None,
+    };
+    let mut frames = vec![];
+    for library in list_libraries(&inner_db.client)? {
+        if pattern.as_ref().is_some_and(|p| !p.matches(&library.name)) {
+            continue;
+        }
+        let functions = library
+            .functions
+            .iter()
+            .map(|f| {
+                resp_array(vec![
+                    resp_str("name"),
+                    resp_bulk(f.name.clone().into_bytes()),
+                    resp_str("description"),
+                    Frame::Null,
+                    resp_str("flags"),
+                    resp_array(f.flags.iter().map(|flag| resp_str(flag)).collect()),
+                ])
+            })
+            .collect();
+        let mut frame = vec![
+            resp_str("library_name"),
+            resp_bulk(library.name.into_bytes()),
+            resp_str("engine"),
+            resp_str("LUA"),
+            resp_str("functions"),
+            resp_array(functions),
+        ];
+        if with_code {
+            frame.push(resp_str("library_code"));
+            frame.push(resp_bulk(library.code.into_bytes()));
+        }
+        frames.push(resp_array(frame));
+    }
+    Ok(resp_array(frames))
+}
+
+/// Call the function with the keys and the arguments, in a txn of the shard of the keys.
+pub(crate) async fn fcall(
+    inner_db: &DBInner,
+    shards: &[ClientRef<'_>],
+    name: &str,
+    keys: &[String],
+    args: &[String],
+    read_only: bool,
+) -> RocksResult<Frame> {
+    let client = &inner_db.client;
+    let cf = client.cf_handle(CF_NAME_FUNCTIONS)?;
+    let library_name = client
+        .get(cf.clone(), function_key(name))?
+        .ok_or(REDIS_FUNCTION_NOT_FOUND_ERR)?;
+    let library = client
+        .get(cf, library_key(&String::from_utf8_lossy(&library_name)))?
+        .ok_or(REDIS_FUNCTION_NOT_FOUND_ERR)?;
+    let library = decode_library(&library)?;
+    let no_writes = library
+        .functions
+        .iter()
+        .find(|f| f.name == name)
+        .ok_or(REDIS_FUNCTION_NOT_FOUND_ERR)?
+        .flags
+        .iter()
+        .any(|flag| flag == "no-writes");
+    if read_only && !no_writes {
+        return Err(REDIS_SCRIPT_RO_CALL_ERR);
+    }
+    let entry = Entry::Function {
+        code: &library.code,
+        name,
+    };
+    let deterministic = inner_db.scripts.deterministic;
+    run_in_shard(
+        shards,
+        keys,
+        args,
+        read_only || no_writes,
+        deterministic,
+        &entry,
+    )
+    .await
+}
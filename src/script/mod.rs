@@ -0,0 +1,842 @@
+//! Server side scripting with EVAL and the function libraries of FUNCTION LOAD.
+//!
+//! The scripts are written in Lua and run by an embedded Lua 5.4 with the base, string, table
+//! and math libraries. Every run of a script has its own Lua state, whose globals can't be
+//! changed by the script.
+//!
+//! A script runs in a single txn of the shard of its keys. The commands called by
+//! `redis.call` are sent to the task driving the script, which executes them in the txn, so
+//! all the writes of a script are committed together, and they are discarded if the script
+//! raises an error. The txn is retried on conflict like the other commands, so a script may
+//! run more than once.
+//!
+//! The scripts written to the raft log are run by every member, so they must give the same
+//! result everywhere: `math.random` starts from the same seed, and the commands depending on
+//! the random numbers or the time can't be called.
+
+mod library;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use mlua::{
+    Function, HookTriggers, IntoLuaMulti, Lua, LuaOptions, MultiValue, StdLib, Table, Value,
+    VmState,
+};
+use sha1::{Digest, Sha1};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::cmd::{Command, Eval};
+use crate::config::{config_script_time_limit, txn_retry_count};
+use crate::db::DBInner;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::{
+    RError, REDIS_CROSSSLOT_ERR, REDIS_NOSCRIPT_ERR, REDIS_SCRIPT_NONDETERMINISTIC_ERR,
+    REDIS_SCRIPT_NON_LOCAL_KEY_ERR, REDIS_SCRIPT_WRITE_ERR, SCRIPT_TIMEOUT_ERR, TXN_ERROR,
+};
+use crate::rocks::Result as RocksResult;
+use crate::shard::keys_shard;
+use crate::utils::resp_err;
+use crate::Frame;
+
+pub(crate) use library::{fcall, function_delete, function_flush, function_list, function_load};
+
+// the name of the chunks in the error messages
+const CHUNK_NAME: &str = "@user_script";
+
+// the hook checking the time limit of the scripts
+const TIME_LIMIT_HOOK: HookTriggers = HookTriggers::new().every_nth_instruction(1000);
+
+// redis.call and redis.pcall around the raw call, which returns the error replies and the
+// message of a wrong call
+const REDIS_CALL: &str = r#"
+local redis, call = ...
+redis.call = function(...)
+    local reply, err = call(...)
+    if err then
+        error(err, 2)
+    end
+    if type(reply) == 'table' and reply.err then
+        error(reply)
+    end
+    return reply
+end
+redis.pcall = function(...)
+    local reply, err = call(...)
+    if err then
+        return {err = err}
+    end
+    return reply
+end
+"#;
+
+// pcall and xpcall raising again the errors caught after the script is aborted, so the
+// scripts can't go on after the time limit
+const ABORTABLE_PCALL: &str = r#"
+local aborted = ...
+local pcall, xpcall, select = pcall, xpcall, select
+local function check(...)
+    if aborted() then
+        error(select(2, ...), 0)
+    end
+    return ...
+end
+_G.pcall = function(...)
+    return check(pcall(...))
+end
+_G.xpcall = function(...)
+    return check(xpcall(...))
+end
+"#;
+
+// the random numbers of the scripts run by the raft members, which start from the same seed,
+// and math.randomseed without a seed doesn't seed randomly
+const DETERMINISTIC_RANDOM: &str = r#"
+local math = ...
+local randomseed = math.randomseed
+math.randomseed = function(seed)
+    randomseed(seed or 0)
+end
+math.randomseed(0)
+"#;
+
+// the environment of the scripts, which reads the globals but can't change them
+const SCRIPT_ENV: &str = r#"
+local globals = ...
+local env = setmetatable({}, {
+    __index = function(_, name)
+        local value = globals[name]
+        if value == nil then
+            error("Script attempted to access nonexistent global variable '" .. tostring(name) .. "'", 2)
+        end
+        return value
+    end,
+    __newindex = function(_, name)
+        if globals[name] ~= nil then
+            error("Attempt to modify a readonly table", 2)
+        end
+        error("Script attempted to create global variable '" .. tostring(name) .. "'", 2)
+    end,
+})
+globals._G = env
+return env
+"#;
+
+/// Return the lowercase hex SHA1 digest of `data`, which names the cached scripts as Redis
+/// does.
+pub(crate) fn sha1_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(data))
+}
+
+/// Cache of the scripts loaded by EVAL and SCRIPT LOAD.
+#[derive(Default)]
+pub(crate) struct Scripts {
+    // code of the script by its sha
+    cache: Mutex<HashMap<String, String>>,
+    // the scripts are applied by the raft members
+    deterministic: bool,
+}
+
+impl Scripts {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The scripts applied by the raft members, which must give the same result on every
+    /// member.
+    pub(crate) fn deterministic() -> Self {
+        Self {
+            deterministic: true,
+            ..Self::default()
+        }
+    }
+
+    /// Compile and cache the script, return its sha.
+    pub(crate) fn load(&self, code: &str) -> Result<String, RError> {
+        let sha = sha1_hex(code.as_bytes());
+        if self.cache.lock().unwrap().contains_key(&sha) {
+            return Ok(sha);
+        }
+        compile(code).map_err(|e| {
+            RError::Owned(format!("ERR Error compiling script (new function): {e}"))
+        })?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(sha.clone(), code.to_string());
+        Ok(sha)
+    }
+
+    pub(crate) fn exists(&self, sha: &str) -> bool {
+        self.cache
+            .lock()
+            .unwrap()
+            .contains_key(&sha.to_ascii_lowercase())
+    }
+
+    pub(crate) fn flush(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn get(&self, sha: &str) -> Option<String> {
+        let cache = self.cache.lock().unwrap();
+        cache.get(&sha.to_ascii_lowercase()).cloned()
+    }
+
+    /// Replace EVALSHA with EVAL of the cached script, so the command can be replayed from
+    /// the journal or applied by the raft members without the cache.
+    pub(crate) fn resolve(&self, cmd: Command) -> Command {
+        let resolved = match &cmd {
+            Command::Evalsha(eval) | Command::EvalshaRo(eval) => match self.get(eval.script()) {
+                Some(code) => eval.with_script(&code),
+                None => return cmd,
+            },
+            _ => return cmd,
+        };
+        match cmd {
+            Command::Evalsha(_) => Command::Eval(resolved),
+            _ => Command::EvalRo(resolved),
+        }
+    }
+}
+
+// compile the chunk, return the message of the syntax error
+fn compile(code: &str) -> Result<(), String> {
+    let lua = Lua::new_with(StdLib::NONE, LuaOptions::default()).map_err(|e| e.to_string())?;
+    lua.load(code)
+        .set_name(CHUNK_NAME)
+        .into_function()
+        .map(|_| ())
+        .map_err(|e| lua_error_message(&e))
+}
+
+/// Run the script of EVAL or EVALSHA, `eval.script()` is the sha if `by_sha` is true.
+pub(crate) async fn eval(
+    inner_db: &DBInner,
+    shards: &[ClientRef<'_>],
+    eval: &Eval,
+    by_sha: bool,
+    read_only: bool,
+) -> RocksResult<Frame> {
+    let code = if by_sha {
+        inner_db
+            .scripts
+            .get(eval.script())
+            .ok_or(REDIS_NOSCRIPT_ERR)?
+    } else {
+        inner_db.scripts.load(eval.script())?;
+        eval.script().to_string()
+    };
+    let entry = Entry::Script(&code);
+    let deterministic = inner_db.scripts.deterministic;
+    run_in_shard(
+        shards,
+        eval.keys(),
+        eval.args(),
+        read_only,
+        deterministic,
+        &entry,
+    )
+    .await
+}
+
+/// The code run by a script.
+enum Entry<'a> {
+    /// The script of EVAL.
+    Script(&'a str),
+    /// The function `name` of the library of `code`, called by FCALL.
+    Function { code: &'a str, name: &'a str },
+}
+
+// a command called by the script, with the sender of its reply
+type Call = (Command, oneshot::Sender<Frame>);
+
+/// Run `entry` in a txn of the shard of `keys`, the txn is retried on conflict. If the shard
+/// is read in the txn of a snapshot, `entry` is run in that txn, which is never committed.
+async fn run_in_shard(
+    shards: &[ClientRef<'_>],
+    keys: &[String],
+    args: &[String],
+    read_only: bool,
+    deterministic: bool,
+    entry: &Entry<'_>,
+) -> RocksResult<Frame> {
+    let key_refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+    let shard = keys_shard(&key_refs, shards.len()).ok_or(REDIS_CROSSSLOT_ERR)?;
+    let host = || ScriptHost::new(shard, shards.len(), read_only, deterministic);
+    if shards[shard].txn().is_some() {
+        let res = run(host(), shards[shard], entry, keys, args).await;
+        return Ok(res.unwrap_or_else(resp_err));
+    }
+    let client = shards[shard].client();
+    let mut frame = Frame::Null;
+    for _ in 0..txn_retry_count() {
+        let txn = client.begin_txn(true);
+        let res = run(host(), ClientRef::with_txn(client, &txn), entry, keys, args).await;
+        frame = match res.and_then(|frame| client.commit(txn).map(|_| frame)) {
+            Ok(frame) => frame,
+            Err(e) => resp_err(e),
+        };
+        if !matches!(frame, Frame::TxnFailed(_)) {
+            break;
+        }
+    }
+    Ok(frame)
+}
+
+/// Run `entry` in a new Lua state, the commands called by the script are executed with
+/// `client`. An error of the script is returned as `Err`, so the writes of the script are
+/// discarded.
+async fn run(
+    host: ScriptHost,
+    client: ClientRef<'_>,
+    entry: &Entry<'_>,
+    keys: &[String],
+    args: &[String],
+) -> RocksResult<Frame> {
+    let (tx, mut calls) = mpsc::unbounded_channel();
+    let host = Arc::new(host.with_calls(tx));
+    let lua = new_lua(&host).map_err(lua_error)?;
+    let res = drive(
+        client,
+        &mut calls,
+        call_entry(&lua, &host, entry, keys, args),
+    )
+    .await;
+    // the abort of the script can't be caught by pcall
+    if let Some(e) = host.abort.lock().unwrap().take() {
+        return Err(e);
+    }
+    Ok(value_to_frame(&res??))
+}
+
+/// Poll the script until it returns, executing the commands called by it in the meantime.
+/// A conflict of the txn ends the script.
+async fn drive<T>(
+    client: ClientRef<'_>,
+    calls: &mut mpsc::UnboundedReceiver<Call>,
+    script: impl Future<Output = T>,
+) -> RocksResult<T> {
+    tokio::pin!(script);
+    loop {
+        tokio::select! {
+            value = &mut script => return Ok(value),
+            Some((cmd, reply)) = calls.recv() => {
+                let frame = match cmd.execute_on(client).await {
+                    Ok(frame) => frame,
+                    Err(e) => match e.downcast_ref::<RError>() {
+                        Some(RError::Txn(_)) => return Err(TXN_ERROR),
+                        _ => Frame::Error(e.to_string()),
+                    },
+                };
+                if let Frame::TxnFailed(_) = frame {
+                    return Err(TXN_ERROR);
+                }
+                let _ = reply.send(frame);
+            }
+        }
+    }
+}
+
+async fn call_entry(
+    lua: &Lua,
+    host: &Arc<ScriptHost>,
+    entry: &Entry<'_>,
+    keys: &[String],
+    args: &[String],
+) -> RocksResult<Value> {
+    match entry {
+        Entry::Script(code) => {
+            let globals = lua.globals();
+            globals
+                .raw_set("KEYS", strings_table(lua, keys)?)
+                .map_err(lua_error)?;
+            globals
+                .raw_set("ARGV", strings_table(lua, args)?)
+                .map_err(lua_error)?;
+            let main = lua
+                .load(*code)
+                .set_name(CHUNK_NAME)
+                .set_environment(script_env(lua).map_err(lua_error)?)
+                .into_function()
+                .map_err(|e| {
+                    RError::Owned(format!(
+                        "ERR Error compiling script (new function): {}",
+                        lua_error_message(&e)
+                    ))
+                })?;
+            pcall(lua, host, main, ()).await
+        }
+        Entry::Function { code, name } => {
+            // the callbacks are created by the library, which is run again in the script
+            let callback = load_library(lua, host, code)?
+                .into_iter()
+                .find(|f| f.name == *name)
+                .map(|f| f.callback)
+                .ok_or(RError::String("ERR Function not found"))?;
+            let keys = strings_table(lua, keys)?;
+            let args = strings_table(lua, args)?;
+            pcall(lua, host, callback, (keys, args)).await
+        }
+    }
+}
+
+fn strings_table(lua: &Lua, values: &[String]) -> RocksResult<Table> {
+    lua.create_sequence_from(values.iter().map(String::as_str))
+        .map_err(lua_error)
+}
+
+// call `f` in protected mode, so the error tables raised by the script are kept
+async fn pcall(
+    lua: &Lua,
+    host: &Arc<ScriptHost>,
+    f: Function,
+    args: impl IntoLuaMulti,
+) -> RocksResult<Value> {
+    let pcall: Function = lua.globals().raw_get("pcall").map_err(lua_error)?;
+    let args = (f, args.into_lua_multi(lua).map_err(lua_error)?);
+    // the hook is set for a single thread, which runs the script
+    let thread = lua.create_thread(pcall).map_err(lua_error)?;
+    let hook_host = host.clone();
+    thread.set_hook(TIME_LIMIT_HOOK, move |_, _| hook_host.check_time_limit());
+    let values = thread
+        .into_async::<MultiValue>(args)
+        .await
+        .map_err(lua_error)?;
+    pcall_result(values)
+}
+
+fn pcall_sync(lua: &Lua, f: Function) -> RocksResult<Value> {
+    let pcall: Function = lua.globals().raw_get("pcall").map_err(lua_error)?;
+    pcall_result(pcall.call::<MultiValue>(f).map_err(lua_error)?)
+}
+
+fn pcall_result(values: MultiValue) -> RocksResult<Value> {
+    let mut values = values.into_iter();
+    match values.next() {
+        Some(Value::Boolean(true)) => Ok(values.next().unwrap_or(Value::Nil)),
+        _ => Err(error_to_rerror(&values.next().unwrap_or(Value::Nil))),
+    }
+}
+
+/// Create the Lua state of a script, with the redis library and the time limit.
+fn new_lua(host: &Arc<ScriptHost>) -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::STRING | StdLib::TABLE | StdLib::MATH,
+        LuaOptions::default(),
+    )?;
+    let globals = lua.globals();
+    // the scripts can't read the files
+    globals.raw_remove("dofile")?;
+    globals.raw_remove("loadfile")?;
+    // unpack of Lua 5.1, which is used by the scripts written for Redis
+    let table: Table = globals.raw_get("table")?;
+    globals.raw_set("unpack", table.raw_get::<Function>("unpack")?)?;
+    globals.raw_set("redis", redis_lib(&lua, host)?)?;
+    let aborted_host = host.clone();
+    let aborted =
+        lua.create_function(move |_, ()| Ok(aborted_host.abort.lock().unwrap().is_some()))?;
+    lua.load(ABORTABLE_PCALL).call::<()>(aborted)?;
+
+    if host.deterministic {
+        lua.load(DETERMINISTIC_RANDOM)
+            .call::<()>(globals.raw_get::<Table>("math")?)?;
+    }
+
+    let hook_host = host.clone();
+    lua.set_hook(TIME_LIMIT_HOOK, move |_, _| hook_host.check_time_limit());
+    Ok(lua)
+}
+
+// create the environment of the chunks of the script, after the globals are set
+fn script_env(lua: &Lua) -> mlua::Result<Table> {
+    lua.load(SCRIPT_ENV).call(lua.globals())
+}
+
+/// Run the main chunk of the library of FUNCTION LOAD, return the functions registered by it.
+fn load_functions(code: &str) -> RocksResult<Vec<RegisteredFunction>> {
+    let host = Arc::new(ScriptHost::new(0, 1, true, false));
+    let lua = new_lua(&host).map_err(lua_error)?;
+    load_library(&lua, &host, code)
+}
+
+/// Run the main chunk of a library, return the functions registered by it.
+fn load_library(lua: &Lua, host: &ScriptHost, code: &str) -> RocksResult<Vec<RegisteredFunction>> {
+    // the header of the library is not Lua, keep its newline for the line numbers
+    let body = code.find('\n').map_or("", |i| &code[i..]);
+    let main = lua
+        .load(body)
+        .set_name(CHUNK_NAME)
+        .set_environment(script_env(lua).map_err(lua_error)?)
+        .into_function()
+        .map_err(|e| {
+            RError::Owned(format!(
+                "ERR Error compiling function: {}",
+                lua_error_message(&e)
+            ))
+        })?;
+    *host.functions.lock().unwrap() = Some(vec![]);
+    let res = pcall_sync(lua, main);
+    let functions = host.functions.lock().unwrap().take().unwrap_or_default();
+    if let Some(e) = host.abort.lock().unwrap().take() {
+        return Err(e);
+    }
+    match res {
+        Ok(_) => Ok(functions),
+        Err(RError::Owned(msg)) => Err(RError::Owned(format!(
+            "ERR Error registering functions: {}",
+            msg.strip_prefix("ERR ").unwrap_or(&msg)
+        ))),
+        Err(e) => Err(e),
+    }
+}
+
+fn lua_error(e: mlua::Error) -> RError {
+    RError::Owned(format!("ERR {}", lua_error_message(&e)))
+}
+
+// the message of the error without the decorations of mlua
+fn lua_error_message(e: &mlua::Error) -> String {
+    match e {
+        mlua::Error::RuntimeError(msg) => msg.clone(),
+        mlua::Error::SyntaxError { message, .. } => message.clone(),
+        mlua::Error::CallbackError { cause, .. } => lua_error_message(cause),
+        mlua::Error::ExternalError(e) => e.to_string(),
+        e => e.to_string(),
+    }
+}
+
+// convert an uncaught error of the script to the error reply
+fn error_to_rerror(e: &Value) -> RError {
+    match e {
+        Value::Table(t) => match t.raw_get::<Value>("err") {
+            Ok(Value::String(s)) => RError::Owned(s.to_string_lossy()),
+            _ => RError::String("ERR Error running script"),
+        },
+        Value::String(s) => RError::Owned(format!("ERR {}", s.to_string_lossy())),
+        Value::Integer(n) => RError::Owned(format!("ERR {n}")),
+        Value::Number(n) => RError::Owned(format!("ERR {n}")),
+        Value::Error(e) => lua_error(*e.clone()),
+        _ => RError::String("ERR Error running script"),
+    }
+}
+
+/// A function registered by `redis.register_function` while loading a library.
+pub(crate) struct RegisteredFunction {
+    name: String,
+    callback: Function,
+    flags: Vec<String>,
+}
+
+// the flags of the functions, only no-writes changes how a function runs
+const FUNCTION_FLAGS: [&str; 5] = [
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// The state of a script shared by the functions of the redis library.
+struct ScriptHost {
+    shard: usize,
+    shards: usize,
+    read_only: bool,
+    // the script is applied by the raft members
+    deterministic: bool,
+    // the end of the time limit of the script
+    deadline: Instant,
+    // the sender of the called commands, none if the script can't call the commands
+    calls: Option<mpsc::UnboundedSender<Call>>,
+    // the registered functions if a library is being loaded
+    functions: Mutex<Option<Vec<RegisteredFunction>>>,
+    // the error which ends the script
+    abort: Mutex<Option<RError>>,
+}
+
+impl ScriptHost {
+    fn new(shard: usize, shards: usize, read_only: bool, deterministic: bool) -> Self {
+        Self {
+            shard,
+            shards,
+            read_only,
+            deterministic,
+            deadline: Instant::now() + config_script_time_limit(),
+            calls: None,
+            functions: Mutex::new(None),
+            abort: Mutex::new(None),
+        }
+    }
+
+    fn with_calls(self, calls: mpsc::UnboundedSender<Call>) -> Self {
+        Self {
+            calls: Some(calls),
+            ..self
+        }
+    }
+
+    fn abort(&self, e: RError) {
+        self.abort.lock().unwrap().get_or_insert(e);
+    }
+
+    // end the script after the time limit, the error raised by the hook may be caught by
+    // pcall, but the script is aborted at the end anyway
+    fn check_time_limit(&self) -> mlua::Result<VmState> {
+        if Instant::now() < self.deadline {
+            return Ok(VmState::Continue);
+        }
+        self.abort(SCRIPT_TIMEOUT_ERR);
+        Err(mlua::Error::runtime(SCRIPT_TIMEOUT_ERR))
+    }
+
+    // check the command called by the script, return the error reply if it can't be called
+    fn command(&self, args: &[Vec<u8>]) -> Result<Command, Frame> {
+        if self.functions.lock().unwrap().is_some() {
+            return Err(Frame::Error(
+                "ERR redis.call can't be used while loading a function library".to_string(),
+            ));
+        }
+        let cmd = Command::from_args(args).map_err(resp_err)?;
+        if self.read_only && cmd.is_write() {
+            return Err(resp_err(REDIS_SCRIPT_WRITE_ERR));
+        }
+        if self.deterministic && !cmd.is_deterministic() {
+            return Err(resp_err(REDIS_SCRIPT_NONDETERMINISTIC_ERR));
+        }
+        if self.shards > 1 && keys_shard(&cmd.keys(), self.shards) != Some(self.shard) {
+            return Err(resp_err(REDIS_SCRIPT_NON_LOCAL_KEY_ERR));
+        }
+        Ok(cmd)
+    }
+
+    // send the command to the txn of the script, return its reply
+    async fn call(&self, args: &[Vec<u8>]) -> Frame {
+        let cmd = match self.command(args) {
+            Ok(cmd) => cmd,
+            Err(frame) => return frame,
+        };
+        let Some(calls) = &self.calls else {
+            return Frame::Error(
+                "ERR redis.call can't be used while loading a function library".to_string(),
+            );
+        };
+        let (tx, rx) = oneshot::channel();
+        if calls.send((cmd, tx)).is_err() {
+            return resp_err(TXN_ERROR);
+        }
+        rx.await.unwrap_or_else(|_| resp_err(TXN_ERROR))
+    }
+
+    fn register_function(&self, function: RegisteredFunction) -> Result<(), String> {
+        let mut functions = self.functions.lock().unwrap();
+        let Some(functions) = functions.as_mut() else {
+            return Err("redis.register_function can only be called on FUNCTION LOAD".to_string());
+        };
+        if !is_valid_name(&function.name) {
+            return Err("Function names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string());
+        }
+        if functions.iter().any(|f| f.name == function.name) {
+            return Err("Function already exists in the library".to_string());
+        }
+        if let Some(flag) = function
+            .flags
+            .iter()
+            .find(|flag| !FUNCTION_FLAGS.contains(&flag.as_str()))
+        {
+            return Err(format!("unknown flag given: {flag}"));
+        }
+        functions.push(function);
+        Ok(())
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+}
+
+fn redis_lib(lua: &Lua, host: &Arc<ScriptHost>) -> mlua::Result<Table> {
+    let lib = lua.create_table()?;
+    lib.raw_set("error_reply", lua.create_function(redis_error_reply)?)?;
+    lib.raw_set("status_reply", lua.create_function(redis_status_reply)?)?;
+    lib.raw_set("sha1hex", lua.create_function(redis_sha1hex)?)?;
+    // there is no log of the scripts
+    lib.raw_set("log", lua.create_function(|_, _: MultiValue| Ok(()))?)?;
+    let register_host = host.clone();
+    lib.raw_set(
+        "register_function",
+        lua.create_function(move |_, args| redis_register_function(&register_host, args))?,
+    )?;
+    let levels = ["LOG_DEBUG", "LOG_VERBOSE", "LOG_NOTICE", "LOG_WARNING"];
+    for (level, name) in levels.into_iter().enumerate() {
+        lib.raw_set(name, level)?;
+    }
+
+    let call_host = host.clone();
+    let call = lua.create_async_function(move |lua, args: MultiValue| {
+        let host = call_host.clone();
+        async move {
+            let args = match call_args(&lua, args)? {
+                Ok(args) => args,
+                Err(msg) => return Ok((Value::Nil, Some(msg))),
+            };
+            let frame = host.call(&args).await;
+            Ok((frame_to_value(&lua, frame)?, None))
+        }
+    })?;
+    lua.load(REDIS_CALL).call::<()>((lib.clone(), call))?;
+    Ok(lib)
+}
+
+// the arguments of redis.call, or the message of the wrong arguments
+fn call_args(lua: &Lua, args: MultiValue) -> mlua::Result<Result<Vec<Vec<u8>>, String>> {
+    if args.is_empty() {
+        return Ok(Err(
+            "Please specify at least one argument for this redis lib call".to_string(),
+        ));
+    }
+    let mut cmd_args = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg {
+            Value::String(_) | Value::Integer(_) | Value::Number(_) => {
+                let arg = lua.coerce_string(arg)?.unwrap();
+                cmd_args.push(arg.as_bytes().to_vec());
+            }
+            _ => {
+                return Ok(Err(
+                    "Lua redis lib command arguments must be strings or integers".to_string(),
+                ))
+            }
+        }
+    }
+    Ok(Ok(cmd_args))
+}
+
+fn redis_error_reply(lua: &Lua, msg: Value) -> mlua::Result<Table> {
+    let Value::String(msg) = msg else {
+        return Err(mlua::Error::runtime("wrong number or type of arguments"));
+    };
+    let table = lua.create_table()?;
+    table.raw_set("err", msg)?;
+    Ok(table)
+}
+
+fn redis_status_reply(lua: &Lua, msg: Value) -> mlua::Result<Table> {
+    let Value::String(msg) = msg else {
+        return Err(mlua::Error::runtime("wrong number or type of arguments"));
+    };
+    let table = lua.create_table()?;
+    table.raw_set("ok", msg)?;
+    Ok(table)
+}
+
+fn redis_sha1hex(lua: &Lua, args: MultiValue) -> mlua::Result<String> {
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (Some(arg), None) => match lua.coerce_string(arg)? {
+            Some(s) => Ok(sha1_hex(&s.as_bytes())),
+            None => Err(mlua::Error::runtime("wrong number of arguments")),
+        },
+        _ => Err(mlua::Error::runtime("wrong number of arguments")),
+    }
+}
+
+fn redis_register_function(host: &ScriptHost, args: MultiValue) -> mlua::Result<()> {
+    let mut args = args.into_iter();
+    let (name, callback, flags) = match (args.next(), args.next(), args.next()) {
+        (Some(name), Some(callback), None) => (name, callback, Value::Nil),
+        (Some(Value::Table(t)), None, None) => (
+            t.raw_get("function_name")?,
+            t.raw_get("callback")?,
+            t.raw_get("flags")?,
+        ),
+        _ => {
+            return Err(mlua::Error::runtime(
+                "wrong number of arguments to redis.register_function",
+            ))
+        }
+    };
+    let Value::String(name) = name else {
+        return Err(mlua::Error::runtime("function name must be a string"));
+    };
+    let Value::Function(callback) = callback else {
+        return Err(mlua::Error::runtime("callback must be a function"));
+    };
+    let flags = match flags {
+        Value::Nil => vec![],
+        Value::Table(t) => t
+            .sequence_values::<Value>()
+            .map(|flag| match flag? {
+                Value::String(flag) => Ok(flag.to_string_lossy()),
+                _ => Err(mlua::Error::runtime(
+                    "flags argument must be a table of strings",
+                )),
+            })
+            .collect::<mlua::Result<_>>()?,
+        _ => {
+            return Err(mlua::Error::runtime(
+                "flags argument must be a table of strings",
+            ))
+        }
+    };
+    let function = RegisteredFunction {
+        name: name.to_string_lossy(),
+        callback,
+        flags,
+    };
+    host.register_function(function)
+        .map_err(mlua::Error::runtime)
+}
+
+/// Convert the reply of a command to the value in the script.
+fn frame_to_value(lua: &Lua, frame: Frame) -> mlua::Result<Value> {
+    let value = match frame {
+        Frame::Integer(n) => Value::Integer(n),
+        Frame::Bulk(b) => Value::String(lua.create_string(&b)?),
+        Frame::Null => Value::Boolean(false),
+        Frame::Array(frames) => {
+            let table = lua.create_table_with_capacity(frames.len(), 0)?;
+            for frame in frames {
+                table.raw_push(frame_to_value(lua, frame)?)?;
+            }
+            Value::Table(table)
+        }
+        Frame::Simple(s) => {
+            let table = lua.create_table()?;
+            table.raw_set("ok", s)?;
+            Value::Table(table)
+        }
+        Frame::Error(e) | Frame::TxnFailed(e) => {
+            let table = lua.create_table()?;
+            table.raw_set("err", e)?;
+            Value::Table(table)
+        }
+    };
+    Ok(value)
+}
+
+/// Convert the value returned by the script to the reply.
+fn value_to_frame(value: &Value) -> Frame {
+    match value {
+        Value::Integer(n) => Frame::Integer(*n),
+        Value::Number(n) => Frame::Integer(*n as i64),
+        Value::String(s) => Frame::Bulk(s.as_bytes().to_vec().into()),
+        Value::Boolean(true) => Frame::Integer(1),
+        Value::Table(t) => {
+            if let Ok(Value::String(e)) = t.raw_get("err") {
+                return Frame::Error(e.to_string_lossy());
+            }
+            if let Ok(Value::String(s)) = t.raw_get("ok") {
+                return Frame::Simple(s.to_string_lossy());
+            }
+            let frames = t
+                .sequence_values::<Value>()
+                .map_while(Result::ok)
+                .map(|value| value_to_frame(&value))
+                .collect();
+            Frame::Array(frames)
+        }
+        _ => Frame::Null,
+    }
+}
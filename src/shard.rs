@@ -0,0 +1,249 @@
+//! Sharding of the keys across multiple db instances.
+//!
+//! The keys are mapped to 16384 hash slots by CRC16 as Redis Cluster does, and every shard
+//! owns a contiguous range of the slots. Only the part in the first `{...}` of a key is hashed
+//! if it is not empty, so the keys with the same hash tag are in the same shard.
+//!
+//! A command is executed on the shard of its keys. MGET, MSET, DEL, EXISTS and TOUCH are split
+//! by shard and the results are merged, so MSET is not atomic across the shards. The other
+//! commands with keys in different slots are rejected with CROSSSLOT. The commands without a
+//! key are executed on all the shards, except BGSAVE and LASTSAVE which use the first shard.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use crc::{Crc, CRC_16_XMODEM};
+use rand::Rng;
+
+use crate::cmd::Command;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::REDIS_CROSSSLOT_ERR;
+use crate::utils::{resp_err, resp_int, resp_ok};
+use crate::Frame;
+
+/// Number of the hash slots.
+pub const SLOT_COUNT: u16 = 16384;
+
+const SLOT_CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_XMODEM);
+
+/// Return the hash slot of the key.
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    let hashed = match key.iter().position(|c| *c == b'{') {
+        Some(start) => match key[start + 1..].iter().position(|c| *c == b'}') {
+            Some(len) if len > 0 => &key[start + 1..start + 1 + len],
+            _ => key,
+        },
+        None => key,
+    };
+    SLOT_CRC.checksum(hashed) % SLOT_COUNT
+}
+
+/// Return the shard owning the hash slot.
+pub(crate) fn slot_shard(slot: u16, shards: usize) -> usize {
+    slot as usize * shards / SLOT_COUNT as usize
+}
+
+/// Return the shard owning the hash slot of the key.
+pub(crate) fn key_shard(key: &[u8], shards: usize) -> usize {
+    slot_shard(key_hash_slot(key), shards)
+}
+
+enum Route {
+    Shard(usize),
+    // split the command by shard or execute it on all the shards
+    FanOut,
+    CrossSlot,
+}
+
+fn route(cmd: &Command, shards: usize) -> Route {
+    use Command::*;
+
+    if matches!(
+        cmd,
+        Mget(_)
+            | Mset(_)
+            | Del(_)
+            | Exists(_)
+            | Touch(_)
+            | Dbsize(_)
+            | Keys(_)
+            | Scan(_)
+            | Randomkey(_)
+            | Gc(_)
+    ) {
+        return Route::FanOut;
+    }
+    match keys_shard(&cmd.keys(), shards) {
+        Some(shard) => Route::Shard(shard),
+        None => Route::CrossSlot,
+    }
+}
+
+/// Return the shard of the keys, the first shard if there is no key. `None` is returned if
+/// the keys are in different slots.
+pub(crate) fn keys_shard(keys: &[&str], shards: usize) -> Option<usize> {
+    let mut slots = keys.iter().map(|key| key_hash_slot(key.as_bytes()));
+    match slots.next() {
+        None => Some(0),
+        Some(slot) if slots.all(|other| other == slot) => Some(slot_shard(slot, shards)),
+        Some(_) => None,
+    }
+}
+
+/// Execute the command on the shards of its keys.
+pub(crate) async fn execute(cmd: Command, shards: &[ClientRef<'_>]) -> crate::Result<Frame> {
+    match route(&cmd, shards.len()) {
+        Route::Shard(shard) => cmd.execute_on(shards[shard]).await,
+        Route::CrossSlot => Ok(resp_err(REDIS_CROSSSLOT_ERR)),
+        Route::FanOut => fan_out(cmd, shards).await,
+    }
+}
+
+async fn fan_out(cmd: Command, shards: &[ClientRef<'_>]) -> crate::Result<Frame> {
+    use Command::*;
+
+    let groups = group_keys(&cmd.keys(), shards.len());
+    match cmd {
+        // the invalid commands without keys are executed as they are
+        Mget(_) | Mset(_) | Del(_) | Exists(_) | Touch(_) if groups.is_empty() => {
+            cmd.execute_on(shards[0]).await
+        }
+        Mget(cmd) => {
+            let mut values = vec![Frame::Null; cmd.keys().len()];
+            for (shard, indices) in groups {
+                match Mget(cmd.select(&indices)).execute_on(shards[shard]).await? {
+                    Frame::Array(frames) => {
+                        for (i, frame) in indices.into_iter().zip(frames) {
+                            values[i] = frame;
+                        }
+                    }
+                    frame => return Ok(frame),
+                }
+            }
+            Ok(Frame::Array(values))
+        }
+        Mset(cmd) => {
+            for (shard, indices) in groups {
+                let frame = Mset(cmd.select(&indices)).execute_on(shards[shard]).await?;
+                if matches!(frame, Frame::Error(_)) {
+                    return Ok(frame);
+                }
+            }
+            Ok(resp_ok())
+        }
+        Del(cmd) => {
+            let cmds = groups
+                .into_iter()
+                .map(|(shard, indices)| (shard, Del(cmd.select(&indices))));
+            sum_integers(cmds, shards).await
+        }
+        Exists(cmd) => {
+            let cmds = groups
+                .into_iter()
+                .map(|(shard, indices)| (shard, Exists(cmd.select(&indices))));
+            sum_integers(cmds, shards).await
+        }
+        Touch(cmd) => {
+            let cmds = groups
+                .into_iter()
+                .map(|(shard, indices)| (shard, Touch(cmd.select(&indices))));
+            sum_integers(cmds, shards).await
+        }
+        Dbsize(_) => {
+            let cmds = (0..shards.len()).map(|shard| (shard, cmd.clone()));
+            sum_integers(cmds, shards).await
+        }
+        Keys(_) => {
+            let mut keys = vec![];
+            for shard in shards {
+                match cmd.clone().execute_on(*shard).await? {
+                    Frame::Array(frames) => keys.extend(frames),
+                    frame => return Ok(frame),
+                }
+            }
+            Ok(Frame::Array(keys))
+        }
+        Scan(_) => scan(cmd, shards).await,
+        Randomkey(_) => {
+            let first = rand::thread_rng().gen_range(0..shards.len());
+            for i in 0..shards.len() {
+                let shard = &shards[(first + i) % shards.len()];
+                let frame = cmd.clone().execute_on(*shard).await?;
+                if !matches!(frame, Frame::Null) {
+                    return Ok(frame);
+                }
+            }
+            Ok(Frame::Null)
+        }
+        _ => {
+            let mut frame = resp_ok();
+            for shard in shards {
+                frame = cmd.clone().execute_on(*shard).await?;
+                if matches!(frame, Frame::Error(_)) {
+                    break;
+                }
+            }
+            Ok(frame)
+        }
+    }
+}
+
+/// Return the indices of the keys grouped by shard.
+fn group_keys(keys: &[&str], shards: usize) -> BTreeMap<usize, Vec<usize>> {
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        groups
+            .entry(key_shard(key.as_bytes(), shards))
+            .or_default()
+            .push(i);
+    }
+    groups
+}
+
+async fn sum_integers<I>(cmds: I, shards: &[ClientRef<'_>]) -> crate::Result<Frame>
+where
+    I: Iterator<Item = (usize, Command)>,
+{
+    let mut sum = 0;
+    for (shard, cmd) in cmds {
+        match cmd.execute_on(shards[shard]).await? {
+            Frame::Integer(n) => sum += n,
+            frame => return Ok(frame),
+        }
+    }
+    Ok(resp_int(sum))
+}
+
+/// Scan all the shards from the cursor. Every shard has returned all of its keys up to its
+/// next cursor, so the merged result is the keys up to the smallest next cursor.
+async fn scan(cmd: Command, shards: &[ClientRef<'_>]) -> crate::Result<Frame> {
+    let mut cursor: Option<Bytes> = None;
+    let mut keys = vec![];
+    for shard in shards {
+        let frame = cmd.clone().execute_on(*shard).await?;
+        let mut parts = match frame {
+            Frame::Array(parts) if parts.len() == 2 => parts,
+            frame => return Ok(frame),
+        };
+        if let (Some(Frame::Array(shard_keys)), Some(Frame::Bulk(next))) =
+            (parts.pop(), parts.pop())
+        {
+            // an empty cursor means the shard is scanned to the end
+            if !next.is_empty() && cursor.as_ref().is_none_or(|cursor| next < *cursor) {
+                cursor = Some(next);
+            }
+            keys.extend(shard_keys.into_iter().filter_map(|key| match key {
+                Frame::Bulk(key) => Some(key),
+                _ => None,
+            }));
+        }
+    }
+    if let Some(cursor) = &cursor {
+        keys.retain(|key| key <= cursor);
+    }
+    keys.sort();
+    Ok(Frame::Array(vec![
+        Frame::Bulk(cursor.unwrap_or_default()),
+        Frame::Array(keys.into_iter().map(Frame::Bulk).collect()),
+    ]))
+}
@@ -0,0 +1,74 @@
+//! Read only snapshots of `DB::snapshot`.
+//!
+//! A snapshot keeps a read only txn of every shard open, the commands executed on the
+//! snapshot are executed in these txns, so they read the data at the time the snapshot is
+//! taken. The txns never lock the keys, so a snapshot doesn't block the writes of the db, but
+//! the data of the snapshot is kept by RocksDB until the snapshot is dropped.
+
+use std::sync::Arc;
+
+use self_cell::self_cell;
+
+use crate::cmd::Command;
+use crate::db::DBInner;
+use crate::rocks::client::ClientRef;
+use crate::rocks::errors::SNAPSHOT_WRITE_ERR;
+use crate::rocks::transaction::RocksTransaction;
+use crate::utils::resp_err;
+use crate::{Frame, Result};
+
+type ShardTxns<'a> = Vec<RocksTransaction<'a>>;
+
+self_cell!(
+    // the txns of the shards, which borrow the dbs of the shards owned by the db
+    struct SnapshotTxns {
+        owner: Arc<DBInner>,
+
+        #[covariant]
+        dependent: ShardTxns,
+    }
+);
+
+/// A read only view of the db at the time it's taken, which is released when dropped.
+///
+/// The read commands executed on the snapshot see the same data, except DBSIZE which
+/// returns the current number of the keys. The shards of a sharded db are snapshotted one
+/// after another.
+pub struct Snapshot {
+    txns: SnapshotTxns,
+}
+
+impl Snapshot {
+    pub(crate) fn new(inner: Arc<DBInner>) -> Self {
+        let txns = SnapshotTxns::new(inner, |inner| {
+            inner
+                .shards
+                .iter()
+                .map(|shard| shard.read_only_txn())
+                .collect()
+        });
+        Self { txns }
+    }
+
+    /// Execute the read command on the snapshot, the write commands are rejected.
+    pub async fn execute(&self, cmd: Command) -> Result<Frame> {
+        let inner = self.txns.borrow_owner();
+        let cmd = inner.scripts.resolve(cmd);
+        if cmd.is_write() {
+            return Ok(resp_err(SNAPSHOT_WRITE_ERR));
+        }
+        cmd.execute_in(inner, &self.shards()).await
+    }
+
+    /// The shards of the db with the txns of the snapshot, the reads of the shards see the
+    /// snapshot.
+    pub(crate) fn shards(&self) -> Vec<ClientRef<'_>> {
+        let inner = self.txns.borrow_owner();
+        inner
+            .shards
+            .iter()
+            .zip(self.txns.borrow_dependent())
+            .map(|(shard, txn)| ClientRef::with_txn(shard, txn))
+            .collect()
+    }
+}
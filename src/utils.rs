@@ -1,4 +1,7 @@
-use crate::rocks::errors::RError;
+use crate::long_double::LongDouble;
+use crate::rocks::errors::{
+    RError, REDIS_INCR_NAN_OR_INFINITY_ERR, REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR,
+};
 use crate::Frame;
 use std::collections::HashSet;
 use std::io;
@@ -69,6 +72,18 @@ pub fn ttl_from_timestamp(timestamp: i64) -> i64 {
     }
 }
 
+/// Add `step` to the saved float value like redis, in long double precision and formatted
+/// as `%.17Lf` without trailing zeros. NaN and Infinity are not valid.
+pub fn incr_float(prev: Option<&[u8]>, step: f64) -> Result<Vec<u8>, RError> {
+    let prev = match prev {
+        Some(prev) => LongDouble::parse(prev).ok_or(REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR)?,
+        None => LongDouble::ZERO,
+    };
+    let step = LongDouble::from_f64(step).ok_or(REDIS_VALUE_IS_NOT_VALID_FLOAT_ERR)?;
+    let new = prev.add(step).ok_or(REDIS_INCR_NAN_OR_INFINITY_ERR)?;
+    Ok(new.to_human_string().into_bytes())
+}
+
 #[allow(dead_code)]
 pub async fn sleep(ms: u32) {
     tokio::time::sleep(Duration::from_millis(ms as u64)).await;
@@ -0,0 +1,121 @@
+//! Watch the changes of the keys with a prefix.
+//!
+//! The writes of every txn are recorded while there is any watch, and decoded into the
+//! `ChangeEvent` of `DB::changes_since` after the txn is committed. The changes are delivered
+//! in the order of commit. Like the subscriptions of pub/sub, every watch has a bounded buffer
+//! of `OpenOptions::pubsub_buffer_size` changes, a watch not read in time is disconnected and
+//! its stream ends after the buffered changes.
+
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use tokio::sync::mpsc;
+
+use crate::rocks::cdc::ChangeEvent;
+
+/// An event received by a watch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchEvent {
+    /// A change of an existing key in the initial snapshot, applying the changes to an empty
+    /// view builds the keys.
+    Snapshot(ChangeEvent),
+    /// All the changes of the initial snapshot are received.
+    SnapshotDone,
+    /// A change of a committed txn. The changes committed while the snapshot is read may be
+    /// received again after it, applying them again leaves the view the same.
+    Change(ChangeEvent),
+}
+
+struct Watcher {
+    prefix: Vec<u8>,
+    sender: mpsc::Sender<ChangeEvent>,
+}
+
+pub(crate) struct Watchers {
+    watchers: Mutex<HashMap<u64, Watcher>>,
+    next_id: AtomicU64,
+    buffer_size: usize,
+}
+
+impl Watchers {
+    pub(crate) fn new(buffer_size: usize) -> Self {
+        Self {
+            watchers: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            buffer_size: buffer_size.max(1),
+        }
+    }
+
+    /// Return true if there is any watch, the changes are recorded only if there is.
+    pub(crate) fn is_active(&self) -> bool {
+        !self.watchers.lock().unwrap().is_empty()
+    }
+
+    /// Deliver the changes of a committed txn to the watches of the keys, the slow watches
+    /// are disconnected.
+    pub(crate) fn notify(&self, events: &[ChangeEvent]) {
+        let mut watchers = self.watchers.lock().unwrap();
+        watchers.retain(|_, watcher| {
+            events
+                .iter()
+                .filter(|event| event.key.starts_with(&watcher.prefix))
+                .all(|event| watcher.sender.try_send(event.clone()).is_ok())
+        });
+    }
+}
+
+/// A stream of the changes of the keys with a prefix, it is created by `Conn::watch_prefix`
+/// or `Conn::watch_prefix_with_snapshot`. The watch is removed when it is dropped.
+pub struct Watch {
+    id: u64,
+    watchers: Arc<Watchers>,
+    snapshot: VecDeque<WatchEvent>,
+    receiver: mpsc::Receiver<ChangeEvent>,
+}
+
+impl Watch {
+    pub(crate) fn new(watchers: Arc<Watchers>, prefix: Vec<u8>) -> Self {
+        let id = watchers.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(watchers.buffer_size);
+        watchers
+            .watchers
+            .lock()
+            .unwrap()
+            .insert(id, Watcher { prefix, sender });
+        Self {
+            id,
+            watchers,
+            snapshot: VecDeque::new(),
+            receiver,
+        }
+    }
+
+    /// Receive the changes of the snapshot before the changes of the txns.
+    pub(crate) fn set_snapshot(&mut self, events: Vec<ChangeEvent>) {
+        self.snapshot = events.into_iter().map(WatchEvent::Snapshot).collect();
+        self.snapshot.push_back(WatchEvent::SnapshotDone);
+    }
+}
+
+impl Stream for Watch {
+    type Item = WatchEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(event) = self.snapshot.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        self.receiver
+            .poll_recv(cx)
+            .map(|event| event.map(WatchEvent::Change))
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.watchers.watchers.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,156 @@
+use mapuche_embedded::{
+    cmd::{Command, Get, Set},
+    frame::Frame,
+    OpenOptions,
+};
+use tokio::task::spawn;
+
+#[tokio::test]
+async fn atomic_closure() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+
+    let rank = conn
+        .atomic(async |tx| {
+            tx.hset("atomic_user", &[("name", "alice"), ("score", "10")])
+                .await?;
+            tx.sadd("atomic_users", &["alice"]).await?;
+            tx.rpush("atomic_log", &["joined"]).await?;
+            tx.zadd("atomic_board", 10.0, "alice").await?;
+            let score = tx.hincrby("atomic_user", "score", 5).await?;
+            tx.zadd("atomic_board", score as f64, "alice").await?;
+            tx.set("atomic_last", "alice").await?;
+            tx.zrange("atomic_board", 0, -1).await
+        })
+        .await
+        .unwrap();
+    assert_eq!(rank, vec!["alice"]);
+
+    let (name, score, members, log) = conn
+        .atomic(async |tx| {
+            Ok((
+                tx.hget("atomic_user", "name").await?,
+                tx.zscore("atomic_board", "alice").await?,
+                tx.smembers("atomic_users").await?,
+                tx.lrange("atomic_log", 0, -1).await?,
+            ))
+        })
+        .await
+        .unwrap();
+    assert_eq!(name.unwrap(), "alice");
+    assert_eq!(score, Some(15.0));
+    assert_eq!(members, vec!["alice"]);
+    assert_eq!(log, vec!["joined"]);
+
+    // the writes are discarded if the closure fails
+    let res: mapuche_embedded::Result<()> = conn
+        .atomic(async |tx| {
+            tx.set("atomic_last", "bob").await?;
+            tx.lpush("atomic_log", &["left"]).await?;
+            // wrong type
+            tx.hget("atomic_last", "name").await?;
+            Ok(())
+        })
+        .await;
+    assert!(res.is_err());
+    let frame = conn
+        .execute(Command::Get(Get::new("atomic_last")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "alice");
+    let len = conn
+        .atomic(async |tx| tx.llen("atomic_log").await)
+        .await
+        .unwrap();
+    assert_eq!(len, 1);
+
+    let res: mapuche_embedded::Result<()> = conn
+        .atomic(async |tx| {
+            tx.del(&["atomic_last"]).await?;
+            Err("cancelled".into())
+        })
+        .await;
+    assert_eq!(res.unwrap_err().to_string(), "cancelled");
+    let frame = conn
+        .execute(Command::Get(Get::new("atomic_last")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "alice");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn atomic_conflicts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+    conn.execute(Command::Set(Set::new("atomic_counter", 0, None, None)))
+        .await
+        .unwrap();
+
+    let mut tasks = vec![];
+    for _ in 0..4 {
+        let db = db.clone();
+        tasks.push(spawn(async move {
+            let conn = db.conn();
+            for _ in 0..25 {
+                // read and write in separate commands, the value must not be lost
+                conn.atomic(async |tx| {
+                    let value = tx.get("atomic_counter").await?.unwrap();
+                    let value: i64 = std::str::from_utf8(&value)?.parse()?;
+                    tx.set("atomic_counter", value + 1).await
+                })
+                .await
+                .unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let frame = conn
+        .execute(Command::Get(Get::new("atomic_counter")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "100");
+}
+
+#[tokio::test]
+async fn sharded_atomic() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().shards(4).open(path).await.unwrap();
+    let conn = db.conn();
+
+    let value = conn
+        .atomic(async |tx| {
+            tx.set("{order}.status", "paid").await?;
+            tx.incrby("{order}.count", 2).await
+        })
+        .await
+        .unwrap();
+    assert_eq!(value, 2);
+    let frame = conn
+        .execute(Command::Get(Get::new("{order}.status")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "paid");
+
+    // the keys must be in the shard of the first key
+    let res = conn
+        .atomic(async |tx| {
+            for i in 0..16 {
+                tx.set(format!("atomic_shard_{i}"), i).await?;
+            }
+            Ok(())
+        })
+        .await;
+    assert!(res.is_err());
+    let frame = conn
+        .execute(Command::Get(Get::new("atomic_shard_0")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+}
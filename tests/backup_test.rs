@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use mapuche_embedded::{
+    cmd::{Bgsave, Command, Get, Lastsave, Set},
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new()
+        .open(dir.path().join("store_checkpoint"))
+        .await
+        .unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Set(Set::new("checkpoint_str", "v", None, None)))
+        .await
+        .unwrap();
+    db.checkpoint(dir.path().join("checkpoint")).await.unwrap();
+    // the checkpoint directory must not exist
+    assert!(db.checkpoint(dir.path().join("checkpoint")).await.is_err());
+
+    let copy = OpenOptions::new()
+        .open(dir.path().join("checkpoint"))
+        .await
+        .unwrap();
+    let frame = copy
+        .conn()
+        .execute(Command::Get(Get::new("checkpoint_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v");
+}
+
+#[tokio::test]
+async fn backup_and_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new()
+        .backup_dir(dir.path().join("backup"))
+        .backup_retention(2)
+        .open(dir.path().join("store_backup"))
+        .await
+        .unwrap();
+    let conn = db.conn();
+
+    let frame = conn
+        .execute(Command::Lastsave(Lastsave::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+
+    let _ = conn
+        .execute(Command::Set(Set::new("backup_str", "v1", None, None)))
+        .await
+        .unwrap();
+    let first = db.backup().await.unwrap();
+    let _ = conn
+        .execute(Command::Set(Set::new("backup_str", "v2", None, None)))
+        .await
+        .unwrap();
+    let second = db.backup().await.unwrap();
+    assert!(second > first);
+    let frame = conn
+        .execute(Command::Lastsave(Lastsave::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(t) if t > 0));
+
+    // only the latest 2 backups are kept
+    let third = db.backup().await.unwrap();
+    let ids: Vec<u32> = db
+        .list_backups()
+        .unwrap()
+        .into_iter()
+        .map(|info| info.id)
+        .collect();
+    assert_eq!(ids, vec![second, third]);
+
+    db.restore_backup(second, dir.path().join("backup_restored"))
+        .await
+        .unwrap();
+    let restored = OpenOptions::new()
+        .open(dir.path().join("backup_restored"))
+        .await
+        .unwrap();
+    let frame = restored
+        .conn()
+        .execute(Command::Get(Get::new("backup_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v2");
+
+    let frame = conn.execute(Command::Bgsave(Bgsave::new())).await.unwrap();
+    assert_eq!(frame, "Background saving started");
+
+    // backup directory is not configured
+    let frame = restored
+        .conn()
+        .execute(Command::Bgsave(Bgsave::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+}
+
+// count the files in `dir` and its sub directories, 0 if it does not exist
+fn count_files(dir: &Path) -> u32 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                count_files(&path)
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+#[tokio::test]
+async fn incremental_backups() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store_backup_incremental");
+    let backup = dir.path().join("backup_incremental");
+    let db = OpenOptions::new()
+        .backup_dir(&backup)
+        .open(path)
+        .await
+        .unwrap();
+    let conn = db.conn();
+
+    for i in 0..100 {
+        conn.execute(Command::Set(Set::new(format!("inc_{i}"), "v", None, None)))
+            .await
+            .unwrap();
+    }
+    db.backup().await.unwrap();
+    conn.execute(Command::Set(Set::new("inc_0", "v2", None, None)))
+        .await
+        .unwrap();
+    db.backup().await.unwrap();
+
+    // the unchanged files of the first backup are shared by the second one
+    let backups = db.list_backups().unwrap();
+    assert_eq!(backups.len(), 2);
+    let private = count_files(&backup.join("private"));
+    let shared = count_files(&backup.join("shared")) + count_files(&backup.join("shared_checksum"));
+    assert!(shared > 0);
+    assert!(shared + private < backups[0].num_files + backups[1].num_files);
+
+    drop(conn);
+    drop(db);
+}
@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use mapuche_embedded::{
+    cmd::{Command, Del, Hset, Sadd, Set},
+    ChangeOp, ChangeStream, DataType, OpenOptions,
+};
+
+async fn next_op(changes: &mut ChangeStream) -> (u64, String, DataType, ChangeOp) {
+    let event = tokio::time::timeout(Duration::from_secs(1), changes.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let key = String::from_utf8(event.key.to_vec()).unwrap();
+    (event.seq, key, event.data_type, event.op)
+}
+
+#[tokio::test]
+async fn changes_since() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new()
+        .change_capture(true)
+        .open(path)
+        .await
+        .unwrap();
+    let conn = db.conn();
+    let mut changes = db.changes_since(0).unwrap();
+
+    conn.execute(Command::Set(Set::new("cdc", "1", None, None)))
+        .await
+        .unwrap();
+    let (seq, key, data_type, op) = next_op(&mut changes).await;
+    assert_eq!((seq, key.as_str()), (1, "cdc"));
+    assert_eq!(data_type, DataType::String);
+    assert_eq!(
+        op,
+        ChangeOp::Set {
+            value: Bytes::from_static(b"1")
+        }
+    );
+
+    conn.execute(Command::Hset(Hset::new("cdc_hash", &[("f", "v")])))
+        .await
+        .unwrap();
+    let mut ops = [next_op(&mut changes).await, next_op(&mut changes).await];
+    ops.sort_by_key(|(.., op)| matches!(op, ChangeOp::Meta));
+    assert!(ops.iter().all(|(seq, key, data_type, _)| *seq == 2
+        && key == "cdc_hash"
+        && *data_type == DataType::Hash));
+    assert_eq!(
+        ops[0].3,
+        ChangeOp::HashSet {
+            field: Bytes::from_static(b"f"),
+            value: Bytes::from_static(b"v"),
+        }
+    );
+    assert_eq!(ops[1].3, ChangeOp::Meta);
+
+    // the removal of the fields is folded into the deletion of the key
+    conn.execute(Command::Del(Del::new(&["cdc_hash"])))
+        .await
+        .unwrap();
+    let (seq, key, data_type, op) = next_op(&mut changes).await;
+    assert_eq!((seq, key.as_str(), op), (3, "cdc_hash", ChangeOp::Del));
+    assert_eq!(data_type, DataType::Hash);
+
+    // nothing is logged without changes
+    conn.execute(Command::Del(Del::new(&["cdc_hash"])))
+        .await
+        .unwrap();
+    conn.execute(Command::Sadd(Sadd::new("cdc_set", &["m"])))
+        .await
+        .unwrap();
+    let mut ops = [next_op(&mut changes).await, next_op(&mut changes).await];
+    ops.sort_by_key(|(.., op)| matches!(op, ChangeOp::Meta));
+    assert_eq!(ops[0].0, 4);
+    assert_eq!(
+        ops[0].3,
+        ChangeOp::SetAdd {
+            member: Bytes::from_static(b"m")
+        }
+    );
+
+    // the changes are resumed after the db is reopened
+    drop(changes);
+    drop(conn);
+    drop(db);
+    let db = OpenOptions::new()
+        .change_capture(true)
+        .open(path)
+        .await
+        .unwrap();
+    let mut changes = db.changes_since(3).unwrap();
+    let (seq, key, ..) = next_op(&mut changes).await;
+    assert_eq!((seq, key.as_str()), (4, "cdc_set"));
+    next_op(&mut changes).await;
+    db.conn()
+        .execute(Command::Set(Set::new("cdc", "2", Some(10_000), None)))
+        .await
+        .unwrap();
+    let (seq, _, _, op) = next_op(&mut changes).await;
+    assert_eq!(seq, 5);
+    assert_eq!(
+        op,
+        ChangeOp::Set {
+            value: Bytes::from_static(b"2")
+        }
+    );
+}
+
+#[tokio::test]
+async fn changes_retention() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store_cdc_retention");
+    let db = OpenOptions::new()
+        .change_capture(true)
+        .change_retention(2)
+        .open(path)
+        .await
+        .unwrap();
+    assert!(OpenOptions::new()
+        .open(dir.path().join("store_cdc_disabled"))
+        .await
+        .unwrap()
+        .changes_since(0)
+        .is_err());
+
+    let conn = db.conn();
+    for i in 0..3 {
+        conn.execute(Command::Set(Set::new("retention", i, None, None)))
+            .await
+            .unwrap();
+    }
+    // the changes of the first txn are trimmed
+    let mut changes = db.changes_since(0).unwrap();
+    assert_eq!(next_op(&mut changes).await.0, 2);
+    assert_eq!(next_op(&mut changes).await.0, 3);
+    let mut changes = db.changes_since(2).unwrap();
+    assert_eq!(next_op(&mut changes).await.0, 3);
+    let mut changes = db.changes_since(0).unwrap();
+    conn.execute(Command::Set(Set::new("retention", 3, None, None)))
+        .await
+        .unwrap();
+    // the stream fails if it falls behind the kept changes
+    let mut behind = db.changes_since(1).unwrap();
+    assert!(behind.next().await.unwrap().is_err());
+    assert_eq!(next_op(&mut changes).await.0, 3);
+}
@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+use mapuche_embedded::{
+    cluster::migrate_slot,
+    cmd::{Asking, Cluster, ClusterOp, Command, Get, Mget, Set, SlotState},
+    frame::Frame,
+    OpenOptions, DB,
+};
+
+const ADDR_A: &str = "127.0.0.1:7001";
+const ADDR_B: &str = "127.0.0.1:7002";
+
+async fn open_node(path: PathBuf, id: &str, peer: &str) -> DB {
+    let (addr, slots, peer_addr, peer_slots) = if id == "node-a" {
+        (ADDR_A, 0..=8191, ADDR_B, 8192..=16383)
+    } else {
+        (ADDR_B, 8192..=16383, ADDR_A, 0..=8191)
+    };
+    OpenOptions::new()
+        .cluster_node(id, addr, vec![slots])
+        .cluster_peer(peer, peer_addr, vec![peer_slots])
+        .open(path)
+        .await
+        .unwrap()
+}
+
+fn error_of(frame: Frame) -> String {
+    match frame {
+        Frame::Error(e) => e,
+        frame => panic!("unexpected frame {frame:?}"),
+    }
+}
+
+#[tokio::test]
+async fn cluster_redirections() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = open_node(dir.path().join("store_cluster_a"), "node-a", "node-b").await;
+    let b = open_node(dir.path().join("store_cluster_b"), "node-b", "node-a").await;
+    let conn_a = a.conn();
+    let conn_b = b.conn();
+
+    let frame = conn_a
+        .execute(Command::Cluster(Cluster::new(ClusterOp::Keyslot {
+            key: "foo".to_string(),
+        })))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(12182)));
+
+    let frame = conn_a
+        .execute(Command::Cluster(Cluster::new(ClusterOp::Slots)))
+        .await
+        .unwrap();
+    let Frame::Array(slots) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(slots.len(), 2);
+
+    // "foo" is in slot 12182 of node-b
+    let frame = conn_a
+        .execute(Command::Set(Set::new("foo", "bar", None, None)))
+        .await
+        .unwrap();
+    assert_eq!(error_of(frame), format!("MOVED 12182 {ADDR_B}"));
+    let frame = conn_b
+        .execute(Command::Set(Set::new("foo", "bar", None, None)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+
+    let frame = conn_b
+        .execute(Command::Mget(Mget::new(&["foo", "bar"])))
+        .await
+        .unwrap();
+    assert!(error_of(frame).starts_with("CROSSSLOT"));
+
+    // a key not moved yet is served by the source, a missing key is asked to the target
+    let migrating = ClusterOp::Setslot {
+        slot: 12182,
+        state: SlotState::Migrating("node-a".to_string()),
+    };
+    let frame = conn_b
+        .execute(Command::Cluster(Cluster::new(migrating)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn_b.execute(Command::Get(Get::new("foo"))).await.unwrap();
+    assert_eq!(frame, "bar");
+    let frame = conn_b
+        .execute(Command::Get(Get::new("{foo}.missing")))
+        .await
+        .unwrap();
+    assert_eq!(error_of(frame), format!("ASK 12182 {ADDR_A}"));
+
+    let importing = ClusterOp::Setslot {
+        slot: 12182,
+        state: SlotState::Importing("node-b".to_string()),
+    };
+    let frame = conn_a
+        .execute(Command::Cluster(Cluster::new(importing)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    // only the command following ASKING is served by the importing node
+    let _ = conn_a
+        .execute(Command::Asking(Asking::new()))
+        .await
+        .unwrap();
+    let frame = conn_a
+        .execute(Command::Get(Get::new("{foo}.missing")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+    let frame = conn_a
+        .execute(Command::Get(Get::new("{foo}.missing")))
+        .await
+        .unwrap();
+    assert!(error_of(frame).starts_with("MOVED"));
+
+    let count = migrate_slot(&conn_b, &conn_a, 12182).await.unwrap();
+    assert_eq!(count, 1);
+    let frame = conn_a.execute(Command::Get(Get::new("foo"))).await.unwrap();
+    assert_eq!(frame, "bar");
+    let frame = conn_b.execute(Command::Get(Get::new("foo"))).await.unwrap();
+    assert_eq!(error_of(frame), format!("MOVED 12182 {ADDR_A}"));
+
+    let frame = conn_b
+        .execute(Command::Cluster(Cluster::new(ClusterOp::Nodes)))
+        .await
+        .unwrap();
+    let Frame::Bulk(nodes) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    let nodes = String::from_utf8(nodes.to_vec()).unwrap();
+    assert!(nodes.contains("node-b 127.0.0.1:7002@17002 myself,master"));
+    assert!(nodes.contains(" 12182"));
+}
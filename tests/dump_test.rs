@@ -0,0 +1,121 @@
+use mapuche_embedded::{
+    cmd::{Command, Del, Dump, Get, Restore, Set, Zadd, Zcard, Zscore, TTL},
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn dump_and_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Del(Del::new(&[
+            "dump_str",
+            "dump_str2",
+            "dump_zset",
+            "dump_zset2",
+        ])))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Set(Set::new("dump_str", "v", Some(100_000), None)))
+        .await
+        .unwrap();
+    let value = match conn
+        .execute(Command::Dump(Dump::new("dump_str")))
+        .await
+        .unwrap()
+    {
+        Frame::Bulk(value) => value,
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+
+    let frame = conn
+        .execute(Command::Restore(Restore::new(
+            "dump_str2",
+            0,
+            value.clone(),
+            false,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Get(Get::new("dump_str2")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v");
+    let frame = conn
+        .execute(Command::TTL(TTL::new("dump_str2")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(ttl) if ttl > 90));
+
+    // the key exists without REPLACE
+    let frame = conn
+        .execute(Command::Restore(Restore::new(
+            "dump_str2",
+            0,
+            value.clone(),
+            false,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    // corrupted value
+    let mut corrupted = value.to_vec();
+    corrupted[6] ^= 1;
+    let frame = conn
+        .execute(Command::Restore(Restore::new(
+            "dump_str2",
+            0,
+            corrupted.into(),
+            true,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let _ = conn
+        .execute(Command::Zadd(Zadd::new(
+            "dump_zset",
+            &["a", "b"],
+            &[1.5, -2.0],
+            None,
+            false,
+        )))
+        .await
+        .unwrap();
+    let value = match conn
+        .execute(Command::Dump(Dump::new("dump_zset")))
+        .await
+        .unwrap()
+    {
+        Frame::Bulk(value) => value,
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+    let frame = conn
+        .execute(Command::Restore(Restore::new("dump_zset2", 0, value, true)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Zcard(Zcard::new("dump_zset2")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+    let frame = conn
+        .execute(Command::Zscore(Zscore::new("dump_zset2", "b")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "-2");
+
+    let frame = conn
+        .execute(Command::Dump(Dump::new("dump_not_exists")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+}
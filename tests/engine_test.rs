@@ -0,0 +1,115 @@
+use mapuche_embedded::{KvIter, MemoryEngine, RError, StorageEngine, CF_NAME_DEFAULT};
+
+const CF: &str = CF_NAME_DEFAULT;
+
+fn keys(it: KvIter) -> Vec<String> {
+    it.map(|kv| String::from_utf8(kv.unwrap().0.to_vec()).unwrap())
+        .collect()
+}
+
+#[test]
+fn memory_engine() {
+    let engine = MemoryEngine::new();
+    engine.put(CF, b"a", b"1").unwrap();
+    engine.put(CF, b"b", b"2").unwrap();
+    engine.put(CF, b"c", b"3").unwrap();
+    engine.put(CF, b"b", b"4").unwrap();
+    engine.del(CF, b"c").unwrap();
+    assert_eq!(engine.get(CF, b"b").unwrap(), Some(b"4".to_vec()));
+    assert_eq!(engine.get(CF, b"c").unwrap(), None);
+    assert_eq!(
+        engine.batch_get(CF, &[b"c", b"a"]).unwrap(),
+        vec![None, Some(b"1".to_vec())]
+    );
+    assert!(engine.get("missing", b"a").is_err());
+
+    // the keys are scanned in batches
+    for i in 0..1000 {
+        engine.put(CF, format!("k{i:04}").as_bytes(), b"v").unwrap();
+    }
+    let scanned = keys(engine.scan(CF, b"k0100", Some(b"k0900")).unwrap());
+    assert_eq!(scanned.len(), 800);
+    assert_eq!(scanned[0], "k0100");
+    assert_eq!(scanned[799], "k0899");
+    let scanned = keys(engine.scan_reverse(CF, b"k0900", Some(b"k0100")).unwrap());
+    assert_eq!(scanned.len(), 800);
+    assert_eq!(scanned[0], "k0900");
+    assert_eq!(scanned[799], "k0101");
+    assert_eq!(keys(engine.scan(CF, b"k", None).unwrap()).len(), 1000);
+    assert!(keys(engine.scan(CF, b"k0500", Some(b"k0500")).unwrap()).is_empty());
+}
+
+#[test]
+fn memory_txn() {
+    let engine = MemoryEngine::new();
+    engine.put(CF, b"a", b"1").unwrap();
+    engine.put(CF, b"c", b"3").unwrap();
+
+    let txn = engine.begin(false);
+    txn.put(CF, b"b", b"2").unwrap();
+    txn.del(CF, b"c").unwrap();
+    assert_eq!(txn.get(CF, b"b").unwrap(), Some(b"2".to_vec()));
+    assert_eq!(txn.get(CF, b"c").unwrap(), None);
+    // the scans see the writes of the txn
+    assert_eq!(keys(txn.scan(CF, b"", None).unwrap()), ["a", "b"]);
+    assert_eq!(keys(txn.scan_reverse(CF, b"z", None).unwrap()), ["b", "a"]);
+    // the writes are not visible until the txn is committed
+    assert_eq!(engine.get(CF, b"b").unwrap(), None);
+    txn.commit().unwrap();
+    assert_eq!(keys(engine.scan(CF, b"", None).unwrap()), ["a", "b"]);
+
+    // the writes are discarded if the txn is dropped
+    let txn = engine.begin(false);
+    txn.put(CF, b"d", b"4").unwrap();
+    drop(txn);
+    assert_eq!(engine.get(CF, b"d").unwrap(), None);
+}
+
+#[test]
+fn memory_txn_snapshot() {
+    let engine = MemoryEngine::new();
+    engine.put(CF, b"a", b"1").unwrap();
+
+    let snapshot = engine.begin(true);
+    engine.put(CF, b"a", b"2").unwrap();
+    engine.put(CF, b"b", b"2").unwrap();
+    assert_eq!(snapshot.get(CF, b"a").unwrap(), Some(b"1".to_vec()));
+    assert_eq!(snapshot.get(CF, b"b").unwrap(), None);
+    assert_eq!(keys(snapshot.scan(CF, b"", None).unwrap()), ["a"]);
+    // writing the key changed after the snapshot fails
+    assert!(matches!(snapshot.put(CF, b"a", b"3"), Err(RError::Txn(_))));
+    assert!(snapshot.put(CF, b"c", b"3").is_ok());
+    drop(snapshot);
+    assert_eq!(engine.get(CF, b"a").unwrap(), Some(b"2".to_vec()));
+    assert_eq!(engine.get(CF, b"c").unwrap(), None);
+}
+
+#[test]
+fn memory_txn_conflicts() {
+    let engine = MemoryEngine::new();
+    engine.put(CF, b"counter", b"0").unwrap();
+
+    let first = engine.begin(false);
+    let second = engine.begin(false);
+    assert_eq!(
+        first.get_for_update(CF, b"counter", false).unwrap(),
+        Some(b"0".to_vec())
+    );
+    assert_eq!(
+        second.get_for_update(CF, b"counter", false).unwrap(),
+        Some(b"0".to_vec())
+    );
+    first.put(CF, b"counter", b"1").unwrap();
+    second.put(CF, b"counter", b"1").unwrap();
+    first.commit().unwrap();
+    // the key is written after it's read by the second txn
+    assert!(matches!(second.commit(), Err(RError::Txn(_))));
+    assert_eq!(engine.get(CF, b"counter").unwrap(), Some(b"1".to_vec()));
+
+    // the keys only read without update are not checked
+    let reader = engine.begin(false);
+    assert!(reader.get(CF, b"counter").unwrap().is_some());
+    reader.put(CF, b"other", b"1").unwrap();
+    engine.put(CF, b"counter", b"2").unwrap();
+    reader.commit().unwrap();
+}
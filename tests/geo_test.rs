@@ -0,0 +1,127 @@
+use mapuche_embedded::{
+    cmd::{
+        Command, Del, GeoFrom, GeoSearchOptions, GeoShape, GeoSort, GeoUnit, Geoadd, Geodist,
+        Geopos, Geosearch, Geosearchstore, Zcard,
+    },
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn geo() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Del(Del::new(&["Sicily", "Sicily_store"])))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Geoadd(Geoadd::new(
+            "Sicily",
+            &[(13.361389, 38.115556), (15.087269, 37.502669)],
+            &["Palermo", "Catania"],
+            None,
+            false,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+
+    let frame = conn
+        .execute(Command::Geoadd(Geoadd::new(
+            "Sicily",
+            &[(200.0, 38.0)],
+            &["Nowhere"],
+            None,
+            false,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::Geodist(Geodist::new(
+            "Sicily",
+            "Palermo",
+            "Catania",
+            GeoUnit::Km,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "166.2742");
+
+    let frame = conn
+        .execute(Command::Geopos(Geopos::new(
+            "Sicily",
+            &["Palermo", "Nowhere"],
+        )))
+        .await
+        .unwrap();
+    match frame {
+        Frame::Array(items) => {
+            assert_eq!(items.len(), 2);
+            assert!(matches!(items[1], Frame::Null));
+        }
+        _ => panic!("unexpected frame {:?}", frame),
+    }
+
+    let frame = conn
+        .execute(Command::Geosearch(Geosearch::new(
+            "Sicily",
+            GeoFrom::LonLat(15.0, 37.0),
+            GeoShape::Radius(200.0),
+            GeoUnit::Km,
+            GeoSearchOptions {
+                sort: Some(GeoSort::Asc),
+                ..Default::default()
+            },
+        )))
+        .await
+        .unwrap();
+    assert_eq!(
+        format!("{:?}", frame),
+        format!(
+            "{:?}",
+            Frame::Array(vec![
+                Frame::Bulk("Catania".into()),
+                Frame::Bulk("Palermo".into())
+            ])
+        )
+    );
+
+    let frame = conn
+        .execute(Command::Geosearch(Geosearch::new(
+            "Sicily",
+            GeoFrom::LonLat(15.0, 37.0),
+            GeoShape::Box(400.0, 400.0),
+            GeoUnit::Km,
+            GeoSearchOptions {
+                any: true,
+                ..Default::default()
+            },
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::Geosearchstore(Geosearchstore::new(
+            "Sicily_store",
+            "Sicily",
+            GeoFrom::Member("Palermo".to_owned()),
+            GeoShape::Radius(100.0),
+            GeoUnit::Km,
+            GeoSearchOptions::default(),
+            true,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Zcard(Zcard::new("Sicily_store")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+}
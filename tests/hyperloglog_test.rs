@@ -0,0 +1,57 @@
+use mapuche_embedded::{
+    cmd::{Command, Del, Pfadd, Pfcount, Pfmerge},
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn hyperloglog() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Del(Del::new(&["hll1", "hll2", "hll3"])))
+        .await
+        .unwrap();
+
+    let frame = conn
+        .execute(Command::Pfadd(Pfadd::new(
+            "hll1",
+            &["a", "b", "c", "d", "e", "f", "g"],
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Pfadd(Pfadd::new("hll1", &["a"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+    let frame = conn
+        .execute(Command::Pfcount(Pfcount::new(&["hll1"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(7)));
+
+    let elements: Vec<String> = (0..1000).map(|i| format!("ele{}", i)).collect();
+    conn.execute(Command::Pfadd(Pfadd::new("hll2", &elements)))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Pfcount(Pfcount::new(&["hll1", "hll2"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(n) if (980..=1030).contains(&n)));
+
+    let frame = conn
+        .execute(Command::Pfmerge(Pfmerge::new("hll3", &["hll1", "hll2"])))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Pfcount(Pfcount::new(&["hll3"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(n) if (980..=1030).contains(&n)));
+}
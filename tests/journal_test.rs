@@ -0,0 +1,143 @@
+use mapuche_embedded::{
+    cmd::{Command, Del, Expire, Get, IncrDecr, Sadd, Set, Smembers, Spop},
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn journal_and_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new()
+        .journal(dir.path().join("journal"))
+        .journal_file_size(100)
+        .open(dir.path().join("store_journal"))
+        .await
+        .unwrap();
+    let conn = db.conn();
+
+    // seq 1
+    let _ = conn
+        .execute(Command::Del(Del::new(&["journal_str", "journal_counter"])))
+        .await
+        .unwrap();
+    // seq 2
+    let _ = conn
+        .execute(Command::Set(Set::new("journal_str", "v", None, None)))
+        .await
+        .unwrap();
+    // reads and failed writes are not journaled
+    let _ = conn
+        .execute(Command::Get(Get::new("journal_str")))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Incr(IncrDecr::new("journal_str", 1)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    // seq 3 and 4
+    for _ in 0..2 {
+        let _ = conn
+            .execute(Command::Incr(IncrDecr::new("journal_counter", 1)))
+            .await
+            .unwrap();
+    }
+    // the journal is rotated by the file size
+    assert!(
+        std::fs::read_dir(dir.path().join("journal"))
+            .unwrap()
+            .count()
+            > 1
+    );
+
+    let db2 = OpenOptions::new()
+        .open(dir.path().join("store_journal_replay"))
+        .await
+        .unwrap();
+    let conn2 = db2.conn();
+    let _ = conn2
+        .execute(Command::Del(Del::new(&["journal_str", "journal_counter"])))
+        .await
+        .unwrap();
+    let replayed = db2
+        .replay_journal(dir.path().join("journal"), 1)
+        .await
+        .unwrap();
+    assert_eq!(replayed, 4);
+    let frame = conn2
+        .execute(Command::Get(Get::new("journal_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v");
+    let frame = conn2
+        .execute(Command::Get(Get::new("journal_counter")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "2");
+
+    let replayed = db2
+        .replay_journal(dir.path().join("journal"), 4)
+        .await
+        .unwrap();
+    assert_eq!(replayed, 1);
+    let frame = conn2
+        .execute(Command::Get(Get::new("journal_counter")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "3");
+}
+
+#[tokio::test]
+async fn journal_deterministic_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new()
+        .journal(dir.path().join("journal"))
+        .open(dir.path().join("store_journal"))
+        .await
+        .unwrap();
+    let conn = db.conn();
+    let members: Vec<String> = (0..10).map(|i| format!("m{i}")).collect();
+    conn.execute(Command::Sadd(Sadd::new("journal_set", &members)))
+        .await
+        .unwrap();
+    // SPOP is journaled with the members it popped
+    let frame = conn
+        .execute(Command::Spop(Spop::new("journal_set", 3)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Array(ref popped) if popped.len() == 3));
+    // the expire time is journaled as an absolute one
+    conn.execute(Command::Set(Set::new("journal_ttl", "v", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Pexpire(Expire::new("journal_ttl", 300)))
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let db2 = OpenOptions::new()
+        .open(dir.path().join("store_journal_replay"))
+        .await
+        .unwrap();
+    let replayed = db2
+        .replay_journal(dir.path().join("journal"), 1)
+        .await
+        .unwrap();
+    assert_eq!(replayed, 4);
+    let conn2 = db2.conn();
+    let frame = conn
+        .execute(Command::Smembers(Smembers::new("journal_set")))
+        .await
+        .unwrap();
+    let frame2 = conn2
+        .execute(Command::Smembers(Smembers::new("journal_set")))
+        .await
+        .unwrap();
+    assert!(matches!(frame2, Frame::Array(ref left) if left.len() == 7));
+    assert_eq!(format!("{frame:?}"), format!("{frame2:?}"));
+    let frame = conn2
+        .execute(Command::Get(Get::new("journal_ttl")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+}
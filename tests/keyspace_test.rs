@@ -0,0 +1,265 @@
+use mapuche_embedded::{
+    cmd::{
+        Command, Copy, Dbsize, Del, Get, Hget, Hlen, Hset, Llen, Push, Randomkey, Rename, Set,
+        Touch, TTL,
+    },
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn rename_and_copy() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Del(Del::new(&[
+            "ks_hash", "ks_hash2", "ks_hash3", "ks_list", "ks_list2", "ks_str",
+        ])))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Hset(Hset::new(
+            "ks_hash",
+            &[("f1", "v1"), ("f2", "v2")],
+        )))
+        .await
+        .unwrap();
+
+    let frame = conn
+        .execute(Command::Rename(Rename::new("ks_hash", "ks_hash2")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Hget(Hget::new("ks_hash2", "f2")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v2");
+    let frame = conn
+        .execute(Command::Hlen(Hlen::new("ks_hash2")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+    let frame = conn
+        .execute(Command::Touch(Touch::new(&["ks_hash", "ks_hash2"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+
+    let frame = conn
+        .execute(Command::Rename(Rename::new("ks_hash", "ks_hash3")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::Copy(Copy::new("ks_hash2", "ks_hash3", false)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Copy(Copy::new("ks_hash2", "ks_hash3", false)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+    let frame = conn
+        .execute(Command::Hget(Hget::new("ks_hash3", "f1")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+
+    let _ = conn
+        .execute(Command::Lpush(Push::new("ks_list", &["a", "b", "c"])))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Set(Set::new("ks_str", "v", Some(100_000), None)))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Renamenx(Rename::new("ks_list", "ks_str")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+    let frame = conn
+        .execute(Command::Renamenx(Rename::new("ks_list", "ks_list2")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Llen(Llen::new("ks_list2")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(3)));
+
+    let frame = conn
+        .execute(Command::Copy(Copy::new("ks_str", "ks_list2", true)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Get(Get::new("ks_list2")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v");
+    let frame = conn
+        .execute(Command::TTL(TTL::new("ks_list2")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(ttl) if ttl > 90));
+}
+
+#[tokio::test]
+async fn dbsize_and_randomkey() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Del(Del::new(&["size1", "size2"])))
+        .await
+        .unwrap();
+    let size = match conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap() {
+        Frame::Integer(n) => n,
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+
+    let _ = conn
+        .execute(Command::Set(Set::new("size1", "v", None, None)))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Set(Set::new("size1", "v2", None, None)))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Hset(Hset::new("size2", &[("f", "v")])))
+        .await
+        .unwrap();
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(n) if n == size + 2));
+
+    let frame = conn
+        .execute(Command::Randomkey(Randomkey::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Bulk(_)));
+
+    let _ = conn
+        .execute(Command::Del(Del::new(&["size1", "size2"])))
+        .await
+        .unwrap();
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(n) if n == size));
+}
+
+#[tokio::test]
+async fn key_count_saved() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+    let _ = conn
+        .execute(Command::Set(Set::new("count1", "v", None, None)))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Hset(Hset::new("count2", &[("f", "v")])))
+        .await
+        .unwrap();
+
+    // the count saved by a key created after the snapshot of the txn doesn't conflict with
+    // the count saved by the txn
+    let mut attempts = 0;
+    conn.atomic(async |tx| {
+        attempts += 1;
+        if attempts == 1 {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(
+                    db.conn()
+                        .execute(Command::Set(Set::new("count3", "v", None, None))),
+                )
+            })
+            .join()
+            .unwrap()
+            .unwrap();
+        }
+        tx.set("count4", "v").await
+    })
+    .await
+    .unwrap();
+    assert_eq!(attempts, 1);
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(4)));
+    drop(conn);
+    drop(db);
+
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(4)));
+    let _ = conn
+        .execute(Command::Del(Del::new(&["count1", "count2"])))
+        .await
+        .unwrap();
+    drop(conn);
+    drop(db);
+
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let frame = db
+        .conn()
+        .execute(Command::Dbsize(Dbsize::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+    drop(db);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn key_count_folded() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+
+    // the txns save their deltas of the count concurrently, which are folded on the way
+    let mut tasks = vec![];
+    for t in 0..4 {
+        let db = db.clone();
+        tasks.push(tokio::spawn(async move {
+            let conn = db.conn();
+            for i in 0..400 {
+                conn.execute(Command::Set(Set::new(
+                    format!("fold_{t}_{i}"),
+                    "v",
+                    None,
+                    None,
+                )))
+                .await
+                .unwrap();
+            }
+            let keys: Vec<String> = (0..25).map(|i| format!("fold_{t}_{i}")).collect();
+            conn.execute(Command::Del(Del::new(&keys))).await.unwrap();
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let frame = db
+        .conn()
+        .execute(Command::Dbsize(Dbsize::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1500)));
+    drop(db);
+
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let frame = db
+        .conn()
+        .execute(Command::Dbsize(Dbsize::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1500)));
+    drop(db);
+}
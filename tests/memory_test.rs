@@ -0,0 +1,205 @@
+use futures::StreamExt;
+use mapuche_embedded::{
+    cmd::{Command, Dbsize, Del, Get, Hgetall, Hset, IncrDecr, Keys, Mset, Set, Zadd, Zrange},
+    frame::Frame,
+    ChangeOp, OpenOptions,
+};
+use tokio::task::spawn;
+
+#[tokio::test]
+async fn in_memory_db() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store_memory");
+    let db = OpenOptions::new().in_memory().open(&path).await.unwrap();
+    let conn = db.conn();
+
+    conn.execute(Command::Set(Set::new("mem_str", "v1", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Hset(Hset::new(
+        "mem_hash",
+        &[("f", "1"), ("g", "2")],
+    )))
+    .await
+    .unwrap();
+    conn.execute(Command::Zadd(Zadd::new(
+        "mem_zset",
+        &["a", "b"],
+        &[2.0, 1.0],
+        None,
+        false,
+    )))
+    .await
+    .unwrap();
+    let frame = conn
+        .execute(Command::Get(Get::new("mem_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+    let frame = conn
+        .execute(Command::Hgetall(Hgetall::new("mem_hash")))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(values.len(), 4);
+    let frame = conn
+        .execute(Command::Zrange(Zrange::new(
+            "mem_zset", 0, -1, false, false,
+        )))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(values[0], "b");
+    let frame = conn
+        .execute(Command::Keys(Keys::new("mem_*")))
+        .await
+        .unwrap();
+    let Frame::Array(keys) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(keys.len(), 3);
+
+    // the snapshots see the data at the time they are taken
+    let snapshot = db.snapshot();
+    conn.execute(Command::Del(Del::new(&["mem_str"])))
+        .await
+        .unwrap();
+    let frame = snapshot
+        .execute(Command::Get(Get::new("mem_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+
+    // nothing is written to the disk, and another in-memory db doesn't see the data
+    assert!(!path.exists());
+    let other = OpenOptions::new().in_memory().open(&path).await.unwrap();
+    let frame = other
+        .conn()
+        .execute(Command::Get(Get::new("mem_zset")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+    assert!(db
+        .checkpoint(dir.path().join("memory_checkpoint"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn in_memory_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store_memory_options");
+    let db = OpenOptions::new()
+        .in_memory()
+        .shards(4)
+        .change_capture(false)
+        .open(&path)
+        .await
+        .unwrap();
+    let conn = db.conn();
+    let keys: Vec<String> = (0..20).map(|i| format!("mem_shard_{i}")).collect();
+    conn.execute(Command::Mset(Mset::new(&keys, &keys)))
+        .await
+        .unwrap();
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(20)));
+
+    let db = OpenOptions::new()
+        .in_memory()
+        .change_capture(true)
+        .open(&path)
+        .await
+        .unwrap();
+    let mut changes = db.changes_since(0).unwrap();
+    db.conn()
+        .execute(Command::Set(Set::new("mem_change", "v", None, None)))
+        .await
+        .unwrap();
+    let event = changes.next().await.unwrap().unwrap();
+    assert_eq!(&event.key[..], b"mem_change");
+    assert!(matches!(event.op, ChangeOp::Set { .. }));
+
+    assert!(OpenOptions::new()
+        .in_memory()
+        .backup_dir(dir.path().join("memory_backup"))
+        .open(&path)
+        .await
+        .is_err());
+    assert!(OpenOptions::new()
+        .in_memory()
+        .raft_node(1, "127.0.0.1:0")
+        .open(&path)
+        .await
+        .is_err());
+    assert!(!path.exists());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn in_memory_concurrency() {
+    let db = OpenOptions::new().open_in_memory().await.unwrap();
+    let mut tasks = vec![];
+    for t in 0..4 {
+        let db = db.clone();
+        tasks.push(spawn(async move {
+            let conn = db.conn();
+            for i in 0..100 {
+                let key = format!("mem_task_{t}_{i}");
+                conn.execute(Command::Incr(IncrDecr::new(&key, 1)))
+                    .await
+                    .unwrap();
+            }
+            // the conflicting writes are retried by the atomic closures
+            for _ in 0..25 {
+                conn.atomic(async |tx| tx.incrby("mem_counter", 1).await)
+                    .await
+                    .unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let conn = db.conn();
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(401)));
+    let frame = conn
+        .execute(Command::Get(Get::new("mem_counter")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "100");
+}
+
+#[tokio::test]
+async fn in_memory_conflicts() {
+    let db = OpenOptions::new().open_in_memory().await.unwrap();
+    let conn = db.conn();
+    let other = db.conn();
+
+    // the write of the other conn doesn't wait for the txn writing the same key, the txn
+    // fails at commit instead and the closure is run again
+    let mut runs = 0;
+    conn.atomic(async |tx| {
+        runs += 1;
+        tx.set("mem_conflict", "txn").await?;
+        if runs == 1 {
+            other
+                .execute(Command::Set(Set::new("mem_conflict", "other", None, None)))
+                .await?;
+        }
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(runs, 2);
+    let frame = conn
+        .execute(Command::Get(Get::new("mem_conflict")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "txn");
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use mapuche_embedded::{
+    cmd::{Command, Del, Get, Push, Set},
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn keyspace_notifications() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new()
+        .notify_keyspace_events("KEA")
+        .open(dir.path())
+        .await
+        .unwrap();
+    let conn = db.conn();
+    let mut keyspace = conn.psubscribe(&["__keyspace@0__:notify*"]);
+    let mut sets = conn.subscribe(&["__keyevent@0__:set"]);
+
+    conn.execute(Command::Set(Set::new("notify", "1", None, None)))
+        .await
+        .unwrap();
+    let message = keyspace.next().await.unwrap();
+    assert_eq!(message.channel, "__keyspace@0__:notify");
+    assert_eq!(message.payload, "set");
+    let message = sets.next().await.unwrap();
+    assert_eq!(message.payload, "notify");
+
+    conn.execute(Command::Lpush(Push::new("notify_list", &["a"])))
+        .await
+        .unwrap();
+    let message = keyspace.next().await.unwrap();
+    assert_eq!(message.channel, "__keyspace@0__:notify_list");
+    assert_eq!(message.payload, "lpush");
+
+    conn.execute(Command::Del(Del::new(&["notify", "notify_list"])))
+        .await
+        .unwrap();
+    for key in ["notify", "notify_list"] {
+        let message = keyspace.next().await.unwrap();
+        assert_eq!(message.channel, format!("__keyspace@0__:{key}"));
+        assert_eq!(message.payload, "del");
+    }
+
+    // the expired key is removed and notified when it is accessed
+    conn.execute(Command::Set(Set::new("notify", "1", Some(1), None)))
+        .await
+        .unwrap();
+    assert_eq!(keyspace.next().await.unwrap().payload, "set");
+    assert_eq!(keyspace.next().await.unwrap().payload, "expire");
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    conn.execute(Command::Get(Get::new("notify")))
+        .await
+        .unwrap();
+    assert_eq!(keyspace.next().await.unwrap().payload, "expired");
+}
+
+#[tokio::test]
+async fn keyspace_event_classes() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(OpenOptions::new()
+        .notify_keyspace_events("K?")
+        .open(dir.path().join("store_notify_invalid"))
+        .await
+        .is_err());
+
+    // only the expired events are published
+    let db = OpenOptions::new()
+        .notify_keyspace_events("Ex")
+        .open(dir.path().join("store_notify_classes"))
+        .await
+        .unwrap();
+    let conn = db.conn();
+    let mut events = conn.psubscribe(&["__key*"]);
+
+    conn.execute(Command::Set(Set::new("classes", "1", Some(1), None)))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    conn.execute(Command::Get(Get::new("classes")))
+        .await
+        .unwrap();
+    let message = events.next().await.unwrap();
+    assert_eq!(message.channel, "__keyevent@0__:expired");
+    assert_eq!(message.payload, "classes");
+}
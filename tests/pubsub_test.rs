@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use mapuche_embedded::{
+    cmd::{Command, Publish, Pubsub, PubsubOp},
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn publish_subscribe() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let mut news = conn.subscribe(&["news"]);
+    let mut pattern = conn.psubscribe(&["n*"]);
+
+    let frame = conn
+        .execute(Command::Publish(Publish::new(
+            "news",
+            Bytes::from_static(b"hello"),
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+
+    let message = news.next().await.unwrap();
+    assert_eq!(message.channel, "news");
+    assert_eq!(message.payload, "hello");
+    assert_eq!(message.pattern, None);
+
+    let message = pattern.next().await.unwrap();
+    assert_eq!(message.pattern.as_deref(), Some("n*"));
+    let Frame::Array(frames) = message.frame() else {
+        panic!("unexpected frame");
+    };
+    assert_eq!(frames[0], "pmessage");
+    assert_eq!(frames[3], "hello");
+
+    let frame = conn
+        .execute(Command::Pubsub(Pubsub::new(PubsubOp::Channels {
+            pattern: None,
+        })))
+        .await
+        .unwrap();
+    let Frame::Array(channels) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(channels.len(), 1);
+    assert_eq!(channels[0], "news");
+    let frame = conn
+        .execute(Command::Pubsub(Pubsub::new(PubsubOp::Numpat)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+
+    // the subscriptions are removed when dropped
+    drop(pattern);
+    news.unsubscribe(&["news"]);
+    let frame = conn
+        .execute(Command::Publish(Publish::new(
+            "news",
+            Bytes::from_static(b"bye"),
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+}
+
+#[tokio::test]
+async fn slow_subscriber() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new()
+        .pubsub_buffer_size(1)
+        .pubsub_publish_timeout(Duration::from_millis(50))
+        .open(dir.path())
+        .await
+        .unwrap();
+    let conn = db.conn();
+    let mut slow = conn.subscribe(&["slow"]);
+
+    for (i, expected) in [1, 0, 0].into_iter().enumerate() {
+        let frame = conn
+            .execute(Command::Publish(Publish::new(
+                "slow",
+                Bytes::from(i.to_string()),
+            )))
+            .await
+            .unwrap();
+        assert!(matches!(frame, Frame::Integer(n) if n == expected));
+    }
+
+    // the buffered message is received before the stream ends
+    assert_eq!(slow.next().await.unwrap().payload, "0");
+    assert!(slow.next().await.is_none());
+}
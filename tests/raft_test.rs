@@ -0,0 +1,241 @@
+use std::{path::Path, time::Duration};
+
+use mapuche_embedded::{
+    cmd::{Command, Eval, Expire, Gc, Get, Sadd, Scard, Set, Spop, TTL},
+    frame::Frame,
+    OpenOptions, RaftRole, DB,
+};
+
+const ADDRS: [&str; 4] = [
+    "127.0.0.1:27301",
+    "127.0.0.1:27302",
+    "127.0.0.1:27303",
+    "127.0.0.1:27304",
+];
+
+const EMPTY: [&str; 0] = [];
+
+const RESTART_ADDRS: [&str; 3] = ["127.0.0.1:27311", "127.0.0.1:27312", "127.0.0.1:27313"];
+
+async fn open_node(dir: &Path, addrs: &[&str], id: u64, members: &[u64]) -> DB {
+    let mut options = OpenOptions::new()
+        .raft_node(id, addrs[id as usize - 1])
+        .raft_snapshot_threshold(4);
+    for member in members {
+        options = options.raft_member(*member, addrs[*member as usize - 1]);
+    }
+    // the db and the address are released after the tasks of the dropped node exit
+    let path = dir.join(format!("store_raft_{id}"));
+    for _ in 0..50 {
+        if let Ok(db) = options.clone().open(&path).await {
+            return db;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{} can't be opened", path.display());
+}
+
+async fn wait_for_leader(nodes: &[DB]) -> usize {
+    for _ in 0..100 {
+        for (i, node) in nodes.iter().enumerate() {
+            if node.raft_status().unwrap().role == RaftRole::Leader {
+                return i;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("no leader is elected");
+}
+
+async fn wait_for_applied(node: &DB, index: u64) {
+    for _ in 0..100 {
+        if node.raft_status().unwrap().applied_index >= index {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("the entries are not applied");
+}
+
+#[tokio::test]
+async fn raft_cluster() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut nodes = vec![];
+    for id in 1..=3 {
+        nodes.push(open_node(dir.path(), &ADDRS, id, &[1, 2, 3]).await);
+    }
+    let leader = wait_for_leader(&nodes).await;
+    let conn = nodes[leader].conn();
+
+    for i in 0..10 {
+        let frame = conn
+            .execute(Command::Set(Set::new(
+                "raft_str",
+                i.to_string(),
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        assert_eq!(frame, "OK");
+    }
+    let frame = conn
+        .execute(Command::Get(Get::new("raft_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "9");
+
+    // SPOP is proposed with the members popped by the leader
+    let frame = conn
+        .execute(Command::Sadd(Sadd::new("raft_set", &["a", "b", "c"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(3)));
+    let frame = conn
+        .execute(Command::Spop(Spop::new("raft_set", 2)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Array(ref members) if members.len() == 2));
+    let frame = conn
+        .execute(Command::Scard(Scard::new("raft_set")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+
+    // the scripts are run by every member, they can't pop random members
+    let frame = conn
+        .execute(Command::Eval(Eval::new(
+            "return redis.call('SPOP', KEYS[1])",
+            &["raft_set"],
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(ref e) if e.contains("Nondeterministic")));
+    let frame = conn
+        .execute(Command::Scard(Scard::new("raft_set")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    // the random numbers of the scripts start from the same seed on every member
+    let mut numbers = vec![];
+    for _ in 0..2 {
+        let frame = conn
+            .execute(Command::Eval(Eval::new(
+                "return math.random(1000000)",
+                &EMPTY,
+                &EMPTY,
+            )))
+            .await
+            .unwrap();
+        let Frame::Integer(number) = frame else {
+            panic!("unexpected frame {frame:?}");
+        };
+        numbers.push(number);
+    }
+    assert_eq!(numbers[0], numbers[1]);
+
+    // the relative expire time is proposed as an absolute one
+    let frame = conn
+        .execute(Command::Expire(Expire::new("raft_str", 100)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::TTL(TTL::new("raft_str")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(ttl) if ttl > 0 && ttl <= 100));
+
+    // only the leader serves the commands
+    let follower = (leader + 1) % nodes.len();
+    let frame = nodes[follower]
+        .conn()
+        .execute(Command::Set(Set::new("raft_str", "x", None, None)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    let frame = nodes[follower]
+        .conn()
+        .execute(Command::Get(Get::new("raft_str")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    // the gc is proposed as the other writes
+    let frame = nodes[follower]
+        .conn()
+        .execute(Command::Gc(Gc::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    let frame = conn.execute(Command::Gc(Gc::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Null));
+
+    let commit_index = nodes[leader].raft_status().unwrap().commit_index;
+    for node in &nodes {
+        wait_for_applied(node, commit_index).await;
+    }
+
+    // the log is compacted, the new member is sent a checkpoint
+    let node4 = open_node(dir.path(), &ADDRS, 4, &[]).await;
+    nodes[leader].raft_add_member(4, ADDRS[3]).await.unwrap();
+    let commit_index = nodes[leader].raft_status().unwrap().commit_index;
+    wait_for_applied(&node4, commit_index).await;
+    assert_eq!(node4.raft_status().unwrap().members.len(), 4);
+
+    nodes[leader].raft_remove_member(4).await.unwrap();
+    assert_eq!(nodes[leader].raft_status().unwrap().members.len(), 3);
+}
+
+#[tokio::test]
+async fn raft_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut nodes = vec![];
+    for id in 1..=3 {
+        nodes.push(open_node(dir.path(), &RESTART_ADDRS, id, &[1, 2, 3]).await);
+    }
+    let leader = wait_for_leader(&nodes).await;
+    let conn = nodes[leader].conn();
+    for i in 0..10 {
+        conn.execute(Command::Set(Set::new(
+            "restart_str",
+            i.to_string(),
+            None,
+            None,
+        )))
+        .await
+        .unwrap();
+    }
+    let commit_index = nodes[leader].raft_status().unwrap().commit_index;
+    for node in &nodes {
+        wait_for_applied(node, commit_index).await;
+    }
+    drop(conn);
+    drop(nodes);
+
+    // the log is compacted, it is loaded after the snapshot saved with the compaction
+    let mut nodes = vec![];
+    for id in 1..=3 {
+        nodes.push(open_node(dir.path(), &RESTART_ADDRS, id, &[1, 2, 3]).await);
+    }
+    for node in &nodes {
+        assert!(node.raft_status().unwrap().applied_index >= commit_index);
+    }
+    let leader = wait_for_leader(&nodes).await;
+    let conn = nodes[leader].conn();
+    // the lease of the new leader starts with the first acknowledged entries
+    let frame = conn
+        .execute(Command::Set(Set::new("restart_new", "v", None, None)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Get(Get::new("restart_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "9");
+    let commit_index = nodes[leader].raft_status().unwrap().commit_index;
+    for node in &nodes {
+        wait_for_applied(node, commit_index).await;
+    }
+}
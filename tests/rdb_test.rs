@@ -0,0 +1,337 @@
+use mapuche_embedded::{
+    cmd::{
+        Command, Dbsize, Del, Get, Hget, Hset, Keys, Lrange, Push, Sadd, Scard, Set, Zadd, Zscore,
+        TTL,
+    },
+    frame::Frame,
+    rdb, OpenOptions,
+};
+
+#[tokio::test]
+async fn export_and_import() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new()
+        .open(dir.path().join("store_rdb_export"))
+        .await
+        .unwrap();
+    let conn = db.conn();
+
+    let keys = ["rdb_str", "rdb_list", "rdb_hash", "rdb_set", "rdb_zset"];
+    let _ = conn.execute(Command::Del(Del::new(&keys))).await.unwrap();
+    let _ = conn
+        .execute(Command::Set(Set::new("rdb_str", "v", Some(100_000), None)))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Rpush(Push::new("rdb_list", &["a", "b", "c"])))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Hset(Hset::new("rdb_hash", &[("f", "1")])))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Sadd(Sadd::new("rdb_set", &["x", "y"])))
+        .await
+        .unwrap();
+    let _ = conn
+        .execute(Command::Zadd(Zadd::new(
+            "rdb_zset",
+            &["m"],
+            &[-1.5],
+            None,
+            false,
+        )))
+        .await
+        .unwrap();
+    let count = rdb::export(dir.path().join("rdb_export.rdb"), &db)
+        .await
+        .unwrap();
+    assert!(count >= keys.len());
+
+    let db2 = OpenOptions::new()
+        .open(dir.path().join("store_rdb_import"))
+        .await
+        .unwrap();
+    let conn2 = db2.conn();
+    let _ = conn2.execute(Command::Del(Del::new(&keys))).await.unwrap();
+    let imported = rdb::import(dir.path().join("rdb_export.rdb"), &db2)
+        .await
+        .unwrap();
+    assert_eq!(imported, count);
+
+    let frame = conn2
+        .execute(Command::Get(Get::new("rdb_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v");
+    let frame = conn2
+        .execute(Command::TTL(TTL::new("rdb_str")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(ttl) if ttl > 90));
+    let frame = conn2
+        .execute(Command::Lrange(Lrange::new("rdb_list", 0, -1)))
+        .await
+        .unwrap();
+    assert_eq!(
+        format!("{:?}", frame),
+        format!(
+            "{:?}",
+            Frame::Array(vec![
+                Frame::Bulk("a".into()),
+                Frame::Bulk("b".into()),
+                Frame::Bulk("c".into())
+            ])
+        )
+    );
+    let frame = conn2
+        .execute(Command::Hget(Hget::new("rdb_hash", "f")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "1");
+    let frame = conn2
+        .execute(Command::Scard(Scard::new("rdb_set")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+    let frame = conn2
+        .execute(Command::Zscore(Zscore::new("rdb_zset", "m")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "-1.5");
+}
+
+#[tokio::test]
+async fn import_compact_encodings() {
+    let dir = tempfile::tempdir().unwrap();
+    // RDB version 11 with the encodings used by redis 7
+    let mut data = b"REDIS0011".to_vec();
+    data.extend([0xfa, 9]);
+    data.extend(b"redis-ver");
+    data.extend([5]);
+    data.extend(b"7.2.0");
+    data.extend([0xfe, 0, 0xfb, 5, 1]);
+    // LZF compressed string with expire time at 2100-01-01
+    data.push(0xfc);
+    data.extend(4102444800000i64.to_le_bytes());
+    data.extend([0, 1, b's', 0xc3, 5, 10, 0x00, b'a', 0xe0, 0x00, 0x00]);
+    // intset
+    data.extend([
+        11, 2, b'i', b's', 14, 2, 0, 0, 0, 3, 0, 0, 0, 1, 0, 2, 0, 0xff, 0xff,
+    ]);
+    // hash listpack
+    data.extend([16, 1, b'h', 20, 20, 0, 0, 0, 4, 0]);
+    data.extend([
+        0x82, b'f', b'1', 3, 0x82, b'v', b'1', 3, 0x81, b'n', 2, 7, 1, 0xff,
+    ]);
+    // zset listpack
+    data.extend([17, 1, b'z', 20, 20, 0, 0, 0, 4, 0]);
+    data.extend([
+        0x81, b'a', 2, 1, 1, 0x81, b'b', 2, 0x83, b'2', b'.', b'5', 4, 0xff,
+    ]);
+    // quicklist with a packed node
+    data.extend([18, 1, b'l', 1, 2, 13, 13, 0, 0, 0, 2, 0]);
+    data.extend([0x81, b'x', 2, 0xc1, 0x2c, 2, 0xff]);
+    // EOF with checksum disabled
+    data.push(0xff);
+    data.extend([0; 8]);
+    std::fs::write(dir.path().join("rdb_compact.rdb"), &data).unwrap();
+
+    let db = OpenOptions::new()
+        .open(dir.path().join("store_rdb_compact"))
+        .await
+        .unwrap();
+    let conn = db.conn();
+    let count = rdb::import(dir.path().join("rdb_compact.rdb"), &db)
+        .await
+        .unwrap();
+    assert_eq!(count, 5);
+
+    let frame = conn.execute(Command::Get(Get::new("s"))).await.unwrap();
+    assert_eq!(frame, "aaaaaaaaaa");
+    let frame = conn.execute(Command::TTL(TTL::new("s"))).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(ttl) if ttl > 0));
+    let frame = conn
+        .execute(Command::Scard(Scard::new("is")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(3)));
+    let frame = conn
+        .execute(Command::Hget(Hget::new("h", "n")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "7");
+    let frame = conn
+        .execute(Command::Zscore(Zscore::new("z", "b")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "2.5");
+    let frame = conn
+        .execute(Command::Lrange(Lrange::new("l", 0, -1)))
+        .await
+        .unwrap();
+    assert_eq!(
+        format!("{:?}", frame),
+        format!(
+            "{:?}",
+            Frame::Array(vec![Frame::Bulk("x".into()), Frame::Bulk("300".into())])
+        )
+    );
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(n) if n >= 5));
+
+    // the checksum is verified if it is set
+    let len = data.len();
+    data[len - 1] = 1;
+    std::fs::write(dir.path().join("rdb_compact.rdb"), &data).unwrap();
+    assert!(rdb::import(dir.path().join("rdb_compact.rdb"), &db)
+        .await
+        .is_err());
+}
+
+// the length encoding of the rdb strings
+fn rdb_len(len: usize) -> Vec<u8> {
+    if len < 1 << 6 {
+        vec![len as u8]
+    } else if len < 1 << 14 {
+        vec![0x40 | (len >> 8) as u8, len as u8]
+    } else {
+        let mut buf = vec![0x80];
+        buf.extend((len as u32).to_be_bytes());
+        buf
+    }
+}
+
+// the backlen of the listpack entries like `lpEncodeBacklen`
+fn lp_backlen(len: usize) -> Vec<u8> {
+    let bytes = if len <= 127 {
+        1
+    } else if len < 16383 {
+        2
+    } else if len < 2097151 {
+        3
+    } else if len < 268435455 {
+        4
+    } else {
+        5
+    };
+    (0..bytes)
+        .map(|i| {
+            let b = (len >> (7 * (bytes - 1 - i))) as u8 & 127;
+            if i == 0 {
+                b
+            } else {
+                b | 128
+            }
+        })
+        .collect()
+}
+
+// the listpack entry of the string with the encoding of its size
+fn lp_string(s: &[u8]) -> Vec<u8> {
+    let mut entry = if s.len() < 64 {
+        vec![0x80 | s.len() as u8]
+    } else if s.len() < 4096 {
+        vec![0xe0 | (s.len() >> 8) as u8, s.len() as u8]
+    } else {
+        let mut header = vec![0xf0];
+        header.extend((s.len() as u32).to_le_bytes());
+        header
+    };
+    entry.extend(s);
+    entry.extend(lp_backlen(entry.len()));
+    entry
+}
+
+#[tokio::test]
+async fn import_listpack_backlen_and_binary_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    // the sizes of the entries around the boundaries of the backlen sizes
+    let sizes = [
+        (2, 125),
+        (2, 126),
+        (5, 16377),
+        (5, 16378),
+        (5, 2097145),
+        (5, 2097146),
+    ];
+    let mut entries = vec![];
+    for (i, (header, len)) in sizes.iter().enumerate() {
+        assert_eq!(lp_backlen(header + len).len(), [1, 2, 2, 3, 3, 4][i]);
+        entries.extend(lp_string(format!("f{i}").as_bytes()));
+        entries.extend(lp_string(&vec![b'a' + i as u8; *len]));
+    }
+    let mut listpack = ((entries.len() + 7) as u32).to_le_bytes().to_vec();
+    listpack.extend((sizes.len() as u16 * 2).to_le_bytes());
+    listpack.extend(entries);
+    listpack.push(0xff);
+
+    let mut data = b"REDIS0011".to_vec();
+    data.extend([0xfe, 0]);
+    data.extend([16, 2, b'l', b'p']);
+    data.extend(rdb_len(listpack.len()));
+    data.extend(listpack);
+    // the key is not valid utf-8
+    data.extend([0, 2, 0xff, 0xfe, 1, b'v']);
+    data.push(0xff);
+    data.extend([0; 8]);
+    let path = dir.path().join("rdb_listpack.rdb");
+    std::fs::write(&path, &data).unwrap();
+
+    let db = OpenOptions::new().open_in_memory().await.unwrap();
+    let conn = db.conn();
+    let count = rdb::import(&path, &db).await.unwrap();
+    assert_eq!(count, 2);
+
+    for (i, (_, len)) in sizes.iter().enumerate() {
+        let frame = conn
+            .execute(Command::Hget(Hget::new("lp", format!("f{i}"))))
+            .await
+            .unwrap();
+        assert!(matches!(frame, Frame::Bulk(value) if value[..] == vec![b'a' + i as u8; *len][..]));
+    }
+    let frame = conn.execute(Command::Keys(Keys::new("*"))).await.unwrap();
+    let Frame::Array(keys) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(keys.len(), 2);
+    assert!(keys
+        .iter()
+        .any(|key| matches!(key, Frame::Bulk(key) if key[..] == [0xff, 0xfe])));
+}
+
+#[tokio::test]
+async fn import_other_dbs_and_truncated_strings() {
+    let dir = tempfile::tempdir().unwrap();
+    // the keys of the db 1 are not merged with the keys of the db 0
+    let mut data = b"REDIS0011".to_vec();
+    data.extend([0xfe, 0]);
+    data.extend([0, 4, b'k', b'e', b'y', b'0', 1, b'a']);
+    data.extend([0xfe, 1]);
+    data.extend([0, 4, b'k', b'e', b'y', b'0', 1, b'b']);
+    data.extend([0, 4, b'k', b'e', b'y', b'1', 1, b'c']);
+    data.push(0xff);
+    data.extend([0; 8]);
+    let path = dir.path().join("rdb_dbs.rdb");
+    std::fs::write(&path, &data).unwrap();
+
+    let db = OpenOptions::new().open_in_memory().await.unwrap();
+    let conn = db.conn();
+    let count = rdb::import(&path, &db).await.unwrap();
+    assert_eq!(count, 1);
+    let frame = conn.execute(Command::Get(Get::new("key0"))).await.unwrap();
+    assert_eq!(frame, "a");
+    let frame = conn.execute(Command::Get(Get::new("key1"))).await.unwrap();
+    assert!(matches!(frame, Frame::Null));
+
+    // the length of the string is far beyond the end of the file
+    let mut data = b"REDIS0011".to_vec();
+    data.extend([0xfe, 0]);
+    data.extend([0, 0x81]);
+    data.extend(u64::MAX.to_be_bytes());
+    data.extend(b"key");
+    let path = dir.path().join("rdb_truncated.rdb");
+    std::fs::write(&path, &data).unwrap();
+    assert!(rdb::import(&path, &db).await.is_err());
+}
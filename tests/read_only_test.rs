@@ -0,0 +1,183 @@
+use mapuche_embedded::{
+    cmd::{Command, Dbsize, Function, FunctionOp, Gc, Get, Hgetall, Hset, Keys, Set},
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn read_only_db() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store_read_only");
+    assert!(OpenOptions::new().read_only().open(&path).await.is_err());
+
+    let primary = OpenOptions::new().open(&path).await.unwrap();
+    let conn = primary.conn();
+    conn.execute(Command::Set(Set::new("ro_str", "v1", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Hset(Hset::new(
+        "ro_hash",
+        &[("f", "1"), ("g", "2")],
+    )))
+    .await
+    .unwrap();
+
+    // the db is opened read only while the primary is open
+    let db = OpenOptions::new().read_only().open(&path).await.unwrap();
+    let ro = db.conn();
+    let frame = ro.execute(Command::Get(Get::new("ro_str"))).await.unwrap();
+    assert_eq!(frame, "v1");
+    let frame = ro
+        .execute(Command::Hgetall(Hgetall::new("ro_hash")))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(values.len(), 4);
+    let frame = ro.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+    let frame = db
+        .snapshot()
+        .execute(Command::Keys(Keys::new("ro_*")))
+        .await
+        .unwrap();
+    let Frame::Array(keys) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(keys.len(), 2);
+
+    // the writes are rejected
+    let frame = ro
+        .execute(Command::Set(Set::new("ro_str", "v2", None, None)))
+        .await
+        .unwrap();
+    let Frame::Error(e) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert!(e.starts_with("READONLY"));
+    let frame = ro.execute(Command::Gc(Gc::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Error(e) if e.starts_with("READONLY")));
+    assert!(ro
+        .atomic(async |tx| tx.set("ro_new", "v").await)
+        .await
+        .is_err());
+    let frame = ro.execute(Command::Get(Get::new("ro_str"))).await.unwrap();
+    assert_eq!(frame, "v1");
+    assert!(db.try_catch_up().await.is_err());
+
+    assert!(OpenOptions::new()
+        .read_only()
+        .in_memory()
+        .open(&path)
+        .await
+        .is_err());
+    assert!(OpenOptions::new()
+        .read_only()
+        .backup_dir(dir.path().join("read_only_backup"))
+        .open(&path)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn secondary_db() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store_primary");
+    let secondary_path = dir.path().join("store_secondary");
+    let primary = OpenOptions::new().open(&path).await.unwrap();
+    let conn = primary.conn();
+    conn.execute(Command::Set(Set::new("sec_1", "v1", None, None)))
+        .await
+        .unwrap();
+
+    let db = OpenOptions::new()
+        .secondary(&path)
+        .open(secondary_path)
+        .await
+        .unwrap();
+    let secondary = db.conn();
+    let frame = secondary
+        .execute(Command::Get(Get::new("sec_1")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+
+    // the new writes of the primary are read after catching up
+    conn.execute(Command::Set(Set::new("sec_1", "v2", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Set(Set::new("sec_2", "v", None, None)))
+        .await
+        .unwrap();
+    let frame = secondary
+        .execute(Command::Get(Get::new("sec_2")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+    db.try_catch_up().await.unwrap();
+    let frame = secondary
+        .execute(Command::Get(Get::new("sec_1")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v2");
+    let frame = secondary
+        .execute(Command::Dbsize(Dbsize::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+
+    let frame = secondary
+        .execute(Command::Set(Set::new("sec_3", "v", None, None)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    assert!(OpenOptions::new()
+        .secondary(&path)
+        .shards(2)
+        .open(dir.path().join("store_secondary_shards"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn read_only_missing_cf() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+    conn.execute(Command::Set(Set::new("cf_str", "v", None, None)))
+        .await
+        .unwrap();
+    drop(conn);
+    drop(db);
+
+    // drop the functions column family, like a db created by a version without it
+    {
+        let opts = rocksdb::Options::default();
+        let cfs = rocksdb::DB::list_cf(&opts, path).unwrap();
+        let db = rocksdb::DB::open_cf(&opts, path, cfs).unwrap();
+        db.drop_cf("functions").unwrap();
+    }
+
+    let db = OpenOptions::new().read_only().open(path).await.unwrap();
+    let conn = db.conn();
+    let frame = conn
+        .execute(Command::Get(Get::new("cf_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v");
+    let frame = conn
+        .execute(Command::Function(Function::new(FunctionOp::List {
+            pattern: None,
+            with_code: false,
+        })))
+        .await
+        .unwrap();
+    let Frame::Array(libraries) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert!(libraries.is_empty());
+    drop(conn);
+    drop(db);
+}
@@ -0,0 +1,274 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use mapuche_embedded::{
+    cmd::{Command, Dbsize, Get, IncrDecr, Set},
+    frame::Frame,
+    OpenOptions, DB,
+};
+
+async fn wait_for_value(db: &DB, key: &str, value: &str) {
+    for _ in 0..100 {
+        let frame = db
+            .conn()
+            .execute(Command::Get(Get::new(key)))
+            .await
+            .unwrap();
+        if frame == value {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{key} is not replicated");
+}
+
+#[tokio::test]
+async fn replicate_to_replica() {
+    let dir = tempfile::tempdir().unwrap();
+    let primary = OpenOptions::new()
+        .replication_listen("127.0.0.1:0")
+        .open(dir.path().join("store_replication_primary"))
+        .await
+        .unwrap();
+    let addr = primary.replication_addr().unwrap();
+    let conn = primary.conn();
+
+    // written before the replica connects, replicated by the full sync
+    let _ = conn
+        .execute(Command::Set(Set::new("repl_before", "v1", None, None)))
+        .await
+        .unwrap();
+
+    let replica = OpenOptions::new()
+        .replica_of(addr.to_string())
+        .open(dir.path().join("store_replication_replica"))
+        .await
+        .unwrap();
+    wait_for_value(&replica, "repl_before", "v1").await;
+
+    // streamed after the full sync
+    let _ = conn
+        .execute(Command::Set(Set::new("repl_after", "v2", None, None)))
+        .await
+        .unwrap();
+    wait_for_value(&replica, "repl_after", "v2").await;
+
+    let frame = primary
+        .conn()
+        .execute(Command::Dbsize(Dbsize::new()))
+        .await
+        .unwrap();
+    let replica_frame = replica
+        .conn()
+        .execute(Command::Dbsize(Dbsize::new()))
+        .await
+        .unwrap();
+    assert_eq!(format!("{frame:?}"), format!("{replica_frame:?}"));
+
+    // the replica is read only
+    let frame = replica
+        .conn()
+        .execute(Command::Set(Set::new("repl_after", "v3", None, None)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    let frame = replica
+        .conn()
+        .execute(Command::Get(Get::new("repl_after")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v2");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn replicate_concurrent_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let primary = OpenOptions::new()
+        .replication_listen("127.0.0.1:0")
+        .open(dir.path().join("store_concurrent_primary"))
+        .await
+        .unwrap();
+    let replica = OpenOptions::new()
+        .replica_of(primary.replication_addr().unwrap().to_string())
+        .open(dir.path().join("store_concurrent_replica"))
+        .await
+        .unwrap();
+
+    // the txns writing the same key are logged in the order of commit
+    let mut tasks = vec![];
+    for t in 0..4 {
+        let primary = primary.clone();
+        tasks.push(tokio::spawn(async move {
+            let conn = primary.conn();
+            for i in 0..100 {
+                conn.execute(Command::Incr(IncrDecr::new("repl_counter", 1)))
+                    .await
+                    .unwrap();
+                conn.execute(Command::Set(Set::new(
+                    "repl_last",
+                    format!("{t}_{i}"),
+                    None,
+                    None,
+                )))
+                .await
+                .unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let frame = primary
+        .conn()
+        .execute(Command::Get(Get::new("repl_last")))
+        .await
+        .unwrap();
+    let Frame::Bulk(last) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    wait_for_value(&replica, "repl_last", std::str::from_utf8(&last).unwrap()).await;
+    wait_for_value(&replica, "repl_counter", "400").await;
+    let frame = replica
+        .conn()
+        .execute(Command::Dbsize(Dbsize::new()))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+}
+
+fn read_u64(stream: &mut TcpStream) -> u64 {
+    let mut buf = [0; 8];
+    stream.read_exact(&mut buf).unwrap();
+    u64::from_be_bytes(buf)
+}
+
+fn read_u8(stream: &mut TcpStream) -> u8 {
+    let mut buf = [0; 1];
+    stream.read_exact(&mut buf).unwrap();
+    buf[0]
+}
+
+// connect to the primary like a replica which applied the batch `seq` of `replid`,
+// returns the first message of the primary
+fn connect_replica(addr: SocketAddr, replid: &[u8], seq: u64) -> (u8, TcpStream) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let mut handshake = (replid.len() as u64).to_be_bytes().to_vec();
+    handshake.extend(replid);
+    handshake.extend(seq.to_be_bytes());
+    stream.write_all(&handshake).unwrap();
+    let msg = read_u8(&mut stream);
+    (msg, stream)
+}
+
+async fn open_primary(path: &Path) -> DB {
+    // the db is released after the replication threads of the dropped db exit
+    for _ in 0..50 {
+        if let Ok(db) = OpenOptions::new()
+            .replication_listen("127.0.0.1:0")
+            .open(path)
+            .await
+        {
+            return db;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{} can't be opened", path.display());
+}
+
+#[tokio::test]
+async fn continue_after_primary_restart() {
+    const MSG_CONTINUE: u8 = 1;
+    const MSG_FULLSYNC: u8 = 2;
+    const MSG_BATCH: u8 = 5;
+    const MSG_PING: u8 = 6;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let primary = open_primary(path).await;
+    let conn = primary.conn();
+    conn.execute(Command::Set(Set::new("restart_1", "v", None, None)))
+        .await
+        .unwrap();
+
+    // a new replica starts with a full sync
+    let (msg, mut stream) = connect_replica(primary.replication_addr().unwrap(), &[], 0);
+    assert_eq!(msg, MSG_FULLSYNC);
+    let len = read_u64(&mut stream) as usize;
+    let mut replid = vec![0; len];
+    stream.read_exact(&mut replid).unwrap();
+    assert_eq!(read_u64(&mut stream), 1);
+    drop(stream);
+
+    conn.execute(Command::Set(Set::new("restart_2", "v", None, None)))
+        .await
+        .unwrap();
+    drop(conn);
+    drop(primary);
+
+    // the replica having the last batch continues after the primary restarts
+    let primary = open_primary(path).await;
+    let addr = primary.replication_addr().unwrap();
+    let (msg, mut stream) = connect_replica(addr, &replid, 2);
+    assert_eq!(msg, MSG_CONTINUE);
+    primary
+        .conn()
+        .execute(Command::Set(Set::new("restart_3", "v", None, None)))
+        .await
+        .unwrap();
+    let mut msg = read_u8(&mut stream);
+    while msg == MSG_PING {
+        msg = read_u8(&mut stream);
+    }
+    assert_eq!(msg, MSG_BATCH);
+    assert_eq!(read_u64(&mut stream), 3);
+    drop(stream);
+
+    // the batches before the restart are not kept
+    let (msg, _) = connect_replica(addr, &replid, 1);
+    assert_eq!(msg, MSG_FULLSYNC);
+
+    drop(primary);
+}
+
+#[tokio::test]
+async fn atomic_on_primary() {
+    let dir = tempfile::tempdir().unwrap();
+    let primary = open_primary(dir.path()).await;
+    let conn = primary.conn();
+    conn.execute(Command::Set(Set::new("atomic_1", "v", None, None)))
+        .await
+        .unwrap();
+
+    // the sequence number saved by a txn committed after the snapshot of the txn doesn't
+    // conflict with the one saved by the txn
+    let mut attempts = 0;
+    conn.atomic(async |tx| {
+        attempts += 1;
+        if attempts == 1 {
+            let primary = primary.clone();
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(
+                    primary
+                        .conn()
+                        .execute(Command::Set(Set::new("atomic_2", "v", None, None))),
+                )
+            })
+            .join()
+            .unwrap()
+            .unwrap();
+        }
+        tx.set("atomic_1", "v2").await
+    })
+    .await
+    .unwrap();
+    assert_eq!(attempts, 1);
+
+    drop(conn);
+    drop(primary);
+}
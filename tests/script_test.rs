@@ -0,0 +1,291 @@
+use mapuche_embedded::{
+    cmd::{Command, Eval, Fcall, Function, FunctionOp, Get, Script, ScriptOp, Set},
+    frame::Frame,
+    OpenOptions,
+};
+
+const EMPTY: [&str; 0] = [];
+
+#[tokio::test]
+async fn eval_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+
+    let frame = conn
+        .execute(Command::Eval(Eval::new(
+            "redis.call('SET', KEYS[1], ARGV[1]) return redis.call('GET', KEYS[1])",
+            &["script_key"],
+            &["v1"],
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+
+    let frame = conn
+        .execute(Command::Eval(Eval::new(
+            "return {1, 'two', {3}, redis.status_reply('OK'), false, 3.9}",
+            &EMPTY,
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(values.len(), 6);
+    assert!(matches!(values[0], Frame::Integer(1)));
+    assert_eq!(values[1], "two");
+    assert!(matches!(values[2], Frame::Array(_)));
+    assert_eq!(values[3], "OK");
+    assert!(matches!(values[4], Frame::Null));
+    // the numbers are truncated to integers
+    assert!(matches!(values[5], Frame::Integer(3)));
+
+    // the writes of a failed script are discarded
+    let frame = conn
+        .execute(Command::Eval(Eval::new(
+            "redis.call('SET', KEYS[1], 'v2') error('failed')",
+            &["script_key"],
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    let frame = conn
+        .execute(Command::Get(Get::new("script_key")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+
+    let frame = conn
+        .execute(Command::Eval(Eval::new(
+            "return redis.pcall('INCR', KEYS[1])",
+            &["script_key"],
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::Eval(Eval::new("return 1 +", &EMPTY, &EMPTY)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(e) if e.starts_with("ERR Error compiling script")));
+
+    let frame = conn
+        .execute(Command::Eval(Eval::new("x = 1", &EMPTY, &EMPTY)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::EvalRo(Eval::new(
+            "return redis.call('SET', KEYS[1], 'v3')",
+            &["script_key"],
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    let frame = conn
+        .execute(Command::EvalRo(Eval::new(
+            "return redis.call('GET', KEYS[1])",
+            &["script_key"],
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+}
+
+#[tokio::test]
+async fn script_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+
+    let code = "return redis.call('INCRBY', KEYS[1], ARGV[1])";
+    let frame = conn
+        .execute(Command::Script(Script::new(ScriptOp::Load(
+            code.to_string(),
+        ))))
+        .await
+        .unwrap();
+    let Frame::Bulk(sha) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    let sha = String::from_utf8(sha.to_vec()).unwrap();
+    assert_eq!(sha.len(), 40);
+
+    let frame = conn
+        .execute(Command::Evalsha(Eval::new(&sha, &["script_counter"], &[5])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(5)));
+    let frame = conn
+        .execute(Command::Evalsha(Eval::new(
+            sha.to_uppercase(),
+            &["script_counter"],
+            &[2],
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(7)));
+
+    let missing = "0".repeat(40);
+    let frame = conn
+        .execute(Command::Script(Script::new(ScriptOp::Exists(vec![
+            sha.clone(),
+            missing.clone(),
+        ]))))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert!(matches!(values[..], [Frame::Integer(1), Frame::Integer(0)]));
+
+    let frame = conn
+        .execute(Command::Evalsha(Eval::new(&missing, &EMPTY, &EMPTY)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(e) if e.starts_with("NOSCRIPT")));
+
+    let frame = conn
+        .execute(Command::Script(Script::new(ScriptOp::Flush)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Evalsha(Eval::new(&sha, &["script_counter"], &[1])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(e) if e.starts_with("NOSCRIPT")));
+}
+
+#[tokio::test]
+async fn function_libraries() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+
+    let code = "#!lua name=mylib
+redis.register_function('my_set', function(keys, args)
+    return redis.call('SET', keys[1], args[1])
+end)
+redis.register_function{
+    function_name = 'my_get',
+    callback = function(keys, args) return redis.call('GET', keys[1]) end,
+    flags = {'no-writes'},
+}";
+    let frame = conn
+        .execute(Command::Function(Function::new(FunctionOp::Load {
+            code: code.to_string(),
+            replace: false,
+        })))
+        .await
+        .unwrap();
+    assert_eq!(frame, "mylib");
+    let frame = conn
+        .execute(Command::Function(Function::new(FunctionOp::Load {
+            code: code.to_string(),
+            replace: false,
+        })))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::Fcall(Fcall::new(
+            "my_set",
+            &["fn_key"],
+            &["fn_val"],
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::FcallRo(Fcall::new("my_get", &["fn_key"], &EMPTY)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "fn_val");
+    let frame = conn
+        .execute(Command::FcallRo(Fcall::new("my_set", &["fn_key"], &["x"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::Function(Function::new(FunctionOp::List {
+            pattern: Some("my*".to_string()),
+            with_code: false,
+        })))
+        .await
+        .unwrap();
+    let Frame::Array(libraries) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(libraries.len(), 1);
+    drop(conn);
+    drop(db);
+
+    // the libraries are stored in the db
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+    let frame = conn
+        .execute(Command::FcallRo(Fcall::new("my_get", &["fn_key"], &EMPTY)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "fn_val");
+
+    let frame = conn
+        .execute(Command::Function(Function::new(FunctionOp::Delete(
+            "mylib".to_string(),
+        ))))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Fcall(Fcall::new("my_set", &["fn_key"], &["x"])))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+    let frame = conn
+        .execute(Command::Set(Set::new("fn_key", "plain", None, None)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+}
+
+#[tokio::test]
+async fn sharded_scripts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().shards(4).open(path).await.unwrap();
+    let conn = db.conn();
+
+    let frame = conn
+        .execute(Command::Eval(Eval::new(
+            "redis.call('SET', KEYS[1], '1') redis.call('SET', KEYS[2], '2') return 'OK'",
+            &["{user}.a", "{user}.b"],
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+
+    let frame = conn
+        .execute(Command::Eval(Eval::new(
+            "return 1",
+            &["{user}.a", "{other}.b"],
+            &EMPTY,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(e) if e.starts_with("CROSSSLOT")));
+}
@@ -0,0 +1,79 @@
+use mapuche_embedded::{
+    cmd::{Command, Dbsize, Del, Get, Mget, Mset, Rename, Set},
+    frame::Frame,
+    key_hash_slot, OpenOptions,
+};
+
+#[test]
+fn hash_slot() {
+    assert_eq!(key_hash_slot(b"foo"), 12182);
+    assert_eq!(
+        key_hash_slot(b"{user1000}.following"),
+        key_hash_slot(b"{user1000}.followers")
+    );
+    assert_eq!(
+        key_hash_slot(b"{user1000}.following"),
+        key_hash_slot(b"user1000")
+    );
+    // an empty hash tag is not used
+    assert_ne!(key_hash_slot(b"{}foo"), key_hash_slot(b"foo"));
+}
+
+#[tokio::test]
+async fn sharded_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().shards(4).open(path).await.unwrap();
+    let conn = db.conn();
+
+    let keys: Vec<String> = (0..20).map(|i| format!("shard_key_{i}")).collect();
+    let vals: Vec<String> = (0..20).map(|i| format!("val_{i}")).collect();
+    let frame = conn
+        .execute(Command::Mset(Mset::new(&keys, &vals)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+
+    let frame = conn
+        .execute(Command::Get(Get::new("shard_key_7")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "val_7");
+
+    let mut mget_keys = keys.clone();
+    mget_keys.push("shard_missing".to_string());
+    let frame = conn
+        .execute(Command::Mget(Mget::new(&mget_keys)))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(values.len(), 21);
+    for (value, val) in values.iter().zip(&vals) {
+        assert_eq!(*value, val.as_str());
+    }
+    assert!(matches!(values[20], Frame::Null));
+
+    let frame = conn.execute(Command::Dbsize(Dbsize::new())).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(20)));
+
+    // the keys with the same hash tag are in the same shard
+    let _ = conn
+        .execute(Command::Set(Set::new("{user}.a", "1", None, None)))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Rename(Rename::new("{user}.a", "{user}.b")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::Rename(Rename::new("{user}.b", "other")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn.execute(Command::Del(Del::new(&keys))).await.unwrap();
+    assert!(matches!(frame, Frame::Integer(20)));
+}
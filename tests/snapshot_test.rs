@@ -0,0 +1,150 @@
+use mapuche_embedded::{
+    cmd::{Command, Del, Get, Hgetall, Hset, Keys, Mget, Mset, Set, Zadd, Zrange},
+    frame::Frame,
+    rdb, OpenOptions,
+};
+use tokio::task::spawn;
+
+#[tokio::test]
+async fn snapshot_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+
+    conn.execute(Command::Set(Set::new("snap_str", "v1", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Hset(Hset::new("snap_hash", &[("f", "1")])))
+        .await
+        .unwrap();
+    conn.execute(Command::Zadd(Zadd::new(
+        "snap_zset",
+        &["a"],
+        &[1.0],
+        None,
+        false,
+    )))
+    .await
+    .unwrap();
+
+    let snapshot = db.snapshot();
+
+    conn.execute(Command::Set(Set::new("snap_str", "v2", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Hset(Hset::new("snap_hash", &[("g", "2")])))
+        .await
+        .unwrap();
+    conn.execute(Command::Del(Del::new(&["snap_zset"])))
+        .await
+        .unwrap();
+    conn.execute(Command::Set(Set::new("snap_new", "v", None, None)))
+        .await
+        .unwrap();
+
+    let frame = snapshot
+        .execute(Command::Get(Get::new("snap_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v1");
+    let frame = snapshot
+        .execute(Command::Hgetall(Hgetall::new("snap_hash")))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(values.len(), 2);
+    let frame = snapshot
+        .execute(Command::Zrange(Zrange::new(
+            "snap_zset",
+            0,
+            -1,
+            false,
+            false,
+        )))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(values[0], "a");
+    let frame = snapshot
+        .execute(Command::Keys(Keys::new("snap_*")))
+        .await
+        .unwrap();
+    let Frame::Array(keys) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    assert_eq!(keys.len(), 3);
+
+    // the writes are rejected
+    let frame = snapshot
+        .execute(Command::Set(Set::new("snap_str", "v3", None, None)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    // the snapshot doesn't block the writes of the db
+    conn.execute(Command::Set(Set::new("snap_str", "v4", None, None)))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Get(Get::new("snap_str")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v4");
+
+    // the snapshot can be used by another task
+    let frame = spawn(async move {
+        snapshot
+            .execute(Command::Get(Get::new("snap_str")))
+            .await
+            .unwrap()
+    })
+    .await
+    .unwrap();
+    assert_eq!(frame, "v1");
+}
+
+#[tokio::test]
+async fn snapshot_export() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store_snapshot_export");
+    let db = OpenOptions::new().shards(2).open(path).await.unwrap();
+    let conn = db.conn();
+
+    let keys: Vec<String> = (0..10).map(|i| format!("snap_export_{i}")).collect();
+    conn.execute(Command::Mset(Mset::new(&keys, &keys)))
+        .await
+        .unwrap();
+    let snapshot = db.snapshot();
+    conn.execute(Command::Del(Del::new(&keys))).await.unwrap();
+
+    let frame = snapshot
+        .execute(Command::Mget(Mget::new(&keys)))
+        .await
+        .unwrap();
+    let Frame::Array(values) = frame else {
+        panic!("unexpected frame {frame:?}");
+    };
+    for (value, key) in values.iter().zip(&keys) {
+        assert_eq!(*value, key.as_str());
+    }
+
+    let file = dir.path().join("snapshot_export.rdb");
+    let count = rdb::export_snapshot(&file, &snapshot).await.unwrap();
+    assert_eq!(count, 10);
+    drop(snapshot);
+
+    let import_path = dir.path().join("store_snapshot_import");
+    let imported = OpenOptions::new().open(import_path).await.unwrap();
+    assert_eq!(rdb::import(&file, &imported).await.unwrap(), 10);
+    let frame = imported
+        .conn()
+        .execute(Command::Get(Get::new("snap_export_3")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "snap_export_3");
+}
@@ -0,0 +1,418 @@
+use mapuche_embedded::{
+    cmd::{
+        Append, BitOperation, BitUnit, Bitcount, Bitfield, BitfieldOp, BitfieldType, Bitop, Bitpos,
+        Command, Expiration, Expire, ExpireCondition, Get, Getbit, Getdel, Getex, Getrange, Getset,
+        Hincrbyfloat, Hset, IncrDecr, Incrbyfloat, Mset, Persist, Set, SetCondition, Setbit, Setex,
+        Setrange, TTL,
+    },
+    frame::Frame,
+    OpenOptions,
+};
+
+#[tokio::test]
+async fn string_manipulation() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Getdel(Getdel::new("str_append")))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Append(Append::new("str_append", "Hello")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(5)));
+    let frame = conn
+        .execute(Command::Append(Append::new("str_append", " World")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(11)));
+
+    let frame = conn
+        .execute(Command::Getrange(Getrange::new("str_append", -5, -1)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "World");
+
+    let frame = conn
+        .execute(Command::Setrange(Setrange::new("str_append", 6, "Redis")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(11)));
+
+    let frame = conn
+        .execute(Command::Getset(Getset::new("str_append", "new")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "Hello Redis");
+
+    let frame = conn
+        .execute(Command::Getdel(Getdel::new("str_append")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "new");
+    let frame = conn
+        .execute(Command::Get(Get::new("str_append")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+}
+
+#[tokio::test]
+async fn string_expiration() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let frame = conn
+        .execute(Command::Setex(Setex::new("str_ttl", 100, "v")))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+    let frame = conn
+        .execute(Command::TTL(TTL::new("str_ttl")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(ttl) if ttl > 90));
+
+    let frame = conn
+        .execute(Command::Setex(Setex::new("str_ttl", 0, "v")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+
+    let frame = conn
+        .execute(Command::Getex(Getex::new(
+            "str_ttl",
+            Some(Expiration::Persist),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "v");
+    let frame = conn
+        .execute(Command::TTL(TTL::new("str_ttl")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(-1)));
+
+    let frame = conn
+        .execute(Command::Msetnx(Mset::new(
+            &["str_ttl", "str_other"],
+            &["a", "b"],
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+}
+
+#[tokio::test]
+async fn set_and_expire_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Getdel(Getdel::new("str_opts")))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Set(Set::new_with_options(
+            "str_opts",
+            "a",
+            None,
+            Some(SetCondition::Xx),
+            false,
+            false,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+
+    let frame = conn
+        .execute(Command::Set(Set::new_with_options(
+            "str_opts",
+            "a",
+            Some(Expiration::Ex(100)),
+            None,
+            false,
+            false,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "OK");
+
+    let frame = conn
+        .execute(Command::Set(Set::new_with_options(
+            "str_opts", "b", None, None, true, true,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "a");
+    let frame = conn
+        .execute(Command::TTL(TTL::new("str_opts")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(ttl) if ttl > 90));
+
+    let frame = conn
+        .execute(Command::Expire(Expire::new_with_condition(
+            "str_opts",
+            10,
+            Some(ExpireCondition::Gt),
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+    let frame = conn
+        .execute(Command::Expire(Expire::new_with_condition(
+            "str_opts",
+            10,
+            Some(ExpireCondition::Lt),
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+
+    let frame = conn
+        .execute(Command::Persist(Persist::new("str_opts")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::ExpireTime(TTL::new("str_opts")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(-1)));
+
+    let frame = conn
+        .execute(Command::Expire(Expire::new("str_opts", -1)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Get(Get::new("str_opts")))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Null));
+}
+
+#[tokio::test]
+async fn incr_by_float() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Getdel(Getdel::new("str_incr")))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Incrby(IncrDecr::new("str_incr", 10)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(10)));
+
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("str_incr", 0.5)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "10.5");
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("str_incr", -0.5)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "10");
+
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("str_incr", f64::NAN)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+}
+
+#[tokio::test]
+async fn float_format() {
+    let db = OpenOptions::new().open_in_memory().await.unwrap();
+    let conn = db.conn();
+
+    // the results are formatted like redis, added as long double
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("float", 0.1)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "0.1");
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("float", 0.2)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "0.3");
+
+    conn.execute(Command::Set(Set::new("float", "10.50", None, None)))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("float", 0.1)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "10.6");
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("float", -5.0)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "5.6");
+    conn.execute(Command::Set(Set::new("float", "5.0e3", None, None)))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("float", 2.0e2)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "5200");
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("float", -5200.0)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "0");
+
+    let frame = conn
+        .execute(Command::Hincrbyfloat(Hincrbyfloat::new("hfloat", "f", 0.1)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "0.1");
+    let frame = conn
+        .execute(Command::Hincrbyfloat(Hincrbyfloat::new("hfloat", "f", 0.2)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "0.3");
+    conn.execute(Command::Hset(Hset::new("hfloat", &[("g", "10.50")])))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Hincrbyfloat(Hincrbyfloat::new("hfloat", "g", 0.1)))
+        .await
+        .unwrap();
+    assert_eq!(frame, "10.6");
+    let frame = conn
+        .execute(Command::Hincrbyfloat(Hincrbyfloat::new(
+            "hfloat", "g", -5.0,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "5.6");
+    conn.execute(Command::Hset(Hset::new("hfloat", &[("g", "5.0e3")])))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Hincrbyfloat(Hincrbyfloat::new(
+            "hfloat", "g", 2.0e2,
+        )))
+        .await
+        .unwrap();
+    assert_eq!(frame, "5200");
+
+    // the values far below the smallest float are zero, without dividing by their power of 10
+    let start = std::time::Instant::now();
+    for value in [
+        "1e-24999",
+        "-123456789e-5060",
+        &format!("{}e-11000", "9".repeat(5000)),
+    ] {
+        conn.execute(Command::Set(Set::new("float", value, None, None)))
+            .await
+            .unwrap();
+        let frame = conn
+            .execute(Command::Incrbyfloat(Incrbyfloat::new("float", 1.0)))
+            .await
+            .unwrap();
+        assert_eq!(frame, "1");
+    }
+    assert!(start.elapsed() < std::time::Duration::from_secs(1));
+
+    conn.execute(Command::Set(Set::new("float", "abc", None, None)))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Incrbyfloat(Incrbyfloat::new("float", 1.0)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Error(_)));
+}
+
+#[tokio::test]
+async fn bitmap() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = OpenOptions::new().open(dir.path()).await.unwrap();
+    let conn = db.conn();
+
+    let _ = conn
+        .execute(Command::Getdel(Getdel::new("bitmap1")))
+        .await
+        .unwrap();
+    let frame = conn
+        .execute(Command::Setbit(Setbit::new("bitmap1", 10, 1)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(0)));
+    let frame = conn
+        .execute(Command::Getbit(Getbit::new("bitmap1", 10)))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Bitcount(Bitcount::new(
+            "bitmap1",
+            None,
+            BitUnit::Byte,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(1)));
+    let frame = conn
+        .execute(Command::Bitpos(Bitpos::new(
+            "bitmap1",
+            1,
+            None,
+            None,
+            BitUnit::Byte,
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(10)));
+
+    let frame = conn
+        .execute(Command::Bitop(Bitop::new(
+            BitOperation::Not,
+            "bitmap2",
+            &["bitmap1"],
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(frame, Frame::Integer(2)));
+
+    let frame = conn
+        .execute(Command::Bitfield(Bitfield::new(
+            "bitmap2",
+            vec![
+                BitfieldOp::Get {
+                    ty: BitfieldType::unsigned(8),
+                    offset: 0,
+                },
+                BitfieldOp::Incrby {
+                    ty: BitfieldType::unsigned(8),
+                    offset: 0,
+                    increment: 1,
+                },
+            ],
+        )))
+        .await
+        .unwrap();
+    assert_eq!(
+        format!("{:?}", frame),
+        format!(
+            "{:?}",
+            Frame::Array(vec![Frame::Integer(255), Frame::Integer(0)])
+        )
+    );
+}
@@ -8,8 +8,7 @@ use tokio::{join, task::spawn};
 
 #[tokio::test]
 async fn db_conn() {
-    let options = OpenOptions::new();
-    let db = options.open("./mapuche_store").await.unwrap();
+    let db = OpenOptions::new().open_in_memory().await.unwrap();
     let conn = db.conn();
     let set_cmd = Command::Set(Set::new("test1", "value", None, None));
     let frame = conn.execute(set_cmd).await.unwrap();
@@ -37,8 +36,7 @@ async fn db_conn() {
 
 #[tokio::test]
 async fn multi_thread() {
-    let options = OpenOptions::new();
-    let db = options.open("./mapuche_store").await.unwrap();
+    let db = OpenOptions::new().open_in_memory().await.unwrap();
 
     let db1 = db.clone();
     let t1 = spawn(async move {
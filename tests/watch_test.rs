@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use mapuche_embedded::{
+    cmd::{Command, Del, Hset, Set},
+    ChangeOp, DataType, OpenOptions, Watch, WatchEvent,
+};
+
+async fn next_event(watch: &mut Watch) -> WatchEvent {
+    tokio::time::timeout(Duration::from_secs(1), watch.next())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn watch_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+    let mut watch = conn.watch_prefix("user:");
+
+    conn.execute(Command::Set(Set::new("other", "1", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Set(Set::new("user:1", "alice", None, None)))
+        .await
+        .unwrap();
+    let WatchEvent::Change(event) = next_event(&mut watch).await else {
+        panic!("unexpected event");
+    };
+    assert_eq!(event.key, "user:1");
+    assert_eq!(event.data_type, DataType::String);
+    assert_eq!(
+        event.op,
+        ChangeOp::Set {
+            value: Bytes::from_static(b"alice")
+        }
+    );
+
+    conn.execute(Command::Del(Del::new(&["user:1"])))
+        .await
+        .unwrap();
+    let WatchEvent::Change(event) = next_event(&mut watch).await else {
+        panic!("unexpected event");
+    };
+    assert_eq!(event.op, ChangeOp::Del);
+}
+
+#[tokio::test]
+async fn watch_prefix_with_snapshot() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    let db = OpenOptions::new().open(path).await.unwrap();
+    let conn = db.conn();
+    conn.execute(Command::Set(Set::new("user:1", "alice", None, None)))
+        .await
+        .unwrap();
+    conn.execute(Command::Hset(Hset::new("user:2", &[("name", "bob")])))
+        .await
+        .unwrap();
+    conn.execute(Command::Set(Set::new("other", "1", None, None)))
+        .await
+        .unwrap();
+
+    let mut watch = conn.watch_prefix_with_snapshot("user:").unwrap();
+    let mut snapshot = vec![];
+    loop {
+        match next_event(&mut watch).await {
+            WatchEvent::Snapshot(event) => snapshot.push(event),
+            WatchEvent::SnapshotDone => break,
+            WatchEvent::Change(event) => panic!("unexpected change {event:?}"),
+        }
+    }
+    assert_eq!(snapshot.len(), 3);
+    assert!(snapshot.iter().all(|event| event.key.starts_with(b"user:")));
+    assert!(snapshot.iter().any(|event| event.op
+        == ChangeOp::HashSet {
+            field: Bytes::from_static(b"name"),
+            value: Bytes::from_static(b"bob"),
+        }));
+
+    conn.execute(Command::Set(Set::new("user:3", "carol", None, None)))
+        .await
+        .unwrap();
+    let WatchEvent::Change(event) = next_event(&mut watch).await else {
+        panic!("unexpected event");
+    };
+    assert_eq!(event.key, "user:3");
+}